@@ -0,0 +1,44 @@
+/*
+ * The exa-cli C API: embed the Exa client, key rotation and response cache
+ * included, without running `exa` per request.
+ *
+ * Build the shared library with
+ *     cargo rustc --lib --release --features ffi --crate-type cdylib
+ * and link against target/release/libexa_cli.so (.dylib, .dll).
+ *
+ * Each call takes an Exa API request body as a JSON string, such as
+ * {"query": "rust async runtimes", "numResults": 5}, and returns the API's
+ * response as a JSON string, or {"error": "..."} when the request failed.
+ * Free the returned string with exa_free. Keys, the API root and the cache
+ * come from the same variables and config files as the CLI's (EXA_API_KEY,
+ * EXA_API_KEYS, EXA_BASE_URL, EXA_CACHE_BACKEND). Calls may come from any
+ * thread; they are answered one at a time.
+ */
+
+#ifndef EXA_H
+#define EXA_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* POST /search */
+char *exa_search(const char *request);
+
+/* POST /findSimilar */
+char *exa_find_similar(const char *request);
+
+/* POST /contents */
+char *exa_contents(const char *request);
+
+/* POST /answer */
+char *exa_answer(const char *request);
+
+/* Free a string returned above; NULL is ignored */
+void exa_free(char *response);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* EXA_H */
@@ -0,0 +1,503 @@
+//! Request and response types for the Exa API
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+/// Body of `POST /search`
+#[derive(Serialize)]
+pub struct SearchRequest {
+    pub query: String,
+    #[serde(rename = "numResults")]
+    pub num_results: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contents: Option<ContentsConfig>,
+    #[serde(rename = "includeDomains", skip_serializing_if = "Option::is_none")]
+    pub include_domains: Option<Vec<String>>,
+    #[serde(rename = "startPublishedDate", skip_serializing_if = "Option::is_none")]
+    pub start_published_date: Option<String>,
+    #[serde(rename = "endPublishedDate", skip_serializing_if = "Option::is_none")]
+    pub end_published_date: Option<String>,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub search_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    #[serde(rename = "maxAgeHours", skip_serializing_if = "Option::is_none")]
+    pub max_age_hours: Option<i64>,
+}
+
+/// Which page contents to return with results
+#[derive(Serialize, Clone)]
+pub struct ContentsConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub highlights: Option<HighlightsConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verbosity: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct HighlightsConfig {
+    #[serde(rename = "maxCharacters")]
+    pub max_characters: usize,
+}
+
+/// Body of `POST /findSimilar`
+#[derive(Serialize)]
+pub struct FindSimilarRequest {
+    pub url: String,
+    #[serde(rename = "numResults")]
+    pub num_results: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contents: Option<ContentsConfig>,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub search_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    #[serde(rename = "maxAgeHours", skip_serializing_if = "Option::is_none")]
+    pub max_age_hours: Option<i64>,
+    #[serde(rename = "excludeDomains", skip_serializing_if = "Option::is_none")]
+    pub exclude_domains: Option<Vec<String>>,
+}
+
+/// Body of `POST /contents`
+#[derive(Serialize)]
+pub struct GetContentsRequest {
+    pub urls: Vec<String>,
+    #[serde(flatten)]
+    pub contents: ContentsConfig,
+}
+
+/// Body of `POST /answer`
+#[derive(Serialize)]
+pub struct AnswerRequest {
+    pub query: String,
+    /// Include the full text of each cited page
+    pub text: bool,
+    /// Server-sent events instead of a single JSON body
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub stream: bool,
+}
+
+/// Body of `POST /research`
+#[derive(Serialize)]
+pub struct ResearchCreateRequest {
+    pub instructions: String,
+    pub model: String,
+    #[serde(rename = "outputSchema", skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<serde_json::Value>,
+}
+
+/// Results per request when a builder isn't given `num` (the API's default)
+pub const DEFAULT_NUM_RESULTS: usize = 10;
+
+impl ContentsConfig {
+    /// Full page text
+    pub fn text() -> Self {
+        Self { text: Some(true), highlights: None, verbosity: None }
+    }
+
+    /// Key excerpts of up to `max_characters` instead of the full text
+    pub fn highlights(max_characters: usize) -> Self {
+        Self { text: None, highlights: Some(HighlightsConfig { max_characters }), verbosity: None }
+    }
+
+    /// compact, standard or full
+    pub fn verbosity<'a>(mut self, verbosity: impl Into<Option<&'a str>>) -> Self {
+        self.verbosity = verbosity.into().map(str::to_string);
+        self
+    }
+}
+
+impl SearchRequest {
+    /// `SearchRequest::builder().query("...").num(5).category("news").build()`.
+    /// Optional settings also take an `Option`, so unset values pass straight through.
+    pub fn builder() -> SearchRequestBuilder {
+        SearchRequestBuilder::default()
+    }
+}
+
+#[derive(Default, Clone)]
+pub struct SearchRequestBuilder {
+    query: String,
+    num_results: Option<usize>,
+    contents: Option<ContentsConfig>,
+    include_domains: Vec<String>,
+    start_published_date: Option<String>,
+    end_published_date: Option<String>,
+    search_type: Option<String>,
+    category: Option<String>,
+    max_age_hours: Option<i64>,
+}
+
+impl SearchRequestBuilder {
+    pub fn query(mut self, query: impl Into<String>) -> Self {
+        self.query = query.into();
+        self
+    }
+
+    pub fn num(mut self, num_results: usize) -> Self {
+        self.num_results = Some(num_results);
+        self
+    }
+
+    pub fn contents(mut self, contents: impl Into<Option<ContentsConfig>>) -> Self {
+        self.contents = contents.into();
+        self
+    }
+
+    /// Only return results from these domains
+    pub fn include_domains<S: Into<String>>(mut self, domains: impl IntoIterator<Item = S>) -> Self {
+        self.include_domains.extend(domains.into_iter().map(Into::into));
+        self
+    }
+
+    /// Published on or after this date (YYYY-MM-DD)
+    pub fn published_after<'a>(mut self, date: impl Into<Option<&'a str>>) -> Self {
+        self.start_published_date = date.into().map(str::to_string);
+        self
+    }
+
+    /// Published on or before this date (YYYY-MM-DD)
+    pub fn published_before<'a>(mut self, date: impl Into<Option<&'a str>>) -> Self {
+        self.end_published_date = date.into().map(str::to_string);
+        self
+    }
+
+    /// instant, auto, fast, deep or neural
+    pub fn search_type<'a>(mut self, search_type: impl Into<Option<&'a str>>) -> Self {
+        self.search_type = search_type.into().map(str::to_string);
+        self
+    }
+
+    pub fn category<'a>(mut self, category: impl Into<Option<&'a str>>) -> Self {
+        self.category = category.into().map(str::to_string);
+        self
+    }
+
+    /// Max content age in hours (0 always fetches live, -1 only uses the index)
+    pub fn max_age_hours(mut self, hours: impl Into<Option<i64>>) -> Self {
+        self.max_age_hours = hours.into();
+        self
+    }
+
+    pub fn build(self) -> Result<SearchRequest> {
+        if self.query.trim().is_empty() {
+            bail!("No query provided");
+        }
+        Ok(SearchRequest {
+            query: self.query,
+            num_results: self.num_results.unwrap_or(DEFAULT_NUM_RESULTS),
+            contents: self.contents,
+            include_domains: (!self.include_domains.is_empty()).then_some(self.include_domains),
+            start_published_date: self.start_published_date,
+            end_published_date: self.end_published_date,
+            search_type: self.search_type,
+            category: self.category,
+            max_age_hours: self.max_age_hours,
+        })
+    }
+}
+
+impl FindSimilarRequest {
+    /// `FindSimilarRequest::builder().url("https://...").num(5).build()`
+    pub fn builder() -> FindSimilarRequestBuilder {
+        FindSimilarRequestBuilder::default()
+    }
+}
+
+#[derive(Default, Clone)]
+pub struct FindSimilarRequestBuilder {
+    url: String,
+    num_results: Option<usize>,
+    contents: Option<ContentsConfig>,
+    search_type: Option<String>,
+    category: Option<String>,
+    max_age_hours: Option<i64>,
+    exclude_domains: Vec<String>,
+}
+
+impl FindSimilarRequestBuilder {
+    /// Page to find similar pages to
+    pub fn url(mut self, url: impl Into<String>) -> Self {
+        self.url = url.into();
+        self
+    }
+
+    pub fn num(mut self, num_results: usize) -> Self {
+        self.num_results = Some(num_results);
+        self
+    }
+
+    pub fn contents(mut self, contents: impl Into<Option<ContentsConfig>>) -> Self {
+        self.contents = contents.into();
+        self
+    }
+
+    pub fn search_type<'a>(mut self, search_type: impl Into<Option<&'a str>>) -> Self {
+        self.search_type = search_type.into().map(str::to_string);
+        self
+    }
+
+    pub fn category<'a>(mut self, category: impl Into<Option<&'a str>>) -> Self {
+        self.category = category.into().map(str::to_string);
+        self
+    }
+
+    pub fn max_age_hours(mut self, hours: impl Into<Option<i64>>) -> Self {
+        self.max_age_hours = hours.into();
+        self
+    }
+
+    /// Leave out results from these domains
+    pub fn exclude_domains<S: Into<String>>(mut self, domains: impl IntoIterator<Item = S>) -> Self {
+        self.exclude_domains.extend(domains.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn build(self) -> Result<FindSimilarRequest> {
+        if self.url.trim().is_empty() {
+            bail!("No URL provided");
+        }
+        Ok(FindSimilarRequest {
+            url: self.url,
+            num_results: self.num_results.unwrap_or(DEFAULT_NUM_RESULTS),
+            contents: self.contents,
+            search_type: self.search_type,
+            category: self.category,
+            max_age_hours: self.max_age_hours,
+            exclude_domains: (!self.exclude_domains.is_empty()).then_some(self.exclude_domains),
+        })
+    }
+}
+
+impl AnswerRequest {
+    /// `AnswerRequest::builder().query("...").text(true).build()`
+    pub fn builder() -> AnswerRequestBuilder {
+        AnswerRequestBuilder::default()
+    }
+}
+
+#[derive(Default)]
+pub struct AnswerRequestBuilder {
+    query: String,
+    text: bool,
+    stream: bool,
+}
+
+impl AnswerRequestBuilder {
+    pub fn query(mut self, query: impl Into<String>) -> Self {
+        self.query = query.into();
+        self
+    }
+
+    /// Include the full text of each cited page
+    pub fn text(mut self, text: bool) -> Self {
+        self.text = text;
+        self
+    }
+
+    /// Ask for server-sent events (see `ExaClient::answer_stream`)
+    pub fn stream(mut self, stream: bool) -> Self {
+        self.stream = stream;
+        self
+    }
+
+    pub fn build(self) -> Result<AnswerRequest> {
+        if self.query.trim().is_empty() {
+            bail!("No query provided");
+        }
+        Ok(AnswerRequest { query: self.query, text: self.text, stream: self.stream })
+    }
+}
+
+/// Results of search, find-similar and contents requests
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct SearchResponse {
+    pub results: Vec<SearchResult>,
+    #[serde(rename = "costDollars", default, skip_serializing_if = "Option::is_none")]
+    pub cost_dollars: Option<CostDollars>,
+    #[serde(rename = "requestId", default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+/// One page in a [`SearchResponse`]
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct SearchResult {
+    pub title: Option<String>,
+    pub url: String,
+    #[serde(rename = "publishedDate")]
+    pub published_date: Option<String>,
+    pub text: Option<String>,
+    pub highlights: Option<Vec<String>>,
+    pub entities: Option<Vec<Entity>>,
+    /// Representative image of the page
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    /// The site's favicon
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub favicon: Option<String>,
+    /// Set locally for domains on the do-not-ingest list
+    #[serde(rename = "doNotIngest", default, skip_serializing_if = "std::ops::Not::not")]
+    pub do_not_ingest: bool,
+    /// Set locally when the text looks paywalled or login-gated
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub paywalled: bool,
+    /// Set locally from the text; see [`fingerprint`](crate::fingerprint)
+    #[serde(rename = "contentHash", default, skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
+    /// Set locally from the text, as 16 hex digits
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub simhash: Option<String>,
+}
+
+/// Structured data about a company (with `category: company`)
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct Entity {
+    #[serde(rename = "type")]
+    pub entity_type: Option<String>,
+    pub properties: Option<EntityProperties>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct EntityProperties {
+    pub name: Option<String>,
+    #[serde(rename = "foundedYear")]
+    pub founded_year: Option<serde_json::Value>,
+    pub description: Option<String>,
+    pub workforce: Option<EntityWorkforce>,
+    pub headquarters: Option<EntityHQ>,
+    pub financials: Option<EntityFinancials>,
+    #[serde(rename = "webTraffic")]
+    pub web_traffic: Option<EntityWebTraffic>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct EntityWorkforce {
+    pub total: Option<u64>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct EntityHQ {
+    pub city: Option<String>,
+    pub country: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct EntityFinancials {
+    #[serde(rename = "revenueAnnual")]
+    pub revenue_annual: Option<serde_json::Value>,
+    #[serde(rename = "fundingTotal")]
+    pub funding_total: Option<f64>,
+    #[serde(rename = "fundingLatestRound")]
+    pub funding_latest_round: Option<EntityFundingRound>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct EntityFundingRound {
+    pub name: Option<String>,
+    pub date: Option<String>,
+    pub amount: Option<f64>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct EntityWebTraffic {
+    #[serde(rename = "visitsMonthly")]
+    pub visits_monthly: Option<u64>,
+}
+
+/// A synthesized answer and the pages it cites
+#[derive(Deserialize, Serialize, Debug)]
+pub struct AnswerResponse {
+    pub answer: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub citations: Vec<AnswerCitation>,
+    #[serde(rename = "costDollars", default, skip_serializing_if = "Option::is_none")]
+    pub cost_dollars: Option<CostDollars>,
+    #[serde(rename = "requestId", default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct AnswerCitation {
+    pub url: String,
+    pub title: Option<String>,
+    #[serde(rename = "publishedDate", default, skip_serializing_if = "Option::is_none")]
+    pub published_date: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ResearchCreateResponse {
+    #[serde(rename = "researchId")]
+    pub research_id: String,
+}
+
+/// State of a research task; `output` is set once it has completed
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ResearchStatusResponse {
+    pub status: String,
+    pub error: Option<String>,
+    pub output: Option<ResearchOutput>,
+    pub outputs: Option<Vec<serde_json::Value>>,
+    pub citations: Option<Vec<Citation>>,
+    #[serde(rename = "costDollars")]
+    pub cost_dollars: Option<CostDollars>,
+    #[serde(rename = "requestId", default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ResearchOutput {
+    pub content: Option<String>,
+}
+
+/// A source cited by a research report
+#[derive(Deserialize, Serialize, Debug)]
+pub struct Citation {
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(rename = "publishedDate", default, skip_serializing_if = "Option::is_none")]
+    pub published_date: Option<String>,
+}
+
+/// What a request cost
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct CostDollars {
+    pub total: Option<f64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_builder() {
+        let request = SearchRequest::builder()
+            .query("rust")
+            .num(3)
+            .contents(ContentsConfig::highlights(200).verbosity("compact"))
+            .include_domains(["doc.rust-lang.org"])
+            .published_after(None)
+            .category("news")
+            .max_age_hours(None)
+            .build()
+            .unwrap();
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            serde_json::json!({
+                "query": "rust",
+                "numResults": 3,
+                "contents": {"highlights": {"maxCharacters": 200}, "verbosity": "compact"},
+                "includeDomains": ["doc.rust-lang.org"],
+                "category": "news"
+            })
+        );
+        assert!(SearchRequest::builder().num(3).build().is_err());
+
+        let find = FindSimilarRequest::builder().url("https://a.com").exclude_domains(None::<String>).build().unwrap();
+        assert_eq!((find.num_results, find.exclude_domains), (DEFAULT_NUM_RESULTS, None));
+    }
+}
@@ -0,0 +1,143 @@
+//! Exa API revisions the client can speak. Requests are built and responses
+//! parsed in the shape of the latest revision; for an older one,
+//! [`ApiVersion`] rewrites the path and request body on the way out and
+//! upgrades the response on the way in, so the rest of the crate only knows
+//! one wire format.
+
+use anyhow::Result;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ApiVersion {
+    /// Research as `/research/v0/tasks`, with `id`, `data` and per-field citations
+    V0,
+    /// The current API
+    #[default]
+    V1,
+}
+
+impl ApiVersion {
+    pub const LATEST: Self = Self::V1;
+    pub const ALL: &'static [Self] = &[Self::V0, Self::V1];
+
+    /// Path to request for an endpoint named by its latest path
+    pub fn path(self, path: &str) -> String {
+        match self {
+            Self::V1 => path.to_string(),
+            Self::V0 => match path.strip_prefix("/research") {
+                Some("") => "/research/v0/tasks".to_string(),
+                Some(rest) if rest.starts_with('/') => format!("/research/v0/tasks{}", rest),
+                _ => path.to_string(),
+            },
+        }
+    }
+
+    /// Rewrite a request body built in the latest shape
+    pub fn downgrade_request(self, path: &str, mut body: Value) -> Value {
+        if self == Self::V0 && path == "/research" {
+            if let Some(obj) = body.as_object_mut() {
+                if let Some(schema) = obj.remove("outputSchema") {
+                    obj.insert("output".to_string(), json!({ "schema": schema }));
+                }
+            }
+        }
+        body
+    }
+
+    /// Rewrite a response into the latest shape
+    pub fn upgrade_response(self, path: &str, mut body: Value) -> Value {
+        if self != Self::V0 || !path.starts_with("/research") {
+            return body;
+        }
+        let Some(obj) = body.as_object_mut() else { return body };
+        if let Some(id) = obj.remove("id") {
+            obj.insert("researchId".to_string(), id);
+        }
+        match obj.remove("data") {
+            Some(Value::String(content)) => {
+                obj.insert("output".to_string(), json!({ "content": content }));
+            }
+            Some(data) if !data.is_null() => {
+                obj.insert("outputs".to_string(), json!([data]));
+            }
+            _ => {}
+        }
+        // v0 groups citations by output field
+        if let Some(Value::Object(by_field)) = obj.get("citations") {
+            let flat: Vec<Value> = by_field.values().filter_map(|v| v.as_array()).flatten().cloned().collect();
+            obj.insert("citations".to_string(), Value::Array(flat));
+        }
+        body
+    }
+
+    /// Deserialize a response body received from this version
+    pub fn parse<R: DeserializeOwned>(self, path: &str, body: Value) -> Result<R> {
+        Ok(serde_json::from_value(self.upgrade_response(path, body))?)
+    }
+}
+
+impl fmt::Display for ApiVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::V0 => write!(f, "v0"),
+            Self::V1 => write!(f, "v1"),
+        }
+    }
+}
+
+impl FromStr for ApiVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "v0" | "0" => Ok(Self::V0),
+            "v1" | "1" | "latest" => Ok(Self::V1),
+            other => {
+                let known: Vec<String> = Self::ALL.iter().map(|v| v.to_string()).collect();
+                Err(format!("unknown API version '{}' (supported: {})", other, known.join(", ")))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::ResearchStatusResponse;
+
+    #[test]
+    fn test_v0_research() {
+        let v0 = ApiVersion::V0;
+        assert_eq!(v0.path("/research"), "/research/v0/tasks");
+        assert_eq!(v0.path("/research/r1"), "/research/v0/tasks/r1");
+        assert_eq!(v0.path("/search"), "/search");
+        assert_eq!(ApiVersion::V1.path("/research/r1"), "/research/r1");
+
+        let body = v0.downgrade_request("/research", json!({"instructions": "q", "outputSchema": {"type": "object"}}));
+        assert_eq!(body, json!({"instructions": "q", "output": {"schema": {"type": "object"}}}));
+
+        let status: ResearchStatusResponse = v0
+            .parse(
+                "/research/r1",
+                json!({
+                    "id": "r1",
+                    "status": "completed",
+                    "data": "Report",
+                    "citations": {"data": [{"url": "https://a.com"}, {"url": "https://b.com"}]}
+                }),
+            )
+            .unwrap();
+        assert_eq!(status.output.and_then(|o| o.content).as_deref(), Some("Report"));
+        assert_eq!(status.citations.map(|c| c.len()), Some(2));
+    }
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!("V0".parse::<ApiVersion>(), Ok(ApiVersion::V0));
+        assert_eq!("latest".parse::<ApiVersion>(), Ok(ApiVersion::LATEST));
+        assert!("v9".parse::<ApiVersion>().unwrap_err().contains("supported: v0, v1"));
+    }
+}
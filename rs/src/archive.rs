@@ -0,0 +1,157 @@
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use exa_cli::fingerprint::{content_hash, simhash_hex};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const META_FILE: &str = "meta.json";
+const CONTENT_FILE: &str = "content.txt";
+
+/// Metadata stored next to each archived text
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveMeta {
+    pub url: String,
+    pub domain: String,
+    pub title: Option<String>,
+    #[serde(rename = "publishedDate")]
+    pub published_date: Option<String>,
+    #[serde(rename = "fetchedAt")]
+    pub fetched_at: DateTime<Utc>,
+    /// [`content_hash`] of the text, also the entry's directory name
+    pub hash: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub simhash: Option<String>,
+}
+
+/// An archived entry found by search, with the first matching line
+pub struct ArchiveHit {
+    pub meta: ArchiveMeta,
+    pub snippet: String,
+}
+
+/// Root of the archive: ~/.local/share/exa/archive (platform data dir)
+pub fn archive_dir() -> Result<PathBuf> {
+    let dir = dirs::data_dir()
+        .context("Could not find data directory")?
+        .join("exa")
+        .join("archive");
+    fs::create_dir_all(&dir).context("Failed to create archive directory")?;
+    Ok(dir)
+}
+
+/// First 12 hex digits of a hash, for display
+pub fn short_hash(hash: &str) -> &str {
+    &hash[..hash.len().min(12)]
+}
+
+/// Host part of a URL, used as the archive's top-level grouping
+pub fn domain_of(url: &str) -> String {
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(|h| h.trim_start_matches("www.").to_string()))
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Store a fetched text. Entries are immutable: returns None if this text
+/// (ignoring whitespace) for this domain is already archived.
+pub fn store(url: &str, title: Option<&str>, published_date: Option<&str>, text: &str) -> Result<Option<PathBuf>> {
+    let domain = domain_of(url);
+    let hash = content_hash(text);
+    let dir = archive_dir()?.join(&domain).join(&hash);
+    if dir.join(META_FILE).exists() {
+        return Ok(None);
+    }
+    fs::create_dir_all(&dir).context("Failed to create archive entry")?;
+
+    let meta = ArchiveMeta {
+        url: url.to_string(),
+        domain,
+        title: title.map(|t| t.to_string()),
+        published_date: published_date.map(|d| d.to_string()),
+        fetched_at: Utc::now(),
+        hash,
+        simhash: Some(simhash_hex(text)),
+    };
+    fs::write(dir.join(CONTENT_FILE), text).context("Failed to write archived content")?;
+    // Metadata last: its presence marks a complete entry
+    fs::write(dir.join(META_FILE), serde_json::to_string_pretty(&meta)?)
+        .context("Failed to write archive metadata")?;
+    Ok(Some(dir))
+}
+
+/// All archived entries, newest first
+pub fn list() -> Result<Vec<(ArchiveMeta, PathBuf)>> {
+    let root = archive_dir()?;
+    let mut entries = Vec::new();
+    for domain in fs::read_dir(&root)?.filter_map(|e| e.ok()) {
+        let Ok(items) = fs::read_dir(domain.path()) else { continue };
+        for item in items.filter_map(|e| e.ok()) {
+            if let Some(meta) = read_meta(&item.path()) {
+                entries.push((meta, item.path()));
+            }
+        }
+    }
+    entries.sort_by_key(|(meta, _)| std::cmp::Reverse(meta.fetched_at));
+    Ok(entries)
+}
+
+/// Look up one entry by hash prefix, returning its metadata and text
+pub fn show(hash_prefix: &str) -> Result<(ArchiveMeta, String)> {
+    let matches: Vec<_> = list()?
+        .into_iter()
+        .filter(|(meta, _)| meta.hash.starts_with(hash_prefix))
+        .collect();
+    match matches.as_slice() {
+        [] => bail!("No archived entry matches '{}'", hash_prefix),
+        [(meta, path)] => {
+            let text = fs::read_to_string(path.join(CONTENT_FILE)).context("Failed to read archived content")?;
+            Ok((meta.clone(), text))
+        }
+        _ => bail!("'{}' matches {} entries; use a longer prefix", hash_prefix, matches.len()),
+    }
+}
+
+/// Case-insensitive search: every term must appear in the title or text
+pub fn search(terms: &[String]) -> Result<Vec<ArchiveHit>> {
+    let terms: Vec<String> = terms.iter().map(|t| t.to_lowercase()).collect();
+    let mut hits = Vec::new();
+    for (meta, path) in list()? {
+        let Ok(text) = fs::read_to_string(path.join(CONTENT_FILE)) else { continue };
+        let haystack = format!("{}\n{}", meta.title.as_deref().unwrap_or(""), text).to_lowercase();
+        if !terms.iter().all(|t| haystack.contains(t.as_str())) {
+            continue;
+        }
+        let snippet = text
+            .lines()
+            .find(|line| {
+                let lower = line.to_lowercase();
+                terms.iter().any(|t| lower.contains(t.as_str()))
+            })
+            .unwrap_or("")
+            .trim()
+            .to_string();
+        hits.push(ArchiveHit { meta, snippet });
+    }
+    Ok(hits)
+}
+
+fn read_meta(dir: &Path) -> Option<ArchiveMeta> {
+    let content = fs::read_to_string(dir.join(META_FILE)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_hash_and_domain() {
+        assert_eq!(
+            content_hash("hello"),
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+        );
+        assert_eq!(domain_of("https://www.example.com/a?b=c"), "example.com");
+        assert_eq!(domain_of("not a url"), "unknown");
+    }
+}
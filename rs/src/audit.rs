@@ -0,0 +1,198 @@
+//! Tamper-evident request log. When `audit.key` exists in the config
+//! directory, every `requests.log` entry carries `prev`, the SHA-256 of the
+//! line before it, and `sig`, an ed25519 signature over the rest of the line.
+//! Editing an entry breaks its signature; deleting or reordering entries
+//! breaks the hash chain. [`verify`] checks both against the public key.
+
+use anyhow::{bail, Context, Result};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::Serialize;
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+pub const KEY_FILE: &str = "audit.key";
+pub const PUBLIC_KEY_FILE: &str = "audit.pub";
+
+pub use crate::key_manager::LOG_FILES;
+
+/// Enough of a log's end to hold its last entry
+const TAIL_BYTES: u64 = 8192;
+
+/// Separates a line's signed part from its signature
+const SIG_FIELD: &str = ",\"sig\":\"";
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn unhex<const N: usize>(s: &str) -> Result<[u8; N]> {
+    let s = s.trim();
+    if s.len() != N * 2 || !s.is_ascii() {
+        bail!("expected {} hex digits", N * 2);
+    }
+    let mut out = [0u8; N];
+    for (i, b) in out.iter_mut().enumerate() {
+        *b = u8::from_str_radix(&s[i * 2..i * 2 + 2], 16).context("invalid hex")?;
+    }
+    Ok(out)
+}
+
+/// Hex SHA-256 of a log line, as chained by the next entry's `prev`
+pub fn line_hash(line: &str) -> String {
+    crate::fingerprint::sha256_hex(line.as_bytes())
+}
+
+/// The last line of a log file, read from its tail
+pub fn last_line(path: &Path) -> Option<String> {
+    let mut file = fs::File::open(path).ok()?;
+    let len = file.metadata().ok()?.len();
+    file.seek(SeekFrom::Start(len.saturating_sub(TAIL_BYTES))).ok()?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail).ok()?;
+    String::from_utf8_lossy(&tail).lines().last().map(String::from)
+}
+
+/// The signing key in `<config_dir>/audit.key`
+pub struct AuditKey {
+    key: SigningKey,
+}
+
+impl AuditKey {
+    /// Create a key pair in `config_dir` (the private key readable only by
+    /// the owner) and return the public key in hex
+    pub fn generate(config_dir: &Path) -> Result<String> {
+        let path = config_dir.join(KEY_FILE);
+        if path.exists() {
+            bail!("{} already exists; remove it to create a new key", path.display());
+        }
+        let mut seed = [0u8; 32];
+        getrandom::getrandom(&mut seed).map_err(|e| anyhow::anyhow!("No system randomness: {}", e))?;
+        let key = SigningKey::from_bytes(&seed);
+        crate::key_manager::write_private(&path, &hex(&seed))?;
+        let public = hex(key.verifying_key().as_bytes());
+        fs::write(config_dir.join(PUBLIC_KEY_FILE), format!("{}\n", public))
+            .context("Failed to write the audit public key")?;
+        Ok(public)
+    }
+
+    /// The key in `config_dir`, if signing has been set up
+    pub fn load(config_dir: &Path) -> Result<Option<Self>> {
+        let path = config_dir.join(KEY_FILE);
+        let Ok(seed) = fs::read_to_string(&path) else { return Ok(None) };
+        let seed = unhex::<32>(&seed).with_context(|| format!("Invalid {}", path.display()))?;
+        Ok(Some(Self { key: SigningKey::from_bytes(&seed) }))
+    }
+
+    /// Append a signature to a serialized JSON object (which already holds `prev`)
+    pub fn sign_line(&self, line: &str) -> String {
+        let sig = self.key.sign(line.as_bytes());
+        let body = line.strip_suffix('}').unwrap_or(line);
+        format!("{}{}{}\"}}", body, SIG_FIELD, hex(&sig.to_bytes()))
+    }
+}
+
+/// The public key in `<config_dir>/audit.pub`, or given in hex
+pub fn public_key(config_dir: &Path, hex_key: Option<&str>) -> Result<VerifyingKey> {
+    let hex_key = match hex_key {
+        Some(k) => k.to_string(),
+        None => {
+            let path = config_dir.join(PUBLIC_KEY_FILE);
+            fs::read_to_string(&path)
+                .with_context(|| format!("No audit key ({} not found); run `exa init --audit-key`", path.display()))?
+        }
+    };
+    VerifyingKey::from_bytes(&unhex::<32>(&hex_key).context("Invalid public key")?).context("Invalid public key")
+}
+
+/// Result of checking a log
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Verification {
+    pub entries: usize,
+    pub signed: usize,
+    /// Entries written before signing was set up
+    pub unsigned_before_signing: usize,
+    pub failure: Option<Failure>,
+}
+
+/// The first entry that fails verification
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Failure {
+    /// 1-based, counting across the log files in order
+    pub line: usize,
+    pub reason: String,
+}
+
+/// Check signatures and the hash chain over a log's lines, oldest first.
+/// The first line's `prev` can't be checked (its predecessor was rotated away).
+pub fn verify(lines: &[&str], key: &VerifyingKey) -> Verification {
+    let mut v = Verification::default();
+    let mut previous: Option<&str> = None;
+    for (i, line) in lines.iter().enumerate() {
+        v.entries += 1;
+        if let Err(reason) = verify_line(line, previous, key) {
+            if reason == "unsigned" && v.signed == 0 {
+                v.unsigned_before_signing += 1;
+            } else {
+                v.failure = Some(Failure { line: i + 1, reason });
+                return v;
+            }
+        } else {
+            v.signed += 1;
+        }
+        previous = Some(line);
+    }
+    v
+}
+
+fn verify_line(line: &str, previous: Option<&str>, key: &VerifyingKey) -> Result<(), String> {
+    let Some(at) = line.rfind(SIG_FIELD) else { return Err("unsigned".into()) };
+    let signed = format!("{}}}", &line[..at]);
+    let sig = line[at + SIG_FIELD.len()..].strip_suffix("\"}").ok_or("malformed signature")?;
+    let sig = unhex::<64>(sig).map_err(|_| "malformed signature")?;
+    key.verify(signed.as_bytes(), &Signature::from_bytes(&sig)).map_err(|_| "bad signature (entry was modified)")?;
+
+    let entry: serde_json::Value = serde_json::from_str(&signed).map_err(|_| "not JSON")?;
+    let prev = entry.get("prev").and_then(|p| p.as_str()).ok_or("missing prev")?;
+    match previous {
+        Some(p) if line_hash(p) != prev => Err("hash chain broken (an entry before it was removed or changed)".into()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_and_verify() {
+        let dir = std::env::temp_dir().join(format!("exa-audit-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let public = AuditKey::generate(&dir).unwrap();
+        assert!(AuditKey::generate(&dir).is_err());
+        let signer = AuditKey::load(&dir).unwrap().unwrap();
+        let key = public_key(&dir, None).unwrap();
+        assert_eq!(hex(key.as_bytes()), public);
+
+        let mut lines = vec![r#"{"cmd":"search","status":200}"#.to_string()];
+        for cmd in ["search", "answer", "contents"] {
+            let prev = line_hash(lines.last().unwrap());
+            lines.push(signer.sign_line(&format!(r#"{{"cmd":"{}","status":200,"prev":"{}"}}"#, cmd, prev)));
+        }
+        let check = |lines: &[String]| verify(&lines.iter().map(String::as_str).collect::<Vec<_>>(), &key);
+        let v = check(&lines);
+        assert_eq!((v.entries, v.signed, v.unsigned_before_signing, v.failure), (4, 3, 1, None));
+
+        let mut edited = lines.clone();
+        edited[2] = edited[2].replace("answer", "search");
+        assert_eq!(check(&edited).failure.unwrap().line, 3);
+        let mut removed = lines.clone();
+        removed.remove(2);
+        assert!(check(&removed).failure.unwrap().reason.contains("chain"));
+        let mut inserted = lines.clone();
+        inserted.push(r#"{"cmd":"search","status":200}"#.into());
+        assert_eq!(check(&inserted).failure.unwrap(), Failure { line: 5, reason: "unsigned".into() });
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
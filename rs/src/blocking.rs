@@ -0,0 +1,185 @@
+//! A blocking client taking and returning JSON, for embedding where async
+//! Rust isn't an option: the C API (feature `ffi`) and the Python module
+//! (feature `python`) are thin layers over it. Requests are Exa API request
+//! bodies and responses the API's own JSON, sent with the CLI's key rotation
+//! and backoff; search, find-similar and contents responses are cached like
+//! the CLI's.
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+
+use crate::cache::ResponseCache;
+use crate::client::{ExaClient, DEFAULT_BASE_URL};
+use crate::key_manager::{KeyManager, KeySource};
+use crate::vars;
+
+/// Minutes a cached response is served for, as the CLI's default --cache-ttl
+const CACHE_TTL_MINUTES: u64 = 60;
+
+/// The API endpoints the blocking client calls
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Endpoint {
+    Search,
+    FindSimilar,
+    Contents,
+    Answer,
+}
+
+impl Endpoint {
+    fn path(self) -> &'static str {
+        match self {
+            Endpoint::Search => "/search",
+            Endpoint::FindSimilar => "/findSimilar",
+            Endpoint::Contents => "/contents",
+            Endpoint::Answer => "/answer",
+        }
+    }
+
+    /// The operation's name in errors
+    fn label(self) -> &'static str {
+        match self {
+            Endpoint::Search => "Search",
+            Endpoint::FindSimilar => "Find similar",
+            Endpoint::Contents => "Get contents",
+            Endpoint::Answer => "Answer",
+        }
+    }
+
+    /// Answers aren't cached, as in the CLI
+    fn cached(self) -> bool {
+        self != Endpoint::Answer
+    }
+}
+
+/// [`ExaClient`] behind a runtime of its own. Key state (rotation, spend)
+/// is saved when the client is dropped, unless its keys are in memory only.
+pub struct Client {
+    runtime: tokio::runtime::Runtime,
+    pub client: ExaClient,
+    /// None turns caching off
+    pub cache: Option<ResponseCache>,
+}
+
+impl Client {
+    /// A client configured as the CLI is: keys from `EXA_API_KEYS` /
+    /// `EXA_API_KEY` or the keys file, the API root from `EXA_BASE_URL`, and
+    /// the response cache (`EXA_CACHE_BACKEND`, else the config directory)
+    pub fn new() -> Result<Self> {
+        let mut client = ExaClient::new(KeyManager::new(false)?, reqwest::Client::new());
+        client.base_url = match vars::get("EXA_BASE_URL").as_deref().map(|u| u.trim().trim_end_matches('/')) {
+            Some(url) if !url.is_empty() => url.to_string(),
+            _ => DEFAULT_BASE_URL.to_string(),
+        };
+        let mut embedded = Self::with_client(client)?;
+        embedded.cache = Some(ResponseCache::new(CACHE_TTL_MINUTES));
+        Ok(embedded)
+    }
+
+    /// A client over `client`, without a cache
+    pub fn with_client(client: ExaClient) -> Result<Self> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .context("Failed to start the runtime")?;
+        Ok(Self { runtime, client, cache: None })
+    }
+
+    /// Send `request` (an API request body) to `endpoint`, returning the
+    /// response body with `requestId` filled in when the API sent one only
+    /// as a header. Must not be called from within an async runtime.
+    pub fn request(&mut self, endpoint: Endpoint, request: &Value) -> Result<Value> {
+        let cache = self.cache.as_ref().filter(|_| endpoint.cached());
+        let key = ResponseCache::key(&["embed", endpoint.path(), &request.to_string()]);
+        if let Some(cached) = cache.and_then(|c| c.read(&key)) {
+            if let Ok(body) = serde_json::from_str(&cached) {
+                return Ok(body);
+            }
+        }
+
+        let label = endpoint.label();
+        let log_cmd = endpoint.path().trim_start_matches('/');
+        let client = &mut self.client;
+        let resp = self.runtime.block_on(client.send::<Value, Value>(
+            reqwest::Method::POST,
+            endpoint.path(),
+            Some(request),
+            label,
+            log_cmd,
+            None,
+        ))?;
+        let cost = resp.body.pointer("/costDollars/total").and_then(Value::as_f64);
+        if let Some(total) = cost.filter(|_| !resp.replayed) {
+            client.key_manager.record_cost(resp.key_idx, total);
+        }
+        let mut body = resp.body;
+        if let (Some(object), Some(id)) = (body.as_object_mut(), resp.request_id) {
+            object.entry("requestId").or_insert(Value::String(id));
+        }
+
+        if let Some(cache) = cache {
+            cache.write(&key, &body.to_string());
+        }
+        Ok(body)
+    }
+
+    pub fn search(&mut self, request: &Value) -> Result<Value> {
+        self.request(Endpoint::Search, request)
+    }
+
+    pub fn find_similar(&mut self, request: &Value) -> Result<Value> {
+        self.request(Endpoint::FindSimilar, request)
+    }
+
+    pub fn contents(&mut self, request: &Value) -> Result<Value> {
+        self.request(Endpoint::Contents, request)
+    }
+
+    pub fn answer(&mut self, request: &Value) -> Result<Value> {
+        self.request(Endpoint::Answer, request)
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        if self.client.key_manager.source() != KeySource::Memory {
+            let _ = self.client.key_manager.save_state();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::{MockTransport, Response};
+    use serde_json::json;
+
+    #[test]
+    fn test_blocking_client() {
+        let mock = MockTransport::new();
+        let results = br#"{"results":[{"url":"https://a.com"}]}"#.to_vec();
+        mock.push(Response::new(429, &[], vec![]))
+            .push(Response::new(200, &[("x-request-id", "req-1")], vec![results]))
+            .push(Response::json(200, &json!({"answer": "42", "costDollars": {"total": 0.005}})));
+        let keys = vec!["key-a".to_string(), "key-b".to_string()];
+        let client = ExaClient::new(KeyManager::from_keys(keys), reqwest::Client::new()).with_transport(mock.clone());
+        let mut client = Client::with_client(client).unwrap();
+        let dir = std::env::temp_dir().join(format!("exa-blocking-test-{}", std::process::id()));
+        client.cache = Some(ResponseCache { backend: Some(dir.display().to_string()), ttl_minutes: 5, verbose: false });
+
+        // Rotated past the 429, then served from the cache
+        let request = json!({"query": "rust", "numResults": 3});
+        let body = client.search(&request).unwrap();
+        assert_eq!(body, json!({"results": [{"url": "https://a.com"}], "requestId": "req-1"}));
+        assert_eq!(client.search(&request).unwrap(), body);
+        let sent = mock.requests();
+        assert_eq!(sent.len(), 2);
+        assert_eq!((sent[0].header("x-api-key"), sent[1].header("x-api-key")), (Some("key-a"), Some("key-b")));
+        let sent_body: Value = serde_json::from_slice(sent[1].body.as_deref().unwrap()).unwrap();
+        assert_eq!(sent_body, request);
+
+        assert_eq!(client.answer(&json!({"query": "meaning"})).unwrap()["answer"], "42");
+        assert_eq!(mock.requests()[2].url, "https://api.exa.ai/answer");
+        assert!(client.contents(&json!({"urls": []})).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
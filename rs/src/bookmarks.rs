@@ -0,0 +1,101 @@
+use regex::Regex;
+use std::sync::LazyLock;
+
+/// Tags that matter in a Netscape bookmark file: folder lists, folder
+/// headings and links
+static TAG: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?is)<(/?)(dl|h3|a)\b([^>]*)>").unwrap());
+static HREF: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"(?i)\bhref\s*=\s*"([^"]*)""#).unwrap());
+
+/// A bookmarked link and the folder path it was filed under
+#[derive(Debug, PartialEq)]
+pub struct Bookmark {
+    /// Folder names joined with " / " (empty for top level)
+    pub folder: String,
+    pub title: String,
+    pub url: String,
+}
+
+/// Parse a Netscape bookmarks export (what Chrome, Firefox and Safari write).
+/// Only http(s) links are kept.
+pub fn parse(html: &str) -> Vec<Bookmark> {
+    let mut folders: Vec<String> = Vec::new();
+    let mut pending_folder: Option<String> = None;
+    let mut out = Vec::new();
+
+    for cap in TAG.captures_iter(html) {
+        let whole = cap.get(0).unwrap();
+        let closing = !cap[1].is_empty();
+        let tag = cap[2].to_ascii_lowercase();
+        match (tag.as_str(), closing) {
+            ("h3", false) => pending_folder = Some(inner_text(&html[whole.end()..], "</h3>")),
+            ("dl", false) => folders.push(pending_folder.take().unwrap_or_default()),
+            ("dl", true) => {
+                folders.pop();
+            }
+            ("a", false) => {
+                let Some(url) = HREF.captures(&cap[3]).map(|h| decode_entities(&h[1])) else { continue };
+                if !url.starts_with("http://") && !url.starts_with("https://") {
+                    continue;
+                }
+                let title = inner_text(&html[whole.end()..], "</a>");
+                let folder = folders
+                    .iter()
+                    .filter(|f| !f.is_empty())
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(" / ");
+                out.push(Bookmark {
+                    folder,
+                    title: if title.is_empty() { url.clone() } else { title },
+                    url,
+                });
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+/// Text up to the (case-insensitive) closing tag, entity-decoded and trimmed
+fn inner_text(rest: &str, close: &str) -> String {
+    let end = rest.to_ascii_lowercase().find(close).unwrap_or(0);
+    decode_entities(rest[..end].trim())
+}
+
+fn decode_entities(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let html = r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<DL><p>
+    <DT><H3 ADD_DATE="1">Dev</H3>
+    <DL><p>
+        <DT><H3>Rust</H3>
+        <DL><p>
+            <DT><A HREF="https://doc.rust-lang.org/book/" ADD_DATE="2">The Rust Book</A>
+        </DL><p>
+        <DT><A HREF="https://example.com/?a=1&amp;b=2">Tom &amp; Jerry</A>
+    </DL><p>
+    <DT><A HREF="javascript:void(0)">Bookmarklet</A>
+    <DT><a href="https://news.ycombinator.com/">HN</a>
+</DL><p>"#;
+        let marks = parse(html);
+        assert_eq!(marks.len(), 3);
+        assert_eq!(marks[0].folder, "Dev / Rust");
+        assert_eq!(marks[0].title, "The Rust Book");
+        assert_eq!(marks[1].folder, "Dev");
+        assert_eq!(marks[1].url, "https://example.com/?a=1&b=2");
+        assert_eq!(marks[1].title, "Tom & Jerry");
+        assert_eq!(marks[2].folder, "");
+    }
+}
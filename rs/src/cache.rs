@@ -0,0 +1,159 @@
+//! Response cache shared between runs: a local directory or a Redis server
+
+use crate::redis_cache::RedisCache;
+use crate::warnings;
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::PathBuf;
+
+/// Entries the directory backend keeps before evicting the oldest
+const MAX_DIR_ENTRIES: usize = 50;
+
+/// Cached API responses, keyed by [`ResponseCache::key`]
+pub struct ResponseCache {
+    /// A directory (e.g. a network mount) or `redis://host:port/db`. When
+    /// unset, `EXA_CACHE_BACKEND`, else `<config dir>/exa/cache`.
+    pub backend: Option<String>,
+    /// Entries older than this are not served by [`ResponseCache::read`]
+    pub ttl_minutes: u64,
+    /// Print backend failures to stderr (they are recorded as warnings either way)
+    pub verbose: bool,
+}
+
+/// Where cached responses are stored
+enum Backend {
+    Dir(PathBuf),
+    Redis(RedisCache),
+}
+
+impl ResponseCache {
+    pub fn new(ttl_minutes: u64) -> Self {
+        Self { backend: None, ttl_minutes, verbose: false }
+    }
+
+    fn backend(&self) -> Result<Backend> {
+        let spec = self
+            .backend
+            .clone()
+            .or_else(|| crate::vars::get("EXA_CACHE_BACKEND"))
+            .filter(|s| !s.trim().is_empty());
+        match spec {
+            Some(url) if url.starts_with("redis://") => Ok(Backend::Redis(RedisCache::from_url(&url)?)),
+            Some(path) => {
+                let dir = PathBuf::from(path.strip_prefix("file://").unwrap_or(&path));
+                fs::create_dir_all(&dir)
+                    .with_context(|| format!("Failed to create cache directory {}", dir.display()))?;
+                Ok(Backend::Dir(dir))
+            }
+            None => Ok(Backend::Dir(cache_dir()?)),
+        }
+    }
+
+    /// Build a cache key from a command and its arguments
+    pub fn key(parts: &[&str]) -> String {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+        let mut h = DefaultHasher::new();
+        for p in parts {
+            p.hash(&mut h);
+        }
+        format!("{:016x}", h.finish())
+    }
+
+    /// Read an entry if fresh (None if missing, stale or unreachable)
+    pub fn read(&self, key: &str) -> Option<String> {
+        self.lookup(key, true)
+    }
+
+    /// Read an entry ignoring the TTL, e.g. to find the previous version of a page
+    pub fn read_stale(&self, key: &str) -> Option<String> {
+        self.lookup(key, false)
+    }
+
+    fn lookup(&self, key: &str, fresh_only: bool) -> Option<String> {
+        match self.backend() {
+            Ok(Backend::Dir(dir)) => {
+                let path = dir.join(format!("{}.json", key));
+                let meta = fs::metadata(&path).ok()?;
+                let age = meta.modified().ok()?.elapsed().ok()?;
+                if fresh_only && age.as_secs() > self.ttl_minutes * 60 {
+                    return None; // stale
+                }
+                let data = fs::read_to_string(&path).ok()?;
+                if fresh_only {
+                    let message = format!("served from cache, {} min old (--no-cache to refresh)", age.as_secs() / 60);
+                    warnings::emit("cache_hit", message, false);
+                }
+                Some(data)
+            }
+            Ok(Backend::Redis(redis)) => match redis.get(key) {
+                Ok(hit) => hit,
+                Err(e) => {
+                    warnings::emit("cache_unavailable", format!("shared cache read failed: {}", e), self.verbose);
+                    None
+                }
+            },
+            Err(e) => {
+                warnings::emit("cache_unavailable", e.to_string(), self.verbose);
+                None
+            }
+        }
+    }
+
+    /// Store an entry; the directory backend evicts the oldest beyond 50 entries
+    pub fn write(&self, key: &str, data: &str) {
+        let dir = match self.backend() {
+            Ok(Backend::Dir(dir)) => dir,
+            Ok(Backend::Redis(redis)) => {
+                if let Err(e) = redis.set(key, data, self.ttl_minutes * 60) {
+                    warnings::emit("cache_unavailable", format!("shared cache write failed: {}", e), self.verbose);
+                }
+                return;
+            }
+            Err(_) => return,
+        };
+        let path = dir.join(format!("{}.json", key));
+        let _ = fs::write(&path, data);
+        if let Ok(entries) = fs::read_dir(&dir) {
+            let mut files: Vec<_> = entries
+                .filter_map(|e| e.ok())
+                .filter(|e| e.path().extension().is_some_and(|ext| ext == "json"))
+                .filter_map(|e| {
+                    let modified = e.metadata().ok()?.modified().ok()?;
+                    Some((e.path(), modified))
+                })
+                .collect();
+            if files.len() > MAX_DIR_ENTRIES {
+                files.sort_by_key(|(_, t)| *t);
+                for (path, _) in files.iter().take(files.len() - MAX_DIR_ENTRIES) {
+                    let _ = fs::remove_file(path);
+                }
+            }
+        }
+    }
+}
+
+/// Default cache directory
+fn cache_dir() -> Result<PathBuf> {
+    let dir = dirs::config_dir().unwrap_or_else(|| PathBuf::from(".")).join("exa").join("cache");
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dir_backend_ttl() {
+        let dir = std::env::temp_dir().join(format!("exa-cache-test-{}", std::process::id()));
+        let cache = ResponseCache { backend: Some(dir.display().to_string()), ttl_minutes: 0, verbose: false };
+        let key = ResponseCache::key(&["search", "q"]);
+        assert_ne!(key, ResponseCache::key(&["search", "q2"]));
+        cache.write(&key, "{}");
+        assert_eq!(cache.read_stale(&key).as_deref(), Some("{}"));
+        let fresh = ResponseCache { ttl_minutes: 60, ..cache };
+        assert_eq!(fresh.read(&key).as_deref(), Some("{}"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
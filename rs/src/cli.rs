@@ -0,0 +1,1437 @@
+//! Command-line arguments, and the HTTP client and cache settings derived from them
+
+use anyhow::{bail, Context, Result};
+use clap::{Parser, Subcommand};
+use exa_cli::api_version::ApiVersion;
+use exa_cli::key_manager::{KeyStrategy, KeyWeight, RateLimit};
+use exa_cli::client::DEFAULT_BASE_URL;
+use exa_cli::polite::Pacer;
+use exa_cli::{diag, vars, websets, ExaClient, KeyManager, ResponseCache};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::PathBuf;
+
+use crate::extractive;
+use crate::schedule::{Cron, QuietHours};
+
+pub const VERSION: &str = "1.3.0";
+
+#[derive(Parser)]
+#[command(name = "exa")]
+#[command(about = "AI-powered web search via Exa API", long_about = None)]
+#[command(version = VERSION)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Commands,
+
+    /// Number of results (default: 5)
+    #[arg(short = 'n', long = "num", global = true, default_value = "5")]
+    pub num: usize,
+
+    /// Include page content
+    #[arg(long = "content", global = true)]
+    pub content: bool,
+
+    /// Filter to domain
+    #[arg(long = "domain", global = true)]
+    pub domain: Option<String>,
+
+    /// Results after YYYY-MM-DD
+    #[arg(long = "after", global = true)]
+    pub after: Option<String>,
+
+    /// Results before YYYY-MM-DD
+    #[arg(long = "before", global = true)]
+    pub before: Option<String>,
+
+    /// Output as JSON
+    #[arg(long = "json", global = true)]
+    pub json: bool,
+
+    /// YAML output for any command, with the same structure and key order as
+    /// --json; either on the command line beats the other set in config.toml
+    #[arg(long = "yaml", global = true, conflicts_with_all = ["json", "markdown", "rss"])]
+    pub yaml: bool,
+
+    /// One JSON object per result per line (the whole objects of --json's
+    /// `results`), written as each result is ready; for search, find and
+    /// content. `--format jsonl` is the trimmed form: only the --fields keys
+    #[arg(long = "jsonl", global = true, conflicts_with_all = ["json", "yaml", "markdown", "rss", "format"])]
+    pub jsonl: bool,
+
+    /// Report warnings as JSON lines on stderr (with --json they are also
+    /// added to the output as a "warnings" array)
+    #[arg(long = "warnings-json", global = true)]
+    pub warnings_json: bool,
+
+    /// Abort multi-item commands (several URLs, eval, cache warm, bookmark
+    /// suggestions) at the first failed item instead of reporting it and
+    /// continuing
+    #[arg(long = "fail-fast", global = true)]
+    pub fail_fast: bool,
+
+    /// Research model (exa-research, exa-research-pro)
+    #[arg(long = "model", global = true, default_value = "exa-research")]
+    pub model: String,
+
+    /// JSON schema file for structured research output
+    #[arg(long = "schema", global = true)]
+    pub schema: Option<String>,
+
+    /// Hide sources in output
+    #[arg(long = "no-sources", global = true)]
+    pub no_sources: bool,
+
+    /// Print the answer as it is generated (to stderr with --json, which
+    /// still prints the assembled response)
+    #[arg(long = "stream", global = true)]
+    pub stream: bool,
+
+    /// For answer/research: list the sources containing supporting text for
+    /// each sentence (by word overlap); in JSON as a "grounding" map.
+    /// Research fetches the cited pages' text for this.
+    #[arg(long = "grounding", global = true)]
+    pub grounding: bool,
+
+    /// Number of sources to show for answer/research (default: 3 answer, 5 research)
+    #[arg(long = "sources", global = true)]
+    pub sources: Option<usize>,
+
+    /// What to show per source: url, title, date (comma-separated, default: url)
+    #[arg(long = "source-fields", global = true, value_delimiter = ',')]
+    pub source_fields: Vec<SourceField>,
+
+    /// Compact output for AI/LLM consumption (minimal tokens)
+    #[arg(long = "compact", global = true)]
+    pub compact: bool,
+
+    /// Bundle of output flags (or EXA_PRESET). agent: --compact, --format
+    /// jsonl with title,url,snippet, --max-chars 200, --highlights 200, no
+    /// color. Flags given explicitly take precedence.
+    #[arg(long = "preset", global = true)]
+    pub preset: Option<Preset>,
+
+    /// Apply the flags in config.toml's [profiles.<NAME>] section (or
+    /// EXA_PROFILE); flags given explicitly take precedence
+    #[arg(long = "profile", global = true)]
+    pub profile: Option<String>,
+
+    /// Drop results whose text looks paywalled or login-gated
+    #[arg(long = "skip-paywalled", global = true)]
+    pub skip_paywalled: bool,
+
+    /// Rank open pages ahead of paywalled ones
+    #[arg(long = "prefer-open", global = true)]
+    pub prefer_open: bool,
+
+    /// Drop results whose text is a near-duplicate (by simhash) of a
+    /// higher-ranked result's
+    #[arg(long = "dedupe", global = true)]
+    pub dedupe: bool,
+
+    /// Send queries that look like they contain secrets (API keys, tokens,
+    /// private keys); see ~/.config/exa/sensitive-patterns.txt
+    #[arg(long = "allow-sensitive", global = true)]
+    pub allow_sensitive: bool,
+
+    /// Redact emails, phone numbers and street addresses from page text before
+    /// it is printed, archived or exported
+    #[arg(long = "scrub-pii", global = true)]
+    pub scrub_pii: bool,
+
+    /// Probe result sites for RSS/Atom feeds and print them as an OPML file
+    #[arg(long = "discover-feeds", global = true)]
+    pub discover_feeds: bool,
+
+    /// Run search or find again every INTERVAL (30s, 15m, 2h, 1d), printing
+    /// only results whose URL no earlier run returned; stop with Ctrl-C
+    #[arg(long = "watch", global = true, value_name = "INTERVAL")]
+    pub watch: Option<Interval>,
+
+    /// Like --watch, but run at the times a cron expression gives, in local
+    /// time (e.g. "0 8 * * 1-5": weekdays at 8:00)
+    #[arg(long = "cron", global = true, value_name = "EXPR", conflicts_with = "watch")]
+    pub cron: Option<Cron>,
+
+    /// Daily window, in local time, in which --watch and --cron don't run
+    /// (e.g. 22:00-07:00); a run that falls in it waits for its end
+    #[arg(long = "quiet-hours", global = true, value_name = "HH:MM-HH:MM")]
+    pub quiet_hours: Option<QuietHours>,
+
+    /// Start each --watch or --cron run up to this much later, at random, so
+    /// watches scheduled together don't all run at once
+    #[arg(long = "jitter", global = true, value_name = "INTERVAL")]
+    pub jitter: Option<Jitter>,
+
+    /// Extractive compression of page text: keep the sentences most relevant
+    /// to the query within a ratio (0.3, 30%) or token budget (400)
+    #[arg(long = "compress-content", global = true)]
+    pub compress_content: Option<extractive::Budget>,
+
+    /// Max characters of content per result (default: 300 compact, 500 normal)
+    #[arg(long = "max-chars", global = true)]
+    pub max_chars: Option<usize>,
+
+    /// Only output specific fields (comma-separated: title,url,date,content)
+    #[arg(long = "fields", global = true)]
+    pub fields: Option<String>,
+
+    /// Disable response caching
+    #[arg(long = "no-cache", global = true)]
+    pub no_cache: bool,
+
+    /// When a request stays rate limited, fall back to the cached response
+    /// however old (search, find, content), else to a cheaper request: 3
+    /// results, no contents and the instant type for search and find, no
+    /// source texts for answer. The output is marked with a `degraded` warning
+    #[arg(long = "degrade", global = true)]
+    pub degrade: bool,
+
+    /// Don't record this run in the query history (see `exa history`)
+    #[arg(long = "no-history", global = true)]
+    pub no_history: bool,
+
+    /// Cache TTL in minutes (default: 60)
+    #[arg(long = "cache-ttl", global = true, default_value = "60")]
+    pub cache_ttl: u64,
+
+    /// Shared cache backend: a directory (e.g. a network mount) or redis://host:port/db
+    /// (or EXA_CACHE_BACKEND)
+    #[arg(long = "cache-backend", global = true)]
+    pub cache_backend: Option<String>,
+
+    /// Tab-separated output (one result per line; columns from --fields)
+    #[arg(long = "tsv", global = true)]
+    pub tsv: bool,
+
+    /// Comma-separated output (one result per line; columns from --fields)
+    #[arg(long = "csv", global = true, conflicts_with = "tsv")]
+    pub csv: bool,
+
+    /// Write diagnostics (progress, retries, warnings, errors) to this file
+    /// instead of stderr
+    #[arg(long = "log-file", global = true)]
+    pub log_file: Option<PathBuf>,
+
+    /// Verbose output for debugging (-vv adds connection-level stats)
+    #[arg(short = 'v', long = "verbose", global = true, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Search type: instant (default, sub-150ms), auto, fast, deep, neural
+    #[arg(long = "type", global = true, default_value = "instant")]
+    pub search_type: String,
+
+    /// Content category filter: company, people, tweet, news, research paper, personal site, financial report
+    #[arg(long = "category", global = true)]
+    pub category: Option<String>,
+
+    /// Max content age in hours (0=always live, -1=cache only)
+    #[arg(long = "max-age", global = true)]
+    pub max_age: Option<i64>,
+
+    /// Key excerpts instead of full text (max chars, default: 2000)
+    #[arg(long = "highlights", global = true, num_args = 0..=1, default_missing_value = "2000")]
+    pub highlights: Option<usize>,
+
+    /// Content verbosity: compact, standard, full
+    #[arg(long = "verbosity", global = true)]
+    pub verbosity: Option<String>,
+
+    /// List results on stderr, read a number from stdin, print only that result's url or content
+    #[arg(long = "pick", global = true, num_args = 0..=1, default_missing_value = "url")]
+    pub pick: Option<PickField>,
+
+    /// Alternate output format for search/find results (quickfix: `url:1: title — snippet`,
+    /// jsonl: one JSON object per result with only the --fields keys; --jsonl
+    /// prints whole results)
+    #[arg(long = "format", global = true)]
+    pub format: Option<OutputFormat>,
+
+    /// Workflow command --format gh-annotations emits for each result
+    #[arg(long = "annotation-level", global = true, value_name = "LEVEL", default_value = "notice")]
+    pub annotation_level: AnnotationLevel,
+
+    /// Tags for org/obsidian notes (comma-separated)
+    #[arg(long = "tags", global = true, value_delimiter = ',')]
+    pub tags: Vec<String>,
+
+    /// With --format org|obsidian, write one note per result into this directory
+    #[arg(long = "vault-dir", global = true)]
+    pub vault_dir: Option<PathBuf>,
+
+    /// fzf-friendly lines: index<TAB>title<TAB>url (pair with `exa preview {1}`)
+    #[arg(long = "fzf", global = true)]
+    pub fzf: bool,
+
+    /// jq filter applied to the JSON output before printing, e.g.
+    /// '.results[] | {url, title}' (implies --json; with --jsonl, per result)
+    #[arg(long = "query-filter", global = true, value_name = "EXPR", conflicts_with_all = ["markdown", "rss"])]
+    pub query_filter: Option<String>,
+
+    /// File the answer or research report, with its source links, as a Jira
+    /// or Linear issue (token in EXA_JIRA_TOKEN / EXA_LINEAR_API_KEY)
+    #[arg(long = "create-issue", global = true, value_name = "TRACKER")]
+    pub create_issue: Option<IssueTracker>,
+
+    /// Jira site for --create-issue (e.g. https://acme.atlassian.net); for
+    /// Linear, the GraphQL endpoint if not the default
+    #[arg(long = "issue-endpoint", global = true, value_name = "URL")]
+    pub issue_endpoint: Option<String>,
+
+    /// Jira project key or Linear team id for --create-issue
+    #[arg(long = "issue-project", global = true, value_name = "KEY")]
+    pub issue_project: Option<String>,
+
+    /// Email search/find results (with --watch, each round's new ones) to
+    /// `email:ADDRESS[,ADDRESS...]`; --format html sends a styled email
+    #[cfg(feature = "email")]
+    #[arg(long = "notify", global = true, value_name = "TARGET")]
+    pub notify: Option<NotifyTarget>,
+
+    /// SMTP server for --notify: smtps://host, or smtp://host:587 for
+    /// STARTTLS (login in EXA_SMTP_USER / EXA_SMTP_PASSWORD)
+    #[cfg(feature = "email")]
+    #[arg(long = "smtp-server", global = true, value_name = "URL")]
+    pub smtp_server: Option<String>,
+
+    /// Sender address for --notify (default: EXA_SMTP_USER)
+    #[cfg(feature = "email")]
+    #[arg(long = "smtp-from", global = true, value_name = "ADDRESS")]
+    pub smtp_from: Option<String>,
+
+    /// Also copy the output to the clipboard, as printed but without colors;
+    /// `--copy url` copies just the first URL in it
+    #[cfg(feature = "clipboard")]
+    #[arg(long = "copy", global = true, num_args = 0..=1, default_missing_value = "output", value_name = "WHAT")]
+    pub copy: Option<CopyTarget>,
+
+    /// Markdown document: search/find results as a numbered list of links with
+    /// quoted excerpts; answer/research text followed by a sources section
+    #[arg(long = "markdown", global = true, conflicts_with_all = ["json", "rss", "format"])]
+    pub markdown: bool,
+
+    /// Print search/find results as an RSS 2.0 feed, or Atom with `--rss atom`
+    #[arg(long = "rss", global = true, num_args = 0..=1, default_missing_value = "rss", conflicts_with = "json")]
+    pub rss: Option<FeedFormat>,
+
+    /// Show result images or favicons inline next to titles in kitty, iTerm2
+    /// and WezTerm (search and find; ignored in other terminals)
+    #[arg(long = "thumbnails", global = true)]
+    pub thumbnails: bool,
+
+    /// Wrap output to this many columns (default: terminal width; 0 disables)
+    #[arg(long = "width", global = true)]
+    pub width: Option<usize>,
+
+    /// Stable output for snapshot tests: results sorted by URL, whitespace
+    /// normalized, no colors, request ids, costs or timings
+    #[arg(long = "deterministic", global = true)]
+    pub deterministic: bool,
+
+    /// Randomly sample N of the retrieved results (retrieve more with -n)
+    #[arg(long = "sample", global = true)]
+    pub sample: Option<usize>,
+
+    /// Seed for --sample (same seed and results give the same sample)
+    #[arg(long = "seed", global = true, requires = "sample")]
+    pub seed: Option<u64>,
+
+    /// Also save every raw API response body in this directory, named
+    /// <UTC time>-<endpoint>-<hash>.json (.sse for streamed answers)
+    #[arg(long = "tee-raw", global = true, value_name = "DIR")]
+    pub tee_raw: Option<PathBuf>,
+
+    /// Record API responses in this directory and replay them on later runs
+    #[arg(long = "replay", global = true)]
+    pub replay: Option<PathBuf>,
+
+    /// Save/export results from do-not-ingest domains anyway
+    #[arg(long = "force", global = true)]
+    pub force: bool,
+
+    /// Store every fetched full text in the local archive (~/.local/share/exa/archive)
+    #[arg(long = "archive", global = true)]
+    pub archive: bool,
+
+    /// Add fetched texts and highlights to the full-text index searched by
+    /// `exa local` (set `index = true` in config.toml to always do so)
+    #[cfg(feature = "local-index")]
+    #[arg(long = "index", global = true)]
+    pub index: bool,
+
+    /// Upsert results into this SQLite database (table `results`, keyed by URL,
+    /// with query, fetched_at and text)
+    #[cfg(feature = "sqlite-export")]
+    #[arg(long = "export-sqlite", global = true, value_name = "PATH")]
+    pub export_sqlite: Option<PathBuf>,
+
+    /// Gzip large request bodies (sent with Content-Encoding: gzip)
+    #[arg(long = "compress-requests", global = true)]
+    pub compress_requests: bool,
+
+    /// Largest API response accepted, in MB; larger ones fail instead of
+    /// being buffered (default: 64)
+    #[arg(long = "max-response-size", global = true, default_value = "64", value_name = "MB")]
+    pub max_response_size: usize,
+
+    /// Max idle keep-alive connections kept per host (default: 8)
+    #[arg(long = "pool-max-idle", global = true, default_value = "8")]
+    pub pool_max_idle: usize,
+
+    /// Seconds an idle pooled connection is kept open (default: 90)
+    #[arg(long = "pool-idle-timeout", global = true, default_value = "90")]
+    pub pool_idle_timeout: u64,
+
+    /// API root (or EXA_BASE_URL; default: https://api.exa.ai)
+    #[arg(long = "base-url", global = true)]
+    pub base_url: Option<String>,
+
+    /// Exa API revision to speak: v1 (latest) or v0; pin it in config.toml
+    #[arg(long = "api-version", global = true, default_value_t = ApiVersion::LATEST)]
+    pub api_version: ApiVersion,
+
+    /// Pace bulk jobs: about one request a second with random jitter, slowing
+    /// down further while the API answers 429 or 5xx
+    #[arg(long = "polite", global = true)]
+    pub polite: bool,
+
+    /// Most requests each key may send, e.g. 5/s or 120/m; keys with capacity
+    /// left are preferred and requests wait rather than draw a 429. Usually
+    /// set in config.toml.
+    #[arg(long = "key-rate", global = true, value_name = "N/PERIOD")]
+    pub key_rate: Option<RateLimit>,
+
+    /// How the next API key is chosen: hybrid (default; in turn, skipping to
+    /// the least used), round_robin, least_used, weighted (by --key-weights)
+    /// or sticky (the last key until it is unavailable). Usually set in config.toml.
+    #[arg(long = "key-strategy", global = true, default_value = "hybrid")]
+    pub key_strategy: KeyStrategy,
+
+    /// Per-key weights for --key-strategy weighted, as <key id>=<weight> with
+    /// the key's end as `exa keys list` shows it (e.g. x7Q=80,k2m=20); keys
+    /// without one weigh 1, and 0 uses a key only as a fallback
+    #[arg(long = "key-weights", global = true, value_delimiter = ',')]
+    pub key_weights: Vec<KeyWeight>,
+
+    /// Tag appended to the User-Agent to identify the calling tool (or EXA_CLIENT_TAG)
+    #[arg(long = "client-tag", global = true)]
+    pub client_tag: Option<String>,
+
+    /// Only connect over IPv4
+    #[arg(long = "ipv4", global = true, conflicts_with = "ipv6")]
+    pub ipv4: bool,
+
+    /// Only connect over IPv6
+    #[arg(long = "ipv6", global = true)]
+    pub ipv6: bool,
+
+    /// Resolve a host to a fixed address, like curl (host:ip or host:port:ip, repeatable)
+    #[arg(long = "resolve", global = true)]
+    pub resolve: Vec<String>,
+}
+
+/// Result formats selected with --format
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Vim quickfix / errorformat-compatible locations
+    Quickfix,
+    /// One JSON object per line per result
+    Jsonl,
+    /// Org-mode headings with a property drawer
+    Org,
+    /// Markdown notes with YAML front-matter
+    Obsidian,
+    /// OpenAI function-calling tool definitions (tools-schema)
+    Openai,
+    /// Anthropic tool-use definitions (tools-schema)
+    Anthropic,
+    /// GitHub Actions workflow commands (::notice etc.), one per result
+    GhAnnotations,
+    /// A standalone, styled HTML page (the body of --notify email digests)
+    Html,
+}
+
+/// Severity of --format gh-annotations lines
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum AnnotationLevel {
+    Notice,
+    Warning,
+    Error,
+}
+
+impl AnnotationLevel {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AnnotationLevel::Notice => "notice",
+            AnnotationLevel::Warning => "warning",
+            AnnotationLevel::Error => "error",
+        }
+    }
+}
+
+/// Flag bundles selected with --preset
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum Preset {
+    /// Token-efficient output for agents
+    Agent,
+}
+
+/// Fill in the settings of --preset (or EXA_PRESET) that weren't set explicitly
+pub fn apply_preset(cli: &mut Cli) -> Result<()> {
+    let preset = match cli.preset {
+        Some(preset) => preset,
+        None => match vars::get("EXA_PRESET").filter(|p| !p.trim().is_empty()) {
+            Some(name) => <Preset as clap::ValueEnum>::from_str(name.trim(), true)
+                .map_err(|_| anyhow::anyhow!("Unknown EXA_PRESET '{}' (expected agent)", name))?,
+            None => return Ok(()),
+        },
+    };
+    match preset {
+        Preset::Agent => {
+            cli.compact = true;
+            if cli.format.is_none() && !cli.json && !cli.jsonl && !cli.tsv && !cli.csv {
+                cli.format = Some(OutputFormat::Jsonl);
+            }
+            cli.fields.get_or_insert_with(|| "title,url,snippet".to_string());
+            cli.max_chars.get_or_insert(200);
+            // Snippets come from highlights unless page text was asked for
+            if !cli.content && cli.highlights.is_none() {
+                cli.highlights = Some(200);
+            }
+            colored::control::set_override(false);
+        }
+    }
+    Ok(())
+}
+
+/// A --watch interval: a number with s, m, h or d
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Interval(pub std::time::Duration);
+
+impl Interval {
+    /// Shorter intervals would mostly spend credits on unchanged results
+    const MIN_SECS: u64 = 10;
+}
+
+/// Seconds in a number with s, m, h or d
+fn parse_secs(s: &str) -> std::result::Result<u64, String> {
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (n, unit) = s.split_at(split);
+    let n: u64 = n.parse().map_err(|_| format!("'{}' is not an interval such as 30s, 15m or 2h", s))?;
+    let unit_secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86400,
+        _ => return Err(format!("'{}' needs a unit: s, m, h or d", s)),
+    };
+    n.checked_mul(unit_secs).ok_or_else(|| format!("'{}' is too long", s))
+}
+
+impl std::str::FromStr for Interval {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let secs = parse_secs(s.trim())?;
+        if secs < Self::MIN_SECS {
+            return Err(format!("the interval must be at least {}s", Self::MIN_SECS));
+        }
+        Ok(Self(std::time::Duration::from_secs(secs)))
+    }
+}
+
+impl std::fmt::Display for Interval {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let secs = self.0.as_secs();
+        let (n, unit) = [(86400, "d"), (3600, "h"), (60, "m")]
+            .into_iter()
+            .find(|(unit_secs, _)| secs.is_multiple_of(*unit_secs))
+            .map_or((secs, "s"), |(unit_secs, unit)| (secs / unit_secs, unit));
+        write!(f, "{}{}", n, unit)
+    }
+}
+
+/// The most --jitter delays a run: an interval as for --watch, but with no
+/// lower bound (0 turns it off)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Jitter(pub std::time::Duration);
+
+impl std::str::FromStr for Jitter {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let secs = match s.trim() {
+            "0" => 0,
+            s => parse_secs(s)?,
+        };
+        Ok(Self(std::time::Duration::from_secs(secs)))
+    }
+}
+
+/// Where --notify sends results
+#[cfg(feature = "email")]
+#[derive(Debug, Clone, PartialEq)]
+pub enum NotifyTarget {
+    /// `email:a@example.com,b@example.com`
+    Email(Vec<String>),
+}
+
+#[cfg(feature = "email")]
+impl std::str::FromStr for NotifyTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let Some(addresses) = s.trim().strip_prefix("email:") else {
+            return Err(format!("'{}' is not a target such as email:me@example.com", s));
+        };
+        let addresses: Vec<String> =
+            addresses.split(',').map(|a| a.trim().to_string()).filter(|a| !a.is_empty()).collect();
+        if addresses.is_empty() {
+            return Err("email: needs an address, e.g. email:me@example.com".to_string());
+        }
+        if let Some(bad) = addresses.iter().find(|a| !a.contains('@')) {
+            return Err(format!("'{}' is not an email address", bad));
+        }
+        Ok(Self::Email(addresses))
+    }
+}
+
+/// What --source-fields shows for each answer or research source
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum SourceField {
+    Url,
+    Title,
+    Date,
+}
+
+impl SourceField {
+    /// Key of the field in citation JSON
+    pub fn json_key(self) -> &'static str {
+        match self {
+            SourceField::Url => "url",
+            SourceField::Title => "title",
+            SourceField::Date => "publishedDate",
+        }
+    }
+}
+
+/// What --pick prints for the chosen result
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum PickField {
+    Url,
+    Content,
+}
+
+#[cfg(feature = "clipboard")]
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum CopyTarget {
+    Output,
+    Url,
+}
+
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum IssueTracker {
+    Jira,
+    Linear,
+}
+
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum FeedFormat {
+    Rss,
+    Atom,
+}
+
+#[derive(Subcommand)]
+pub enum Commands {
+    /// Search the web
+    Search {
+        /// Search query
+        query: Vec<String>,
+    },
+    /// Semantic similarity search
+    Find {
+        /// Query or URL for similarity search
+        query: Vec<String>,
+
+        /// Leave out other pages from the seed URL's site (--exclude-source-domain=false to keep them)
+        #[arg(long = "exclude-source-domain", default_value_t = true, action = clap::ArgAction::Set,
+              num_args = 0..=1, default_missing_value = "true")]
+        exclude_source_domain: bool,
+    },
+    /// Extract content from one or more URLs
+    Content {
+        /// URLs to extract content from
+        #[arg(required = true)]
+        urls: Vec<String>,
+
+        /// Compare against the previously cached text and print a unified diff
+        #[arg(long = "diff")]
+        diff: bool,
+    },
+    /// Get AI answer with sources
+    Answer {
+        /// Question to answer
+        query: Vec<String>,
+
+        /// Keep asking follow-up questions (read from stdin) grounded in the
+        /// earlier answers and their sources; an empty line or "exit" ends it
+        #[arg(long = "follow-up")]
+        follow_up: bool,
+
+        /// With --follow-up, save the conversation (.json, else Markdown)
+        #[arg(long = "transcript", requires = "follow_up")]
+        transcript: Option<PathBuf>,
+    },
+    /// Deep AI research (async, multi-step)
+    Research {
+        /// Research instructions
+        query: Vec<String>,
+
+        /// Write the report as Markdown into this directory of a git repository
+        /// and commit it, with the query and cost in the commit message
+        #[arg(long = "commit-notes", value_name = "DIR")]
+        commit_notes: Option<PathBuf>,
+
+        /// With --commit-notes, commit onto this branch (created from HEAD if
+        /// missing) without checking it out
+        #[arg(long = "branch", requires = "commit_notes")]
+        branch: Option<String>,
+    },
+
+    #[cfg(feature = "eval")]
+    /// Score search quality against expected URLs (hit@k and MRR, k = -n)
+    Eval {
+        /// YAML file of cases: [{query: ..., expect: [url patterns]}]
+        #[arg(long = "cases")]
+        cases: PathBuf,
+    },
+
+    /// Read a Netscape bookmarks export (HTML); with --find-similar, suggest
+    /// new sources for each bookmark (-n per bookmark), grouped by folder
+    ImportBookmarks {
+        /// Bookmarks HTML file
+        path: PathBuf,
+
+        /// Run find-similar for each bookmark
+        #[arg(long = "find-similar")]
+        find_similar: bool,
+
+        /// Stop before spending more than this many dollars
+        #[arg(long = "budget")]
+        budget: Option<f64>,
+
+        /// Maximum number of API requests to make (default: 50)
+        #[arg(long = "max-requests", default_value = "50")]
+        max_requests: usize,
+    },
+
+    /// Send one minimal request per endpoint and report pass/fail for each,
+    /// e.g. after an upgrade or an API change
+    Smoke {
+        /// Spend at most this many dollars; endpoints that could pass it are skipped
+        #[arg(long = "budget")]
+        budget: f64,
+    },
+
+    /// Set up API keys and a starter config.toml
+    Init {
+        /// Save the keys without checking them against the API
+        #[arg(long)]
+        skip_validation: bool,
+
+        /// Only create an ed25519 key pair for signing the request log
+        /// (EXA_LOG_REQUESTS=1); API keys and config.toml are left alone
+        #[cfg(feature = "audit")]
+        #[arg(long)]
+        audit_key: bool,
+    },
+
+    /// Manage the API key pool
+    Keys {
+        #[command(subcommand)]
+        action: KeysCommand,
+    },
+
+    /// Show API key status, cooldowns, and usage
+    Status,
+
+    /// Reset cooldowns and usage statistics
+    Reset,
+
+    /// Browse the local archive of fetched pages (see --archive)
+    Archive {
+        #[command(subcommand)]
+        action: ArchiveCommand,
+    },
+
+    #[cfg(feature = "local-index")]
+    /// Full-text search over archived and --index'ed pages, offline and free
+    #[command(alias = "local")]
+    LocalSearch {
+        /// Search terms (all must match; supports "phrases" and -exclusions)
+        query: Vec<String>,
+    },
+
+    /// Show one result from the last search/find (for fzf preview windows)
+    Preview {
+        /// Result number as printed by --fzf
+        id: usize,
+    },
+
+    /// List past queries, newest last; terms filter by query text
+    History {
+        /// Only entries whose query contains all of these terms
+        terms: Vec<String>,
+
+        /// Only entries run by this command (search, find, content, answer, research)
+        #[arg(long)]
+        command: Option<String>,
+
+        /// Show at most this many entries
+        #[arg(long, default_value = "20")]
+        limit: usize,
+
+        /// Delete the history
+        #[arg(long)]
+        clear: bool,
+    },
+
+    /// Run a past query again by its number in `exa history`; arguments after
+    /// the number are appended (e.g. `exa rerun 12 -n 10`)
+    Rerun {
+        /// Entry number as printed by `exa history`
+        id: usize,
+
+        /// Extra arguments for the rerun
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        extra: Vec<String>,
+    },
+
+    /// Compare two result sets: `exa diff OLD.json NEW.json` (`--json` output
+    /// of search/find, or `last` for the last printed results), or
+    /// `exa diff --query "..."` to run a search again and compare it with its
+    /// last cached run
+    Diff {
+        /// Two result files to compare, oldest first
+        #[arg(num_args = 2, value_names = ["OLD", "NEW"], required_unless_present = "query", conflicts_with = "query")]
+        files: Vec<String>,
+
+        /// Search again (with the usual search flags) and compare with the cached run
+        #[arg(long)]
+        query: Option<String>,
+    },
+
+    /// Save a query and its flags under a name, e.g.
+    /// `exa save nightly-ai "AI chip startups" --category company -n 20`
+    Save {
+        /// Name to run it by (letters, digits, '-', '_', '.')
+        name: String,
+
+        /// Replace a saved search of the same name
+        #[arg(long = "replace")]
+        replace: bool,
+
+        /// The query and flags, optionally starting with a command (default: search)
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true, required = true)]
+        args: Vec<String>,
+    },
+
+    /// Run a saved search; arguments after the name are appended
+    Run {
+        /// Name given to `exa save`
+        name: String,
+
+        /// Extra arguments for this run
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        extra: Vec<String>,
+    },
+
+    /// Manage saved searches
+    Saved {
+        #[command(subcommand)]
+        action: SavedCommand,
+    },
+
+    /// Print LLM tool definitions for search, content, answer and research
+    /// (--format openai|anthropic, default openai)
+    ToolsSchema,
+
+    /// Run one tool call read from stdin ({"name": ..., "arguments": {...}},
+    /// as published by tools-schema) and print the result as JSON
+    ToolCall,
+
+    /// Serve copied text from stdin as the clipboard until something else is
+    /// copied (started by --copy on Linux)
+    #[cfg(all(feature = "clipboard", target_os = "linux"))]
+    #[command(hide = true)]
+    ClipboardHold,
+
+    #[cfg(feature = "serve")]
+    /// Serve the tools-schema tools to agent hosts over stdio
+    Serve {
+        /// Speak the Model Context Protocol (JSON-RPC over stdin/stdout)
+        #[arg(long = "mcp", required = true)]
+        mcp: bool,
+    },
+
+    #[cfg(feature = "export")]
+    /// Export results from the last search/find to a reference manager
+    Export {
+        /// Zotero (via the running desktop app's connector) or a CSL-JSON file
+        #[arg(long = "to", value_enum)]
+        to: ExportTarget,
+
+        /// Result numbers to export (comma-separated; default: all)
+        #[arg(long = "ids", value_delimiter = ',')]
+        ids: Vec<usize>,
+
+        /// Write CSL-JSON here instead of stdout
+        #[arg(short = 'o', long = "output")]
+        output: Option<PathBuf>,
+    },
+
+    #[cfg(feature = "state-bundle")]
+    /// Export local state (key state, logs, settings) to a .tar.zst bundle
+    ExportState {
+        /// Bundle path, e.g. exa-state.tar.zst
+        path: PathBuf,
+
+        /// Also include the archive and local index (~/.local/share/exa)
+        #[arg(long = "with-archive")]
+        with_archive: bool,
+
+        /// Also include the API keys, encrypted with a passphrase you are asked for
+        #[arg(long = "with-keys")]
+        with_keys: bool,
+    },
+
+    #[cfg(feature = "state-bundle")]
+    /// Restore local state from a bundle created by export-state; keys in
+    /// the bundle are added to the pool (asking for their passphrase)
+    ImportState {
+        /// Bundle path
+        path: PathBuf,
+
+        /// Overwrite files that already exist
+        #[arg(long = "overwrite")]
+        overwrite: bool,
+    },
+
+    #[cfg(feature = "audit")]
+    /// Check the signed request log for tampering
+    Audit {
+        #[command(subcommand)]
+        action: AuditCommand,
+    },
+
+    /// Manage the request log (requests.log, written with EXA_LOG_REQUESTS=1)
+    Log {
+        #[command(subcommand)]
+        action: LogCommand,
+    },
+
+    /// Manage the response cache
+    Cache {
+        #[command(subcommand)]
+        action: CacheCommand,
+    },
+
+    /// Inspect the effective configuration
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommand,
+    },
+
+    /// Build and inspect curated collections (Exa Websets)
+    Websets {
+        #[command(subcommand)]
+        action: WebsetsCommand,
+    },
+
+    /// Run a saved watch in the background as a systemd user service or
+    /// launchd agent
+    Service {
+        #[command(subcommand)]
+        action: ServiceCommand,
+    },
+
+    /// List or stop the watches (--watch, --cron) running on this machine
+    Watch {
+        #[command(subcommand)]
+        action: WatchCommand,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ServiceCommand {
+    /// Install and start a service running a saved search that has --watch or
+    /// --cron (`exa run <name>`), started at login and restarted on failure
+    Install {
+        /// Saved search name (see `exa saved list`)
+        name: String,
+
+        /// Print the systemd unit or launchd plist instead of installing it
+        #[arg(long = "print")]
+        print: bool,
+    },
+    /// Show whether the service for a saved search (or each one) is running
+    Status { name: Option<String> },
+    /// Stop the service and remove it
+    Uninstall { name: String },
+}
+
+#[derive(Subcommand)]
+pub enum WatchCommand {
+    /// List running watches: process id, schedule, start time and command line
+    List,
+    /// Stop a running watch by process id
+    Stop {
+        /// Process id, from `exa watch list`
+        #[arg(required_unless_present = "all", conflicts_with = "all")]
+        pid: Option<u32>,
+
+        /// Stop every running watch
+        #[arg(long = "all")]
+        all: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum LogCommand {
+    /// Replace the query text in existing entries with its HMAC. Signed
+    /// entries need --include-signed, and then no longer pass `exa audit verify`
+    Redact {
+        /// Redact signed entries too
+        #[arg(long = "include-signed")]
+        include_signed: bool,
+    },
+}
+
+#[cfg(feature = "audit")]
+#[derive(Subcommand)]
+pub enum AuditCommand {
+    /// Verify every entry's signature and the hash chain between entries
+    /// (requests.log.1, then requests.log); exits 1 on the first failure
+    Verify {
+        /// Public key in hex (default: audit.pub in the config directory);
+        /// pass the one recorded at `exa init --audit-key` to rule out a replaced key
+        #[arg(long)]
+        public_key: Option<String>,
+
+        /// Verify this log file instead
+        #[arg(long)]
+        file: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ArchiveCommand {
+    /// List archived pages, newest first (filter with --domain)
+    List,
+    /// Find archived pages containing all the given terms
+    Search {
+        /// Search terms
+        terms: Vec<String>,
+    },
+    /// Print an archived page by hash (prefix)
+    Show {
+        /// Content hash or unique prefix
+        hash: String,
+    },
+}
+
+#[cfg(feature = "export")]
+/// Destinations for `exa export`
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum ExportTarget {
+    Zotero,
+    CslJson,
+}
+
+#[derive(Subcommand)]
+pub enum CacheCommand {
+    /// Pre-execute searches so later runs hit a warm cache
+    Warm {
+        /// File with one query per line ('-' for stdin, '#' starts a comment)
+        #[arg(long = "from", required_unless_present = "saved", conflicts_with = "saved")]
+        from: Option<String>,
+
+        /// Warm every saved search (`exa save`), each with its own flags
+        #[arg(long = "saved")]
+        saved: bool,
+
+        /// Stop before spending more than this many dollars
+        #[arg(long = "budget")]
+        budget: Option<f64>,
+
+        /// Maximum number of API requests to make (default: 50)
+        #[arg(long = "max-requests", default_value = "50")]
+        max_requests: usize,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum WebsetsCommand {
+    /// Create a webset from a search
+    Create {
+        /// What the webset should contain
+        query: Vec<String>,
+
+        /// Number of items to find
+        #[arg(long = "count", default_value = "10")]
+        count: usize,
+
+        /// Entity type: company, person, article, research_paper
+        #[arg(long = "entity")]
+        entity: Option<String>,
+
+        /// Criterion every item must meet (repeatable)
+        #[arg(long = "criterion")]
+        criteria: Vec<String>,
+    },
+    /// List websets, newest first
+    List {
+        /// Maximum number to list
+        #[arg(long = "limit", default_value = "25")]
+        limit: usize,
+
+        /// Follow pagination to the end
+        #[arg(long = "all")]
+        all: bool,
+    },
+    /// Show a webset and the progress of its searches
+    Get {
+        id: String,
+    },
+    /// Delete a webset
+    Delete {
+        id: String,
+    },
+    /// Add an enrichment: a field extracted for every item of a webset
+    Enrich {
+        /// Webset ID
+        id: String,
+
+        /// What to extract, e.g. "Number of employees"
+        #[arg(long = "description", required = true)]
+        description: String,
+
+        /// Value type of the field
+        #[arg(long = "format", value_enum, default_value = "text")]
+        format: websets::EnrichmentFormat,
+
+        /// Allowed value for --format options (repeatable)
+        #[arg(long = "option")]
+        options: Vec<String>,
+
+        /// Poll until the enrichment has finished
+        #[arg(long = "wait")]
+        wait: bool,
+    },
+    /// Track enrichment tasks
+    Enrichments {
+        #[command(subcommand)]
+        action: EnrichmentsCommand,
+    },
+    /// List the items found for a webset
+    Items {
+        id: String,
+
+        /// Maximum number of items
+        #[arg(long = "limit", default_value = "25")]
+        limit: usize,
+
+        /// Follow pagination to the end
+        #[arg(long = "all")]
+        all: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum EnrichmentsCommand {
+    /// List a webset's enrichments and their status
+    List {
+        /// Webset ID
+        id: String,
+    },
+    /// Show one enrichment
+    Get {
+        /// Webset ID
+        id: String,
+
+        /// Enrichment ID
+        enrichment_id: String,
+
+        /// Poll until the enrichment has finished
+        #[arg(long = "wait")]
+        wait: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum SavedCommand {
+    /// List saved searches with their command lines
+    List,
+    /// Delete a saved search
+    Remove { name: String },
+}
+
+#[derive(Subcommand)]
+pub enum ConfigCommand {
+    /// Print the effective configuration as environment variable assignments
+    /// (or, with --json, as an EXA_CONFIG_JSON blob)
+    RenderEnv {
+        /// Include API keys instead of hiding them
+        #[arg(long = "show-secrets")]
+        show_secrets: bool,
+    },
+    /// Print every setting in config.toml
+    List,
+    /// Print one setting from config.toml
+    Get {
+        /// Global flag name, e.g. num or cache-ttl; prefix a command
+        /// (find.type) to set it for that command only
+        key: String,
+    },
+    /// Save a default for a global flag in config.toml (lists are comma-separated)
+    Set {
+        /// Global flag name, e.g. num or cache-ttl; prefix a command
+        /// (find.type) to set it for that command only
+        key: String,
+        value: String,
+    },
+    /// Remove a setting from config.toml
+    Unset {
+        /// Global flag name, e.g. num or cache-ttl; prefix a command
+        /// (find.type) to set it for that command only
+        key: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum KeysCommand {
+    /// Add a key to the pool, saved where the pool was loaded from
+    Add {
+        /// API key; omit to enter it at a prompt instead of the shell history
+        key: Option<String>,
+    },
+    /// Remove a key by its index in `exa keys list`
+    Remove { index: usize },
+    /// List the keys (masked) with their state and usage
+    List,
+    /// Check every key against the API now, instead of waiting for the daily check
+    Test,
+    /// Show or set a key's budgets; a key over any budget is skipped until
+    /// the day or month (UTC) rolls over
+    Budget {
+        /// Key index in `exa keys list`
+        index: usize,
+        #[arg(long)]
+        daily_requests: Option<u64>,
+        #[arg(long)]
+        monthly_requests: Option<u64>,
+        /// Dollars per day, from the costs the API reports
+        #[arg(long)]
+        daily_dollars: Option<f64>,
+        /// Dollars per month, from the costs the API reports
+        #[arg(long)]
+        monthly_dollars: Option<f64>,
+        /// Remove the key's budgets
+        #[arg(long, conflicts_with_all = ["daily_requests", "monthly_requests", "daily_dollars", "monthly_dollars"])]
+        clear: bool,
+    },
+    /// Save keys in the OS keyring (prompts when none are given); they are
+    /// used ahead of EXA_API_KEYS / EXA_API_KEY
+    #[cfg(feature = "keyring")]
+    Store {
+        /// API keys; omit to enter them at a prompt instead of the shell history
+        keys: Vec<String>,
+    },
+    /// Remove the keys from the OS keyring
+    #[cfg(feature = "keyring")]
+    Forget,
+}
+
+/// Resolve the client tag from --client-tag or EXA_CLIENT_TAG
+pub fn client_tag(cli: &Cli) -> Option<String> {
+    cli.client_tag
+        .clone()
+        .or_else(|| vars::get("EXA_CLIENT_TAG"))
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+}
+
+/// User-Agent header: exa-cli/<version> (<os>) [tag]
+pub fn user_agent(tag: Option<&str>) -> String {
+    let base = format!("exa-cli/{} ({})", VERSION, std::env::consts::OS);
+    match tag {
+        Some(tag) => format!("{} {}", base, tag),
+        None => base,
+    }
+}
+
+/// Parse a --resolve override: `host:ip` or curl-style `host:port:ip`.
+/// IPv6 addresses may be bracketed. The port only matters for display;
+/// reqwest always connects to the URL's port.
+pub fn parse_resolve(spec: &str) -> Result<(String, SocketAddr)> {
+    let parse_ip = |s: &str| s.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>();
+
+    let (host, rest) = spec
+        .split_once(':')
+        .with_context(|| format!("Invalid --resolve '{}': expected host:ip", spec))?;
+    if host.is_empty() {
+        bail!("Invalid --resolve '{}': missing host", spec);
+    }
+
+    if let Ok(ip) = parse_ip(rest) {
+        return Ok((host.to_string(), SocketAddr::new(ip, 443)));
+    }
+    if let Some((port, ip)) = rest.split_once(':') {
+        if let (Ok(port), Ok(ip)) = (port.parse::<u16>(), parse_ip(ip)) {
+            return Ok((host.to_string(), SocketAddr::new(ip, port)));
+        }
+    }
+    bail!("Invalid --resolve '{}': expected host:ip or host:port:ip", spec)
+}
+
+/// Build the shared HTTP client, tuned for many sequential/parallel calls
+pub fn build_http_client(cli: &Cli) -> Result<reqwest::Client> {
+    let mut builder = reqwest::Client::builder()
+        .user_agent(user_agent(client_tag(cli).as_deref()))
+        .pool_max_idle_per_host(cli.pool_max_idle)
+        .pool_idle_timeout(std::time::Duration::from_secs(cli.pool_idle_timeout))
+        .tcp_keepalive(std::time::Duration::from_secs(60))
+        .tcp_nodelay(true)
+        .http2_adaptive_window(true);
+
+    // Binding to the unspecified address of a family restricts connections to it
+    if cli.ipv4 {
+        builder = builder.local_address(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+    } else if cli.ipv6 {
+        builder = builder.local_address(IpAddr::V6(Ipv6Addr::UNSPECIFIED));
+    }
+
+    for spec in &cli.resolve {
+        let (host, addr) = parse_resolve(spec)?;
+        if cli.verbose > 0 {
+            diag!("Resolving {} to {}", host, addr.ip());
+        }
+        builder = builder.resolve(&host, addr);
+    }
+
+    builder.build().context("Failed to build HTTP client")
+}
+
+/// The API root: --base-url, EXA_BASE_URL or the default, without a trailing slash
+pub fn base_url(cli: &Cli) -> String {
+    let url = cli.base_url.clone().or_else(|| vars::get("EXA_BASE_URL")).unwrap_or_default();
+    match url.trim().trim_end_matches('/') {
+        "" => DEFAULT_BASE_URL.to_string(),
+        url => url.to_string(),
+    }
+}
+
+/// The key manager with the key rotation flags applied
+pub fn load_key_manager(cli: &Cli) -> Result<KeyManager> {
+    let mut key_manager = KeyManager::new(cli.verbose > 0)?;
+    key_manager.client_tag = client_tag(cli);
+    key_manager.set_rate_limit(cli.key_rate);
+    key_manager.set_strategy(cli.key_strategy, cli.key_weights.clone())?;
+    key_manager.set_log_queries(crate::config::include_queries());
+    Ok(key_manager)
+}
+
+/// The API client for `key_manager`, with keys revalidated if their state
+/// is stale and the transport flags applied
+pub async fn build_client(cli: &Cli, mut key_manager: KeyManager) -> Result<ExaClient> {
+    let http_client = build_http_client(cli)?;
+    key_manager.validate_keys_if_stale(&http_client, &base_url(cli)).await?;
+
+    let mut client = ExaClient::new(key_manager, http_client);
+    client.compress_requests = cli.compress_requests;
+    client.verbose = cli.verbose;
+    client.max_response_bytes = cli.max_response_size.saturating_mul(1024 * 1024);
+    client.replay_dir = cli.replay.clone();
+    client.tee_dir = cli.tee_raw.clone();
+    client.api_version = cli.api_version;
+    if cli.polite {
+        client.pacer = Some(Pacer::default());
+    }
+    client.base_url = base_url(cli);
+    Ok(client)
+}
+
+pub fn response_cache(cli: &Cli) -> ResponseCache {
+    ResponseCache { backend: cli.cache_backend.clone(), ttl_minutes: cli.cache_ttl, verbose: cli.verbose > 0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_user_agent() {
+        let os = std::env::consts::OS;
+        assert_eq!(user_agent(None), format!("exa-cli/{} ({})", VERSION, os));
+        assert_eq!(
+            user_agent(Some("acme-research-bot")),
+            format!("exa-cli/{} ({}) acme-research-bot", VERSION, os)
+        );
+    }
+
+    #[test]
+    fn test_parse_resolve() {
+        let (host, addr) = parse_resolve("api.exa.ai:1.2.3.4").unwrap();
+        assert_eq!(host, "api.exa.ai");
+        assert_eq!(addr.ip().to_string(), "1.2.3.4");
+
+        let (_, addr) = parse_resolve("api.exa.ai:8443:1.2.3.4").unwrap();
+        assert_eq!(addr.port(), 8443);
+
+        let (_, addr) = parse_resolve("api.exa.ai:[2606:4700::1]").unwrap();
+        assert!(addr.is_ipv6());
+
+        assert!(parse_resolve("api.exa.ai").is_err());
+        assert!(parse_resolve(":1.2.3.4").is_err());
+        assert!(parse_resolve("api.exa.ai:not-an-ip").is_err());
+    }
+
+    #[test]
+    fn test_interval() {
+        let parse = |s: &str| s.parse::<Interval>().map(|i| i.0.as_secs());
+        assert_eq!(parse("15m"), Ok(900));
+        assert_eq!(parse("30s"), Ok(30));
+        assert_eq!(parse("2h"), Ok(7200));
+        assert_eq!(parse("1d"), Ok(86400));
+        assert!(parse("15").is_err());
+        assert!(parse("5s").is_err());
+        assert!(parse("m").is_err());
+        assert_eq!("90m".parse::<Interval>().unwrap().to_string(), "90m");
+        assert_eq!("120m".parse::<Interval>().unwrap().to_string(), "2h");
+
+        // A jitter has no floor
+        let jitter = |s: &str| s.parse::<Jitter>().map(|j| j.0.as_secs());
+        assert_eq!(jitter("5s"), Ok(5));
+        assert_eq!(jitter("0"), Ok(0));
+        assert_eq!(jitter("0s"), Ok(0));
+        assert_eq!(jitter("10m"), Ok(600));
+        assert!(jitter("5").is_err());
+        let cli = Cli::try_parse_from(["exa", "search", "q", "--watch", "1m", "--jitter", "5s"]).unwrap();
+        assert_eq!(cli.jitter, Some(Jitter(std::time::Duration::from_secs(5))));
+    }
+
+    #[test]
+    fn test_preset_agent() {
+        let mut cli = Cli::parse_from(["exa", "search", "q", "--preset", "agent", "--max-chars", "80"]);
+        apply_preset(&mut cli).unwrap();
+        colored::control::unset_override();
+        assert!(cli.compact);
+        assert!(cli.format == Some(OutputFormat::Jsonl));
+        assert_eq!(cli.fields.as_deref(), Some("title,url,snippet"));
+        assert_eq!((cli.max_chars, cli.highlights), (Some(80), Some(200)));
+
+        let mut cli = Cli::parse_from(["exa", "search", "q", "--preset", "agent", "--tsv", "--content"]);
+        apply_preset(&mut cli).unwrap();
+        colored::control::unset_override();
+        assert!(cli.format.is_none() && cli.highlights.is_none());
+    }
+}
@@ -0,0 +1,887 @@
+//! HTTP client for the Exa API with key rotation, retries and response replay
+
+use crate::api::{
+    AnswerCitation, AnswerRequest, AnswerResponse, ContentsConfig, CostDollars, FindSimilarRequest, GetContentsRequest,
+    ResearchCreateRequest, ResearchCreateResponse, ResearchStatusResponse, SearchRequest, SearchResponse,
+};
+use crate::api_version::ApiVersion;
+use crate::key_manager::{KeyManager, NextKey};
+use crate::model::ResearchStatus;
+use crate::polite::Pacer;
+use crate::transport::{self, HttpTransport, Transport};
+use crate::{compression, diag, fingerprint, sse, warnings, websets};
+use anyhow::{bail, Context, Result};
+use chrono::Utc;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs;
+use std::io::Read;
+use std::path::PathBuf;
+
+/// Default for [`ExaClient::max_response_bytes`]
+pub const DEFAULT_MAX_RESPONSE_BYTES: usize = 64 * 1024 * 1024;
+
+/// The Exa API root
+pub const DEFAULT_BASE_URL: &str = "https://api.exa.ai";
+
+/// Exa API client. Requests rotate across the keys in its [`KeyManager`],
+/// retrying on 429 with another key.
+pub struct ExaClient {
+    /// Shared HTTP client (connection pool), also used for non-API fetches
+    pub client: reqwest::Client,
+    /// Carries the API requests; reqwest over `client` unless replaced
+    pub transport: Box<dyn Transport>,
+    pub key_manager: KeyManager,
+    /// API root, `https://api.exa.ai` by default
+    pub base_url: String,
+    /// API revision requests are translated for
+    pub api_version: ApiVersion,
+    /// Gzip large request bodies
+    pub compress_requests: bool,
+    /// 1: log body sizes to stderr, 2: also per-request timings
+    pub verbose: u8,
+    /// Largest response body accepted, compressed or decoded
+    pub max_response_bytes: usize,
+    stats: ConnStats,
+    /// Record responses here and replay them on later requests with the same
+    /// method, path and body
+    pub replay_dir: Option<PathBuf>,
+    /// Recordings written by this run (re-fetched rather than replayed, so
+    /// polls record their final state)
+    recorded: HashSet<PathBuf>,
+    /// Spaces out requests and backs off on 429/5xx (--polite)
+    pub pacer: Option<Pacer>,
+    /// Save a copy of every successful response body here (--tee-raw)
+    pub tee_dir: Option<PathBuf>,
+}
+
+/// Connection-level counters reported under -vv
+#[derive(Default)]
+struct ConnStats {
+    requests: usize,
+    http2: usize,
+    total_ms: u128,
+    remotes: HashSet<std::net::SocketAddr>,
+}
+
+/// A successful API response along with the key and server request ID that produced it
+pub struct ApiResponse<T> {
+    pub body: T,
+    /// Index of the key that made the request
+    pub key_idx: usize,
+    pub request_id: Option<String>,
+    /// Served from a recording rather than the API
+    pub replayed: bool,
+}
+
+/// Extract the server-assigned request ID from response headers
+fn request_id_from(headers: &reqwest::header::HeaderMap) -> Option<String> {
+    ["x-request-id", "request-id", "x-amzn-requestid"]
+        .iter()
+        .find_map(|name| headers.get(*name))
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string())
+}
+
+/// The free text of a request body, for the request log: a query or research instructions
+fn request_query(body: &serde_json::Value) -> Option<&str> {
+    body.get("query").or_else(|| body.get("instructions")).and_then(|q| q.as_str())
+}
+
+/// A response accepted by [`ExaClient::dispatch`], its body still unread
+struct Sent {
+    resp: transport::Response,
+    idx: usize,
+    request_id: Option<String>,
+    /// Content-Encoding of the body
+    encoding: Option<String>,
+}
+
+/// The error for a response over --max-response-bytes
+fn too_large(label: &str, limit: usize, request_id: &Option<String>) -> anyhow::Error {
+    anyhow::anyhow!(
+        "{} response is larger than the {} limit{}",
+        label,
+        compression::format_bytes(limit),
+        request_id_suffix(request_id)
+    )
+}
+
+/// Format a request ID for inclusion in error messages (empty if unknown)
+fn request_id_suffix(request_id: &Option<String>) -> String {
+    request_id
+        .as_ref()
+        .map(|id| format!(" [request-id: {}]", id))
+        .unwrap_or_default()
+}
+
+impl ExaClient {
+    pub fn new(key_manager: KeyManager, client: reqwest::Client) -> Self {
+        Self {
+            transport: Box::new(HttpTransport(client.clone())),
+            client,
+            key_manager,
+            base_url: DEFAULT_BASE_URL.to_string(),
+            api_version: ApiVersion::LATEST,
+            compress_requests: false,
+            verbose: 0,
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+            stats: ConnStats::default(),
+            replay_dir: None,
+            recorded: HashSet::new(),
+            pacer: None,
+            tee_dir: None,
+        }
+    }
+
+    /// Send API requests through `transport` instead, e.g. a [`transport::MockTransport`]
+    pub fn with_transport(mut self, transport: impl Transport + 'static) -> Self {
+        self.transport = Box::new(transport);
+        self
+    }
+
+    /// Print connection-level stats (only under -vv)
+    pub fn print_conn_stats(&self) {
+        if self.verbose < 2 || self.stats.requests == 0 {
+            return;
+        }
+        diag!(
+            "connections: {} requests, {} over HTTP/2, {} remote address(es), avg {}ms",
+            self.stats.requests,
+            self.stats.http2,
+            self.stats.remotes.len(),
+            self.stats.total_ms / self.stats.requests as u128
+        );
+    }
+
+    /// Send a request with key rotation and retry on 429.
+    /// `label` names the operation in errors, `log_cmd` in the request log.
+    /// When `key_idx` is set, that key is used for every attempt.
+    pub async fn send<B: Serialize, R: DeserializeOwned>(
+        &mut self,
+        method: reqwest::Method,
+        path: &str,
+        body: Option<&B>,
+        label: &str,
+        log_cmd: &str,
+        key_idx: Option<usize>,
+    ) -> Result<ApiResponse<R>> {
+        let version = self.api_version;
+        let body = self.request_body(path, body)?;
+        let replay_path = self.replay_path(&method, path, body.as_ref(), "json")?;
+        if let Some(data) = self.replayed(replay_path.as_ref()) {
+            let body = serde_json::from_slice(&data)
+                .map_err(anyhow::Error::from)
+                .and_then(|value| version.parse(path, value))
+                .with_context(|| format!("Failed to parse recorded response {}", replay_path.unwrap().display()))?;
+            return Ok(ApiResponse { body, key_idx: key_idx.unwrap_or(0), request_id: None, replayed: true });
+        }
+
+        let Sent { mut resp, idx, request_id, encoding } =
+            self.dispatch(method, path, body.as_ref(), false, label, log_cmd, key_idx).await?;
+        let limit = self.max_response_bytes;
+        let keep = replay_path.is_some() || self.tee_dir.is_some();
+        let too_large = || too_large(label, limit, &request_id);
+        let read = async {
+            let mut wire = Vec::new();
+            while let Some(chunk) =
+                resp.chunk().await.with_context(|| format!("Failed to read {} response", log_cmd))?
+            {
+                wire.extend_from_slice(&chunk);
+                if wire.len() > limit {
+                    return Err(too_large());
+                }
+            }
+
+            // Parse while decoding, so a large body is never held decoded as
+            // well as parsed; only a recording or tee needs the decoded bytes
+            let mut reader = compression::body_reader(&wire, encoding.as_deref(), limit)?;
+            let mut recording = Vec::new();
+            let parsed = if keep {
+                reader
+                    .read_to_end(&mut recording)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|_| version.parse(path, serde_json::from_slice(&recording)?))
+            } else if version == ApiVersion::LATEST {
+                serde_json::from_reader(std::io::BufReader::new(&mut reader)).map_err(anyhow::Error::from)
+            } else {
+                serde_json::from_reader(std::io::BufReader::new(&mut reader))
+                    .map_err(anyhow::Error::from)
+                    .and_then(|value| version.parse(path, value))
+            };
+            if reader.check().is_err() {
+                return Err(too_large());
+            }
+            let body: R = parsed.with_context(|| {
+                format!("Failed to parse {} response{}", log_cmd, request_id_suffix(&request_id))
+            })?;
+            Ok((body, recording, wire.len(), reader.bytes_read()))
+        };
+        let (body, recording, wire_len, decoded) = match read.await {
+            Ok(read) => read,
+            Err(e) => {
+                self.key_manager.record_failure(idx);
+                return Err(e);
+            }
+        };
+        self.key_manager.record_success(idx);
+
+        if self.verbose > 0 {
+            diag!(
+                "{}: {} on the wire, {} decoded ({}, {} saved)",
+                log_cmd,
+                compression::format_bytes(wire_len),
+                compression::format_bytes(decoded),
+                encoding.as_deref().unwrap_or("identity"),
+                compression::format_bytes(decoded.saturating_sub(wire_len))
+            );
+        }
+
+        self.record(replay_path, &recording)?;
+        self.tee(log_cmd, "json", &recording);
+        Ok(ApiResponse { body, key_idx: idx, request_id, replayed: false })
+    }
+
+    /// A request body as JSON, translated for the API revision in use
+    fn request_body<B: Serialize>(&self, endpoint: &str, body: Option<&B>) -> Result<Option<serde_json::Value>> {
+        Ok(body
+            .map(serde_json::to_value)
+            .transpose()?
+            .map(|b| self.api_version.downgrade_request(endpoint, b)))
+    }
+
+    /// Where the response to a request is recorded under --replay
+    fn replay_path(
+        &self,
+        method: &reqwest::Method,
+        endpoint: &str,
+        body: Option<&serde_json::Value>,
+        ext: &str,
+    ) -> Result<Option<PathBuf>> {
+        let Some(dir) = &self.replay_dir else { return Ok(None) };
+        let body_json = body.map(serde_json::to_string).transpose()?.unwrap_or_default();
+        let path = self.api_version.path(endpoint);
+        let digest = Sha256::digest(format!("{} {}\n{}", method, path, body_json).as_bytes());
+        let id: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+        Ok(Some(dir.join(format!("{}.{}", id, ext))))
+    }
+
+    /// A recorded response, unless this run recorded it
+    fn replayed(&self, replay_path: Option<&PathBuf>) -> Option<Vec<u8>> {
+        replay_path.filter(|p| !self.recorded.contains(*p)).and_then(|p| fs::read(p).ok())
+    }
+
+    /// Save a response for later runs to replay
+    fn record(&mut self, replay_path: Option<PathBuf>, data: &[u8]) -> Result<()> {
+        let Some(replay_path) = replay_path else { return Ok(()) };
+        if let Some(dir) = replay_path.parent() {
+            fs::create_dir_all(dir).context("Failed to create replay directory")?;
+        }
+        fs::write(&replay_path, data)
+            .with_context(|| format!("Failed to record response to {}", replay_path.display()))?;
+        self.recorded.insert(replay_path);
+        Ok(())
+    }
+
+    /// Send until a key gets a response other than 429, logging each attempt.
+    /// Fails on an error status; the successful response's body is left for
+    /// the caller to read. `stream` asks for server-sent events, uncompressed.
+    #[allow(clippy::too_many_arguments)]
+    async fn dispatch(
+        &mut self,
+        method: reqwest::Method,
+        endpoint: &str,
+        body: Option<&serde_json::Value>,
+        stream: bool,
+        label: &str,
+        log_cmd: &str,
+        key_idx: Option<usize>,
+    ) -> Result<Sent> {
+        const MAX_RETRIES: usize = 3;
+
+        let path = self.api_version.path(endpoint);
+        for attempt in 0..MAX_RETRIES {
+            let (idx, api_key) = if let Some(specific_idx) = key_idx {
+                let key = self.key_manager.get_key_by_index(specific_idx)
+                    .context("Invalid key index")?;
+                (specific_idx, key)
+            } else {
+                // Throttled or cooled-down keys are waited for here, so the
+                // wait doesn't hold up other requests on the runtime
+                loop {
+                    match self.key_manager.get_next_key()? {
+                        NextKey::Ready(idx, key) => break (idx, key),
+                        NextKey::Wait(wait) => tokio::time::sleep(wait).await,
+                    }
+                }
+            };
+
+            let mut req = transport::Request {
+                method: method.clone(),
+                url: format!("{}{}", self.base_url, path),
+                headers: vec![("x-api-key", api_key)],
+                body: None,
+            };
+            if stream {
+                req.headers.push(("Accept", "text/event-stream".to_string()));
+            } else {
+                req.headers.push(("Accept-Encoding", compression::ACCEPT_ENCODING.to_string()));
+            }
+            let mut body_sha256 = None;
+            if let Some(body) = body {
+                let json = serde_json::to_vec(body)?;
+                body_sha256 = Some(fingerprint::sha256_hex(&json));
+                req.headers.push(("Content-Type", "application/json".to_string()));
+                if self.compress_requests && json.len() >= compression::REQUEST_COMPRESS_THRESHOLD {
+                    let packed = compression::gzip(&json)?;
+                    if self.verbose > 0 {
+                        diag!(
+                            "{}: request body {} -> {} gzipped",
+                            log_cmd,
+                            compression::format_bytes(json.len()),
+                            compression::format_bytes(packed.len())
+                        );
+                    }
+                    req.headers.push(("Content-Encoding", "gzip".to_string()));
+                    req.body = Some(packed);
+                } else {
+                    req.body = Some(json);
+                }
+            }
+
+            self.pace().await;
+            let started = std::time::Instant::now();
+            let mut resp = self
+                .transport
+                .send(req)
+                .await
+                .with_context(|| format!("Failed to send {} request", log_cmd))?;
+
+            let status = resp.status;
+            self.pace_response(status.as_u16());
+            let elapsed_ms = started.elapsed().as_millis();
+            self.stats.requests += 1;
+            self.stats.total_ms += elapsed_ms;
+            if resp.http2 {
+                self.stats.http2 += 1;
+            }
+            if let Some(addr) = resp.remote_addr {
+                self.stats.remotes.insert(addr);
+            }
+            if self.verbose >= 2 {
+                diag!(
+                    "{}: {} via {} in {}ms",
+                    log_cmd,
+                    if resp.http2 { "HTTP/2" } else { "HTTP/1.1" },
+                    resp.remote_addr.map(|a| a.to_string()).unwrap_or_else(|| "?".to_string()),
+                    elapsed_ms
+                );
+            }
+            let request_id = request_id_from(&resp.headers);
+            let _ = self.key_manager.log_request(
+                idx,
+                log_cmd,
+                status.as_u16(),
+                request_id.as_deref(),
+                body_sha256.as_deref(),
+                body.and_then(request_query),
+            );
+
+            if status.as_u16() == 429 {
+                let retry_after = resp
+                    .headers
+                    .get("Retry-After")
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| v.parse::<u64>().ok());
+                self.key_manager.mark_rate_limited(idx, retry_after);
+                if attempt < MAX_RETRIES - 1 {
+                    continue;
+                }
+                let message = format!("Rate limited after {} retries{}", MAX_RETRIES, request_id_suffix(&request_id));
+                return Err(self.key_manager.rate_limited(message).into());
+            }
+
+            let encoding = resp
+                .headers
+                .get(reqwest::header::CONTENT_ENCODING)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.to_string());
+            let limit = self.max_response_bytes;
+            let oversized = resp.content_length().is_some_and(|len| len as usize > limit);
+            // A rejected key isn't billed; any other answer may have been
+            if (!status.is_success() && !matches!(status.as_u16(), 401 | 403)) || oversized {
+                self.key_manager.record_failure(idx);
+            }
+            if oversized {
+                return Err(too_large(label, limit, &request_id));
+            }
+
+            if !status.is_success() {
+                let mut wire = Vec::new();
+                while let Some(chunk) =
+                    resp.chunk().await.with_context(|| format!("Failed to read {} response", log_cmd))?
+                {
+                    wire.extend_from_slice(&chunk);
+                    if wire.len() > limit {
+                        return Err(too_large(label, limit, &request_id));
+                    }
+                }
+                let decoded = compression::decode_body(&wire, encoding.as_deref(), limit)?;
+                let text = String::from_utf8_lossy(&decoded);
+                bail!("{} failed ({}){}: {}", label, status, request_id_suffix(&request_id), text);
+            }
+            return Ok(Sent { resp, idx, request_id, encoding });
+        }
+
+        bail!("{} failed after {} retries", label, MAX_RETRIES)
+    }
+
+    /// With --tee-raw, save a response body as `<UTC time>-<endpoint>-<hash>.<ext>`;
+    /// a failure only warns
+    fn tee(&self, endpoint: &str, ext: &str, body: &[u8]) {
+        let Some(dir) = &self.tee_dir else { return };
+        let name = format!(
+            "{}-{}-{}.{}",
+            Utc::now().format("%Y%m%dT%H%M%S%.3fZ"),
+            endpoint,
+            &fingerprint::sha256_hex(body)[..12],
+            ext
+        );
+        if let Err(e) = fs::create_dir_all(dir).and_then(|_| fs::write(dir.join(&name), body)) {
+            warnings::emit("tee_failed", format!("could not save {} to {}: {}", name, dir.display(), e), true);
+        }
+    }
+
+    /// With --polite, wait out the gap before the next request
+    async fn pace(&mut self) {
+        let Some(pacer) = &mut self.pacer else { return };
+        let wait = pacer.delay();
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    fn pace_response(&mut self, status: u16) {
+        let Some(pacer) = &mut self.pacer else { return };
+        if pacer.record(status) {
+            let secs = pacer.interval().as_secs_f64();
+            let message = format!("HTTP {}: slowing to one request every {:.0}s", status, secs);
+            warnings::emit("polite_backoff", message, self.verbose > 0);
+        }
+    }
+
+    /// Count a response's cost against the budgets of the key that made it
+    fn record_cost<T>(&mut self, resp: &ApiResponse<T>, cost: Option<&CostDollars>) {
+        if let Some(total) = cost.and_then(|c| c.total).filter(|_| !resp.replayed) {
+            self.key_manager.record_cost(resp.key_idx, total);
+        }
+    }
+
+    pub async fn search(&mut self, request: SearchRequest) -> Result<SearchResponse> {
+        let resp: ApiResponse<SearchResponse> = self
+            .send(reqwest::Method::POST, "/search", Some(&request), "Search", "search", None)
+            .await?;
+        self.record_cost(&resp, resp.body.cost_dollars.as_ref());
+        let mut body = resp.body;
+        body.request_id = body.request_id.or(resp.request_id);
+        Ok(body)
+    }
+
+    pub async fn answer(&mut self, request: AnswerRequest) -> Result<AnswerResponse> {
+        let resp: ApiResponse<AnswerResponse> = self
+            .send(reqwest::Method::POST, "/answer", Some(&request), "Answer", "answer", None)
+            .await?;
+        self.record_cost(&resp, resp.body.cost_dollars.as_ref());
+        let mut body = resp.body;
+        body.request_id = body.request_id.or(resp.request_id);
+        Ok(body)
+    }
+
+    /// POST /answer as a server-sent event stream, calling `on_text` with each
+    /// piece of the answer as it arrives. Returns the assembled response.
+    /// Under --replay the raw stream is recorded, and replayed all at once.
+    pub async fn answer_stream(&mut self, request: &AnswerRequest, mut on_text: impl FnMut(&str)) -> Result<AnswerResponse> {
+        let method = reqwest::Method::POST;
+        let body = self.request_body("/answer", Some(request))?;
+        let replay_path = self.replay_path(&method, "/answer", body.as_ref(), "sse")?;
+        let mut result = AnswerResponse { answer: String::new(), citations: Vec::new(), cost_dollars: None, request_id: None };
+        let mut parser = sse::SseParser::default();
+        if let Some(data) = self.replayed(replay_path.as_ref()) {
+            let mut done = false;
+            for data in parser.push(&String::from_utf8_lossy(&data)).into_iter().chain(parser.finish()) {
+                if !done {
+                    done = apply_answer_event(&mut result, &data, &mut on_text)?;
+                }
+            }
+            return Ok(result);
+        }
+
+        let Sent { mut resp, idx, request_id, .. } =
+            self.dispatch(method, "/answer", body.as_ref(), true, "Answer", "answer", None).await?;
+        result.request_id = request_id;
+
+        let limit = self.max_response_bytes;
+        let keep = replay_path.is_some() || self.tee_dir.is_some();
+        // The key's request succeeded only once the whole stream is read
+        let read = async {
+            // Bytes of a UTF-8 sequence split across chunks
+            let mut pending: Vec<u8> = Vec::new();
+            let mut raw: Vec<u8> = Vec::new();
+            let mut received = 0;
+            let mut done = false;
+            while !done {
+                let Some(chunk) = resp.chunk().await.context("Failed to read answer stream")? else { break };
+                received += chunk.len();
+                if received > limit {
+                    return Err(too_large("Answer", limit, &result.request_id));
+                }
+                if keep {
+                    raw.extend_from_slice(&chunk);
+                }
+                pending.extend_from_slice(&chunk);
+                let valid = match std::str::from_utf8(&pending) {
+                    Ok(text) => text.len(),
+                    Err(e) => e.valid_up_to(),
+                };
+                let text = String::from_utf8_lossy(&pending[..valid]).into_owned();
+                pending.drain(..valid);
+                for data in parser.push(&text) {
+                    done |= apply_answer_event(&mut result, &data, &mut on_text)?;
+                }
+            }
+            if !done {
+                if let Some(data) = parser.finish() {
+                    apply_answer_event(&mut result, &data, &mut on_text)?;
+                }
+            }
+            Ok((raw, received))
+        };
+        let (raw, received) = match read.await {
+            Ok(read) => read,
+            Err(e) => {
+                self.key_manager.record_failure(idx);
+                return Err(e);
+            }
+        };
+        self.key_manager.record_success(idx);
+        if let Some(cost) = result.cost_dollars.as_ref().and_then(|c| c.total) {
+            self.key_manager.record_cost(idx, cost);
+        }
+        if self.verbose > 0 {
+            diag!("answer: {} streamed", compression::format_bytes(received));
+        }
+        self.record(replay_path, &raw)?;
+        self.tee("answer", "sse", &raw);
+        Ok(result)
+    }
+
+    pub async fn find_similar(&mut self, request: FindSimilarRequest) -> Result<SearchResponse> {
+        let resp: ApiResponse<SearchResponse> = self
+            .send(reqwest::Method::POST, "/findSimilar", Some(&request), "Find similar", "findSimilar", None)
+            .await?;
+        self.record_cost(&resp, resp.body.cost_dollars.as_ref());
+        let mut body = resp.body;
+        body.request_id = body.request_id.or(resp.request_id);
+        Ok(body)
+    }
+
+    pub async fn get_contents(&mut self, urls: Vec<String>, contents: ContentsConfig) -> Result<SearchResponse> {
+        let request = GetContentsRequest { urls, contents };
+        let resp: ApiResponse<SearchResponse> = self
+            .send(reqwest::Method::POST, "/contents", Some(&request), "Get contents", "contents", None)
+            .await?;
+        self.record_cost(&resp, resp.body.cost_dollars.as_ref());
+        let mut body = resp.body;
+        body.request_id = body.request_id.or(resp.request_id);
+        Ok(body)
+    }
+
+    pub async fn research_create(&mut self, request: ResearchCreateRequest) -> Result<(ResearchCreateResponse, usize)> {
+        let resp: ApiResponse<ResearchCreateResponse> = self
+            .send(reqwest::Method::POST, "/research", Some(&request), "Research create", "research", None)
+            .await?;
+        Ok((resp.body, resp.key_idx))
+    }
+
+    pub async fn webset_create(&mut self, request: websets::CreateWebsetRequest) -> Result<websets::Webset> {
+        let resp = self
+            .send(reqwest::Method::POST, websets::API, Some(&request), "Webset create", "websets", None)
+            .await?;
+        Ok(resp.body)
+    }
+
+    pub async fn webset_get(&mut self, id: &str) -> Result<websets::Webset> {
+        let path = format!("{}/{}", websets::API, websets::segment(id)?);
+        let resp = self
+            .send(reqwest::Method::GET, &path, None::<&()>, "Webset get", "websets", None)
+            .await?;
+        Ok(resp.body)
+    }
+
+    pub async fn webset_delete(&mut self, id: &str) -> Result<websets::Webset> {
+        let path = format!("{}/{}", websets::API, websets::segment(id)?);
+        let resp = self
+            .send(reqwest::Method::DELETE, &path, None::<&()>, "Webset delete", "websets", None)
+            .await?;
+        Ok(resp.body)
+    }
+
+    pub async fn enrichment_create(
+        &mut self,
+        webset_id: &str,
+        request: websets::CreateEnrichmentRequest,
+    ) -> Result<websets::Enrichment> {
+        let path = format!("{}/{}/enrichments", websets::API, websets::segment(webset_id)?);
+        let resp = self
+            .send(reqwest::Method::POST, &path, Some(&request), "Enrichment create", "websets", None)
+            .await?;
+        Ok(resp.body)
+    }
+
+    pub async fn enrichment_get(&mut self, webset_id: &str, enrichment_id: &str) -> Result<websets::Enrichment> {
+        let path = format!(
+            "{}/{}/enrichments/{}",
+            websets::API,
+            websets::segment(webset_id)?,
+            websets::segment(enrichment_id)?
+        );
+        let resp = self
+            .send(reqwest::Method::GET, &path, None::<&()>, "Enrichment get", "websets", None)
+            .await?;
+        Ok(resp.body)
+    }
+
+    /// Collect a cursor-paginated listing: up to `limit` entries, or all of them
+    pub async fn paginate<T: DeserializeOwned>(&mut self, path: &str, limit: usize, all: bool) -> Result<Vec<T>> {
+        let mut out = Vec::new();
+        if !all && limit == 0 {
+            return Ok(out);
+        }
+        let mut cursor: Option<String> = None;
+        loop {
+            let want = if all { websets::MAX_PAGE } else { (limit - out.len()).min(websets::MAX_PAGE) };
+            let mut query = reqwest::Url::parse("http://localhost/").expect("static URL");
+            query.query_pairs_mut().append_pair("limit", &want.to_string());
+            if let Some(cursor) = &cursor {
+                query.query_pairs_mut().append_pair("cursor", cursor);
+            }
+            let page_path = format!("{}?{}", path, query.query().unwrap_or_default());
+            let resp: ApiResponse<websets::Page<T>> = self
+                .send(reqwest::Method::GET, &page_path, None::<&()>, "Websets list", "websets", None)
+                .await?;
+            let page = resp.body;
+            out.extend(page.data);
+            cursor = page.next_cursor.filter(|_| page.has_more);
+            if cursor.is_none() || (!all && out.len() >= limit) {
+                break;
+            }
+        }
+        if !all {
+            out.truncate(limit);
+        }
+        Ok(out)
+    }
+
+    pub async fn research_status(&mut self, research_id: &str, key_idx: Option<usize>) -> Result<ResearchStatusResponse> {
+        let path = format!("/research/{}", research_id);
+        let resp: ApiResponse<ResearchStatusResponse> = self
+            .send(reqwest::Method::GET, &path, None::<&()>, "Research status", "research_status", key_idx)
+            .await?;
+        Ok(self.research_task(research_id, resp))
+    }
+
+    /// Stop a running research task so it costs no more; returns the task as it was left
+    pub async fn research_cancel(&mut self, research_id: &str, key_idx: Option<usize>) -> Result<ResearchStatusResponse> {
+        let path = format!("/research/{}/cancel", research_id);
+        let resp: ApiResponse<ResearchStatusResponse> = self
+            .send(reqwest::Method::POST, &path, None::<&()>, "Research cancel", "research_status", key_idx)
+            .await?;
+        Ok(self.research_task(research_id, resp))
+    }
+
+    fn research_task(&mut self, research_id: &str, resp: ApiResponse<ResearchStatusResponse>) -> ResearchStatusResponse {
+        // Every poll reports the task's cost so far; count it once, when final
+        let finished = ResearchStatus::parse(&resp.body.status) != ResearchStatus::Running;
+        if let Some(total) = resp.body.cost_dollars.as_ref().and_then(|c| c.total).filter(|_| finished && !resp.replayed) {
+            self.key_manager.record_task_cost(resp.key_idx, research_id, total);
+        }
+        let mut body = resp.body;
+        body.request_id = body.request_id.or(resp.request_id);
+        body
+    }
+}
+
+/// Fold one streamed answer event into `result`; returns true at end of stream
+fn apply_answer_event(result: &mut AnswerResponse, data: &str, on_text: &mut impl FnMut(&str)) -> Result<bool> {
+    if data.trim() == "[DONE]" {
+        return Ok(true);
+    }
+    let event: serde_json::Value =
+        serde_json::from_str(data).with_context(|| format!("Invalid answer stream event: {}", data))?;
+    if let Some(error) = event.get("error") {
+        bail!("Answer stream failed: {}", error);
+    }
+    if let Some(text) = event.pointer("/choices/0/delta/content").and_then(|v| v.as_str()) {
+        result.answer.push_str(text);
+        on_text(text);
+    }
+    if let Some(citations) = event.get("citations") {
+        let citations: Vec<AnswerCitation> =
+            serde_json::from_value(citations.clone()).context("Invalid citations in answer stream")?;
+        result.citations.extend(citations);
+    }
+    if let Some(cost) = event.get("costDollars") {
+        result.cost_dollars = serde_json::from_value(cost.clone()).ok();
+    }
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_answer_event() {
+        let mut result = AnswerResponse { answer: String::new(), citations: Vec::new(), cost_dollars: None, request_id: None };
+        let mut shown = String::new();
+        let mut on_text = |t: &str| shown.push_str(t);
+        for data in [
+            r#"{"choices":[{"delta":{"content":"Hello"}}]}"#,
+            r#"{"choices":[{"delta":{"content":", world"}}]}"#,
+            r#"{"citations":[{"url":"https://a.com","title":"A"}],"costDollars":{"total":0.005}}"#,
+        ] {
+            assert!(!apply_answer_event(&mut result, data, &mut on_text).unwrap());
+        }
+        assert!(apply_answer_event(&mut result, "[DONE]", &mut on_text).unwrap());
+        assert!(apply_answer_event(&mut result, r#"{"error":"boom"}"#, &mut on_text).is_err());
+        assert_eq!(shown, "Hello, world");
+        assert_eq!(result.answer, "Hello, world");
+        assert_eq!(result.citations[0].url, "https://a.com");
+        assert_eq!(result.cost_dollars.and_then(|c| c.total), Some(0.005));
+    }
+
+    fn mock_client(keys: &[&str], mock: &transport::MockTransport) -> ExaClient {
+        let keys = keys.iter().map(|k| k.to_string()).collect();
+        ExaClient::new(KeyManager::from_keys(keys), reqwest::Client::new()).with_transport(mock.clone())
+    }
+
+    #[tokio::test]
+    async fn test_rotates_key_on_429() {
+        let mock = transport::MockTransport::new();
+        mock.push(transport::Response::new(429, &[("retry-after", "30")], vec![]))
+            .push(transport::Response::new(
+                200,
+                &[("x-request-id", "req-1"), ("content-encoding", "gzip")],
+                vec![compression::gzip(br#"{"results":[{"url":"https://a.com"}]}"#).unwrap()],
+            ));
+        let mut client = mock_client(&["key-a", "key-b"], &mock);
+        let tee = std::env::temp_dir().join(format!("exa-tee-test-{}", std::process::id()));
+        client.tee_dir = Some(tee.clone());
+        let request = SearchRequest::builder().query("rust").build().unwrap();
+        let resp = client.search(request).await.unwrap();
+        assert_eq!(resp.results[0].url, "https://a.com");
+        assert_eq!(resp.request_id.as_deref(), Some("req-1"));
+
+        // Only the successful response is teed, decoded
+        let teed: Vec<_> = fs::read_dir(&tee).unwrap().map(|e| e.unwrap().path()).collect();
+        assert_eq!(teed.len(), 1);
+        assert!(teed[0].file_name().unwrap().to_str().unwrap().contains("-search-"));
+        assert_eq!(fs::read_to_string(&teed[0]).unwrap(), r#"{"results":[{"url":"https://a.com"}]}"#);
+        fs::remove_dir_all(&tee).unwrap();
+
+        let sent = mock.requests();
+        assert_eq!(sent.len(), 2);
+        assert_eq!(sent[0].url, "https://api.exa.ai/search");
+        assert_eq!((sent[0].header("x-api-key"), sent[1].header("x-api-key")), (Some("key-a"), Some("key-b")));
+    }
+
+    #[tokio::test]
+    async fn test_errors_and_size_limit() {
+        let mock = transport::MockTransport::new();
+        mock.push(transport::Response::json(401, &serde_json::json!({"error": "bad key"})))
+            .push(transport::Response::json(200, &serde_json::json!({"results": [], "pad": "x".repeat(100)})));
+        let mut client = mock_client(&["k"], &mock);
+        let err = client.get_contents(vec!["https://a.com".to_string()], ContentsConfig::text()).await.unwrap_err();
+        assert!(err.to_string().contains("Get contents failed (401 Unauthorized)"), "{}", err);
+        let body: serde_json::Value = serde_json::from_slice(mock.requests()[0].body.as_deref().unwrap()).unwrap();
+        assert_eq!(body, serde_json::json!({"urls": ["https://a.com"], "text": true}));
+
+        client.max_response_bytes = 64;
+        let err = client.get_contents(vec!["https://a.com".to_string()], ContentsConfig::text()).await.unwrap_err();
+        assert!(err.to_string().contains("response is larger than the 64 B limit"), "{}", err);
+
+        // Highlights alone don't ask for the text
+        mock.push(transport::Response::json(200, &serde_json::json!({"results": []})));
+        client.get_contents(vec!["https://a.com".to_string()], ContentsConfig::highlights(200)).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(mock.requests()[2].body.as_deref().unwrap()).unwrap();
+        assert_eq!(body, serde_json::json!({"urls": ["https://a.com"], "highlights": {"maxCharacters": 200}}));
+    }
+
+    #[tokio::test]
+    async fn test_answer_stream_chunks() {
+        let events = "data: {\"choices\":[{\"delta\":{\"content\":\"caf\u{e9}\"}}]}\n\ndata: [DONE]\n\n".as_bytes();
+        // Split inside the two-byte "é"
+        let split = events.iter().position(|&b| b == 0xc3).unwrap() + 1;
+        let mock = transport::MockTransport::new();
+        mock.push(transport::Response::new(200, &[], vec![events[..split].to_vec(), events[split..].to_vec()]));
+        let mut client = mock_client(&["k"], &mock);
+        let replay = std::env::temp_dir().join(format!("exa-replay-test-{}", std::process::id()));
+        client.replay_dir = Some(replay.clone());
+        let request = AnswerRequest::builder().query("q").stream(true).build().unwrap();
+        let mut shown = String::new();
+        let resp = client.answer_stream(&request, |t| shown.push_str(t)).await.unwrap();
+        assert_eq!((resp.answer.as_str(), shown.as_str()), ("café", "café"));
+        assert_eq!(mock.requests()[0].header("accept"), Some("text/event-stream"));
+
+        // A later run replays the recorded stream without a request
+        let mut client = mock_client(&["k"], &mock);
+        client.replay_dir = Some(replay.clone());
+        let mut shown = String::new();
+        let resp = client.answer_stream(&request, |t| shown.push_str(t)).await.unwrap();
+        assert_eq!((resp.answer.as_str(), shown.as_str()), ("café", "café"));
+        assert_eq!(mock.requests().len(), 1);
+        fs::remove_dir_all(&replay).unwrap();
+
+        mock.push(transport::Response::new(200, &[], vec![events.to_vec()]));
+        client.replay_dir = None;
+        client.max_response_bytes = 16;
+        let err = client.answer_stream(&request, |_| {}).await.unwrap_err();
+        assert!(err.to_string().contains("Answer response is larger than the 16 B limit"), "{}", err);
+
+        // A stream that fails partway counts against the key, not for it
+        let failed = "data: {\"choices\":[{\"delta\":{\"content\":\"ca\"}}]}\n\ndata: {\"error\":\"overloaded\"}\n\n";
+        mock.push(transport::Response::new(200, &[], vec![failed.as_bytes().to_vec()]));
+        let mut client = mock_client(&["k"], &mock);
+        assert!(client.answer_stream(&request, |_| {}).await.is_err());
+        let usage = client.key_manager.key_info(0).usage;
+        assert_eq!((usage.success, usage.errors), (0, 1));
+    }
+
+    #[tokio::test]
+    async fn test_api_version_v0() {
+        let mock = transport::MockTransport::new();
+        mock.push(transport::Response::json(200, &serde_json::json!({"id": "r1"})))
+            .push(transport::Response::json(200, &serde_json::json!({"id": "r1", "status": "running"})));
+        let mut client = mock_client(&["k"], &mock);
+        client.api_version = ApiVersion::V0;
+        let request = ResearchCreateRequest {
+            instructions: "q".to_string(),
+            model: "exa-research".to_string(),
+            output_schema: Some(serde_json::json!({"type": "object"})),
+        };
+        let (created, _) = client.research_create(request).await.unwrap();
+        assert_eq!(created.research_id, "r1");
+        assert_eq!(client.research_status("r1", None).await.unwrap().status, "running");
+        mock.push(transport::Response::json(200, &serde_json::json!({"id": "r1", "status": "canceled"})));
+        assert_eq!(client.research_cancel("r1", None).await.unwrap().status, "canceled");
+
+        let sent = mock.requests();
+        assert_eq!(sent[0].url, "https://api.exa.ai/research/v0/tasks");
+        assert_eq!(sent[1].url, "https://api.exa.ai/research/v0/tasks/r1");
+        assert_eq!((sent[2].method.as_str(), sent[2].url.as_str()), ("POST", "https://api.exa.ai/research/v0/tasks/r1/cancel"));
+        let body: serde_json::Value = serde_json::from_slice(sent[0].body.as_deref().unwrap()).unwrap();
+        assert_eq!(body["output"]["schema"]["type"], "object");
+    }
+}
@@ -0,0 +1,192 @@
+//! --copy: print as usual while a thread tees stdout, then put what was
+//! printed (without colors or other terminal escapes), or just its first URL,
+//! on the clipboard. Stdout is swapped for a pipe in this process, so
+//! everything printed, streamed answers included, is seen as it is written.
+
+use anyhow::{Context, Result};
+use exa_cli::{diag, warnings};
+use regex::Regex;
+use std::io::{Read, Write};
+#[cfg(unix)]
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::process::{Command, Stdio};
+use std::sync::LazyLock;
+#[cfg(unix)]
+use std::sync::Mutex;
+
+use crate::cli::CopyTarget;
+
+/// Set for the clipboard holder, which must not copy itself (config.toml may set `copy` too)
+pub const CHILD_VAR: &str = "EXA_COPY_CHILD";
+
+static URL: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"https?://[^\s<>"'|\])}]+"#).unwrap());
+
+/// Colors and styles (CSI), and OSC/APC sequences such as terminal images
+static ESCAPES: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\x1b\[[0-9;?]*[ -/]*[@-~]|\x1b[\]_P^][^\x07\x1b]*(?:\x07|\x1b\\)").unwrap());
+
+/// The capture in progress, finished by [`finish`] when the process exits
+#[cfg(unix)]
+static CAPTURE: Mutex<Option<Capture>> = Mutex::new(None);
+
+#[cfg(unix)]
+struct Capture {
+    target: CopyTarget,
+    /// The real stdout, put back when the capture ends
+    terminal: OwnedFd,
+    tee: std::thread::JoinHandle<Vec<u8>>,
+}
+
+pub fn is_child() -> bool {
+    std::env::var_os(CHILD_VAR).is_some()
+}
+
+/// The first URL in `text`, without trailing sentence punctuation
+fn first_url(text: &str) -> Option<&str> {
+    URL.find(text).map(|m| m.as_str().trim_end_matches(['.', ',', ';', ':']))
+}
+
+/// Text as it reads on screen, without escape sequences
+fn plain(text: &str) -> String {
+    ESCAPES.replace_all(text, "").into_owned()
+}
+
+/// Start teeing stdout. Whether stdout is a terminal (for colors, compact
+/// output and the wrap width) must be settled before this is called.
+#[cfg(unix)]
+pub fn start(target: CopyTarget) -> Result<()> {
+    // Colors are decided once, on first use; decide while stdout is still the terminal
+    colored::control::SHOULD_COLORIZE.should_colorize();
+    let _ = std::io::stdout().flush();
+    let stdout = std::io::stdout().as_raw_fd();
+    // SAFETY: dup and dup2 on the process's own stdout; fds are owned below
+    let terminal = unsafe { libc::dup(stdout) };
+    if terminal < 0 {
+        return Err(std::io::Error::last_os_error()).context("Failed to capture output");
+    }
+    let terminal = unsafe { OwnedFd::from_raw_fd(terminal) };
+    let (mut reader, writer) = std::io::pipe().context("Failed to capture output")?;
+    if unsafe { libc::dup2(writer.as_raw_fd(), stdout) } < 0 {
+        return Err(std::io::Error::last_os_error()).context("Failed to capture output");
+    }
+    drop(writer);
+
+    let mut out = std::fs::File::from(terminal.try_clone()?);
+    let tee = std::thread::spawn(move || {
+        // Pass output through as it arrives, so streamed answers still stream
+        let mut captured = Vec::new();
+        let mut buf = [0u8; 8192];
+        while let Ok(n) = reader.read(&mut buf) {
+            if n == 0 {
+                break;
+            }
+            captured.extend_from_slice(&buf[..n]);
+            let _ = out.write_all(&buf[..n]);
+            let _ = out.flush();
+        }
+        captured
+    });
+    *CAPTURE.lock().unwrap() = Some(Capture { target, terminal, tee });
+    // Every way out of the process, std::process::exit included, goes through exit()
+    unsafe { libc::atexit(at_exit) };
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn start(_target: CopyTarget) -> Result<()> {
+    anyhow::bail!("--copy is not supported on this system yet")
+}
+
+#[cfg(unix)]
+extern "C" fn at_exit() {
+    finish();
+}
+
+/// Put stdout back and copy what was printed. Runs once, at exit.
+#[cfg(unix)]
+fn finish() {
+    let Some(capture) = CAPTURE.lock().ok().and_then(|mut c| c.take()) else { return };
+    let _ = std::io::stdout().flush();
+    // Restoring stdout closes the pipe's last writer, which ends the tee
+    unsafe { libc::dup2(capture.terminal.as_raw_fd(), std::io::stdout().as_raw_fd()) };
+    let Ok(captured) = capture.tee.join() else { return };
+
+    copy(capture.target, &plain(&String::from_utf8_lossy(&captured)));
+}
+
+/// Copy `text`, or its first URL, and say so
+#[cfg(unix)]
+fn copy(target: CopyTarget, text: &str) {
+    let copied = match target {
+        CopyTarget::Output => Some(text.trim_end()),
+        CopyTarget::Url => first_url(text),
+    };
+    match copied.filter(|t| !t.is_empty()) {
+        Some(copied) => match set_text(copied) {
+            Ok(()) if target == CopyTarget::Url => diag!("Copied {}", copied),
+            Ok(()) => diag!("Copied {} lines", copied.lines().count()),
+            Err(e) => warnings::emit("copy_failed", format!("could not copy to the clipboard: {:#}", e), true),
+        },
+        None => warnings::emit("copy_failed", "nothing to copy", true),
+    }
+    warnings::flush();
+}
+
+/// On X11 and Wayland the clipboard is served by the process that set it, so
+/// a detached `exa clipboard-hold` keeps it until something else is copied
+#[cfg(target_os = "linux")]
+fn set_text(text: &str) -> Result<()> {
+    // Fail here, where it can be reported, when there is no display
+    arboard::Clipboard::new()?;
+    let exe = std::env::current_exe().context("Failed to find the exa executable")?;
+    let mut holder = Command::new(exe)
+        .arg("clipboard-hold")
+        .env(CHILD_VAR, "1")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .context("Failed to start the clipboard holder")?;
+    holder.stdin.take().context("Failed to start the clipboard holder")?.write_all(text.as_bytes())?;
+    Ok(())
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn set_text(text: &str) -> Result<()> {
+    let mut clipboard = arboard::Clipboard::new()?;
+    Ok(clipboard.set_text(text)?)
+}
+
+/// `exa clipboard-hold`: own the clipboard with stdin's text until replaced
+#[cfg(target_os = "linux")]
+pub fn hold() -> Result<()> {
+    use arboard::SetExtLinux;
+    let mut text = String::new();
+    std::io::stdin().read_to_string(&mut text)?;
+    if text.is_empty() {
+        anyhow::bail!("Nothing to hold");
+    }
+    let mut clipboard = arboard::Clipboard::new()?;
+    Ok(clipboard.set().wait().text(text)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_url() {
+        assert_eq!(first_url("Ownership | https://a.com/x?y=1 | 2024"), Some("https://a.com/x?y=1"));
+        assert_eq!(first_url("see (https://b.com/page)."), Some("https://b.com/page"));
+        assert_eq!(first_url("1. [A](https://c.com/a_%28b%29) — 2024"), Some("https://c.com/a_%28b%29"));
+        assert_eq!(first_url("Source: https://e.com/post."), Some("https://e.com/post"));
+        assert_eq!(first_url("no links here"), None);
+    }
+
+    #[test]
+    fn test_plain() {
+        assert_eq!(plain("\x1b[1;32mAnswer:\x1b[0m text"), "Answer: text");
+        assert_eq!(plain("a\x1b]1337;File=inline=1:QUJD\x07b\x1b_Gf=100;QUJD\x1b\\c"), "abc");
+        assert_eq!(plain("https://a.com/x"), "https://a.com/x");
+    }
+}
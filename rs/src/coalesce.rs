@@ -0,0 +1,99 @@
+//! Request coalescing for `exa serve`: identical tool calls that arrive while
+//! one is already running wait for its result instead of starting their own
+//! run, so N agents asking the same question cost one upstream request.
+
+use exa_cli::fingerprint;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Identity of a call: its tool and arguments, with object keys sorted so
+/// argument order doesn't matter
+pub fn key(name: &str, arguments: &Map<String, Value>) -> String {
+    let canonical = canonical(&Value::Object(arguments.clone()));
+    fingerprint::sha256_hex(format!("{}\n{}", name, canonical).as_bytes())
+}
+
+fn canonical(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<(&String, &Value)> = map.iter().collect();
+            entries.sort_by_key(|(k, _)| *k);
+            Value::Object(entries.into_iter().map(|(k, v)| (k.clone(), canonical(v))).collect())
+        }
+        Value::Array(items) => Value::Array(items.iter().map(canonical).collect()),
+        other => other.clone(),
+    }
+}
+
+/// Calls and how many of them shared another call's run
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Stats {
+    pub calls: u64,
+    pub coalesced: u64,
+}
+
+/// Calls in flight by key, each with the requests waiting for its result
+pub struct Inflight<T> {
+    state: Mutex<(HashMap<String, Vec<T>>, Stats)>,
+}
+
+impl<T> Default for Inflight<T> {
+    fn default() -> Self {
+        Self { state: Mutex::new((HashMap::new(), Stats::default())) }
+    }
+}
+
+impl<T> Inflight<T> {
+    /// Wait on `key` as `waiter`. True when nothing with this key is running,
+    /// so the caller must run it; false when it joined a running call.
+    pub fn join(&self, key: &str, waiter: T) -> bool {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let (running, stats) = &mut *state;
+        stats.calls += 1;
+        match running.get_mut(key) {
+            Some(waiters) => {
+                waiters.push(waiter);
+                stats.coalesced += 1;
+                false
+            }
+            None => {
+                running.insert(key.to_string(), vec![waiter]);
+                true
+            }
+        }
+    }
+
+    /// The run for `key` is done: everyone waiting for it, in arrival order
+    pub fn finish(&self, key: &str) -> Vec<T> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.0.remove(key).unwrap_or_default()
+    }
+
+    pub fn stats(&self) -> Stats {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_inflight() {
+        let args = |v: Value| v.as_object().unwrap().clone();
+        let a = key("web_search", &args(json!({"query": "rust", "num": 3})));
+        assert_eq!(a, key("web_search", &args(json!({"num": 3, "query": "rust"}))));
+        assert_ne!(a, key("web_search", &args(json!({"query": "rust", "num": 4}))));
+        assert_ne!(a, key("get_contents", &args(json!({"query": "rust", "num": 3}))));
+
+        let inflight = Inflight::default();
+        assert!(inflight.join(&a, 1));
+        assert!(!inflight.join(&a, 2));
+        assert!(inflight.join("other", 3));
+        assert_eq!(inflight.finish(&a), [1, 2]);
+        assert!(inflight.join(&a, 4));
+        assert_eq!(inflight.stats(), Stats { calls: 4, coalesced: 1 });
+    }
+}
@@ -0,0 +1,356 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use exa_cli::api::{AnswerRequest, AnswerResponse};
+use exa_cli::model::Answer;
+use exa_cli::{diag, diag_inline, warnings, ExaClient};
+use std::fs;
+use std::io::{IsTerminal, Write};
+use std::path::PathBuf;
+
+use crate::cli::Cli;
+use crate::commands::{check_query, is_rate_limited, mark_degraded};
+use crate::conflict;
+use crate::extractive;
+use crate::followup;
+use crate::grounding::{self, Grounding};
+use crate::history;
+use crate::issues;
+use crate::markdown;
+use crate::pii;
+use crate::render::{fill, normalize_whitespace, output_width, select_sources, source_line, to_json, truncate_text};
+
+/// Sources shown by default (--sources overrides)
+pub const ANSWER_SOURCES: usize = 3;
+
+pub async fn cmd_answer(
+    client: &mut ExaClient,
+    cli: &Cli,
+    query: String,
+    follow_up: bool,
+    transcript_path: Option<&PathBuf>,
+) -> Result<()> {
+    issues::check(cli)?;
+    if let Some(flag) = stream_blocker(cli).filter(|_| cli.stream) {
+        warnings::emit("stream_off", format!("--stream is ignored with {}", flag), true);
+    }
+    let result = answer_turn(client, cli, query.clone(), &query).await?;
+    history::record(cli, result.sources.len());
+    if !result.text.trim().is_empty() {
+        let sources = result.sources.iter().map(|c| (c.url.as_str(), c.title.as_deref(), c.published.as_deref()));
+        let issue = issues::Issue { query: &query, body: &result.text, sources: sources.collect() };
+        issues::file(&client.client, cli, &issue).await;
+    }
+    if !follow_up {
+        if !cli.json && result.text.trim().is_empty() {
+            std::process::exit(3);
+        }
+        return Ok(());
+    }
+
+    let mut transcript = followup::Transcript::default();
+    transcript.push(&query, &result.text, followup_sources(&result));
+    let interactive = std::io::stdin().is_terminal();
+    loop {
+        if interactive {
+            diag::on_terminal(|| diag_inline!("{} ", ">".cyan().bold()));
+        }
+        let mut line = String::new();
+        if std::io::stdin().read_line(&mut line)? == 0 {
+            break;
+        }
+        let question = line.trim();
+        if question.is_empty() || question == "exit" || question == "quit" {
+            break;
+        }
+        if !cli.json {
+            println!();
+        }
+        let sent = transcript.contextual_query(question);
+        match answer_turn(client, cli, sent, question).await {
+            Ok(result) => transcript.push(question, &result.text, followup_sources(&result)),
+            // One failed question shouldn't end the conversation
+            Err(e) => diag!("{} {:#}", "Error:".red(), e),
+        }
+    }
+
+    if let Some(path) = transcript_path {
+        let out = if path.extension().is_some_and(|e| e == "json") {
+            serde_json::to_string_pretty(&transcript)?
+        } else {
+            transcript.markdown()
+        };
+        fs::write(path, out).with_context(|| format!("Failed to write transcript {}", path.display()))?;
+        if !cli.json {
+            diag!("{}", format!("Transcript saved to {}", path.display()).dimmed());
+        }
+    }
+    Ok(())
+}
+
+pub fn followup_sources(result: &Answer) -> Vec<followup::Source> {
+    result
+        .sources
+        .iter()
+        .map(|c| followup::Source { url: c.url.clone(), title: c.title.clone(), text: c.text.clone() })
+        .collect()
+}
+
+/// The flag that turns --stream off, if any
+fn stream_blocker(cli: &Cli) -> Option<&'static str> {
+    [(cli.markdown, "--markdown"), (cli.scrub_pii, "--scrub-pii"), (cli.deterministic, "--deterministic")]
+        .into_iter()
+        .find_map(|(set, flag)| set.then_some(flag))
+}
+
+/// Ask one question and print the answer. `query` is what is sent (for a
+/// follow-up it carries the conversation so far); `question` is what the user asked.
+pub async fn answer_turn(client: &mut ExaClient, cli: &Cli, query: String, question: &str) -> Result<Answer> {
+    check_query(cli, &query)?;
+    // Streamed text can't be scrubbed or normalized before it is shown
+    let stream = cli.stream && stream_blocker(cli).is_none();
+    // Citation texts feed the conflict check; they are dropped from JSON unless --content
+    let request = AnswerRequest::builder().query(query).text(true).stream(stream).build()?;
+    let mut result = if stream {
+        if !cli.json && !cli.compact {
+            println!("{}", "Answer:".bold().green());
+            println!();
+        }
+        let mut out: Box<dyn Write> = if cli.json { Box::new(diag::Writer) } else { Box::new(std::io::stdout()) };
+        let streamed = client
+            .answer_stream(&request, |text| {
+                let _ = write!(out, "{}", text);
+                let _ = out.flush();
+            })
+            .await;
+        let result = match streamed {
+            Err(e) if cli.degrade && is_rate_limited(&e) => {
+                let result = degraded_answer(client, request.query).await?;
+                let _ = write!(out, "{}", result.answer);
+                result
+            }
+            other => other?,
+        };
+        let _ = writeln!(out);
+        Answer::from(result)
+    } else {
+        let query = request.query.clone();
+        match client.answer(request).await {
+            Err(e) if cli.degrade && is_rate_limited(&e) => Answer::from(degraded_answer(client, query).await?),
+            other => Answer::from(other?),
+        }
+    };
+
+    if cli.scrub_pii {
+        result.text = pii::scrub(&result.text);
+        for c in &mut result.sources {
+            c.title = c.title.as_deref().map(pii::scrub);
+            c.text = c.text.as_deref().map(pii::scrub);
+        }
+    }
+    if cli.deterministic {
+        result.request_id = None;
+        result.cost = None;
+        result.text = normalize_whitespace(&result.text);
+        for c in &mut result.sources {
+            c.title = c.title.as_deref().map(normalize_whitespace);
+            c.text = c.text.as_deref().map(normalize_whitespace);
+        }
+    }
+
+    // Every citation keeps its position, so source numbers match the citations
+    let grounding = cli.grounding.then(|| {
+        let sources: Vec<(&str, &str)> =
+            result.sources.iter().map(|c| (c.url.as_str(), c.text.as_deref().unwrap_or(""))).collect();
+        grounding::align(&result.text, &sources)
+    });
+
+    if cli.json {
+        println!("{}", to_json(&answer_json(cli, &result, grounding.as_ref())?, cli.compact)?);
+        return Ok(result);
+    }
+
+    if result.text.trim().is_empty() {
+        diag!("No answer returned.");
+        return Ok(result);
+    }
+
+    if cli.markdown {
+        let shown = if cli.no_sources { 0 } else { cli.sources.unwrap_or(ANSWER_SOURCES) };
+        let sources: Vec<markdown::Source> = result
+            .sources
+            .iter()
+            .take(shown)
+            .map(|c| (c.url.as_str(), c.title.as_deref(), c.published.as_deref()))
+            .collect();
+        print!("{}", markdown::document(question, &result.text, &sources));
+        return Ok(result);
+    }
+
+    let width = output_width(cli);
+
+    // Check whether the cited sources disagree, comparing each source's
+    // passages most relevant to the question
+    let passages: Vec<String> = result
+        .sources
+        .iter()
+        .take(5)
+        .map(|c| {
+            c.text
+                .as_deref()
+                .map(|t| extractive::compress(t, question, extractive::Budget::Tokens(CONFLICT_PASSAGE_TOKENS)))
+                .unwrap_or_default()
+        })
+        .collect();
+    let snippets: Vec<(usize, &str)> = passages
+        .iter()
+        .enumerate()
+        .filter(|(_, p)| !p.is_empty())
+        .map(|(i, p)| (i, p.as_str()))
+        .collect();
+    let conflict = conflict::detect(&snippets);
+
+    if cli.compact {
+        if !stream {
+            println!("{}", fill(&result.text, width, "", ""));
+        }
+        if let Some(c) = &conflict {
+            let (a, b) = (snippets[c.a], snippets[c.b]);
+            println!("sources conflict: {}", c.reason);
+            println!("{}", fill(&conflict_line(&result.sources[a.0].url, a.1), width, "", "  "));
+            println!("{}", fill(&conflict_line(&result.sources[b.0].url, b.1), width, "", "  "));
+        }
+        if let Some(g) = &grounding {
+            print_grounding(g, width, false);
+        }
+        if !cli.no_sources && !result.sources.is_empty() {
+            let sources: Vec<String> = result
+                .sources
+                .iter()
+                .take(cli.sources.unwrap_or(ANSWER_SOURCES))
+                .map(|c| source_line(cli, &c.url, c.title.as_deref(), c.published.as_deref(), false))
+                .collect();
+            println!("sources: {}", sources.join(" | "));
+        }
+    } else {
+        if !stream {
+            println!("{}", "Answer:".bold().green());
+            println!();
+            println!("{}", fill(&result.text, width, "  ", "  "));
+        }
+        println!();
+
+        if let Some(c) = &conflict {
+            let (a, b) = (snippets[c.a], snippets[c.b]);
+            println!("{} {}", "Sources conflict:".yellow().bold(), c.reason);
+            print_side_by_side(
+                (&result.sources[a.0].url, a.1),
+                (&result.sources[b.0].url, b.1),
+                width,
+            );
+            println!();
+        }
+
+        if let Some(g) = &grounding {
+            print_grounding(g, width, true);
+            println!();
+        }
+
+        if !cli.no_sources && !result.sources.is_empty() {
+            println!("{}", "Sources:".dimmed());
+            for c in result.sources.iter().take(cli.sources.unwrap_or(ANSWER_SOURCES)) {
+                println!("  {}", source_line(cli, &c.url, c.title.as_deref(), c.published.as_deref(), true));
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+/// --degrade for an answer that stayed rate limited: ask again without the
+/// cited pages' texts (so no conflict check or grounding), unstreamed
+async fn degraded_answer(client: &mut ExaClient, query: String) -> Result<AnswerResponse> {
+    let request = AnswerRequest::builder().query(query).text(false).build()?;
+    let result = client.answer(request).await.context("Degraded request failed too")?;
+    mark_degraded("an answer without source texts");
+    Ok(result)
+}
+
+/// The answer as JSON: citation texts only with --content, sources cut to --sources
+pub fn answer_json(cli: &Cli, result: &Answer, grounding: Option<&Grounding>) -> Result<serde_json::Value> {
+    let mut value = serde_json::to_value(result)?;
+    if let Some(g) = grounding {
+        value["grounding"] = serde_json::to_value(g)?;
+    }
+    if let Some(citations) = value.get_mut("citations").and_then(|c| c.as_array_mut()) {
+        if cli.no_sources {
+            citations.clear();
+        } else if !cli.content {
+            for c in citations.iter_mut().filter_map(|c| c.as_object_mut()) {
+                c.remove("text");
+            }
+        }
+    }
+    select_sources(cli, &mut value, ANSWER_SOURCES);
+    Ok(value)
+}
+
+/// Size of the per-source passage compared by the answer conflict check
+pub const CONFLICT_PASSAGE_TOKENS: usize = 80;
+
+/// Compact conflict line: `url: snippet`
+pub fn conflict_line(url: &str, snippet: &str) -> String {
+    format!("{}: {}", url, snippet.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+/// The --grounding report: each claim prefixed by the sources supporting it
+pub fn print_grounding(g: &Grounding, width: Option<usize>, pretty: bool) {
+    let summary = format!("{}/{} claims supported", g.supported, g.claims.len());
+    if !pretty {
+        println!("grounding: {}", summary);
+        for c in &g.claims {
+            println!("{}", fill(&format!("{} {}", c.marker(), c.sentence), width, "", "  "));
+        }
+        return;
+    }
+    println!("{} {}", "Grounding:".bold(), summary.dimmed());
+    for c in &g.claims {
+        let marker = if c.sources.is_empty() { c.marker().yellow() } else { c.marker().cyan() };
+        let indent = " ".repeat(c.marker().len() + 3);
+        let line = fill(&c.sentence, width.map(|w| w.saturating_sub(indent.len())), "", "");
+        let mut lines = line.lines();
+        println!("  {} {}", marker, lines.next().unwrap_or(""));
+        for l in lines {
+            println!("{}{}", indent, l);
+        }
+    }
+}
+
+/// Two labelled snippets in columns, or stacked when the terminal is narrow
+pub fn print_side_by_side(left: (&str, &str), right: (&str, &str), width: Option<usize>) {
+    let total = width.unwrap_or(100);
+    let col = total.saturating_sub(3) / 2;
+    if col < 30 {
+        for (url, text) in [left, right] {
+            println!("  {}", url.cyan());
+            println!("{}", fill(text, width, "    ", "    "));
+        }
+        return;
+    }
+
+    let column = |url: &str, text: &str| -> Vec<String> {
+        let mut lines = vec![truncate_text(url, col)];
+        lines.extend(fill(text, Some(col), "", "").lines().map(|l| l.to_string()));
+        lines
+    };
+    let (l, r) = (column(left.0, left.1), column(right.0, right.1));
+    for i in 0..l.len().max(r.len()) {
+        let a = l.get(i).map(String::as_str).unwrap_or("");
+        let b = r.get(i).map(String::as_str).unwrap_or("");
+        let pad = col.saturating_sub(a.chars().count());
+        if i == 0 {
+            println!("{}{} | {}", a.cyan(), " ".repeat(pad), b.cyan());
+        } else {
+            println!("{}{} | {}", a, " ".repeat(pad), b);
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use anyhow::{bail, Result};
+use colored::Colorize;
+use exa_cli::diag;
+
+use crate::archive;
+use crate::cli::{ArchiveCommand, Cli};
+#[cfg(feature = "local-index")]
+use crate::local_index;
+use crate::render::{get_max_chars, to_json, truncate_text};
+
+pub fn cmd_archive(cli: &Cli, action: &ArchiveCommand) -> Result<()> {
+    match action {
+        ArchiveCommand::List => {
+            let entries: Vec<_> = archive::list()?
+                .into_iter()
+                .filter(|(meta, _)| cli.domain.as_ref().is_none_or(|d| meta.domain.ends_with(d.as_str())))
+                .map(|(meta, _)| meta)
+                .collect();
+            if cli.json {
+                println!("{}", to_json(&entries, cli.compact)?);
+                return Ok(());
+            }
+            if entries.is_empty() {
+                diag!("Archive is empty.");
+                std::process::exit(3);
+            }
+            for meta in &entries {
+                let title = meta.title.as_deref().unwrap_or("N/A");
+                let fetched = meta.fetched_at.format("%Y-%m-%d");
+                if cli.compact {
+                    println!("{} {} {} {}", archive::short_hash(&meta.hash), fetched, meta.url, title);
+                } else {
+                    println!("{} {} {}", archive::short_hash(&meta.hash).yellow(), fetched.to_string().dimmed(), title.bold());
+                    println!("  {}", meta.url.cyan());
+                }
+            }
+        }
+        ArchiveCommand::Search { terms } => {
+            if terms.is_empty() {
+                bail!("No search terms provided");
+            }
+            let hits = archive::search(terms)?;
+            if cli.json {
+                let metas: Vec<_> = hits.iter().map(|h| &h.meta).collect();
+                println!("{}", to_json(&metas, cli.compact)?);
+                return Ok(());
+            }
+            if hits.is_empty() {
+                diag!("No archived pages match.");
+                std::process::exit(3);
+            }
+            let max_chars = get_max_chars(cli);
+            for hit in &hits {
+                let title = hit.meta.title.as_deref().unwrap_or("N/A");
+                if cli.compact {
+                    println!("{} {} {}", archive::short_hash(&hit.meta.hash), hit.meta.url, title);
+                    println!("  {}", truncate_text(&hit.snippet, max_chars));
+                } else {
+                    println!("{} {}", archive::short_hash(&hit.meta.hash).yellow(), title.bold());
+                    println!("  {}", hit.meta.url.cyan());
+                    println!("  {}", truncate_text(&hit.snippet, max_chars).dimmed());
+                }
+            }
+        }
+        ArchiveCommand::Show { hash } => {
+            let (meta, text) = archive::show(hash)?;
+            if cli.json {
+                let mut value = serde_json::to_value(&meta)?;
+                value["text"] = serde_json::Value::String(text);
+                println!("{}", to_json(&value, cli.compact)?);
+                return Ok(());
+            }
+            if cli.compact {
+                println!("{}", meta.title.as_deref().unwrap_or("N/A"));
+                println!("url: {}", meta.url);
+                println!("fetched: {}", meta.fetched_at.to_rfc3339());
+            } else {
+                println!("{} {}", "Title:".bold(), meta.title.as_deref().unwrap_or("N/A"));
+                println!("{} {}", "URL:".cyan(), meta.url);
+                println!("{} {}", "Fetched:".dimmed(), meta.fetched_at.format("%Y-%m-%d %H:%M:%S UTC"));
+                println!();
+            }
+            println!("{}", text);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(feature = "local-index")]
+pub fn cmd_local_search(cli: &Cli, query: &str) -> Result<()> {
+    let index = local_index::LocalIndex::open()?;
+    let added = index.sync_archive()?;
+    if cli.verbose > 0 && added > 0 {
+        diag!("Indexed {} new archived page(s)", added);
+    }
+
+    let hits = index.search(query, cli.num)?;
+    if cli.json {
+        println!("{}", to_json(&hits, cli.compact)?);
+        return Ok(());
+    }
+    if hits.is_empty() {
+        diag!("No local results found.");
+        std::process::exit(3);
+    }
+
+    let max_chars = get_max_chars(cli);
+    for (i, hit) in hits.iter().enumerate() {
+        let title = if hit.title.is_empty() { "N/A" } else { hit.title.as_str() };
+        if cli.compact {
+            println!("[{}] {}", i + 1, title);
+            println!("url: {}", hit.url);
+            println!("snippet: {}", truncate_text(&hit.snippet, max_chars));
+        } else {
+            println!("{}", format!("--- Result {} ({:.2}) ---", i + 1, hit.score).dimmed());
+            println!("{} {}", "Title:".bold(), title);
+            println!("{} {}", "Link:".cyan(), hit.url);
+            println!("{} {}", "Hash:".dimmed(), archive::short_hash(&hit.id));
+            println!("{}", truncate_text(&hit.snippet, max_chars));
+            println!();
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,65 @@
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use exa_cli::audit::{self, AuditKey};
+use exa_cli::{diag, vars, KeyManager};
+use std::fs;
+
+use crate::cli::{AuditCommand, Cli};
+use crate::render::to_json;
+
+/// `exa init --audit-key`: create the request-log signing key
+pub fn cmd_audit_init(cli: &Cli) -> Result<()> {
+    let dir = KeyManager::get_config_dir()?;
+    let public = AuditKey::generate(&dir)?;
+    if cli.json {
+        let summary = serde_json::json!({
+            "publicKey": public,
+            "keyFile": dir.join(audit::KEY_FILE),
+            "publicKeyFile": dir.join(audit::PUBLIC_KEY_FILE),
+        });
+        println!("{}", to_json(&summary, cli.compact)?);
+        return Ok(());
+    }
+    println!("{}", public);
+    diag!("{} {} (readable only by you)", "Wrote".green(), dir.join(audit::KEY_FILE).display());
+    diag!("Keep the public key above somewhere the log's writer can't change, for `exa audit verify --public-key`");
+    if vars::get("EXA_LOG_REQUESTS").is_none_or(|v| v != "1") {
+        diag!("{} entries are only written with EXA_LOG_REQUESTS=1", "Note:".yellow());
+    }
+    Ok(())
+}
+
+pub fn cmd_audit(cli: &Cli, action: &AuditCommand) -> Result<()> {
+    let AuditCommand::Verify { public_key, file } = action;
+    let dir = KeyManager::get_config_dir()?;
+    let key = audit::public_key(&dir, public_key.as_deref())?;
+    let paths = match file {
+        Some(path) => vec![path.clone()],
+        None => audit::LOG_FILES.iter().map(|f| dir.join(f)).filter(|p| p.exists()).collect(),
+    };
+    if paths.is_empty() {
+        bail!("No request log in {}", dir.display());
+    }
+    let mut text = String::new();
+    for path in &paths {
+        text.push_str(&fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?);
+    }
+    let lines: Vec<&str> = text.lines().filter(|l| !l.trim().is_empty()).collect();
+    let result = audit::verify(&lines, &key);
+
+    if cli.json {
+        println!("{}", to_json(&result, cli.compact)?);
+    } else if result.failure.is_none() {
+        println!("{} {} signed entries verified", "OK".green(), result.signed);
+        if result.unsigned_before_signing > 0 {
+            println!("{}", format!("{} earlier entries are unsigned", result.unsigned_before_signing).dimmed());
+        }
+    }
+    if let Some(f) = &result.failure {
+        bail!("Entry {} fails verification: {}", f.line, f.reason);
+    }
+    if result.signed == 0 {
+        bail!("No signed entries; run `exa init --audit-key` and log with EXA_LOG_REQUESTS=1");
+    }
+    Ok(())
+}
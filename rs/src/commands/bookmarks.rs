@@ -0,0 +1,152 @@
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use exa_cli::{diag, warnings, ExaClient};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+
+use crate::bookmarks;
+use crate::cli::Cli;
+use crate::commands::{finish_items, search_estimate, ItemError};
+use crate::commands::search::fetch_find;
+use crate::render::to_json;
+
+pub fn read_bookmarks(path: &std::path::Path) -> Result<Vec<bookmarks::Bookmark>> {
+    let html = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let marks = bookmarks::parse(&html);
+    if marks.is_empty() {
+        bail!("No http(s) bookmarks found in {}", path.display());
+    }
+    Ok(marks)
+}
+
+/// List parsed bookmarks by folder (no API calls)
+pub fn cmd_list_bookmarks(cli: &Cli, path: &std::path::Path) -> Result<()> {
+    let marks = read_bookmarks(path)?;
+    if cli.json {
+        let value: Vec<_> = marks
+            .iter()
+            .map(|b| serde_json::json!({ "folder": b.folder, "title": b.title, "url": b.url }))
+            .collect();
+        println!("{}", to_json(&value, cli.compact)?);
+        return Ok(());
+    }
+    let mut folder = None;
+    for b in &marks {
+        if folder != Some(&b.folder) {
+            folder = Some(&b.folder);
+            let name = if b.folder.is_empty() { "(top level)" } else { &b.folder };
+            if cli.compact {
+                println!("[{}]", name);
+            } else {
+                println!("{}", name.bold());
+            }
+        }
+        println!("  {} {}", b.title, b.url.dimmed());
+    }
+    Ok(())
+}
+
+/// A find-similar suggestion seeded by a bookmark
+#[derive(Serialize)]
+pub struct BookmarkSuggestion {
+    folder: String,
+    seed: String,
+    title: Option<String>,
+    url: String,
+}
+
+/// Run find-similar for each bookmark (budget-capped like `cache warm`) and
+/// print new URLs grouped by the bookmark's folder
+pub async fn cmd_import_bookmarks(
+    client: &mut ExaClient,
+    cli: &Cli,
+    path: &std::path::Path,
+    budget: Option<f64>,
+    max_requests: usize,
+) -> Result<()> {
+    let marks = read_bookmarks(path)?;
+    let mut seen: HashSet<String> = marks.iter().map(|b| b.url.trim_end_matches('/').to_string()).collect();
+
+    let mut suggestions: Vec<BookmarkSuggestion> = Vec::new();
+    let mut attempted = 0;
+    let mut errors = Vec::new();
+    let mut requests = 0;
+    let mut spent = 0.0;
+    let mut max_cost = search_estimate(cli);
+    let mut stopped = None;
+
+    for mark in &marks {
+        if requests >= max_requests {
+            stopped = Some(format!("request limit ({}) reached", max_requests));
+            break;
+        }
+        // Strict budget: don't start a request that could push spend past the cap
+        if let Some(budget) = budget {
+            if spent + max_cost > budget {
+                stopped = Some(format!("budget ${:.4} reached", budget));
+                break;
+            }
+        }
+
+        attempted += 1;
+        let (results, cached) = match fetch_find(client, cli, mark.url.clone(), true).await {
+            Ok(r) => r,
+            Err(e) if cli.fail_fast => return Err(e),
+            Err(e) => {
+                // The output is a plain list, so failures go to the warnings channel
+                warnings::emit("item_failed", format!("find-similar failed for {}: {}", mark.url, e), true);
+                errors.push(ItemError::new(&mark.url, &e));
+                continue;
+            }
+        };
+        if !cached {
+            requests += 1;
+            let cost = results.cost_dollars.as_ref().and_then(|c| c.total).unwrap_or(0.0);
+            spent += cost;
+            max_cost = max_cost.max(cost);
+        }
+        for r in results.results {
+            if seen.insert(r.url.trim_end_matches('/').to_string()) {
+                suggestions.push(BookmarkSuggestion {
+                    folder: mark.folder.clone(),
+                    seed: mark.url.clone(),
+                    title: r.title,
+                    url: r.url,
+                });
+            }
+        }
+    }
+    // Group by folder, keeping the file's folder order
+    let order: Vec<&String> = marks.iter().map(|b| &b.folder).collect();
+    suggestions.sort_by_key(|s| order.iter().position(|f| **f == s.folder).unwrap_or(usize::MAX));
+
+    if cli.json {
+        println!("{}", to_json(&suggestions, cli.compact)?);
+    } else {
+        let mut folder = None;
+        for s in &suggestions {
+            if folder != Some(&s.folder) {
+                folder = Some(&s.folder);
+                let name = if s.folder.is_empty() { "(top level)" } else { &s.folder };
+                if cli.compact {
+                    println!("[{}]", name);
+                } else {
+                    println!("{}", name.bold());
+                }
+            }
+            let title = s.title.as_deref().unwrap_or("N/A");
+            if cli.compact {
+                println!("{} | {}", s.url, title);
+            } else {
+                println!("  {} {}", title, s.url.cyan());
+                println!("    {}", format!("similar to {}", s.seed).dimmed());
+            }
+        }
+        diag!("{} new source(s), {} request(s), ${:.4}", suggestions.len(), requests, spent);
+    }
+    if let Some(reason) = stopped {
+        warnings::emit("stopped_early", format!("stopped early: {}", reason), true);
+    }
+    finish_items(&errors, attempted)
+}
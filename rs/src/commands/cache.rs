@@ -0,0 +1,112 @@
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use exa_cli::{diag, warnings, ExaClient};
+
+use crate::cli::{apply_preset, Cli, Commands};
+use crate::commands::search::fetch_search;
+use crate::commands::{finish_items, print_item_errors, read_query_file, search_estimate, ItemError};
+use crate::{config, saved};
+
+/// Every saved search with the flags it was saved with, parsed as `exa run`
+/// would parse it; saved finds, answers and the like are left out
+fn saved_searches(cli: &Cli) -> Result<Vec<(String, Cli)>> {
+    let mut searches = Vec::new();
+    for (name, entry) in saved::load()? {
+        let mut parsed: Cli =
+            config::parse_saved(&entry.args).with_context(|| format!("Saved search '{}' no longer parses", name))?;
+        let Commands::Search { query } = &parsed.command else {
+            if cli.verbose > 0 {
+                diag!("skipped: {} (not a search)", name);
+            }
+            continue;
+        };
+        let query = query.join(" ");
+        apply_preset(&mut parsed)?;
+        searches.push((query, parsed));
+    }
+    Ok(searches)
+}
+
+pub async fn cmd_cache_warm(
+    client: &mut ExaClient,
+    cli: &Cli,
+    from: Option<&str>,
+    budget: Option<f64>,
+    max_requests: usize,
+) -> Result<()> {
+    if cli.no_cache {
+        bail!("cache warm cannot run with --no-cache");
+    }
+    // Saved searches keep their own flags; queries from a file use these
+    let queries: Vec<(String, Option<Cli>)> = match from {
+        Some(from) => read_query_file(from)?.into_iter().map(|q| (q, None)).collect(),
+        None => saved_searches(cli)?.into_iter().map(|(q, c)| (q, Some(c))).collect(),
+    };
+
+    let mut warmed = 0;
+    let mut already = 0;
+    let mut requests = 0;
+    let mut spent = 0.0;
+    // The worst cost seen so far, or before any, an upper bound on one search
+    let mut max_cost: Option<f64> = None;
+    let mut stopped = None;
+    let mut errors = Vec::new();
+
+    for (query, saved_cli) in &queries {
+        let search_cli = saved_cli.as_ref().unwrap_or(cli);
+        if requests >= max_requests {
+            stopped = Some(format!("request limit ({}) reached", max_requests));
+            break;
+        }
+        // Strict budget: don't start a request that could push spend past the cap
+        if let Some(budget) = budget {
+            if spent + max_cost.unwrap_or_else(|| search_estimate(search_cli)) > budget {
+                stopped = Some(format!("budget ${:.4} reached", budget));
+                break;
+            }
+        }
+
+        let (results, cached) = match fetch_search(client, search_cli, query.clone()).await {
+            Ok(r) => r,
+            Err(e) if cli.fail_fast => return Err(e),
+            Err(e) => {
+                requests += 1;
+                errors.push(ItemError::new(query, &e));
+                continue;
+            }
+        };
+        if cached {
+            already += 1;
+            continue;
+        }
+        requests += 1;
+        warmed += 1;
+        let cost = results.cost_dollars.as_ref().and_then(|c| c.total).unwrap_or(0.0);
+        spent += cost;
+        max_cost = Some(max_cost.map_or(cost, |m| m.max(cost)));
+        if cli.verbose > 0 {
+            diag!("warmed: {} ({} results, ${:.4})", query, results.results.len(), cost);
+        }
+    }
+
+    let failed = errors.len();
+    let skipped = queries.len() - warmed - already - failed;
+    if cli.compact {
+        println!(
+            "warmed: {} | cached: {} | failed: {} | skipped: {} | spent: ${:.4}",
+            warmed, already, failed, skipped, spent
+        );
+    } else {
+        println!("{} {}", "Warmed:".bold(), warmed);
+        println!("{} {}", "Already cached:".dimmed(), already);
+        println!("{} {}", "Failed:".dimmed(), failed);
+        println!("{} {}", "Skipped:".dimmed(), skipped);
+        println!("{} ${:.4}", "Spent:".dimmed(), spent);
+    }
+    print_item_errors(&errors);
+    if let Some(reason) = stopped {
+        warnings::emit("stopped_early", format!("stopped early: {}", reason), true);
+    }
+
+    finish_items(&errors, warmed + already + failed)
+}
@@ -0,0 +1,105 @@
+use anyhow::{bail, Result};
+use clap::CommandFactory;
+use colored::Colorize;
+use exa_cli::diag;
+
+use crate::cli::{Cli, ConfigCommand};
+use crate::config;
+use crate::render::to_json;
+
+pub fn cmd_config(cli: &Cli, action: &ConfigCommand) -> Result<()> {
+    match action {
+        ConfigCommand::RenderEnv { show_secrets } => cmd_render_env(cli, *show_secrets),
+        ConfigCommand::List => {
+            let table = config::load_file()?.unwrap_or_default();
+            if cli.json {
+                println!("{}", to_json(&table, cli.compact)?);
+                return Ok(());
+            }
+            if table.is_empty() {
+                diag!("No settings in {}", config::file_path()?.display());
+                std::process::exit(3);
+            }
+            print!("{}", toml::to_string(&table)?);
+            Ok(())
+        }
+        ConfigCommand::Get { key } => {
+            let command = Cli::command();
+            let (section, key) = config::split_key(&command, key)?;
+            let name = display_key(section.as_deref(), &config::setting_name(&command, section.as_deref(), &key)?);
+            let table = config::load_file()?.unwrap_or_default();
+            let value = config::section(&table, section.as_deref())
+                .and_then(|t| config::find_setting(t, &key).and_then(|k| t.get(&k)));
+            let Some(value) = value else {
+                diag!("'{}' is not set", name);
+                std::process::exit(3);
+            };
+            match value {
+                _ if cli.json => println!("{}", to_json(value, cli.compact)?),
+                toml::Value::String(s) => println!("{}", s),
+                other => println!("{}", other),
+            }
+            Ok(())
+        }
+        ConfigCommand::Set { key, value } => {
+            let command = Cli::command();
+            let (section, key) = config::split_key(&command, key)?;
+            let (name, value) = config::parse_setting(&command, section.as_deref(), &key, value)?;
+            let mut table = config::load_file()?.unwrap_or_default();
+            let settings = config::section_mut(&mut table, section.as_deref())?;
+            if let Some(existing) = config::find_setting(settings, &name) {
+                settings.remove(&existing);
+            }
+            settings.insert(name.clone(), value.clone());
+            config::save_file(&table)?;
+            let name = display_key(section.as_deref(), &name);
+            if cli.compact {
+                println!("{} = {}", name, value);
+            } else {
+                println!("{} {} = {}", "Set".green(), name, value);
+            }
+            Ok(())
+        }
+        ConfigCommand::Unset { key } => {
+            let command = Cli::command();
+            let (section, key) = config::split_key(&command, key)?;
+            let mut table = config::load_file()?.unwrap_or_default();
+            let settings = config::section_mut(&mut table, section.as_deref())?;
+            let Some(existing) = config::find_setting(settings, &key) else {
+                let name = display_key(section.as_deref(), &config::setting_name(&command, section.as_deref(), &key)?);
+                bail!("'{}' is not set in {}", name, config::file_path()?.display());
+            };
+            settings.remove(&existing);
+            if let Some(name) = section.as_deref().filter(|_| settings.is_empty()) {
+                config::remove_section(&mut table, name);
+            }
+            config::save_file(&table)?;
+            let existing = display_key(section.as_deref(), &existing);
+            if cli.compact {
+                println!("unset: {}", existing);
+            } else {
+                println!("{} {}", "Unset".green(), existing);
+            }
+            Ok(())
+        }
+    }
+}
+
+/// `find.type` for a setting in the [find] section (`profiles.papers.num` in
+/// a profile), else just the name
+fn display_key(section: Option<&str>, name: &str) -> String {
+    match section {
+        Some(section) => format!("{}.{}", section, name),
+        None => name.to_string(),
+    }
+}
+
+fn cmd_render_env(cli: &Cli, show_secrets: bool) -> Result<()> {
+    let effective = config::effective()?;
+    if cli.json {
+        println!("{}", to_json(&config::render_json(&effective, show_secrets), cli.compact)?);
+    } else {
+        print!("{}", config::render_env(&effective, show_secrets));
+    }
+    Ok(())
+}
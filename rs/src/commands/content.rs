@@ -0,0 +1,279 @@
+use anyhow::{bail, Result};
+use colored::Colorize;
+use exa_cli::api::{ContentsConfig, CostDollars, SearchResponse, SearchResult};
+use exa_cli::model::Page;
+use exa_cli::{diag, fingerprint, ExaClient, ResponseCache};
+
+use crate::cli::{response_cache, Cli};
+use crate::commands::{
+    archive_results, build_contents, compress_results, finish_items, is_rate_limited, print_item_errors, scrub_results, stale_response,
+    ItemError,
+};
+use crate::diff;
+use crate::history;
+use crate::pii;
+use crate::render::{
+    deterministic_view, fill, get_max_chars, output_width, parse_fields, print_result_line, show_field, to_json,
+    truncate_text, warn_truncated,
+};
+
+pub async fn cmd_content(client: &mut ExaClient, cli: &Cli, urls: &[String], diff: bool) -> Result<()> {
+    if diff {
+        let [url] = urls else { bail!("--diff takes a single URL") };
+        return cmd_content_diff(client, cli, url.clone(), &ResponseCache::key(&["content", url])).await;
+    }
+    if urls.len() > 1 {
+        return cmd_content_many(client, cli, urls).await;
+    }
+
+    let (results, cached) = fetch_content(client, cli, &urls[0]).await?;
+    history::record(cli, results.results.len());
+    archive_results(cli, &results, cached);
+    let results = prepare(cli, results);
+
+    if cli.json {
+        println!("{}", to_json(&results, cli.compact)?);
+        return Ok(());
+    }
+
+    if results.results.is_empty() {
+        diag!("Could not extract content.");
+        std::process::exit(1);
+    }
+
+    if cli.jsonl {
+        return print_result_line(&Page::from(&results.results[0]));
+    }
+    print_content_result(cli, &Page::from(&results.results[0]))
+}
+
+/// What every output gets done to fetched pages, one page or many at a time
+pub fn prepare(cli: &Cli, results: SearchResponse) -> SearchResponse {
+    let mut results = if cli.deterministic { deterministic_view(&results, false) } else { results };
+    scrub_results(cli, &mut results);
+    fingerprint::annotate(&mut results.results);
+    compress_results(cli, &mut results, None);
+    results
+}
+
+/// Contents for one URL with the response cache. Returns the response and whether it came from cache.
+pub async fn fetch_content(client: &mut ExaClient, cli: &Cli, url: &str) -> Result<(SearchResponse, bool)> {
+    let contents = build_contents(cli).unwrap_or_else(ContentsConfig::text);
+    // Plain text keeps the key --diff compares against
+    let ckey = match (cli.highlights, &cli.verbosity) {
+        (None, None) => ResponseCache::key(&["content", url]),
+        (highlights, verbosity) => {
+            let highlights = highlights.map(|n| n.to_string()).unwrap_or_default();
+            ResponseCache::key(&["content", url, &highlights, verbosity.as_deref().unwrap_or("")])
+        }
+    };
+    if !cli.no_cache {
+        if let Some(cached) = response_cache(cli).read(&ckey) {
+            if let Ok(results) = serde_json::from_str::<SearchResponse>(&cached) {
+                if !results.results.is_empty() {
+                    return Ok((results, true));
+                }
+            }
+        }
+    }
+
+    let results = match client.get_contents(vec![url.to_string()], contents).await {
+        // There is no cheaper request for a page than fetching it
+        Err(e) if cli.degrade && is_rate_limited(&e) => match stale_response(cli, &ckey) {
+            Some(stale) => return Ok((stale, true)),
+            None => return Err(e),
+        },
+        other => other?,
+    };
+    if !cli.no_cache {
+        if let Ok(data) = serde_json::to_string(&results) {
+            response_cache(cli).write(&ckey, &data);
+        }
+    }
+    Ok((results, false))
+}
+
+/// Extract several URLs, one request each. Failed URLs are reported in an
+/// "errors" section (or on stderr) while the rest are printed.
+pub async fn cmd_content_many(client: &mut ExaClient, cli: &Cli, urls: &[String]) -> Result<()> {
+    let mut results: Vec<SearchResult> = Vec::new();
+    let mut total_cost = None;
+    let mut errors = Vec::new();
+    // With --jsonl each page is written as soon as it is fetched
+    let mut streamed = 0;
+
+    for url in urls {
+        let failure = match fetch_content(client, cli, url).await {
+            Ok((resp, cached)) => {
+                archive_results(cli, &resp, cached);
+                if let Some(cost) = resp.cost_dollars.as_ref().and_then(|c| c.total) {
+                    *total_cost.get_or_insert(0.0) += cost;
+                }
+                match resp.results.into_iter().next() {
+                    Some(r) if cli.jsonl => {
+                        let page = SearchResponse { results: vec![r], cost_dollars: None, request_id: None };
+                        print_result_line(&Page::from(&prepare(cli, page).results[0]))?;
+                        streamed += 1;
+                        continue;
+                    }
+                    Some(r) => {
+                        results.push(r);
+                        continue;
+                    }
+                    None => anyhow::anyhow!("Could not extract content"),
+                }
+            }
+            Err(e) => e,
+        };
+        if cli.fail_fast {
+            return Err(failure.context(format!("Failed to extract {}", url)));
+        }
+        errors.push(ItemError::new(url, &failure));
+    }
+    history::record(cli, results.len() + streamed);
+
+    let response = SearchResponse {
+        results,
+        cost_dollars: total_cost.map(|total| CostDollars { total: Some(total) }),
+        request_id: None,
+    };
+    let response = prepare(cli, response);
+
+    if cli.jsonl {
+        print_item_errors(&errors);
+    } else if cli.json {
+        let mut value = serde_json::to_value(&response)?;
+        if !errors.is_empty() {
+            value["errors"] = serde_json::to_value(&errors)?;
+        }
+        println!("{}", to_json(&value, cli.compact)?);
+    } else {
+        for (i, r) in response.results.iter().enumerate() {
+            if i > 0 {
+                println!();
+            }
+            print_content_result(cli, &Page::from(r))?;
+        }
+        print_item_errors(&errors);
+    }
+    finish_items(&errors, urls.len())
+}
+
+/// Fetch fresh text for a URL and diff it against the last cached version.
+/// With --no-cache the fresh text isn't stored, so the baseline stays.
+pub async fn cmd_content_diff(client: &mut ExaClient, cli: &Cli, url: String, ckey: &str) -> Result<()> {
+    let previous = response_cache(cli).read_stale(ckey)
+        .and_then(|cached| serde_json::from_str::<SearchResponse>(&cached).ok())
+        .and_then(|r| r.results.into_iter().next())
+        .and_then(|r| r.text)
+        .map(|t| if cli.scrub_pii { pii::scrub(&t) } else { t });
+
+    let results = client.get_contents(vec![url.clone()], ContentsConfig::text()).await?;
+    history::record(cli, results.results.len());
+    archive_results(cli, &results, false);
+    if !cli.no_cache {
+        if let Ok(data) = serde_json::to_string(&results) {
+            response_cache(cli).write(ckey, &data);
+        }
+    }
+
+    let mut results = results;
+    scrub_results(cli, &mut results);
+    let current = match results.results.first().and_then(|r| r.text.as_deref()) {
+        Some(text) => text,
+        None => {
+            diag!("Could not extract content.");
+            std::process::exit(1);
+        }
+    };
+
+    let Some(previous) = previous else {
+        diag!("No previous version of {} cached; stored the current version.", url);
+        return Ok(());
+    };
+
+    let result = diff::diff_text(&url, &previous, current);
+
+    if cli.json {
+        println!("{}", to_json(&result, cli.compact)?);
+        return Ok(());
+    }
+
+    if !result.changed {
+        println!("No changes.");
+        return Ok(());
+    }
+
+    if cli.compact {
+        print!("{}", result.diff);
+        println!("changes: +{} -{}", result.lines_added, result.lines_removed);
+    } else {
+        for line in result.diff.lines() {
+            if line.starts_with("+++") || line.starts_with("---") {
+                println!("{}", line.bold());
+            } else if line.starts_with('+') {
+                println!("{}", line.green());
+            } else if line.starts_with('-') {
+                println!("{}", line.red());
+            } else if line.starts_with("@@") {
+                println!("{}", line.cyan());
+            } else {
+                println!("{}", line);
+            }
+        }
+        println!();
+        println!(
+            "{} {} added, {} removed",
+            "Changes:".bold(),
+            format!("{} lines", result.lines_added).green(),
+            format!("{} lines", result.lines_removed).red()
+        );
+    }
+
+    Ok(())
+}
+
+pub fn print_content_result(cli: &Cli, r: &Page) -> Result<()> {
+    let max_chars = get_max_chars(cli);
+    warn_truncated(std::iter::once(r), max_chars);
+    let fields = parse_fields(cli);
+    let width = output_width(cli);
+
+    if cli.compact {
+        if show_field(&fields, "title") {
+            println!("{}", r.title.as_deref().unwrap_or("N/A"));
+        }
+        if show_field(&fields, "url") {
+            println!("url: {}", r.url);
+        }
+        if show_field(&fields, "content") {
+            if let Some(text) = &r.text {
+                println!("{}", fill(&truncate_text(text, max_chars), width, "", ""));
+            }
+            for h in &r.highlights {
+                println!("{}", fill(h, width, "highlight: ", "  "));
+            }
+        }
+    } else {
+        if show_field(&fields, "title") {
+            println!("{} {}", "Title:".bold(), r.title.as_deref().unwrap_or("N/A"));
+        }
+        if show_field(&fields, "url") {
+            println!("{} {}", "URL:".cyan(), r.url);
+        }
+        println!();
+        if show_field(&fields, "content") {
+            if let Some(text) = &r.text {
+                println!("{}", fill(text, width, "", ""));
+            }
+            if !r.highlights.is_empty() {
+                println!("{}", "Highlights:".yellow());
+                for h in &r.highlights {
+                    println!("{}", fill(h, width, "  ", "  "));
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,99 @@
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use exa_cli::api::SearchResponse;
+use exa_cli::{diag, ExaClient};
+use std::fs;
+
+use crate::cli::{response_cache, Cli};
+use crate::commands::load_last_results;
+use crate::commands::search::{fetch_search, search_key};
+use crate::diff::{diff_results, ResultDiff};
+use crate::history;
+use crate::render::to_json;
+
+/// Compare two saved result sets (`--json` output files, or `last`)
+pub fn cmd_diff_files(cli: &Cli, files: &[String]) -> Result<()> {
+    let [previous, current] = files else { bail!("Give two result files to compare") };
+    let diff = diff_results(&load(previous)?.results, &load(current)?.results);
+    print_diff(cli, &diff)
+}
+
+/// Search again and compare with the last cached run of the same search,
+/// which the new results then replace
+pub async fn cmd_diff_query(client: &mut ExaClient, cli: &Cli, query: &str) -> Result<()> {
+    let key = search_key(cli, query);
+    let Some(cached) = response_cache(cli).read_stale(&key) else {
+        bail!("No cached run of this search; run `exa search` with the same flags first");
+    };
+    let previous: SearchResponse = serde_json::from_str(&cached).context("Failed to parse the cached run")?;
+    let (current, _) = fetch_search(client, cli, query.to_string()).await?;
+    history::record(cli, current.results.len());
+    if let Ok(data) = serde_json::to_string(&current) {
+        response_cache(cli).write(&key, &data);
+    }
+    print_diff(cli, &diff_results(&previous.results, &current.results))
+}
+
+fn load(path: &str) -> Result<SearchResponse> {
+    if path == "last" {
+        return load_last_results();
+    }
+    let data = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path))?;
+    serde_json::from_str(&data).with_context(|| format!("{} is not a search result set (exa search --json)", path))
+}
+
+fn print_diff(cli: &Cli, diff: &ResultDiff) -> Result<()> {
+    if cli.json {
+        println!("{}", to_json(diff, cli.compact)?);
+        return Ok(());
+    }
+    if diff.is_empty() {
+        diag!("No differences ({} results unchanged).", diff.unchanged);
+        return Ok(());
+    }
+    let title = |t: &Option<String>| t.clone().unwrap_or_else(|| "(untitled)".to_string());
+    for r in &diff.added {
+        if cli.compact {
+            println!("+ {} {} {}", r.rank, r.url, title(&r.title));
+        } else {
+            println!("{} {} {}", format!("+ {:>2}", r.rank).green(), title(&r.title).bold(), r.url.dimmed());
+        }
+    }
+    for r in &diff.removed {
+        if cli.compact {
+            println!("- {} {} {}", r.rank, r.url, title(&r.title));
+        } else {
+            println!("{} {} {}", format!("- {:>2}", r.rank).red(), title(&r.title).bold(), r.url.dimmed());
+        }
+    }
+    for c in &diff.changed {
+        let changes: Vec<String> = c
+            .changes
+            .iter()
+            .map(|f| match f.field {
+                // Hashes mean nothing to a reader
+                "content" => "text changed".to_string(),
+                field => format!(
+                    "{} {} -> {}",
+                    field,
+                    f.previous.as_deref().unwrap_or("none"),
+                    f.current.as_deref().unwrap_or("none")
+                ),
+            })
+            .collect();
+        if cli.compact {
+            println!("~ {} {}", c.url, changes.join("; "));
+        } else {
+            println!("{} {} {}", "~   ".yellow(), title(&c.title).bold(), c.url.dimmed());
+            println!("     {}", changes.join("; ").dimmed());
+        }
+    }
+    diag!(
+        "{} added, {} removed, {} changed, {} unchanged",
+        diff.added.len(),
+        diff.removed.len(),
+        diff.changed.len(),
+        diff.unchanged
+    );
+    Ok(())
+}
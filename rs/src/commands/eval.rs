@@ -0,0 +1,78 @@
+use anyhow::{bail, Result};
+use colored::Colorize;
+use exa_cli::ExaClient;
+
+use crate::cli::Cli;
+use crate::commands::{finish_items, print_item_errors, ItemError};
+use crate::commands::search::fetch_search;
+use crate::eval;
+use crate::render::to_json;
+
+/// Run every eval case through search and report hit@k / MRR
+pub async fn cmd_eval(client: &mut ExaClient, cli: &Cli, path: &std::path::Path) -> Result<()> {
+    let cases = eval::load_cases(path)?;
+    if cases.is_empty() {
+        bail!("No cases in {}", path.display());
+    }
+
+    let mut scores = Vec::new();
+    let mut errors = Vec::new();
+    for case in &cases {
+        let results = match fetch_search(client, cli, case.query.clone()).await {
+            Ok((results, _)) => results,
+            Err(e) if cli.fail_fast => return Err(e),
+            Err(e) => {
+                errors.push(ItemError::new(&case.query, &e));
+                continue;
+            }
+        };
+        let urls: Vec<&str> = results.results.iter().map(|r| r.url.as_str()).collect();
+        scores.push(eval::score_case(case, &urls));
+    }
+    if scores.is_empty() {
+        print_item_errors(&errors);
+        return finish_items(&errors, cases.len());
+    }
+    let report = eval::report(cli.num, &cli.search_type, scores);
+
+    if cli.json {
+        let mut value = serde_json::to_value(&report)?;
+        if !errors.is_empty() {
+            value["errors"] = serde_json::to_value(&errors)?;
+        }
+        println!("{}", to_json(&value, cli.compact)?);
+        return finish_items(&errors, cases.len());
+    }
+    print_item_errors(&errors);
+
+    if cli.compact {
+        for c in &report.cases {
+            let rank = c.rank.map(|r| r.to_string()).unwrap_or_else(|| "-".to_string());
+            println!("{}\t{}", rank, c.query);
+        }
+        println!("hit@{}: {:.3} mrr: {:.3}", report.k, report.hit_at_k, report.mrr);
+        return finish_items(&errors, cases.len());
+    }
+
+    println!("{}", format!("{:>5}  {:>5}  query", "rank", "rr").bold());
+    for c in &report.cases {
+        let rank = c.rank.map(|r| r.to_string()).unwrap_or_else(|| "-".to_string());
+        let line = format!("{:>5}  {:>5.2}  {}", rank, c.reciprocal_rank, c.query);
+        if c.hit {
+            println!("{}", line);
+        } else {
+            println!("{}", line.red());
+        }
+    }
+    println!();
+    println!(
+        "{} cases, type {}: {} {:.3}  {} {:.3}",
+        report.cases.len(),
+        report.search_type,
+        format!("hit@{}", report.k).bold(),
+        report.hit_at_k,
+        "MRR".bold(),
+        report.mrr
+    );
+    finish_items(&errors, cases.len())
+}
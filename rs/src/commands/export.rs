@@ -0,0 +1,82 @@
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use exa_cli::api::SearchResult;
+use exa_cli::diag;
+use exa_cli::model::Page;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::cli::{Cli, ExportTarget};
+use crate::commands::{load_last_results, load_policy, scrub_results};
+use crate::render::truncate_text;
+use crate::zotero;
+
+/// Send (a selection of) the last result set to Zotero or write it as CSL-JSON
+pub async fn cmd_export(cli: &Cli, target: ExportTarget, ids: &[usize], output: Option<&PathBuf>) -> Result<()> {
+    let mut results = load_last_results()?;
+    scrub_results(cli, &mut results);
+    let selected: Vec<&SearchResult> = if ids.is_empty() {
+        results.results.iter().collect()
+    } else {
+        ids.iter()
+            .map(|&id| {
+                id.checked_sub(1)
+                    .and_then(|i| results.results.get(i))
+                    .with_context(|| format!("No result #{} in the last result set", id))
+            })
+            .collect::<Result<_>>()?
+    };
+    let policy = load_policy(cli);
+    let selected: Vec<&SearchResult> = selected
+        .into_iter()
+        .filter(|r| {
+            let refused = !cli.force && policy.blocks(&r.url);
+            if refused {
+                diag!("{} {} (do-not-ingest; use --force)", "Refused".red(), r.url);
+            }
+            !refused
+        })
+        .collect();
+    if selected.is_empty() {
+        bail!("Nothing to export");
+    }
+
+    let pages: Vec<Page> = selected.into_iter().map(Page::from).collect();
+    let abstracts: Vec<Option<String>> = pages
+        .iter()
+        .map(|p| match p.highlights.first() {
+            Some(highlight) => Some(highlight.clone()),
+            None => p.text.as_deref().map(|t| truncate_text(t, 500)),
+        })
+        .collect();
+    let refs: Vec<zotero::Reference> = pages
+        .iter()
+        .zip(&abstracts)
+        .map(|(p, abstract_note)| zotero::Reference {
+            title: p.title.as_deref().unwrap_or(&p.url),
+            url: &p.url,
+            published_date: p.published.as_deref(),
+            abstract_note: abstract_note.as_deref(),
+            content_hash: p.content_hash.as_deref(),
+        })
+        .collect();
+
+    match target {
+        ExportTarget::Zotero => {
+            zotero::save_to_connector(&reqwest::Client::new(), &refs).await?;
+            diag!("{} {} item(s) to Zotero", "Saved".green(), refs.len());
+        }
+        ExportTarget::CslJson => {
+            let items: Vec<_> = refs.iter().enumerate().map(|(i, r)| zotero::csl_item(r, i + 1)).collect();
+            let json = serde_json::to_string_pretty(&items)?;
+            match output {
+                Some(path) => {
+                    fs::write(path, json).with_context(|| format!("Failed to write {}", path.display()))?;
+                    diag!("{} {} item(s) to {}", "Wrote".green(), items.len(), path.display());
+                }
+                None => println!("{}", json),
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,82 @@
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use exa_cli::key_manager::query_hmac;
+use exa_cli::{diag, KeyManager};
+use std::fs;
+
+use crate::cli::Cli;
+use crate::commands::run_exa;
+use crate::history;
+use crate::render::to_json;
+
+/// Shown in place of a query kept only as a hash
+const HIDDEN: &str = "(query not kept)";
+
+pub fn cmd_history(cli: &Cli, terms: &[String], command: Option<&str>, limit: usize, clear: bool) -> Result<()> {
+    if clear {
+        let path = history::path()?;
+        match fs::remove_file(&path) {
+            Ok(()) => diag!("History cleared."),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => diag!("History is empty."),
+            Err(e) => return Err(e).with_context(|| format!("Failed to remove {}", path.display())),
+        }
+        return Ok(());
+    }
+
+    let entries = history::load()?;
+    let terms_hmac = match terms {
+        [] => None,
+        terms => Some(query_hmac(&KeyManager::get_config_dir()?, &terms.join(" "))?),
+    };
+    let matches = history::filter(&entries, terms, terms_hmac.as_deref(), command);
+    let shown = &matches[matches.len().saturating_sub(limit)..];
+    if cli.json {
+        let values: Vec<_> = shown
+            .iter()
+            .map(|(id, e)| {
+                let mut value = serde_json::to_value(e)?;
+                value["id"] = (*id).into();
+                Ok(value)
+            })
+            .collect::<Result<_>>()?;
+        println!("{}", to_json(&values, cli.compact)?);
+        return Ok(());
+    }
+    if shown.is_empty() {
+        diag!("No matching history entries.");
+        std::process::exit(3);
+    }
+    for (id, e) in shown {
+        let ts = e.ts.format("%Y-%m-%d %H:%M");
+        let query = e.query.as_deref();
+        if cli.compact {
+            println!("{} {} {} {} ({})", id, ts, e.command, query.unwrap_or(HIDDEN), e.results);
+        } else {
+            println!(
+                "{} {} {} {} {}",
+                format!("{:>4}", id).yellow(),
+                ts.to_string().dimmed(),
+                format!("{:<8}", e.command).cyan(),
+                query.map_or_else(|| HIDDEN.dimmed(), |q| q.bold()),
+                format!("({} results)", e.results).dimmed()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Run entry `id` again with its original arguments plus `extra`, exiting with its status
+pub fn cmd_rerun(id: usize, extra: &[String]) -> Result<()> {
+    let entries = history::load()?;
+    let Some(entry) = id.checked_sub(1).and_then(|i| entries.get(i)) else {
+        bail!("No history entry #{} (see `exa history`)", id);
+    };
+    if entry.args.is_empty() {
+        bail!(
+            "History entry #{} kept only a hash of its query; `exa config set log.include_queries true` \
+             keeps queries for `exa rerun`",
+            id
+        );
+    }
+    run_exa(entry.args.iter().chain(extra))
+}
@@ -0,0 +1,165 @@
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use exa_cli::key_manager::{check_key, mask_key, parse_keys};
+use exa_cli::{diag, vars, KeyManager};
+use std::fs;
+
+use crate::cli::{base_url, build_http_client, Cli};
+use crate::commands::{prompt, prompt_secret};
+use crate::config;
+use crate::render::to_json;
+
+/// Written by `exa init` when there is no config.toml yet
+pub const CONFIG_TEMPLATE: &str = "\
+# Defaults for exa's global flags, keyed by long flag name.
+# The command line beats the environment, which beats this file.
+# Manage it with `exa config set|get|unset|list`.
+
+# num = 10
+# type = \"auto\"
+# compact = true
+# cache-ttl = 120
+
+# A [<command>] section applies to that command only, over the settings above
+# (`exa config set find.type neural`).
+# [find]
+# type = \"neural\"
+
+# A [profiles.<name>] section bundles flags for --profile <name>, over all
+# of the above (`exa config set profiles.papers.num 20`).
+# [profiles.papers]
+# category = \"research paper\"
+# type = \"deep\"
+
+# Keep query text in requests.log and history.jsonl; by default only a
+# keyed hash of each query is logged (or EXA_LOG_QUERIES=1).
+# [log]
+# include_queries = true
+";
+
+/// Prompt for API keys, check them, save them (OS keyring or keys file) and
+/// write a starter config.toml
+pub async fn cmd_init(cli: &Cli, skip_validation: bool) -> Result<()> {
+    let keys_path = KeyManager::keys_file_path()?;
+    let replace = !keys_path.exists()
+        || prompt(&format!("Keys are already saved in {}. Replace them? [y/N]", keys_path.display()))?
+            .eq_ignore_ascii_case("y");
+
+    let mut saved = None;
+    if replace {
+        let keys = parse_keys(&prompt_secret("Exa API key(s), comma-separated (get one at https://exa.ai):")?);
+        if keys.is_empty() {
+            bail!("No API key entered");
+        }
+
+        let keys = if skip_validation {
+            keys
+        } else {
+            let (http, base_url) = (build_http_client(cli)?, base_url(cli));
+            let mut accepted = Vec::new();
+            for key in keys {
+                match check_key(&http, &base_url, &key).await {
+                    Ok(true) => {
+                        diag!("  {} {}", mask_key(&key), "valid".green());
+                        accepted.push(key);
+                    }
+                    Ok(false) => diag!("  {} {}", mask_key(&key), "rejected by the API".red()),
+                    Err(e) => {
+                        // Offline isn't proof the key is bad; keep it
+                        diag!("  {} {} ({:#})", mask_key(&key), "not checked".yellow(), e);
+                        accepted.push(key);
+                    }
+                }
+            }
+            if accepted.is_empty() {
+                bail!("None of the keys were accepted by the API");
+            }
+            accepted
+        };
+
+        save_keys(&keys)?;
+        saved = Some(keys.len());
+    }
+
+    let config_path = config::file_path()?;
+    let created = !config_path.exists();
+    if created {
+        fs::write(&config_path, CONFIG_TEMPLATE)
+            .with_context(|| format!("Failed to write {}", config_path.display()))?;
+        diag!("{} {}", "Wrote".green(), config_path.display());
+    } else {
+        diag!("{} {}", "Kept existing".dimmed(), config_path.display());
+    }
+
+    if cli.json {
+        let summary = serde_json::json!({
+            "keysSaved": saved,
+            "keysFile": keys_path,
+            "configFile": config_path,
+            "configCreated": created,
+        });
+        println!("{}", to_json(&summary, cli.compact)?);
+    } else if !cli.compact {
+        diag!("Try it: exa search \"rust async runtimes\" -n 3");
+    }
+    Ok(())
+}
+
+/// Store keys in the OS keyring when built with it and it works, else in the keys file
+fn save_keys(keys: &[String]) -> Result<()> {
+    #[cfg(feature = "keyring")]
+    match exa_cli::key_manager::store_keyring_keys(keys) {
+        Ok(()) => {
+            diag!("{} {} key(s) to the OS keyring", "Saved".green(), keys.len());
+            return Ok(());
+        }
+        Err(e) => diag!("{} {:#}; using the keys file instead", "Warning:".yellow(), e),
+    }
+    let path = KeyManager::save_keys_file(keys)?;
+    diag!("{} {} key(s) to {} (readable only by you)", "Saved".green(), keys.len(), path.display());
+    if vars::get("EXA_API_KEYS").is_some() || vars::get("EXA_API_KEY").is_some() {
+        diag!("{} EXA_API_KEYS/EXA_API_KEY is set and takes precedence over the saved keys", "Note:".yellow());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_template() {
+        // Everything is commented out, so the template sets nothing
+        let table: toml::Table = CONFIG_TEMPLATE.parse().unwrap();
+        assert!(table.is_empty());
+        let uncommented: String = CONFIG_TEMPLATE
+            .lines()
+            .filter(|l| l.starts_with("# ") && (l.contains(" = ") || l.starts_with("# [")))
+            .map(|l| format!("{}\n", &l[2..]))
+            .collect();
+        let table: toml::Table = uncommented.parse().unwrap();
+        let command = <Cli as clap::CommandFactory>::command();
+        let check = |section: Option<&str>, key: &str, value: &toml::Value| {
+            let raw = match value {
+                toml::Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            config::parse_setting(&command, section, key, &raw).unwrap();
+        };
+        for (key, value) in &table {
+            match value {
+                toml::Value::Table(profiles) if key == "profiles" => {
+                    for (name, profile) in profiles {
+                        config::split_key(&command, &format!("profiles.{}.x", name)).unwrap();
+                        profile.as_table().unwrap().iter().for_each(|(key, value)| check(None, key, value));
+                    }
+                }
+                toml::Value::Table(section) => {
+                    config::split_key(&command, &format!("{}.x", key)).unwrap();
+                    section.iter().for_each(|(k, value)| check(Some(key), k, value));
+                }
+                value => check(None, key, value),
+            }
+        }
+    }
+}
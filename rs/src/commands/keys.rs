@@ -0,0 +1,227 @@
+use anyhow::{bail, Result};
+use chrono::Utc;
+use colored::Colorize;
+#[cfg(feature = "keyring")]
+use exa_cli::key_manager::{forget_keyring_keys, parse_keys, store_keyring_keys};
+use exa_cli::key_manager::{mask_key, KeyBudget, KeyInfo};
+use exa_cli::{diag, KeyManager};
+use serde::Serialize;
+
+use crate::cli::{base_url, build_http_client, Cli, KeysCommand};
+use crate::commands::{finish_items, prompt_secret, ItemError};
+use crate::render::to_json;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct KeyRow {
+    index: usize,
+    key: String,
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cooldown_secs: Option<i64>,
+    requests: u64,
+    success: u64,
+    errors: u64,
+}
+
+fn key_row(index: usize, key: &str, info: &KeyInfo) -> KeyRow {
+    let cooldown_secs = info
+        .cooldown_until
+        .map(|until| (until - Utc::now()).num_seconds())
+        .filter(|secs| *secs > 0);
+    let status = if !info.valid {
+        "invalid"
+    } else if cooldown_secs.is_some() {
+        "cooldown"
+    } else {
+        "ready"
+    };
+    KeyRow {
+        index,
+        key: mask_key(key),
+        status,
+        cooldown_secs,
+        requests: info.usage.requests,
+        success: info.usage.success,
+        errors: info.usage.errors,
+    }
+}
+
+pub async fn cmd_keys(cli: &Cli, action: &KeysCommand) -> Result<()> {
+    match action {
+        KeysCommand::Add { key } => {
+            let key = match key {
+                Some(key) => key.clone(),
+                None => prompt_secret("Exa API key:")?,
+            };
+            let mut km = KeyManager::open(cli.verbose > 0)?;
+            let idx = km.add_key(&key)?;
+            if cli.compact {
+                println!("added: {} {}", idx, mask_key(key.trim()));
+            } else {
+                diag!("{} key {} {} to the {}", "Added".green(), idx, mask_key(key.trim()), km.source().as_str());
+            }
+        }
+        KeysCommand::Remove { index } => {
+            let mut km = KeyManager::open(cli.verbose > 0)?;
+            let key = km.remove_key(*index)?;
+            if cli.compact {
+                println!("removed: {} {}", index, mask_key(&key));
+            } else {
+                diag!("{} key {} {} from the {}", "Removed".green(), index, mask_key(&key), km.source().as_str());
+            }
+        }
+        KeysCommand::List => {
+            let km = KeyManager::open(cli.verbose > 0)?;
+            let rows: Vec<KeyRow> = (0..km.key_count())
+                .filter_map(|i| km.get_key_by_index(i).map(|key| key_row(i, &key, &km.key_info(i))))
+                .collect();
+            if cli.json {
+                let value = serde_json::json!({ "source": km.source().as_str(), "keys": rows });
+                println!("{}", to_json(&value, cli.compact)?);
+                return Ok(());
+            }
+            if rows.is_empty() {
+                diag!("No API keys; add one with `exa keys add` or run `exa init`");
+                std::process::exit(3);
+            }
+            for r in &rows {
+                let status = match (r.status, r.cooldown_secs) {
+                    (_, Some(secs)) => format!("cooldown {}s", secs),
+                    (status, None) => status.to_string(),
+                };
+                if cli.compact {
+                    let usage = format!("{} requests, {} ok, {} errors", r.requests, r.success, r.errors);
+                    println!("{} {} {} | {}", r.index, r.key, status, usage);
+                    continue;
+                }
+                let status = match r.status {
+                    "invalid" => status.red(),
+                    "cooldown" => status.yellow(),
+                    _ => status.green(),
+                };
+                println!("{}  {}  {}", r.index, r.key.cyan(), status);
+                println!("   {}", format!("{} requests | {} ok | {} errors", r.requests, r.success, r.errors).dimmed());
+            }
+            if !cli.compact {
+                diag!("{}", format!("From the {}", km.source().as_str()).dimmed());
+            }
+        }
+        KeysCommand::Test => {
+            let mut km = KeyManager::open(cli.verbose > 0)?;
+            if km.key_count() == 0 {
+                bail!("No API keys to test; add one with `exa keys add`");
+            }
+            let results = km.validate_keys(&build_http_client(cli)?, &base_url(cli)).await?;
+            let mut errors = Vec::new();
+            let mut rows = Vec::new();
+            for (idx, result) in results.iter().enumerate() {
+                let masked = km.get_key_by_index(idx).map(|k| mask_key(&k)).unwrap_or_default();
+                let (status, detail) = match result {
+                    Ok(true) => ("valid", None),
+                    Ok(false) => ("invalid", Some("rejected by the API".to_string())),
+                    Err(e) => ("unchecked", Some(format!("{:#}", e))),
+                };
+                if status != "valid" {
+                    let err = anyhow::anyhow!(detail.clone().unwrap_or_default());
+                    errors.push(ItemError::new(&format!("key {} {}", idx, masked), &err));
+                }
+                rows.push(serde_json::json!({ "index": idx, "key": masked, "status": status, "detail": detail }));
+            }
+            if cli.json {
+                println!("{}", to_json(&rows, cli.compact)?);
+            } else {
+                for r in &rows {
+                    let status = r["status"].as_str().unwrap_or_default();
+                    let detail = r["detail"].as_str().map(|d| format!(" ({})", d)).unwrap_or_default();
+                    let shown = match status {
+                        "valid" => status.green(),
+                        "invalid" => status.red(),
+                        _ => status.yellow(),
+                    };
+                    let key = r["key"].as_str().unwrap_or_default();
+                    if cli.compact {
+                        println!("{} {} {}{}", r["index"], key, status, detail);
+                    } else {
+                        println!("{}  {}  {}{}", r["index"], key.cyan(), shown, detail);
+                    }
+                }
+            }
+            return finish_items(&errors, rows.len());
+        }
+        KeysCommand::Budget { index, daily_requests, monthly_requests, daily_dollars, monthly_dollars, clear } => {
+            let mut km = KeyManager::open(cli.verbose > 0)?;
+            if *index >= km.key_count() {
+                bail!("No key {} (the pool has {})", index, km.key_count());
+            }
+            let mut budget = km.key_info(*index).budget;
+            if *clear {
+                budget = KeyBudget::default();
+            }
+            budget.daily_requests = daily_requests.or(budget.daily_requests);
+            budget.monthly_requests = monthly_requests.or(budget.monthly_requests);
+            budget.daily_dollars = daily_dollars.or(budget.daily_dollars);
+            budget.monthly_dollars = monthly_dollars.or(budget.monthly_dollars);
+            km.set_budget(*index, budget.clone())?;
+
+            let info = km.key_info(*index);
+            let remaining = info.remaining_budget(Utc::now());
+            if cli.json {
+                let value = serde_json::json!({ "index": index, "budget": budget, "remaining": remaining });
+                println!("{}", to_json(&value, cli.compact)?);
+            } else if remaining.is_empty() {
+                println!("Key {}: no budget", index);
+            } else {
+                println!("Key {}: {} left", index, remaining.join(", "));
+            }
+        }
+        #[cfg(feature = "keyring")]
+        KeysCommand::Store { keys } => {
+            let keys = if keys.is_empty() {
+                parse_keys(&prompt_secret("Exa API key(s), comma-separated:")?)
+            } else {
+                parse_keys(&keys.join(","))
+            };
+            if keys.is_empty() {
+                bail!("No API key entered");
+            }
+            store_keyring_keys(&keys)?;
+            if cli.compact {
+                println!("stored: {}", keys.len());
+            } else {
+                let masked: Vec<String> = keys.iter().map(|k| mask_key(k)).collect();
+                diag!("{} {} in the OS keyring", "Stored".green(), masked.join(", "));
+            }
+        }
+        #[cfg(feature = "keyring")]
+        KeysCommand::Forget => {
+            if !forget_keyring_keys()? {
+                diag!("No keys in the OS keyring");
+                std::process::exit(3);
+            }
+            if cli.compact {
+                println!("forgotten");
+            } else {
+                diag!("{} the keys in the OS keyring", "Removed".green());
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_row() {
+        let mut info = KeyInfo::default();
+        assert_eq!(key_row(0, "abcdef", &info).status, "ready");
+        info.cooldown_until = Some(Utc::now() + chrono::Duration::seconds(30));
+        let row = key_row(1, "abcdef", &info);
+        assert_eq!((row.status, row.key.as_str()), ("cooldown", "...def"));
+        assert!(row.cooldown_secs.is_some_and(|s| s > 0 && s <= 30));
+        info.valid = false;
+        assert_eq!(key_row(2, "abcdef", &info).status, "invalid");
+    }
+}
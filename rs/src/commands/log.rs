@@ -0,0 +1,136 @@
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use exa_cli::key_manager::{query_hmac, LOG_FILES};
+use exa_cli::{diag, KeyManager};
+use serde_json::{Map, Value};
+use std::fs;
+
+use crate::cli::{Cli, LogCommand};
+use crate::history;
+use crate::render::to_json;
+
+/// Fields that give a query away: its text, the arguments history keeps for
+/// `exa rerun` (which hold the text too), and the unkeyed hash older versions
+/// logged (short queries are easy to guess from it)
+const QUERY_FIELDS: [&str; 3] = ["query", "args", "query_sha256"];
+
+/// A log or history entry with its `query` text replaced by `query_hmac` (kept in
+/// place, computed with `hmac`) and any unkeyed hash dropped, and whether
+/// the entry is signed. None when there is nothing to redact.
+fn redact_line(line: &str, hmac: impl Fn(&str) -> Result<String>) -> Result<Option<(String, bool)>> {
+    let Ok(Value::Object(entry)) = serde_json::from_str::<Value>(line) else { return Ok(None) };
+    if !QUERY_FIELDS.iter().any(|f| entry.contains_key(*f)) {
+        return Ok(None);
+    }
+    let has_hmac = entry.contains_key("query_hmac");
+    let signed = entry.contains_key("sig");
+    let mut redacted = Map::new();
+    for (key, value) in entry {
+        match (key.as_str(), value.as_str()) {
+            ("query", Some(query)) if !has_hmac => {
+                redacted.insert("query_hmac".into(), hmac(query)?.into());
+            }
+            (field, _) if QUERY_FIELDS.contains(&field) => {}
+            _ => {
+                redacted.insert(key, value);
+            }
+        }
+    }
+    Ok(Some((serde_json::to_string(&redacted)?, signed)))
+}
+
+pub fn cmd_log(cli: &Cli, action: &LogCommand) -> Result<()> {
+    let LogCommand::Redact { include_signed } = *action;
+    let dir = KeyManager::get_config_dir()?;
+
+    // Redact every file in memory first, so a refusal leaves them all untouched
+    let mut rewrites = Vec::new();
+    let mut signed = 0;
+    let paths = LOG_FILES.iter().map(|name| dir.join(name)).chain([history::path()?]);
+    for path in paths {
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        let mut redacted = 0;
+        let mut out = String::with_capacity(text.len());
+        for line in text.lines() {
+            match redact_line(line, |query| query_hmac(&dir, query))? {
+                Some((line, is_signed)) => {
+                    redacted += 1;
+                    signed += usize::from(is_signed);
+                    out.push_str(&line);
+                }
+                None => out.push_str(line),
+            }
+            out.push('\n');
+        }
+        if redacted > 0 {
+            rewrites.push((path, out, redacted));
+        }
+    }
+    if signed > 0 && !include_signed {
+        bail!(
+            "{} signed entries hold query text or unkeyed hashes; redacting them breaks `exa audit verify` \
+             (pass --include-signed to redact anyway)",
+            signed
+        );
+    }
+
+    for (path, text, _) in &rewrites {
+        let tmp = path.with_extension("redact.tmp");
+        fs::write(&tmp, text).with_context(|| format!("Failed to write {}", tmp.display()))?;
+        fs::rename(&tmp, path).with_context(|| format!("Failed to replace {}", path.display()))?;
+    }
+
+    if cli.json {
+        let files: Vec<Value> = rewrites
+            .iter()
+            .map(|(path, _, redacted)| serde_json::json!({ "path": path, "redacted": redacted }))
+            .collect();
+        println!("{}", to_json(&serde_json::json!({ "files": files }), cli.compact)?);
+        return Ok(());
+    }
+    if rewrites.is_empty() {
+        diag!("No query text or unkeyed hashes in the request log or history.");
+    }
+    for (path, _, redacted) in &rewrites {
+        diag!("{} {} entries in {}", "Redacted".green(), redacted, path.display());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_line() {
+        let hmac = |query: &str| Ok(format!("k({})", query));
+        let line = r#"{"ts":"2026-01-01T00:00:00Z","cmd":"search","query":"rust","status":200}"#;
+        let (redacted, signed) = redact_line(line, hmac).unwrap().unwrap();
+        assert_eq!(redacted, r#"{"ts":"2026-01-01T00:00:00Z","cmd":"search","query_hmac":"k(rust)","status":200}"#);
+        assert!(!signed);
+
+        let line = r#"{"cmd":"search","query_hmac":"k(rust)","query":"rust","sig":"ab"}"#;
+        let (redacted, signed) = redact_line(line, hmac).unwrap().unwrap();
+        assert_eq!(redacted, r#"{"cmd":"search","query_hmac":"k(rust)","sig":"ab"}"#);
+        assert!(signed);
+
+        // An unkeyed hash from an older version goes, with or without the text
+        let line = r#"{"cmd":"search","query_sha256":"5f3a","query":"rust"}"#;
+        assert_eq!(redact_line(line, hmac).unwrap().unwrap().0, r#"{"cmd":"search","query_hmac":"k(rust)"}"#);
+        let line = r#"{"cmd":"search","query_sha256":"5f3a"}"#;
+        assert_eq!(redact_line(line, hmac).unwrap().unwrap().0, r#"{"cmd":"search"}"#);
+
+        let line = r#"{"command":"search","query":"rust","query_hmac":"k(rust)","args":["search","rust"],"results":5}"#;
+        assert_eq!(
+            redact_line(line, hmac).unwrap().unwrap().0,
+            r#"{"command":"search","query_hmac":"k(rust)","results":5}"#
+        );
+
+        assert!(redact_line(&redacted, hmac).unwrap().is_none());
+        assert!(redact_line("not json", hmac).unwrap().is_none());
+    }
+}
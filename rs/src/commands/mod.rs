@@ -0,0 +1,453 @@
+//! One module per subcommand. Helpers shared by several commands (scrubbing,
+//! archiving, the last result set, per-item errors) live here.
+
+pub mod answer;
+pub mod archive;
+#[cfg(feature = "audit")]
+pub mod audit;
+pub mod bookmarks;
+pub mod cache;
+pub mod config;
+pub mod content;
+pub mod diff;
+#[cfg(feature = "eval")]
+pub mod eval;
+#[cfg(feature = "export")]
+pub mod export;
+pub mod history;
+pub mod init;
+pub mod keys;
+pub mod log;
+pub mod research;
+pub mod saved;
+pub mod search;
+pub mod service;
+pub mod smoke;
+#[cfg(feature = "state-bundle")]
+pub mod state;
+pub mod tools;
+pub mod watch;
+pub mod websets;
+
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use exa_cli::api::{ContentsConfig, SearchResponse};
+use chrono::{DateTime, Utc};
+use exa_cli::key_manager::RateLimited;
+use exa_cli::{diag, diag_inline, warnings, KeyManager};
+use serde::Serialize;
+use std::fs;
+use std::io::IsTerminal;
+use std::path::PathBuf;
+
+use crate::cli::{response_cache, Cli};
+use crate::extractive;
+#[cfg(feature = "local-index")]
+use crate::local_index;
+use crate::pii;
+use crate::policy;
+use crate::render::to_json;
+use crate::sensitive;
+#[cfg(feature = "sqlite-export")]
+use crate::sqlite_export;
+
+/// Apply --scrub-pii to result titles, texts and highlights
+pub fn scrub_results(cli: &Cli, results: &mut SearchResponse) {
+    if !cli.scrub_pii {
+        return;
+    }
+    for r in &mut results.results {
+        r.title = r.title.as_deref().map(pii::scrub);
+        r.text = r.text.as_deref().map(pii::scrub);
+        if let Some(highlights) = &mut r.highlights {
+            for h in highlights.iter_mut() {
+                *h = pii::scrub(h);
+            }
+        }
+    }
+}
+
+/// Apply --compress-content to result texts. Sentences are scored against
+/// `query`, or against each result's title when there is no textual query
+/// (content extraction, find-similar by URL).
+pub fn compress_results(cli: &Cli, results: &mut SearchResponse, query: Option<&str>) {
+    let Some(budget) = cli.compress_content else { return };
+    let query = query.filter(|q| !q.starts_with("http://") && !q.starts_with("https://"));
+    for r in &mut results.results {
+        if let Some(text) = &r.text {
+            let focus = query.or(r.title.as_deref()).unwrap_or("");
+            r.text = Some(extractive::compress(text, focus, budget));
+        }
+    }
+}
+
+/// Build ContentsConfig from CLI flags (--content, --highlights, --verbosity)
+pub fn build_contents(cli: &Cli) -> Option<ContentsConfig> {
+    let contents = if let Some(max_characters) = cli.highlights {
+        ContentsConfig::highlights(max_characters)
+    } else if cli.content {
+        ContentsConfig::text()
+    } else {
+        return None;
+    };
+    Some(contents.verbosity(cli.verbosity.as_deref()))
+}
+
+/// Upper bound on what one search with `cli`'s flags costs (25 results or
+/// fewer, then more; a page's contents on top), so a strict --budget can be
+/// checked before the first request has reported a real cost
+pub fn search_estimate(cli: &Cli) -> f64 {
+    let search = if cli.num > 25 { 0.025 } else { 0.005 };
+    let contents = if build_contents(cli).is_some() { 0.001 * cli.num as f64 } else { 0.0 };
+    search + contents
+}
+
+/// Store result texts in the local archive when --archive is set, in the
+/// full-text index when --index is, and in a SQLite database with
+/// --export-sqlite. `cached` results were archived and indexed when they
+/// were fetched, but still go to the database, which may be a new one.
+pub fn archive_results(cli: &Cli, results: &SearchResponse, cached: bool) {
+    #[cfg(feature = "sqlite-export")]
+    if let Some(path) = &cli.export_sqlite {
+        export_sqlite(cli, path, results);
+    }
+    if cached {
+        return;
+    }
+    #[cfg(feature = "local-index")]
+    if cli.index {
+        index_results(cli, results);
+    }
+    if !cli.archive {
+        return;
+    }
+    let policy = load_policy(cli);
+    let mut stored = 0;
+    let mut refused = 0;
+    for r in &results.results {
+        let Some(text) = &r.text else { continue };
+        if !cli.force && policy.blocks(&r.url) {
+            refused += 1;
+            continue;
+        }
+        let text = if cli.scrub_pii { pii::scrub(text) } else { text.clone() };
+        match crate::archive::store(&r.url, r.title.as_deref(), r.published_date.as_deref(), &text) {
+            Ok(Some(_)) => stored += 1,
+            Ok(None) => {}
+            Err(e) => warnings::emit("archive_failed", format!("failed to archive {}: {}", r.url, e), true),
+        }
+    }
+    if refused > 0 {
+        warnings::emit(
+            "do_not_ingest",
+            format!("not archiving {} page(s) on the do-not-ingest list (use --force to override)", refused),
+            true,
+        );
+    }
+    if cli.verbose > 0 {
+        diag!("Archived {} new page(s)", stored);
+    }
+}
+
+/// Add result texts (or highlights) to the local index for `exa local`
+#[cfg(feature = "local-index")]
+fn index_results(cli: &Cli, results: &SearchResponse) {
+    let policy = load_policy(cli);
+    let mut refused = 0;
+    let mut docs = Vec::new();
+    for r in &results.results {
+        let Some(text) = r.text.clone().or_else(|| r.highlights.as_ref().map(|h| h.join("\n"))) else { continue };
+        if !cli.force && policy.blocks(&r.url) {
+            refused += 1;
+            continue;
+        }
+        let text = if cli.scrub_pii { pii::scrub(&text) } else { text };
+        docs.push(local_index::Doc {
+            id: exa_cli::fingerprint::content_hash(&text),
+            url: r.url.clone(),
+            title: r.title.clone().unwrap_or_default(),
+            text,
+        });
+    }
+    if refused > 0 {
+        warnings::emit(
+            "do_not_ingest",
+            format!("not indexing {} page(s) on the do-not-ingest list (use --force to override)", refused),
+            true,
+        );
+    }
+    match local_index::LocalIndex::open().and_then(|index| index.add(docs)) {
+        Ok(added) if cli.verbose > 0 => diag!("Indexed {} new page(s)", added),
+        Ok(_) => {}
+        Err(e) => warnings::emit("index_failed", format!("failed to index results: {:#}", e), true),
+    }
+}
+
+/// Upsert results into the --export-sqlite database; a failure only warns
+#[cfg(feature = "sqlite-export")]
+fn export_sqlite(cli: &Cli, path: &std::path::Path, results: &SearchResponse) {
+    let policy = load_policy(cli);
+    let mut rows = Vec::new();
+    let mut refused = 0;
+    for r in &results.results {
+        if !cli.force && policy.blocks(&r.url) {
+            refused += 1;
+            continue;
+        }
+        let mut r = r.clone();
+        if cli.scrub_pii {
+            r.text = r.text.as_deref().map(pii::scrub);
+        }
+        rows.push(r);
+    }
+    if refused > 0 {
+        warnings::emit(
+            "do_not_ingest",
+            format!("not exporting {} page(s) on the do-not-ingest list (use --force to override)", refused),
+            true,
+        );
+    }
+    // Content fetches have URLs, not a query
+    let query = crate::history::describe(&cli.command)
+        .filter(|(command, _)| *command != "content")
+        .map(|(_, query)| query);
+    match sqlite_export::upsert(path, query.as_deref(), &rows) {
+        Ok(exported) if cli.verbose > 0 => diag!("Exported {} result(s) to {}", exported, path.display()),
+        Ok(_) => {}
+        Err(e) => warnings::emit("export_failed", format!("failed to export to {}: {:#}", path.display(), e), true),
+    }
+}
+
+/// The do-not-ingest list; a broken config only warns
+pub fn load_policy(cli: &Cli) -> policy::DoNotIngest {
+    policy::DoNotIngest::load().unwrap_or_else(|e| {
+        warnings::emit("policy_unavailable", format!("could not load do-not-ingest list: {}", e), cli.verbose > 0);
+        policy::DoNotIngest::default()
+    })
+}
+
+/// Check a query against the sensitive-query patterns before it is sent:
+/// an error for secrets, a warning for internal hostnames and emails.
+/// --allow-sensitive skips the check.
+pub fn check_query(cli: &Cli, query: &str) -> Result<()> {
+    if cli.allow_sensitive {
+        return Ok(());
+    }
+    let findings = sensitive::SensitiveGuard::load()?.scan(query);
+    sensitive::check(&findings)?;
+    for f in &findings {
+        warnings::emit("sensitive_query", format!("query appears to contain {} ({})", f.name, f.sample), true);
+    }
+    Ok(())
+}
+
+/// Ask on stderr (even with --log-file), answer from stdin (empty at end of input)
+pub fn prompt(question: &str) -> Result<String> {
+    diag::on_terminal(|| diag_inline!("{} ", question));
+    let mut line = String::new();
+    std::io::stdin().read_line(&mut line).context("Failed to read answer")?;
+    Ok(line.trim().to_string())
+}
+
+/// Like [`prompt`], without echoing what is typed (API keys). Piped input
+/// is read as is.
+pub fn prompt_secret(question: &str) -> Result<String> {
+    if !std::io::stdin().is_terminal() {
+        return prompt(question);
+    }
+    diag::on_terminal(|| diag_inline!("{} ", question));
+    let line = rpassword::read_password().context("Failed to read answer")?;
+    Ok(line.trim().to_string())
+}
+
+/// Read queries for batch commands: one per line, '#' comments, '-' for stdin
+pub fn read_query_file(path: &str) -> Result<Vec<String>> {
+    let content = if path == "-" {
+        std::io::read_to_string(std::io::stdin()).context("Failed to read queries from stdin")?
+    } else {
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path))?
+    };
+    Ok(content
+        .lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(|l| l.to_string())
+        .collect())
+}
+
+/// A failed item of a multi-item command
+#[derive(Serialize)]
+pub struct ItemError {
+    item: String,
+    error: String,
+}
+
+impl ItemError {
+    fn new(item: &str, error: &anyhow::Error) -> Self {
+        Self { item: item.to_string(), error: format!("{:#}", error) }
+    }
+}
+
+/// Exit status when some, but not all, items of a multi-item command failed
+pub const EXIT_PARTIAL_FAILURE: i32 = 4;
+
+pub fn print_item_errors(errors: &[ItemError]) {
+    for e in errors {
+        diag!("{} {}: {}", "Failed".red(), e.item, e.error);
+    }
+}
+
+/// End a multi-item command once its output is written: success when nothing
+/// failed, an error when every attempted item failed, and otherwise exit
+/// with EXIT_PARTIAL_FAILURE
+pub fn finish_items(errors: &[ItemError], attempted: usize) -> Result<()> {
+    if errors.is_empty() {
+        return Ok(());
+    }
+    if errors.len() >= attempted {
+        bail!("All {} item(s) failed", attempted);
+    }
+    warnings::flush();
+    std::process::exit(EXIT_PARTIAL_FAILURE);
+}
+
+fn rate_limited(err: &anyhow::Error) -> Option<&RateLimited> {
+    err.chain().find_map(|e| e.downcast_ref::<RateLimited>())
+}
+
+/// Whether `err` is a request that stayed rate limited through its retries
+pub fn is_rate_limited(err: &anyhow::Error) -> bool {
+    rate_limited(err).is_some()
+}
+
+/// Results a --degrade request is cut down to
+pub const DEGRADED_NUM: usize = 3;
+
+/// --degrade's first fallback: the cached response for `ckey`, however old
+/// (none with --no-cache)
+pub fn stale_response(cli: &Cli, ckey: &str) -> Option<SearchResponse> {
+    if cli.no_cache {
+        return None;
+    }
+    let results = serde_json::from_str(&response_cache(cli).read_stale(ckey)?).ok()?;
+    mark_degraded("stale cached results");
+    Some(results)
+}
+
+/// Say what a --degrade fallback returned; shown even without --verbose
+pub fn mark_degraded(what: &str) {
+    warnings::emit("degraded", format!("Rate limited; showing {} (--degrade)", what), true);
+}
+
+/// When `err` is a request that stayed rate limited, show when each key
+/// cools down: a table on stderr, or with --json the error as JSON on stdout
+/// (`error`, `retry_at`, `keys`) so a caller can schedule the retry
+pub fn report_rate_limit(cli: &Cli, err: &anyhow::Error) {
+    let Some(limited) = rate_limited(err) else { return };
+    if cli.json {
+        if let Ok(json) = to_json(limited, cli.compact) {
+            println!("{}", json);
+        }
+        return;
+    }
+    let now = Utc::now();
+    let eta = |at: DateTime<Utc>| {
+        let secs = (at - now).num_seconds().max(0);
+        format!("{} (in {}m{:02}s)", at.format("%H:%M:%S UTC"), secs / 60, secs % 60)
+    };
+    diag!("{:<3} {:<8} {:<12} {}", "#", "Key", "State", "Ready at");
+    for k in &limited.keys {
+        let state = match k.state {
+            "ready" => k.state.green(),
+            "cooldown" => k.state.yellow(),
+            _ => k.state.red(),
+        };
+        let ready = match (k.state, k.until) {
+            ("cooldown", Some(until)) => eta(until),
+            ("ready", _) => "now".to_string(),
+            _ => "-".to_string(),
+        };
+        diag!("{:<3} {:<8} {:<12} {}", k.index, k.key, state, ready);
+    }
+    match limited.retry_at {
+        Some(at) => diag!("{} {}", "Earliest retry:".bold(), eta(at)),
+        None => diag!("{} no key will become usable by waiting; see `exa status`", "Earliest retry:".bold()),
+    }
+}
+
+/// Run exa again with `args` (config.toml applies as usual) and exit with its status
+pub fn run_exa<'a>(args: impl IntoIterator<Item = &'a String>) -> Result<()> {
+    let exe = std::env::current_exe().context("Failed to find the exa executable")?;
+    let status = std::process::Command::new(exe).args(args).status().context("Failed to run exa")?;
+    warnings::flush();
+    std::process::exit(status.code().unwrap_or(1));
+}
+
+/// Path of the last printed result set (used by preview and exporters)
+pub fn last_results_path() -> Result<PathBuf> {
+    Ok(KeyManager::get_config_dir()?.join("last_results.json"))
+}
+
+/// Remember the last printed result set; best effort
+pub fn save_last_results(results: &SearchResponse) {
+    if let (Ok(path), Ok(data)) = (last_results_path(), serde_json::to_string(results)) {
+        let _ = fs::write(path, data);
+    }
+}
+
+pub fn load_last_results() -> Result<SearchResponse> {
+    let path = last_results_path()?;
+    let data = fs::read_to_string(&path).context("No previous results; run a search first")?;
+    serde_json::from_str(&data).context("Failed to parse last results")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use exa_cli::api::SearchResult;
+
+    #[test]
+    fn test_build_contents() {
+        let contents = |args: &[&str]| {
+            let cli = Cli::parse_from(["exa", "search", "q"].iter().chain(args));
+            build_contents(&cli).map(|c| serde_json::to_value(c).unwrap())
+        };
+        assert_eq!(contents(&[]), None);
+        assert_eq!(contents(&["--content"]), Some(serde_json::json!({"text": true})));
+        assert_eq!(
+            contents(&["--content", "--highlights", "--verbosity", "full"]),
+            Some(serde_json::json!({"highlights": {"maxCharacters": 2000}, "verbosity": "full"}))
+        );
+    }
+
+    #[test]
+    fn test_compress_results() {
+        let text = "Rust has a borrow checker. The weather was nice. Ownership rules apply to every value.";
+        let mut results = SearchResponse {
+            results: vec![SearchResult {
+                title: Some("Ownership in Rust".into()),
+                url: "https://a.com".into(),
+                published_date: None,
+                text: Some(text.into()),
+                highlights: None,
+                entities: None,
+                image: None,
+                favicon: None,
+                do_not_ingest: false,
+                paywalled: false,
+                content_hash: None,
+                simhash: None,
+            }],
+            cost_dollars: None,
+            request_id: None,
+        };
+        compress_results(&Cli::parse_from(["exa", "content", "https://a.com"]), &mut results, None);
+        assert_eq!(results.results[0].text.as_deref(), Some(text));
+
+        let cli = Cli::parse_from(["exa", "content", "https://a.com", "--compress-content", "50%"]);
+        compress_results(&cli, &mut results, Some("https://a.com"));
+        let compressed = results.results[0].text.as_deref().unwrap();
+        assert!(compressed.len() < text.len() && !compressed.contains("weather"), "{}", compressed);
+    }
+}
@@ -0,0 +1,340 @@
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use chrono::{DateTime, Utc};
+use exa_cli::api::{ContentsConfig, ResearchCreateRequest};
+use exa_cli::key_manager::RateLimited;
+use exa_cli::model::{ResearchStatus, ResearchTask};
+use exa_cli::{diag, diag_inline, warnings, ExaClient};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::cli::Cli;
+use crate::commands::{check_query, rate_limited};
+use crate::commands::answer::print_grounding;
+use crate::git_notes;
+use crate::grounding::{self, Grounding};
+use crate::history;
+use crate::issues;
+use crate::markdown;
+use crate::pii;
+use crate::render::{select_sources, source_line, to_json};
+
+pub const RESEARCH_SOURCES: usize = 5;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// Longest wait for a rate-limited key before the next status check
+const MAX_POLL_WAIT: Duration = Duration::from_secs(300);
+/// Status checks rate limited in a row before giving up on the task
+const MAX_LIMITED_POLLS: u32 = 10;
+
+/// `notes` is --commit-notes' directory and --branch
+pub async fn cmd_research(
+    client: &mut ExaClient,
+    cli: &Cli,
+    query: String,
+    notes: Option<(&Path, Option<&str>)>,
+) -> Result<()> {
+    check_query(cli, &query)?;
+    issues::check(cli)?;
+    if let Some((dir, _)) = notes {
+        git_notes::repo_root(dir)?;
+    }
+    let (result, task_id) = run_task(client, cli, &query).await?;
+    history::record(cli, result.sources.len());
+
+    if !cli.json && !cli.compact {
+        diag!(); // newline after dots
+    }
+
+    let mut result = result;
+    if cli.deterministic {
+        result.request_id = None;
+        result.cost = None;
+    }
+    if cli.scrub_pii {
+        result.report = result.report.as_deref().map(pii::scrub);
+    }
+
+    let grounding = match &result.report {
+        Some(report) if cli.grounding => report_grounding(client, cli, report, &result).await,
+        _ => None,
+    };
+
+    if let Some((dir, branch)) = notes {
+        let message = git_notes::commit_message(&query, result.cost, &task_id);
+        let committed = report_markdown(cli, &query, &result)
+            .and_then(|note| git_notes::commit_note(dir, &query, &note, &message, branch));
+        match committed {
+            Ok((path, commit)) => diag!(
+                "{} {} ({})",
+                "Committed".green(),
+                path.display(),
+                commit.get(..10).unwrap_or(&commit)
+            ),
+            Err(e) => warnings::emit("notes_commit_failed", format!("could not commit the notes: {:#}", e), true),
+        }
+    }
+
+    if cli.create_issue.is_some() {
+        let body = report_body(&result)?;
+        let sources = result.sources.iter().map(|c| (c.url.as_str(), c.title.as_deref(), c.published.as_deref()));
+        let issue = issues::Issue { query: &query, body: &body, sources: sources.collect() };
+        issues::file(&client.client, cli, &issue).await;
+    }
+
+    if cli.json {
+        println!("{}", to_json(&research_json(cli, &result, grounding.as_ref())?, cli.compact)?);
+        return Ok(());
+    }
+
+    if cli.markdown {
+        print!("{}", report_markdown(cli, &query, &result)?);
+        return Ok(());
+    }
+
+    if cli.compact {
+        // Compact: just the content and sources, nothing else
+        if let Some(report) = &result.report {
+            println!("{}", report);
+        } else {
+            for output in &result.outputs {
+                println!("{}", serde_json::to_string(output)?);
+            }
+        }
+        if let Some(g) = &grounding {
+            print_grounding(g, None, false);
+        }
+        if !cli.no_sources && !result.sources.is_empty() {
+            let sources: Vec<String> = result
+                .sources
+                .iter()
+                .take(cli.sources.unwrap_or(RESEARCH_SOURCES))
+                .map(|c| source_line(cli, &c.url, c.title.as_deref(), c.published.as_deref(), false))
+                .collect();
+            println!("sources: {}", sources.join(" | "));
+        }
+    } else {
+        // Normal pretty print
+        println!();
+        println!("{}", "Research Complete".bold().green());
+        if let Some(total) = result.cost {
+            println!("{}", format!("Cost: ${:.4}", total).dimmed());
+        }
+        println!();
+
+        if let Some(report) = &result.report {
+            println!("{}", report);
+            println!();
+        } else {
+            for (i, output) in result.outputs.iter().enumerate() {
+                if result.outputs.len() > 1 {
+                    println!("{}", format!("--- Output {} ---", i + 1).bold());
+                }
+                println!("{}", serde_json::to_string_pretty(output)?);
+                println!();
+            }
+        }
+
+        if let Some(g) = &grounding {
+            print_grounding(g, None, true);
+            println!();
+        }
+
+        if !cli.no_sources && !result.sources.is_empty() {
+            println!("{}", "Sources:".dimmed());
+            for c in result.sources.iter().take(cli.sources.unwrap_or(RESEARCH_SOURCES)) {
+                println!("  {}", source_line(cli, &c.url, c.title.as_deref(), c.published.as_deref(), true));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Start a research task and poll it until it finishes. Returns the task and its id.
+pub async fn run_task(client: &mut ExaClient, cli: &Cli, query: &str) -> Result<(ResearchTask, String)> {
+    // Load schema if provided
+    let output_schema = if let Some(schema_path) = &cli.schema {
+        let schema_content =
+            fs::read_to_string(schema_path).context("Failed to read schema file")?;
+        Some(serde_json::from_str(&schema_content).context("Failed to parse schema JSON")?)
+    } else {
+        None
+    };
+
+    let model = if cli.model == "exa-research-pro" {
+        "exa-research-pro"
+    } else {
+        "exa-research"
+    };
+
+    let request = ResearchCreateRequest {
+        instructions: query.to_string(),
+        model: model.to_string(),
+        output_schema,
+    };
+
+    if !cli.json && !cli.compact {
+        diag!("{}", "Starting research task...".dimmed());
+    }
+
+    let (created, key_idx) = client.research_create(request).await?;
+    let task_id = created.research_id;
+
+    if !cli.json && !cli.compact {
+        diag!("{}", format!("Task ID: {}", task_id).dimmed());
+        diag!("{}", "Polling for results...".dimmed());
+    }
+
+    // Poll until finished, using the same key that was used for create. A
+    // rate-limited check waits for that key's cooldown instead of failing,
+    // since the task keeps running (and costing) either way.
+    let mut wait = POLL_INTERVAL;
+    let mut limited_polls = 0;
+    let result = loop {
+        tokio::time::sleep(wait).await;
+        wait = POLL_INTERVAL;
+        let status = client.research_status(&task_id, Some(key_idx)).await;
+        if let Some(limited) = status.as_ref().err().and_then(rate_limited) {
+            if limited_polls < MAX_LIMITED_POLLS {
+                limited_polls += 1;
+                wait = poll_wait(limited, key_idx, Utc::now());
+                if !cli.json && !cli.compact {
+                    diag!();
+                    diag!("{}", format!("Status check rate limited; next in {}s", wait.as_secs()).dimmed());
+                }
+                continue;
+            }
+        }
+        limited_polls = 0;
+        let status = status.with_context(|| format!("Research task {} may still be running", task_id))?;
+        let task = ResearchTask::from(status);
+
+        match task.status {
+            ResearchStatus::Completed => break task,
+            ResearchStatus::Failed => {
+                bail!(
+                    "Research task failed: {}",
+                    task.error.unwrap_or_else(|| "Unknown error".to_string())
+                );
+            }
+            ResearchStatus::Canceled => {
+                bail!("Research task was canceled");
+            }
+            ResearchStatus::Running => {
+                // Streaming: print dot to stderr so user knows it's working
+                if !cli.json && !cli.compact {
+                    diag_inline!(".");
+                }
+                continue;
+            },
+        }
+    };
+    Ok((result, task_id))
+}
+
+/// The finished task as JSON, sources cut to --sources
+pub fn research_json(cli: &Cli, result: &ResearchTask, grounding: Option<&Grounding>) -> Result<serde_json::Value> {
+    let mut value = serde_json::to_value(result)?;
+    if let Some(g) = grounding {
+        value["grounding"] = serde_json::to_value(g)?;
+    }
+    select_sources(cli, &mut value, RESEARCH_SOURCES);
+    Ok(value)
+}
+
+/// The report, or the structured outputs as JSON code blocks
+fn report_body(result: &ResearchTask) -> Result<String> {
+    Ok(match &result.report {
+        Some(report) => report.clone(),
+        None => result
+            .outputs
+            .iter()
+            .map(|o| Ok(format!("```json\n{}\n```", serde_json::to_string_pretty(o)?)))
+            .collect::<Result<Vec<_>>>()?
+            .join("\n\n"),
+    })
+}
+
+/// The report (or structured outputs) as a Markdown document with its sources
+fn report_markdown(cli: &Cli, query: &str, result: &ResearchTask) -> Result<String> {
+    let body = report_body(result)?;
+    let shown = if cli.no_sources { 0 } else { cli.sources.unwrap_or(RESEARCH_SOURCES) };
+    let sources: Vec<markdown::Source> = result
+        .sources
+        .iter()
+        .take(shown)
+        .map(|c| (c.url.as_str(), c.title.as_deref(), c.published.as_deref()))
+        .collect();
+    Ok(markdown::document(query, &body, &sources))
+}
+
+/// How long to wait after a rate-limited status check: until the polling
+/// key cools down (or, failing that, any key), within the poll bounds
+fn poll_wait(limited: &RateLimited, key_idx: usize, now: DateTime<Utc>) -> Duration {
+    let until = limited.keys.iter().find(|k| k.index == key_idx).and_then(|k| k.until).or(limited.retry_at);
+    let wait = until.and_then(|at| (at - now).to_std().ok()).unwrap_or(POLL_INTERVAL);
+    wait.clamp(POLL_INTERVAL, MAX_POLL_WAIT)
+}
+
+/// Align the report with its sources' text. Research citations carry no
+/// text, so the cited pages are fetched in one contents request; when that
+/// fails the report is still shown, without grounding.
+async fn report_grounding(client: &mut ExaClient, cli: &Cli, report: &str, task: &ResearchTask) -> Option<Grounding> {
+    let urls: Vec<String> = task.sources.iter().map(|c| c.url.clone()).collect();
+    let texts: HashMap<String, String> = if urls.is_empty() {
+        HashMap::new()
+    } else {
+        match client.get_contents(urls, ContentsConfig::text()).await {
+            Ok(pages) => pages
+                .results
+                .into_iter()
+                .filter_map(|r| {
+                    let text = if cli.scrub_pii { pii::scrub(r.text.as_deref()?) } else { r.text? };
+                    Some((r.url, text))
+                })
+                .collect(),
+            Err(e) => {
+                let msg = format!("could not fetch sources for --grounding: {:#}", e);
+                warnings::emit("grounding_unavailable", msg, true);
+                return None;
+            }
+        }
+    };
+    let sources: Vec<(&str, &str)> = task
+        .sources
+        .iter()
+        .map(|c| (c.url.as_str(), texts.get(&c.url).map(String::as_str).unwrap_or("")))
+        .collect();
+    Some(grounding::align(report, &sources))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exa_cli::key_manager::KeyCooldown;
+
+    #[test]
+    fn test_poll_wait() {
+        let now = Utc::now();
+        let key = |index: usize, secs: i64| KeyCooldown {
+            index,
+            key: "sk-...".into(),
+            state: "cooldown",
+            until: Some(now + chrono::Duration::seconds(secs)),
+        };
+        let limited = |keys: Vec<KeyCooldown>, retry_at: Option<i64>| RateLimited {
+            message: "Rate limited".into(),
+            retry_at: retry_at.map(|s| now + chrono::Duration::seconds(s)),
+            keys,
+        };
+        // The polling key's own cooldown counts, not the soonest key's
+        assert_eq!(poll_wait(&limited(vec![key(0, 20), key(1, 60)], Some(20)), 1, now), Duration::from_secs(60));
+        assert_eq!(poll_wait(&limited(vec![], Some(30)), 0, now), Duration::from_secs(30));
+        assert_eq!(poll_wait(&limited(vec![key(0, 1)], None), 0, now), POLL_INTERVAL);
+        assert_eq!(poll_wait(&limited(vec![key(0, 3600)], None), 0, now), MAX_POLL_WAIT);
+        assert_eq!(poll_wait(&limited(vec![], None), 0, now), POLL_INTERVAL);
+    }
+}
@@ -0,0 +1,71 @@
+use anyhow::{bail, Result};
+use chrono::Utc;
+use colored::Colorize;
+use exa_cli::diag;
+
+use crate::cli::{Cli, SavedCommand};
+use crate::commands::run_exa;
+use crate::render::to_json;
+use crate::saved::{self, Saved};
+
+/// `exa save <name> <query> [flags]`
+pub fn cmd_save(name: &str, replace: bool, args: &[String]) -> Result<()> {
+    saved::check_name(name)?;
+    let args = saved::command_args(args)?;
+    let mut all = saved::load()?;
+    if all.contains_key(name) && !replace {
+        bail!("'{}' is already saved; pass --replace (before the name) to replace it", name);
+    }
+    all.insert(name.to_string(), Saved { args, saved_at: Utc::now() });
+    saved::store(&all)?;
+    diag!("{} {} (run it with `exa run {}`)", "Saved".green(), name, name);
+    Ok(())
+}
+
+/// `exa run <name> [extra flags]`
+pub fn cmd_run(name: &str, extra: &[String]) -> Result<()> {
+    let all = saved::load()?;
+    let Some(entry) = all.get(name) else {
+        bail!("No saved search '{}' (see `exa saved list`)", name);
+    };
+    run_exa(entry.args.iter().chain(extra))
+}
+
+pub fn cmd_saved(cli: &Cli, action: &SavedCommand) -> Result<()> {
+    let mut all = saved::load()?;
+    match action {
+        SavedCommand::List => {
+            if cli.json {
+                println!("{}", to_json(&all, cli.compact)?);
+                return Ok(());
+            }
+            if all.is_empty() {
+                diag!("No saved searches; create one with `exa save <name> <query> [flags]`.");
+                std::process::exit(3);
+            }
+            for (name, entry) in &all {
+                if cli.compact {
+                    println!("{} {}", name, shell_words(&entry.args));
+                } else {
+                    println!("{} {}", name.bold(), shell_words(&entry.args).dimmed());
+                }
+            }
+        }
+        SavedCommand::Remove { name } => {
+            if all.remove(name).is_none() {
+                bail!("No saved search '{}'", name);
+            }
+            saved::store(&all)?;
+            diag!("Removed {}", name);
+        }
+    }
+    Ok(())
+}
+
+/// Arguments joined for display, quoting those with spaces
+pub fn shell_words(args: &[String]) -> String {
+    args.iter()
+        .map(|a| if a.contains(char::is_whitespace) || a.is_empty() { format!("\"{}\"", a) } else { a.clone() })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -0,0 +1,499 @@
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use exa_cli::api::{FindSimilarRequest, SearchRequest, SearchResponse};
+use chrono::{DateTime, Local, Utc};
+use exa_cli::key_manager::query_hmac;
+use exa_cli::{diag, fingerprint, warnings, ExaClient, KeyManager, ResponseCache};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::archive;
+use crate::cli::{response_cache, Cli};
+use crate::commands::{
+    archive_results, build_contents, check_query, compress_results, is_rate_limited, load_last_results, mark_degraded,
+    scrub_results, stale_response, DEGRADED_NUM,
+};
+use crate::feeds;
+use crate::history;
+#[cfg(feature = "email")]
+use crate::notify;
+use crate::commands::watch;
+use crate::render::{fill, output_width, print_search_results, truncate_text};
+use crate::schedule::Schedule;
+use crate::thumbnails;
+
+pub async fn cmd_search(client: &mut ExaClient, cli: &Cli, query: String) -> Result<()> {
+    #[cfg(feature = "email")]
+    notify::check(cli)?;
+    let (mut results, cached) = fetch_search(client, cli, query.clone()).await?;
+    history::record(cli, results.results.len());
+    archive_results(cli, &results, cached);
+    if cli.discover_feeds {
+        return cmd_discover_feeds(client, &results, &query).await;
+    }
+    scrub_results(cli, &mut results);
+    fingerprint::annotate(&mut results.results);
+    compress_results(cli, &mut results, Some(&query));
+    prefetch_thumbnails(client, cli, &results).await;
+    print_search_results(cli, &results)?;
+    #[cfg(feature = "email")]
+    notify::send(cli, &query, &results, false).await;
+    Ok(())
+}
+
+/// --thumbnails: only for the pretty output, and only in a terminal that can draw them
+async fn prefetch_thumbnails(client: &ExaClient, cli: &Cli, results: &SearchResponse) {
+    let other_output = cli.json || cli.jsonl || cli.markdown || cli.fzf || cli.format.is_some() || cli.rss.is_some();
+    if !cli.thumbnails || cli.compact || other_output {
+        return;
+    }
+    if let Some(protocol) = thumbnails::detect() {
+        thumbnails::prefetch(&client.client, protocol, &results.results).await;
+    }
+}
+
+/// Most URLs a --watch seen-file keeps; the oldest are dropped first
+const WATCH_SEEN_MAX: usize = 10_000;
+
+/// Where a --watch keeps the URLs it has printed, so a restarted watch
+/// resumes: `watch/<hash>.txt` in the config directory, one URL per line,
+/// named by a keyed hash of the command and query
+fn watch_seen_path(query: &str, find: bool) -> Result<PathBuf> {
+    let dir = KeyManager::get_config_dir()?;
+    let command = if find { "find" } else { "search" };
+    let hash = query_hmac(&dir, &format!("{} {}", command, query))?;
+    Ok(dir.join("watch").join(format!("{}.txt", &hash[..16])))
+}
+
+/// The URLs an earlier watch of the same query printed, oldest first
+fn load_watch_seen(path: &Path) -> Vec<String> {
+    fs::read_to_string(path).map(|text| text.lines().map(String::from).collect()).unwrap_or_default()
+}
+
+fn save_watch_seen(path: &Path, seen: &[String]) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let tmp = path.with_extension("tmp");
+    let kept = &seen[seen.len().saturating_sub(WATCH_SEEN_MAX)..];
+    fs::write(&tmp, kept.iter().map(|url| format!("{}\n", url)).collect::<String>())?;
+    fs::rename(&tmp, path).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// --watch or --cron: run the search (or, with `find`, find-similar) on
+/// `schedule`, printing the first run's results and then only results with
+/// URLs no earlier run returned. The URLs printed are saved after each run,
+/// so a watch of the same query started later carries on where it stopped. A
+/// failed run after the first only warns. With --notify, each run's new
+/// results are emailed as well. The watch shows in `exa watch list` while it
+/// runs.
+pub async fn cmd_watch(
+    client: &mut ExaClient,
+    cli: &Cli,
+    query: String,
+    schedule: &Schedule,
+    find: Option<bool>,
+) -> Result<()> {
+    #[cfg(feature = "email")]
+    notify::check(cli)?;
+    let next_run = |first: bool| schedule.next_run(first, cli.quiet_hours.as_ref(), cli.jitter);
+    let mut next = next_run(true)?;
+    let seen_path = watch_seen_path(&query, find.is_some())?;
+    let mut order = load_watch_seen(&seen_path);
+    let mut seen: HashSet<String> = order.iter().cloned().collect();
+    if !order.is_empty() && cli.verbose > 0 {
+        diag!("Resuming watch: {} URL(s) already seen ({})", order.len(), seen_path.display());
+    }
+    let _registration = watch::register(schedule)?;
+    for round in 0usize.. {
+        if round > 0 {
+            client.key_manager.save_state()?;
+        }
+        let wait = (next - Local::now()).to_std().unwrap_or_default();
+        if wait > Duration::from_secs(1) && round == 0 {
+            diag!("{}", format!("First run at {}", run_time(next)).dimmed());
+        }
+        tokio::time::sleep(wait).await;
+        let fetched = match find {
+            Some(exclude_source_domain) => fetch_find(client, cli, query.clone(), exclude_source_domain).await,
+            None => fetch_search(client, cli, query.clone()).await,
+        };
+        next = next_run(false)?;
+        let mut results = match fetched {
+            Ok((results, _)) => results,
+            Err(e) if round == 0 => return Err(e),
+            Err(e) => {
+                warnings::emit("watch_failed", format!("{:#}; trying again at {}", e, run_time(next)), true);
+                warnings::flush();
+                continue;
+            }
+        };
+        archive_results(cli, &results, false);
+        results.results.retain(|r| seen.insert(r.url.clone()));
+        order.extend(results.results.iter().map(|r| r.url.clone()));
+        if let Err(e) = save_watch_seen(&seen_path, &order) {
+            warnings::emit("watch_failed", format!("could not save the URLs seen: {:#}", e), true);
+        }
+        let time = Utc::now().format("%H:%M UTC");
+        if results.results.is_empty() {
+            if cli.verbose > 0 || round == 0 {
+                diag!("{}", format!("{} no new results; next run at {}", time, run_time(next)).dimmed());
+            }
+            continue;
+        }
+        if round > 0 && !cli.json {
+            diag!("{}", format!("{} {} new result(s)", time, results.results.len()).bold());
+        }
+        scrub_results(cli, &mut results);
+        fingerprint::annotate(&mut results.results);
+        compress_results(cli, &mut results, Some(&query));
+        print_search_results(cli, &results)?;
+        #[cfg(feature = "email")]
+        notify::send(cli, &query, &results, round > 0).await;
+        warnings::flush();
+    }
+    Ok(())
+}
+
+/// A watch's next run time: `14:30`, or `Mon 08:00` when not today
+fn run_time(at: DateTime<Local>) -> String {
+    if at.date_naive() == Local::now().date_naive() {
+        at.format("%H:%M").to_string()
+    } else {
+        at.format("%a %H:%M").to_string()
+    }
+}
+
+/// The root of `url`'s site, unless `url` is the root
+fn site_root(url: &str) -> Option<String> {
+    let mut root = reqwest::Url::parse(url).ok()?;
+    root.set_path("/");
+    root.set_query(None);
+    root.set_fragment(None);
+    Some(root.to_string()).filter(|root| root != url)
+}
+
+/// Probe each result page for feed links, falling back to its site root,
+/// and print the feeds found as OPML. Each host gets one task that probes its
+/// pages in turn, so no site sees more than one request at a time, and no
+/// URL (a shared site root especially) is fetched twice.
+pub async fn cmd_discover_feeds(client: &ExaClient, results: &SearchResponse, query: &str) -> Result<()> {
+    let mut by_host: Vec<(String, Vec<(usize, String)>)> = Vec::new();
+    for (i, r) in results.results.iter().enumerate() {
+        let host = reqwest::Url::parse(&r.url).ok().and_then(|u| u.host_str().map(str::to_string)).unwrap_or_default();
+        match by_host.iter_mut().find(|(h, _)| *h == host) {
+            Some((_, pages)) => pages.push((i, r.url.clone())),
+            None => by_host.push((host, vec![(i, r.url.clone())])),
+        }
+    }
+
+    let mut tasks = tokio::task::JoinSet::new();
+    for (_, pages) in by_host {
+        let http = client.client.clone();
+        tasks.spawn(async move {
+            let mut probed: HashMap<String, Vec<feeds::Feed>> = HashMap::new();
+            let mut out = Vec::new();
+            for (i, url) in pages {
+                let mut tried = vec![url.clone()];
+                tried.extend(site_root(&url));
+                let mut found = Vec::new();
+                for url in tried {
+                    if !probed.contains_key(&url) {
+                        let feeds = feeds::probe(&http, &url).await;
+                        probed.insert(url.clone(), feeds);
+                    }
+                    found = probed[&url].clone();
+                    if !found.is_empty() {
+                        break;
+                    }
+                }
+                out.push((i, found));
+            }
+            out
+        });
+    }
+
+    let mut per_result = Vec::new();
+    while let Some(joined) = tasks.join_next().await {
+        per_result.extend(joined?);
+    }
+    // Keep result order so the OPML follows Exa's ranking
+    per_result.sort_by_key(|(i, _)| *i);
+    let mut all: Vec<feeds::Feed> = Vec::new();
+    for (_, found) in per_result {
+        for feed in found {
+            if !all.iter().any(|f| f.url == feed.url) {
+                all.push(feed);
+            }
+        }
+    }
+
+    if all.is_empty() {
+        diag!("No feeds found on {} result sites.", results.results.len());
+        std::process::exit(3);
+    }
+    diag!(
+        "{}",
+        format!("{} feeds from {} results", all.len(), results.results.len()).dimmed()
+    );
+    print!("{}", feeds::opml(&format!("Exa: {}", query), &all));
+    Ok(())
+}
+
+/// Response cache key of a search for `query` with the flags in `cli`
+pub fn search_key(cli: &Cli, query: &str) -> String {
+    let max_age_str = cli.max_age.map(|v| v.to_string()).unwrap_or_default();
+    let highlights_str = cli.highlights.map(|v| v.to_string()).unwrap_or_default();
+    ResponseCache::key(&["search", query, &cli.num.to_string(),
+        cli.domain.as_deref().unwrap_or(""), cli.after.as_deref().unwrap_or(""),
+        cli.before.as_deref().unwrap_or(""), &cli.search_type,
+        cli.category.as_deref().unwrap_or(""), &max_age_str, &highlights_str])
+}
+
+/// Run a search through the cache. Returns the results and whether they came from cache.
+pub async fn fetch_search(client: &mut ExaClient, cli: &Cli, query: String) -> Result<(SearchResponse, bool)> {
+    check_query(cli, &query)?;
+    let ckey = search_key(cli, &query);
+
+    // Check cache
+    if !cli.no_cache {
+        if let Some(cached) = response_cache(cli).read(&ckey) {
+            if let Ok(results) = serde_json::from_str::<SearchResponse>(&cached) {
+                return Ok((results, true));
+            }
+        }
+    }
+
+    let request = SearchRequest::builder()
+        .query(&query)
+        .num(cli.num)
+        .contents(build_contents(cli))
+        .include_domains(&cli.domain)
+        .published_after(cli.after.as_deref())
+        .published_before(cli.before.as_deref())
+        .search_type(cli.search_type.as_str())
+        .category(cli.category.as_deref())
+        .max_age_hours(cli.max_age);
+
+    let results = match client.search(request.clone().build()?).await {
+        Err(e) if cli.degrade && is_rate_limited(&e) => {
+            if let Some(stale) = stale_response(cli, &ckey) {
+                return Ok((stale, true));
+            }
+            let num = cli.num.min(DEGRADED_NUM);
+            let request = request.num(num).contents(None).search_type("instant").build()?;
+            let results = client.search(request).await.context("Degraded request failed too")?;
+            mark_degraded(&format!("{} results without contents from an instant search", num));
+            // Not cached: the key is for the full request
+            return Ok((results, false));
+        }
+        other => other?,
+    };
+
+    // Write to cache
+    if !cli.no_cache {
+        if let Ok(data) = serde_json::to_string(&results) {
+            response_cache(cli).write(&ckey, &data);
+        }
+    }
+
+    Ok((results, false))
+}
+
+pub async fn cmd_find(client: &mut ExaClient, cli: &Cli, query: String, exclude_source_domain: bool) -> Result<()> {
+    #[cfg(feature = "email")]
+    notify::check(cli)?;
+    let (mut results, cached) = fetch_find(client, cli, query.clone(), exclude_source_domain).await?;
+    history::record(cli, results.results.len());
+    archive_results(cli, &results, cached);
+    if cli.discover_feeds {
+        return cmd_discover_feeds(client, &results, &query).await;
+    }
+    scrub_results(cli, &mut results);
+    fingerprint::annotate(&mut results.results);
+    compress_results(cli, &mut results, Some(&query));
+    prefetch_thumbnails(client, cli, &results).await;
+    print_search_results(cli, &results)?;
+    #[cfg(feature = "email")]
+    notify::send(cli, &query, &results, false).await;
+    Ok(())
+}
+
+/// Find-similar with the response cache. Returns the response and whether it came from cache.
+/// The seed is a URL, a bare domain or path (`exa.ai/blog`), or text, which is
+/// resolved to the top search result's URL first. With `exclude_source_domain`,
+/// pages from the seed's site are left out.
+pub async fn fetch_find(
+    client: &mut ExaClient,
+    cli: &Cli,
+    query: String,
+    exclude_source_domain: bool,
+) -> Result<(SearchResponse, bool)> {
+    check_query(cli, &query)?;
+    let seed = seed_url(&query);
+    let exclude_domain = exclude_source_domain.then(|| seed.as_deref().and_then(seed_domain)).flatten();
+    let num = cli.num.to_string();
+    let mut ckey_parts = vec!["find", query.as_str(), num.as_str(), cli.search_type.as_str()];
+    if let Some(domain) = &exclude_domain {
+        ckey_parts.push(domain);
+    } else if seed.is_none() && exclude_source_domain {
+        // A text seed's site is only known once resolved
+        ckey_parts.push("exclude-seed-site");
+    }
+    let ckey = ResponseCache::key(&ckey_parts);
+
+    if !cli.no_cache {
+        if let Some(cached) = response_cache(cli).read(&ckey) {
+            if let Ok(results) = serde_json::from_str::<SearchResponse>(&cached) {
+                return Ok((results, true));
+            }
+        }
+    }
+
+    let (seed, exclude_domain) = match seed {
+        Some(seed) => {
+            if cli.verbose > 0 && seed != query {
+                diag!("find: using {} as the seed URL", seed);
+            }
+            (seed, exclude_domain)
+        }
+        None => {
+            let seed = resolve_seed(client, cli, &query).await?;
+            let exclude_domain = exclude_source_domain.then(|| seed_domain(&seed)).flatten();
+            (seed, exclude_domain)
+        }
+    };
+
+    let request = FindSimilarRequest::builder()
+        .url(seed)
+        .num(cli.num)
+        .contents(build_contents(cli))
+        .search_type(cli.search_type.as_str())
+        .category(cli.category.as_deref())
+        .max_age_hours(cli.max_age)
+        .exclude_domains(exclude_domain);
+
+    let results = match client.find_similar(request.clone().build()?).await {
+        Err(e) if cli.degrade && is_rate_limited(&e) => {
+            if let Some(stale) = stale_response(cli, &ckey) {
+                return Ok((stale, true));
+            }
+            let num = cli.num.min(DEGRADED_NUM);
+            let request = request.num(num).contents(None).search_type("instant").build()?;
+            let results = client.find_similar(request).await.context("Degraded request failed too")?;
+            mark_degraded(&format!("{} similar pages without contents from an instant search", num));
+            return Ok((results, false));
+        }
+        other => other?,
+    };
+
+    if !cli.no_cache {
+        if let Ok(data) = serde_json::to_string(&results) {
+            response_cache(cli).write(&ckey, &data);
+        }
+    }
+
+    Ok((results, false))
+}
+
+/// The seed as a URL: http(s) URLs as given, and `https://` added to
+/// something shaped like a domain or domain/path. None for plain text.
+pub fn seed_url(seed: &str) -> Option<String> {
+    let seed = seed.trim();
+    if seed_domain(seed).is_some() {
+        return Some(seed.to_string());
+    }
+    if seed.contains(char::is_whitespace) || seed.contains("://") {
+        return None;
+    }
+    let url = reqwest::Url::parse(&format!("https://{}", seed)).ok()?;
+    let host = url.host_str()?;
+    let tld = host.rsplit('.').next().unwrap_or_default();
+    let domain_like = host.contains('.') && tld.len() >= 2 && tld.chars().all(|c| c.is_ascii_alphabetic());
+    domain_like.then(|| url.to_string())
+}
+
+/// Turn a text seed into a URL: the top result of a search for it
+async fn resolve_seed(client: &mut ExaClient, cli: &Cli, query: &str) -> Result<String> {
+    let request = SearchRequest::builder().query(query).num(1).search_type(cli.search_type.as_str()).build()?;
+    let results = client.search(request).await?;
+    let Some(top) = results.results.into_iter().next() else {
+        bail!("'{}' is not a URL and a search for it found no page to use as the seed; pass a URL", query);
+    };
+    if cli.verbose > 0 {
+        diag!("find: '{}' is not a URL; using the top search result {} as the seed", query, top.url);
+    }
+    Ok(top.url)
+}
+
+/// Registrable-ish site of a seed URL ("www." dropped); None for non-URLs
+pub fn seed_domain(seed: &str) -> Option<String> {
+    let url = reqwest::Url::parse(seed).ok()?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return None;
+    }
+    Some(archive::domain_of(seed))
+}
+
+/// Print one result of the last result set, sized for an fzf preview pane
+pub fn cmd_preview(cli: &Cli, id: usize) -> Result<()> {
+    let mut results = load_last_results()?;
+    scrub_results(cli, &mut results);
+    let Some(r) = id.checked_sub(1).and_then(|i| results.results.get(i)) else {
+        bail!("No result #{} in the last result set", id);
+    };
+
+    let width = std::env::var("FZF_PREVIEW_COLUMNS")
+        .ok()
+        .and_then(|w| w.parse().ok())
+        .or_else(|| output_width(cli));
+
+    println!("{}", fill(r.title.as_deref().unwrap_or("N/A"), width, "", "").bold());
+    println!("{}", r.url.cyan());
+    if let Some(date) = &r.published_date {
+        println!("{}", date.dimmed());
+    }
+    println!();
+    if let Some(text) = &r.text {
+        println!("{}", fill(&truncate_text(text, cli.max_chars.unwrap_or(4000)), width, "", ""));
+    }
+    if let Some(highlights) = &r.highlights {
+        for h in highlights {
+            println!("{}", fill(h, width, "• ", "  "));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Commands;
+    use clap::Parser;
+
+    #[test]
+    fn test_seed_domain() {
+        assert_eq!(seed_domain("https://www.example.com/post/1").as_deref(), Some("example.com"));
+        assert_eq!(seed_domain("https://blog.example.com/").as_deref(), Some("blog.example.com"));
+        assert_eq!(seed_domain("rust async runtimes"), None);
+        assert_eq!(seed_domain("mailto:a@b.com"), None);
+    }
+
+    #[test]
+    fn test_seed_url() {
+        assert_eq!(seed_url("https://exa.ai/blog").as_deref(), Some("https://exa.ai/blog"));
+        assert_eq!(seed_url("exa.ai/blog").as_deref(), Some("https://exa.ai/blog"));
+        assert_eq!(seed_url("www.rust-lang.org").as_deref(), Some("https://www.rust-lang.org/"));
+        assert_eq!(seed_url("rust async runtimes"), None);
+        assert_eq!(seed_url("tokio"), None);
+        assert_eq!(seed_url("v1.2"), None);
+        assert_eq!(seed_url("ftp://example.com"), None);
+
+        let cli = Cli::parse_from(["exa", "find", "https://x.com"]);
+        assert!(matches!(cli.command, Commands::Find { exclude_source_domain: true, .. }));
+        let cli = Cli::parse_from(["exa", "find", "https://x.com", "--exclude-source-domain=false"]);
+        assert!(matches!(cli.command, Commands::Find { exclude_source_domain: false, .. }));
+    }
+}
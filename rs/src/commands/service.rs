@@ -0,0 +1,324 @@
+//! `exa service install/status/uninstall <name>`: keep a saved search with
+//! --watch or --cron running in the background as a systemd user service
+//! (Linux and other Unix systems) or a launchd agent (macOS), which runs
+//! `exa run <name>`, starts at login and restarts it after a failure.
+
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use exa_cli::{diag, KeyManager};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::cli::{Cli, ServiceCommand};
+use crate::html::escape;
+use crate::saved;
+
+/// Unit names are `exa-watch-<name>.service`; launchd labels `dev.exa.watch.<name>`
+const SYSTEMD_PREFIX: &str = "exa-watch-";
+const LAUNCHD_PREFIX: &str = "dev.exa.watch.";
+
+#[derive(Clone, Copy, PartialEq)]
+enum Manager {
+    Systemd,
+    Launchd,
+}
+
+impl Manager {
+    fn current() -> Result<Self> {
+        if cfg!(target_os = "macos") {
+            Ok(Manager::Launchd)
+        } else if cfg!(unix) {
+            Ok(Manager::Systemd)
+        } else {
+            bail!("`exa service` is not supported on this system yet")
+        }
+    }
+
+    /// The unit or agent name for saved search `name`
+    fn service_name(self, name: &str) -> String {
+        match self {
+            Manager::Systemd => format!("{}{}.service", SYSTEMD_PREFIX, name),
+            Manager::Launchd => format!("{}{}", LAUNCHD_PREFIX, name),
+        }
+    }
+
+    /// The saved search a unit or agent file belongs to, if it is one of ours
+    fn saved_name(self, file_name: &str) -> Option<&str> {
+        match self {
+            Manager::Systemd => file_name.strip_prefix(SYSTEMD_PREFIX)?.strip_suffix(".service"),
+            Manager::Launchd => file_name.strip_prefix(LAUNCHD_PREFIX)?.strip_suffix(".plist"),
+        }
+    }
+
+    fn dir(self) -> Result<PathBuf> {
+        Ok(match self {
+            Manager::Systemd => dirs::config_dir().context("Could not find config directory")?.join("systemd/user"),
+            Manager::Launchd => dirs::home_dir().context("Could not find home directory")?.join("Library/LaunchAgents"),
+        })
+    }
+
+    fn path(self, name: &str) -> Result<PathBuf> {
+        let file = match self {
+            Manager::Systemd => self.service_name(name),
+            Manager::Launchd => format!("{}.plist", self.service_name(name)),
+        };
+        Ok(self.dir()?.join(file))
+    }
+}
+
+/// An ExecStart argument, quoted when it needs to be; `%` is systemd's
+/// specifier character
+fn systemd_arg(arg: &str) -> String {
+    let arg = arg.replace('%', "%%");
+    if !arg.is_empty() && !arg.contains(|c: char| c.is_whitespace() || matches!(c, '"' | '\'' | '\\' | ';')) {
+        return arg;
+    }
+    format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// A systemd user unit running `exa run <name>`. Variables the watch needs
+/// (EXA_API_KEY, EXA_SMTP_PASSWORD) can go in `env_file`, which may be absent.
+fn systemd_unit(exe: &Path, name: &str, env_file: &Path) -> String {
+    let exec = [exe.to_string_lossy().as_ref(), "run", name].map(systemd_arg).join(" ");
+    format!(
+        "[Unit]\nDescription=exa watch: {name}\nWants=network-online.target\nAfter=network-online.target\n\n\
+         [Service]\nExecStart={exec}\nEnvironmentFile=-{env}\nRestart=on-failure\nRestartSec=60\n\n\
+         [Install]\nWantedBy=default.target\n",
+        env = systemd_arg(&env_file.to_string_lossy()),
+    )
+}
+
+/// A launchd agent running `exa run <name>` at login, restarted unless it
+/// exits cleanly, with its output in `log`
+fn launchd_plist(exe: &Path, name: &str, log: &Path) -> String {
+    let label = Manager::Launchd.service_name(name);
+    let string = |s: &str| format!("<string>{}</string>", escape(s));
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
+         <plist version=\"1.0\">\n<dict>\n\
+         \x20 <key>Label</key>{label}\n\
+         \x20 <key>ProgramArguments</key>\n  <array>{exe}{run}{name}</array>\n\
+         \x20 <key>RunAtLoad</key><true/>\n\
+         \x20 <key>KeepAlive</key><dict><key>SuccessfulExit</key><false/></dict>\n\
+         \x20 <key>ThrottleInterval</key><integer>60</integer>\n\
+         \x20 <key>StandardOutPath</key>{log}\n\
+         \x20 <key>StandardErrorPath</key>{log}\n\
+         </dict>\n</plist>\n",
+        label = string(&label),
+        exe = string(&exe.to_string_lossy()),
+        run = string("run"),
+        name = string(name),
+        log = string(&log.to_string_lossy()),
+    )
+}
+
+/// Run a service manager command, failing with its error output
+fn run(program: &str, args: &[&str]) -> Result<String> {
+    let output = Command::new(program).args(args).output().with_context(|| format!("Failed to run {}", program))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!("{} {} failed: {}", program, args.join(" "), stderr.trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn systemctl(args: &[&str]) -> Result<String> {
+    run("systemctl", &[&["--user"], args].concat())
+}
+
+/// launchd's domain for this user's agents
+#[cfg(unix)]
+fn launchd_domain() -> String {
+    // SAFETY: getuid has no preconditions and cannot fail
+    format!("gui/{}", unsafe { libc::getuid() })
+}
+
+#[cfg(not(unix))]
+fn launchd_domain() -> String {
+    String::new()
+}
+
+/// Whether the service is running, as the manager puts it
+fn state(manager: Manager, name: &str) -> String {
+    let service = manager.service_name(name);
+    match manager {
+        // is-active exits non-zero for anything but active, printing the state all the same
+        Manager::Systemd => Command::new("systemctl")
+            .args(["--user", "is-active", &service])
+            .output()
+            .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+            .ok()
+            .filter(|s| !s.is_empty())
+            .unwrap_or_else(|| "unknown".to_string()),
+        Manager::Launchd => match run("launchctl", &["print", &format!("{}/{}", launchd_domain(), service)]) {
+            Ok(out) => out
+                .lines()
+                .find_map(|l| l.trim().strip_prefix("state = "))
+                .unwrap_or("loaded")
+                .to_string(),
+            Err(_) => "not loaded".to_string(),
+        },
+    }
+}
+
+fn install(manager: Manager, name: &str, print: bool) -> Result<()> {
+    saved::check_name(name)?;
+    let all = saved::load()?;
+    let Some(entry) = all.get(name) else {
+        bail!("No saved search '{}' (see `exa saved list`)", name);
+    };
+    let watches = entry.args.iter().any(|a| {
+        ["--watch", "--cron"].iter().any(|flag| a == flag || a.starts_with(&format!("{}=", flag)))
+    });
+    if !watches {
+        bail!("'{}' has no --watch or --cron, so it would run once and exit; save it again with one", name);
+    }
+    let exe = std::env::current_exe().context("Failed to find the exa executable")?;
+    let config_dir = KeyManager::get_config_dir()?;
+    let contents = match manager {
+        Manager::Systemd => systemd_unit(&exe, name, &config_dir.join("service.env")),
+        Manager::Launchd => {
+            let logs = dirs::home_dir().context("Could not find home directory")?.join("Library/Logs/exa");
+            if !print {
+                fs::create_dir_all(&logs).with_context(|| format!("Failed to create {}", logs.display()))?;
+            }
+            launchd_plist(&exe, name, &logs.join(format!("{}.log", name)))
+        }
+    };
+    if print {
+        print!("{}", contents);
+        return Ok(());
+    }
+
+    let path = manager.path(name)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    let replacing = path.exists();
+    fs::write(&path, contents).with_context(|| format!("Failed to write {}", path.display()))?;
+    let service = manager.service_name(name);
+    let started = match manager {
+        Manager::Systemd => systemctl(&["daemon-reload"])
+            .and_then(|_| systemctl(&["enable", &service]))
+            .and_then(|_| systemctl(&[if replacing { "restart" } else { "start" }, &service])),
+        Manager::Launchd => {
+            let domain = launchd_domain();
+            if replacing {
+                let _ = run("launchctl", &["bootout", &format!("{}/{}", domain, service)]);
+            }
+            run("launchctl", &["bootstrap", &domain, &path.to_string_lossy()])
+        }
+    };
+    if let Err(e) = started {
+        // Leave no half-installed service behind (a replaced one keeps its new file)
+        if !replacing {
+            let _ = fs::remove_file(&path);
+        }
+        return Err(e);
+    }
+    diag!("{} {} ({})", "Installed".green(), service, path.display());
+    if manager == Manager::Systemd {
+        let env_file = config_dir.join("service.env");
+        diag!("Variables set only in your shell (EXA_API_KEY, EXA_SMTP_PASSWORD) go in {}", env_file.display());
+    }
+    Ok(())
+}
+
+fn uninstall(manager: Manager, name: &str) -> Result<()> {
+    saved::check_name(name)?;
+    let path = manager.path(name)?;
+    if !path.exists() {
+        bail!("No service installed for '{}' (see `exa service status`)", name);
+    }
+    let service = manager.service_name(name);
+    match manager {
+        Manager::Systemd => {
+            systemctl(&["disable", "--now", &service])?;
+            fs::remove_file(&path).with_context(|| format!("Failed to remove {}", path.display()))?;
+            systemctl(&["daemon-reload"])?;
+        }
+        Manager::Launchd => {
+            let _ = run("launchctl", &["bootout", &format!("{}/{}", launchd_domain(), service)]);
+            fs::remove_file(&path).with_context(|| format!("Failed to remove {}", path.display()))?;
+        }
+    }
+    diag!("{} {}", "Uninstalled".green(), service);
+    Ok(())
+}
+
+fn status(cli: &Cli, manager: Manager, name: Option<&str>) -> Result<()> {
+    let names: Vec<String> = match name {
+        Some(name) => {
+            saved::check_name(name)?;
+            if !manager.path(name)?.exists() {
+                bail!("No service installed for '{}'", name);
+            }
+            vec![name.to_string()]
+        }
+        None => {
+            let mut names: Vec<String> = fs::read_dir(manager.dir()?)
+                .map(|files| {
+                    files
+                        .flatten()
+                        .filter_map(|f| manager.saved_name(&f.file_name().to_string_lossy()).map(String::from))
+                        .collect()
+                })
+                .unwrap_or_default();
+            names.sort();
+            names
+        }
+    };
+    if names.is_empty() {
+        diag!("No services installed; add one with `exa service install <saved-name>`.");
+        std::process::exit(3);
+    }
+    for name in names {
+        let state = state(manager, &name);
+        if cli.compact {
+            println!("{}\t{}\t{}", name, manager.service_name(&name), state);
+        } else {
+            let shown = if state == "active" || state == "running" { state.green() } else { state.yellow() };
+            println!("{} {} {}", name.bold(), manager.service_name(&name).dimmed(), shown);
+        }
+    }
+    Ok(())
+}
+
+pub fn cmd_service(cli: &Cli, action: &ServiceCommand) -> Result<()> {
+    let manager = Manager::current()?;
+    match action {
+        ServiceCommand::Install { name, print } => install(manager, name, *print),
+        ServiceCommand::Status { name } => status(cli, manager, name.as_deref()),
+        ServiceCommand::Uninstall { name } => uninstall(manager, name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_service_files() {
+        let exe = Path::new("/opt/my tools/exa");
+        let unit = systemd_unit(exe, "acme", Path::new("/home/u/.config/exa/service.env"));
+        assert!(unit.contains("Description=exa watch: acme\n"));
+        assert!(unit.contains("\nExecStart=\"/opt/my tools/exa\" run acme\n"));
+        assert!(unit.contains("\nEnvironmentFile=-/home/u/.config/exa/service.env\n"));
+        assert!(unit.contains("\nRestart=on-failure\n") && unit.ends_with("WantedBy=default.target\n"));
+        assert_eq!(systemd_arg("100%"), "100%%");
+        assert_eq!(systemd_arg("a \"b\""), "\"a \\\"b\\\"\"");
+
+        let log = Path::new("/Users/u/Library/Logs/exa/r&d.log");
+        let plist = launchd_plist(Path::new("/usr/local/bin/exa"), "r&d", log);
+        assert!(plist.contains("<key>Label</key><string>dev.exa.watch.r&amp;d</string>"));
+        let args = "<array><string>/usr/local/bin/exa</string><string>run</string><string>r&amp;d</string></array>";
+        assert!(plist.contains(args));
+        assert!(plist.contains("<key>StandardErrorPath</key><string>/Users/u/Library/Logs/exa/r&amp;d.log</string>"));
+
+        assert_eq!(Manager::Systemd.saved_name("exa-watch-acme.service"), Some("acme"));
+        assert_eq!(Manager::Launchd.saved_name("dev.exa.watch.acme.plist"), Some("acme"));
+        assert_eq!(Manager::Systemd.saved_name("other.service"), None);
+    }
+}
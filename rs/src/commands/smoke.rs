@@ -0,0 +1,196 @@
+use anyhow::{bail, ensure, Context, Result};
+use colored::Colorize;
+use exa_cli::api::{AnswerRequest, ContentsConfig, FindSimilarRequest, ResearchCreateRequest, SearchRequest};
+use exa_cli::model::{ResearchStatus, ResearchTask};
+use exa_cli::ExaClient;
+use serde::Serialize;
+use std::time::Instant;
+
+use crate::cli::Cli;
+use crate::commands::{finish_items, ItemError};
+use crate::render::to_json;
+
+/// Endpoints checked, in order, with a rough upper bound on what the
+/// cheapest request to each costs (used before the real cost is known)
+const ENDPOINTS: &[(&str, f64)] = &[
+    ("search", 0.005),
+    ("findSimilar", 0.005),
+    ("contents", 0.001),
+    ("answer", 0.005),
+    ("research", 0.1),
+];
+
+#[derive(Serialize)]
+struct Check {
+    endpoint: &'static str,
+    status: &'static str,
+    detail: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ms: Option<u128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cost: Option<f64>,
+}
+
+/// Send the cheapest useful request to `endpoint` and check the response
+/// parses into something sensible. Returns a detail line and the cost, if reported.
+async fn probe(client: &mut ExaClient, endpoint: &str) -> Result<(String, Option<f64>)> {
+    match endpoint {
+        "search" => {
+            let r = client.search(SearchRequest::builder().query("exa search engine").num(1).build()?).await?;
+            ensure!(!r.results.is_empty(), "no results");
+            Ok((format!("{} result(s)", r.results.len()), r.cost_dollars.and_then(|c| c.total)))
+        }
+        "findSimilar" => {
+            let request = FindSimilarRequest::builder().url("https://exa.ai").num(1).build()?;
+            let r = client.find_similar(request).await?;
+            ensure!(!r.results.is_empty(), "no results");
+            Ok((format!("{} result(s)", r.results.len()), r.cost_dollars.and_then(|c| c.total)))
+        }
+        "contents" => {
+            let r = client.get_contents(vec!["https://exa.ai".to_string()], ContentsConfig::text()).await?;
+            let chars = r.results.first().and_then(|p| p.text.as_ref()).map(|t| t.len()).unwrap_or(0);
+            ensure!(chars > 0, "no page text");
+            Ok((format!("{} chars of text", chars), r.cost_dollars.and_then(|c| c.total)))
+        }
+        "answer" => {
+            let r = client.answer(AnswerRequest::builder().query("What is 2 + 2?").build()?).await?;
+            ensure!(!r.answer.trim().is_empty(), "empty answer");
+            let detail = format!("{} chars, {} citation(s)", r.answer.len(), r.citations.len());
+            Ok((detail, r.cost_dollars.and_then(|c| c.total)))
+        }
+        "research" => {
+            let request = ResearchCreateRequest {
+                instructions: "Name one programming language.".to_string(),
+                model: "exa-research".to_string(),
+                output_schema: None,
+            };
+            let (created, key_idx) = client.research_create(request).await?;
+            let id = created.research_id;
+            // One poll checks the status shape; waiting for the report would take minutes
+            let task = ResearchTask::from(client.research_status(&id, Some(key_idx)).await?);
+            if task.status != ResearchStatus::Running {
+                return Ok((format!("task {} {}", id, task.status.as_str()), task.cost));
+            }
+            // Left running, the task would go on costing money
+            let task = client
+                .research_cancel(&id, Some(key_idx))
+                .await
+                .with_context(|| format!("could not cancel task {}, which is still running", id))?;
+            let task = ResearchTask::from(task);
+            Ok((format!("task {} running, then {}", id, task.status.as_str()), task.cost))
+        }
+        other => bail!("unknown endpoint '{}'", other),
+    }
+}
+
+/// Run the checks that fit in `budget`; returns them, the failures and the spend
+async fn run_checks(client: &mut ExaClient, budget: f64) -> (Vec<Check>, Vec<ItemError>, f64) {
+    let mut checks = Vec::new();
+    let mut errors = Vec::new();
+    let mut spent = 0.0;
+    for &(endpoint, estimate) in ENDPOINTS {
+        // Strict budget: skip a check whose worst case would pass the cap
+        if spent + estimate > budget {
+            checks.push(Check {
+                endpoint,
+                status: "skipped",
+                detail: format!("needs up to ${:.4}, ${:.4} left", estimate, budget - spent),
+                ms: None,
+                cost: None,
+            });
+            continue;
+        }
+        let started = Instant::now();
+        let result = probe(client, endpoint).await;
+        let ms = Some(started.elapsed().as_millis());
+        match result {
+            Ok((detail, cost)) => {
+                spent += cost.unwrap_or(estimate);
+                checks.push(Check { endpoint, status: "pass", detail, ms, cost });
+            }
+            Err(e) => {
+                // A failed request may still have been billed
+                spent += estimate;
+                checks.push(Check { endpoint, status: "fail", detail: format!("{:#}", e), ms, cost: None });
+                errors.push(ItemError::new(endpoint, &e));
+            }
+        }
+    }
+    (checks, errors, spent)
+}
+
+/// One minimal request per endpoint, within `budget` dollars, reporting
+/// pass/fail for each
+pub async fn cmd_smoke(client: &mut ExaClient, cli: &Cli, budget: f64) -> Result<()> {
+    let cheapest = ENDPOINTS.iter().map(|(_, estimate)| *estimate).fold(f64::MAX, f64::min);
+    if budget < cheapest {
+        bail!("--budget ${:.4} is too small for any check (the cheapest needs up to ${:.4})", budget, cheapest);
+    }
+
+    let (checks, errors, spent) = run_checks(client, budget).await;
+
+    if cli.json {
+        let value = serde_json::json!({ "checks": checks, "spent": spent, "budget": budget });
+        println!("{}", to_json(&value, cli.compact)?);
+    } else if cli.compact {
+        for c in &checks {
+            println!("{}: {} ({})", c.endpoint, c.status, c.detail);
+        }
+        println!("spent: ${:.4} of ${:.4}", spent, budget);
+    } else {
+        for c in &checks {
+            let status = match c.status {
+                "pass" => "PASS".green().bold(),
+                "fail" => "FAIL".red().bold(),
+                _ => "SKIP".dimmed(),
+            };
+            let timing = match (c.ms, c.cost) {
+                (Some(ms), Some(cost)) => format!("  {}ms ${:.4}", ms, cost),
+                (Some(ms), None) => format!("  {}ms", ms),
+                _ => String::new(),
+            };
+            println!("{}  {:<12} {}{}", status, c.endpoint, c.detail, timing.dimmed());
+        }
+        println!("{} ${:.4} of ${:.4}", "Spent:".dimmed(), spent, budget);
+    }
+
+    let attempted = checks.iter().filter(|c| c.status != "skipped").count();
+    finish_items(&errors, attempted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exa_cli::transport::{MockTransport, Response};
+    use exa_cli::KeyManager;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_run_checks() {
+        let page = json!({"url": "https://exa.ai", "title": "Exa", "text": "Search"});
+        let mock = MockTransport::new();
+        mock.push(Response::json(200, &json!({"results": [page], "costDollars": {"total": 0.005}})))
+            .push(Response::json(200, &json!({"results": []})))
+            .push(Response::json(200, &json!({"results": [page], "costDollars": {"total": 0.001}})))
+            .push(Response::json(200, &json!({"answer": "4", "costDollars": {"total": 0.005}})));
+        let mut client = ExaClient::new(KeyManager::from_keys(vec!["k".to_string()]), reqwest::Client::new())
+            .with_transport(mock.clone());
+
+        let (checks, errors, spent) = run_checks(&mut client, 0.02).await;
+        let statuses: Vec<_> = checks.iter().map(|c| (c.endpoint, c.status)).collect();
+        assert_eq!(
+            statuses,
+            [
+                ("search", "pass"),
+                ("findSimilar", "fail"),
+                ("contents", "pass"),
+                ("answer", "pass"),
+                ("research", "skipped")
+            ]
+        );
+        assert_eq!(errors.len(), 1);
+        // The failed check counts at its estimate
+        assert!((spent - 0.016).abs() < 1e-9);
+        assert_eq!(mock.requests().len(), 4);
+    }
+}
@@ -0,0 +1,92 @@
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use exa_cli::key_manager::{KeyManager, KeySource};
+use std::io::IsTerminal;
+use std::path::PathBuf;
+
+use crate::cli::Cli;
+use crate::commands::prompt_secret;
+use crate::state_bundle;
+
+/// Shortest passphrase accepted for the keys in a bundle
+const MIN_PASSPHRASE_CHARS: usize = 8;
+
+/// Data directory for the archive and local index (~/.local/share/exa)
+pub fn data_dir() -> Result<PathBuf> {
+    Ok(dirs::data_dir().context("Could not find data directory")?.join("exa"))
+}
+
+/// The API keys, sealed with a new passphrase (typed twice at a terminal)
+fn sealed_keys() -> Result<Vec<u8>> {
+    let km = KeyManager::open(false)?;
+    if km.key_count() == 0 {
+        bail!("No API keys to export (see `exa keys list`)");
+    }
+    let passphrase = prompt_secret("Passphrase for the API keys in the bundle:")?;
+    if passphrase.chars().count() < MIN_PASSPHRASE_CHARS {
+        bail!("The passphrase needs at least {} characters", MIN_PASSPHRASE_CHARS);
+    }
+    if std::io::stdin().is_terminal() && prompt_secret("Again:")? != passphrase {
+        bail!("The passphrases don't match");
+    }
+    state_bundle::seal_keys(km.keys(), &passphrase)
+}
+
+/// Add the bundle's keys to the pool; returns how many were new and where
+/// the pool lives (the keyring or keys file)
+fn import_keys(sealed: &[u8]) -> Result<(usize, KeySource)> {
+    let mut km = KeyManager::open(false)?;
+    if km.source() == KeySource::Env {
+        bail!("Keys come from EXA_API_KEYS / EXA_API_KEY here; unset them to import the bundle's keys");
+    }
+    let passphrase = prompt_secret("Passphrase for the API keys in the bundle:")?;
+    let keys = state_bundle::open_keys(sealed, &passphrase)?;
+    let mut added = 0;
+    for key in keys {
+        if !km.keys().contains(&key) {
+            km.add_key(&key)?;
+            added += 1;
+        }
+    }
+    Ok((added, km.source()))
+}
+
+pub fn cmd_export_state(cli: &Cli, path: &std::path::Path, with_archive: bool, with_keys: bool) -> Result<()> {
+    let config_dir = KeyManager::get_config_dir()?;
+    let data = if with_archive { Some(data_dir()?) } else { None };
+    let keys = if with_keys { Some(sealed_keys()?) } else { None };
+    let count = state_bundle::export(path, &config_dir, data.as_deref(), keys.as_deref())?;
+    if cli.compact {
+        println!("exported: {} files -> {}", count, path.display());
+    } else {
+        println!("{} {} files to {}", "Exported".green(), count, path.display());
+    }
+    Ok(())
+}
+
+pub fn cmd_import_state(cli: &Cli, path: &std::path::Path, force: bool) -> Result<()> {
+    let config_dir = KeyManager::get_config_dir()?;
+    let summary = state_bundle::import(path, &config_dir, &data_dir()?, force)?;
+    let keys = summary.sealed_keys.as_deref().map(import_keys).transpose()?;
+    if cli.compact {
+        match keys {
+            Some((keys, _)) => {
+                println!("imported: {} | skipped: {} | keys: {}", summary.written, summary.skipped, keys)
+            }
+            None => println!("imported: {} | skipped: {}", summary.written, summary.skipped),
+        }
+    } else {
+        println!("{} {} files", "Imported".green(), summary.written);
+        if summary.skipped > 0 {
+            println!(
+                "{} {} existing files (use --overwrite to replace them)",
+                "Skipped".yellow(),
+                summary.skipped
+            );
+        }
+        if let Some((keys, source)) = keys {
+            println!("{} {} API key(s) to the {}", "Added".green(), keys, source.as_str());
+        }
+    }
+    Ok(())
+}
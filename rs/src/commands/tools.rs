@@ -0,0 +1,193 @@
+use anyhow::{bail, Context, Result};
+use clap::Parser;
+use std::io::Read;
+
+use crate::cli::{Cli, OutputFormat};
+#[cfg(feature = "serve")]
+use crate::cli::{build_client, load_key_manager, Commands};
+#[cfg(feature = "serve")]
+use crate::{coalesce, mcp};
+#[cfg(feature = "serve")]
+use exa_cli::{fingerprint, warnings, ExaClient};
+#[cfg(feature = "serve")]
+use serde_json::{Map, Value};
+use crate::tools;
+
+/// Build the command line for a tool call payload on stdin. Accepts OpenAI
+/// (`arguments` as an object or JSON string, optionally under `function`)
+/// and Anthropic (`input`) shapes.
+pub fn tool_call_cli() -> Result<Cli> {
+    let mut payload = String::new();
+    std::io::stdin().read_to_string(&mut payload).context("Failed to read tool call from stdin")?;
+    let payload: serde_json::Value = serde_json::from_str(&payload).context("Tool call is not valid JSON")?;
+    let call = payload.get("function").unwrap_or(&payload);
+
+    let name = call["name"].as_str().context("Tool call is missing 'name'")?;
+    let args = match call.get("arguments").or_else(|| call.get("input")) {
+        Some(serde_json::Value::String(s)) => serde_json::from_str(s).context("Tool call 'arguments' is not valid JSON")?,
+        Some(v) => v.clone(),
+        None => serde_json::json!({}),
+    };
+    let args = args.as_object().context("Tool call arguments must be an object")?;
+
+    parse_tool_argv(&tools::tool_call_argv(name, args)?)
+}
+
+/// The command line of a tool call, with JSON output
+fn parse_tool_argv(argv: &[String]) -> Result<Cli> {
+    let mut cli = Cli::try_parse_from(argv).map_err(|e| {
+        let message = e.to_string();
+        let first = message.lines().next().unwrap_or("").trim_start_matches("error: ");
+        anyhow::anyhow!("Invalid tool arguments: {}", first)
+    })?;
+    cli.json = true;
+    cli.compact = true;
+    Ok(cli)
+}
+
+/// MCP server on stdio. Tool calls run in this process, each on a client of
+/// its own so they can run concurrently (research can take minutes); they
+/// share the response cache, and key rotation state is saved after each
+/// call. A call identical to one still running gets that run's result
+/// instead of a run of its own.
+#[cfg(feature = "serve")]
+pub async fn cmd_serve_mcp(cli: Cli) -> Result<()> {
+    use exa_cli::diag;
+    use std::sync::Arc;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+
+    let cli = Arc::new(cli);
+    let stdout = Arc::new(tokio::sync::Mutex::new(tokio::io::stdout()));
+    let write = |stdout: Arc<tokio::sync::Mutex<tokio::io::Stdout>>, message: serde_json::Value| async move {
+        let mut out = stdout.lock().await;
+        out.write_all(format!("{}\n", message).as_bytes()).await?;
+        out.flush().await
+    };
+
+    let specs = tools::tool_specs();
+    let inflight = Arc::new(coalesce::Inflight::default());
+    let mut calls = tokio::task::JoinSet::new();
+    let mut lines = tokio::io::BufReader::new(tokio::io::stdin()).lines();
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match mcp::dispatch(&line, &specs) {
+            mcp::Action::Reply(message) => write(stdout.clone(), message).await?,
+            mcp::Action::Ignore => {}
+            mcp::Action::Call { id, name, arguments } => {
+                let key = coalesce::key(&name, &arguments);
+                if !inflight.join(&key, id) {
+                    if cli.verbose > 0 {
+                        diag!("{}: joined an identical call in flight", name);
+                    }
+                    continue;
+                }
+                let (cli, stdout, inflight) = (cli.clone(), stdout.clone(), inflight.clone());
+                calls.spawn(async move {
+                    let (text, is_error) = match call_tool(&cli, &name, &arguments).await {
+                        Ok(text) => (text, false),
+                        Err(e) => (format!("{:#}", e), true),
+                    };
+                    for id in inflight.finish(&key) {
+                        write(stdout.clone(), mcp::tool_result(id, &text, is_error)).await?;
+                    }
+                    Ok::<_, std::io::Error>(())
+                });
+            }
+        }
+    }
+    // stdin closed: let in-flight calls answer before exiting
+    while let Some(done) = calls.join_next().await {
+        done??;
+    }
+    let stats = inflight.stats();
+    if stats.coalesced > 0 || cli.verbose > 0 {
+        diag!("{} tool calls; {} shared the run of an identical call in flight", stats.calls, stats.coalesced);
+    }
+    Ok(())
+}
+
+/// One tool call on a client of its own, saving key state afterwards. Its
+/// warnings are its own, not those of calls running alongside it.
+#[cfg(feature = "serve")]
+async fn call_tool(server: &Cli, name: &str, arguments: &Map<String, Value>) -> Result<String> {
+    let mut client = build_client(server, load_key_manager(server)?).await?;
+    let output = warnings::scope(run_tool(&mut client, server, name, arguments)).await;
+    client.key_manager.save_state()?;
+    output
+}
+
+/// Run one tool call in this process and return its JSON output. Nothing is
+/// printed: under `exa serve`, stdout carries the protocol. The server's
+/// --yaml and --query-filter don't apply; the output is always JSON.
+#[cfg(feature = "serve")]
+pub async fn run_tool(client: &mut ExaClient, server: &Cli, name: &str, arguments: &Map<String, Value>) -> Result<String> {
+    use crate::commands::answer::answer_json;
+    use crate::commands::content::{fetch_content, prepare};
+    use crate::commands::research::{research_json, run_task};
+    use crate::commands::search::{fetch_find, fetch_search};
+    use crate::commands::{check_query, compress_results, scrub_results};
+    use crate::render::{result_view, tool_json};
+    use exa_cli::api::{AnswerRequest, SearchResponse};
+    use exa_cli::model::Answer;
+
+    let mut cli = parse_tool_argv(&tools::tool_call_argv(name, arguments)?)?;
+    // The server's cache settings apply to every call
+    cli.no_cache = server.no_cache;
+    cli.cache_ttl = server.cache_ttl;
+    cli.cache_backend = server.cache_backend.clone();
+    cli.verbose = server.verbose;
+    let cli = &cli;
+
+    let pages = |mut results: SearchResponse, query: Option<&str>| -> Result<Option<Value>> {
+        if results.results.is_empty() {
+            return Ok(None);
+        }
+        scrub_results(cli, &mut results);
+        fingerprint::annotate(&mut results.results);
+        compress_results(cli, &mut results, query);
+        Ok(Some(serde_json::to_value(result_view(cli, &results).unwrap_or(results))?))
+    };
+    let value = match &cli.command {
+        Commands::Search { query } => {
+            let query = query.join(" ");
+            pages(fetch_search(client, cli, query.clone()).await?.0, Some(&query))?
+        }
+        Commands::Find { query, exclude_source_domain } => {
+            let query = query.join(" ");
+            pages(fetch_find(client, cli, query.clone(), *exclude_source_domain).await?.0, Some(&query))?
+        }
+        Commands::Content { urls, .. } => {
+            let results = fetch_content(client, cli, &urls[0]).await?.0;
+            pages(prepare(cli, results), None)?
+        }
+        Commands::Answer { query, .. } => {
+            let query = query.join(" ");
+            check_query(cli, &query)?;
+            let request = AnswerRequest::builder().query(query).text(true).build()?;
+            Some(answer_json(cli, &Answer::from(client.answer(request).await?), None)?)
+        }
+        Commands::Research { query, .. } => {
+            let query = query.join(" ");
+            check_query(cli, &query)?;
+            Some(research_json(cli, &run_task(client, cli, &query).await?.0, None)?)
+        }
+        _ => bail!("Unknown tool: {}", name),
+    };
+    match value {
+        Some(value) => tool_json(&value),
+        None => Ok("No results.".to_string()),
+    }
+}
+
+pub fn cmd_tools_schema(cli: &Cli) -> Result<()> {
+    let specs = tools::tool_specs();
+    let schema = match cli.format {
+        None | Some(OutputFormat::Openai) => tools::openai_schema(&specs),
+        Some(OutputFormat::Anthropic) => tools::anthropic_schema(&specs),
+        Some(_) => bail!("tools-schema supports --format openai or anthropic"),
+    };
+    println!("{}", serde_json::to_string_pretty(&schema)?);
+    Ok(())
+}
@@ -0,0 +1,199 @@
+//! `exa watch list` / `stop`: a running --watch or --cron registers itself as
+//! `watch/running/<pid>.json` in the config directory and removes the entry
+//! when it stops, Ctrl-C and `exa watch stop` included. Entries left by a
+//! watch that was killed outright are dropped when next listed.
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Local, Utc};
+use colored::Colorize;
+use exa_cli::{diag, KeyManager};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crate::cli::{Cli, WatchCommand};
+use crate::commands::saved::shell_words;
+use crate::render::to_json;
+use crate::schedule::Schedule;
+
+/// How long `stop` waits for a watch to exit
+const STOP_WAIT: Duration = Duration::from_secs(5);
+
+#[derive(Serialize, Deserialize)]
+struct Running {
+    pid: u32,
+    schedule: String,
+    started: DateTime<Utc>,
+    /// The command line the watch was started with
+    args: Vec<String>,
+}
+
+fn running_dir() -> Result<PathBuf> {
+    Ok(KeyManager::get_config_dir()?.join("watch").join("running"))
+}
+
+/// This watch's registry entry, removed when the watch returns
+pub struct Registration(PathBuf);
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+/// Add this process to the registry. A Ctrl-C or SIGTERM then removes the
+/// entry before exiting with the code the signal would have given.
+pub fn register(schedule: &Schedule) -> Result<Registration> {
+    let dir = running_dir()?;
+    fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let pid = std::process::id();
+    let path = dir.join(format!("{}.json", pid));
+    let entry = Running {
+        pid,
+        schedule: schedule.to_string(),
+        started: Utc::now(),
+        args: std::env::args().skip(1).collect(),
+    };
+    fs::write(&path, serde_json::to_string_pretty(&entry)?)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    let entry_path = path.clone();
+    tokio::spawn(async move {
+        let code = stopped().await;
+        let _ = fs::remove_file(&entry_path);
+        std::process::exit(code);
+    });
+    Ok(Registration(path))
+}
+
+/// Wait for Ctrl-C (exit code 130) or SIGTERM (143)
+#[cfg(unix)]
+async fn stopped() -> i32 {
+    use tokio::signal::unix::{signal, SignalKind};
+    let mut interrupt = signal(SignalKind::interrupt()).ok();
+    let mut terminate = signal(SignalKind::terminate()).ok();
+    tokio::select! {
+        Some(()) = async { interrupt.as_mut()?.recv().await } => 130,
+        Some(()) = async { terminate.as_mut()?.recv().await } => 143,
+        else => std::future::pending().await,
+    }
+}
+
+#[cfg(not(unix))]
+async fn stopped() -> i32 {
+    match tokio::signal::ctrl_c().await {
+        Ok(()) => 130,
+        Err(_) => std::future::pending().await,
+    }
+}
+
+/// Whether process `pid` still exists
+#[cfg(unix)]
+fn alive(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else { return false };
+    // SAFETY: signal 0 only checks the process exists; EPERM means it does,
+    // under another user
+    let exists = unsafe { libc::kill(pid, 0) } == 0;
+    exists || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(not(unix))]
+fn alive(_pid: u32) -> bool {
+    true
+}
+
+#[cfg(unix)]
+fn terminate(pid: u32) -> Result<()> {
+    let raw = libc::pid_t::try_from(pid)?;
+    // SAFETY: sends SIGTERM to a process id taken from the registry
+    if unsafe { libc::kill(raw, libc::SIGTERM) } != 0 {
+        return Err(std::io::Error::last_os_error()).with_context(|| format!("Failed to stop watch {}", pid));
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn terminate(_pid: u32) -> Result<()> {
+    bail!("`exa watch stop` is not supported on this system yet")
+}
+
+/// The registered watches still running, oldest first; entries of watches
+/// that are gone are removed
+fn load(cli: &Cli) -> Result<Vec<Running>> {
+    let dir = running_dir()?;
+    let Ok(files) = fs::read_dir(&dir) else { return Ok(Vec::new()) };
+    let mut running = Vec::new();
+    for file in files.flatten() {
+        let path = file.path();
+        if path.extension().is_none_or(|e| e != "json") {
+            continue;
+        }
+        let entry: Option<Running> = fs::read_to_string(&path).ok().and_then(|text| serde_json::from_str(&text).ok());
+        match entry {
+            Some(entry) if alive(entry.pid) => running.push(entry),
+            _ => {
+                if cli.verbose > 0 {
+                    diag!("Removed the entry of a watch that is gone: {}", path.display());
+                }
+                let _ = fs::remove_file(&path);
+            }
+        }
+    }
+    running.sort_by_key(|r| r.started);
+    Ok(running)
+}
+
+pub fn cmd_watches(cli: &Cli, action: &WatchCommand) -> Result<()> {
+    let running = load(cli)?;
+    match action {
+        WatchCommand::List => {
+            if cli.json {
+                println!("{}", to_json(&running, cli.compact)?);
+                return Ok(());
+            }
+            if running.is_empty() {
+                diag!("No watches running; start one with `exa search <query> --watch 15m` or `--cron`.");
+                std::process::exit(3);
+            }
+            for r in &running {
+                let started = r.started.with_timezone(&Local).format("%Y-%m-%d %H:%M");
+                let args = shell_words(&r.args);
+                if cli.compact {
+                    println!("{}\t{}\t{}\t{}", r.pid, r.schedule, started, args);
+                } else {
+                    let since = format!("since {}", started);
+                    println!("{} {} {} {}", r.pid.to_string().bold(), r.schedule, since.dimmed(), args.dimmed());
+                }
+            }
+        }
+        WatchCommand::Stop { pid, all } => {
+            let targets: Vec<&Running> = match pid {
+                Some(pid) => match running.iter().find(|r| r.pid == *pid) {
+                    Some(r) => vec![r],
+                    None => bail!("No running watch with process id {} (see `exa watch list`)", pid),
+                },
+                // clap requires one or the other
+                None => running.iter().filter(|_| *all).collect(),
+            };
+            if targets.is_empty() {
+                diag!("No watches running.");
+                return Ok(());
+            }
+            for r in &targets {
+                terminate(r.pid)?;
+            }
+            let deadline = Instant::now() + STOP_WAIT;
+            for r in targets {
+                while alive(r.pid) && Instant::now() < deadline {
+                    std::thread::sleep(Duration::from_millis(100));
+                }
+                if alive(r.pid) {
+                    bail!("Watch {} has not stopped after {}s", r.pid, STOP_WAIT.as_secs());
+                }
+                let _ = fs::remove_file(running_dir()?.join(format!("{}.json", r.pid)));
+                diag!("{} {} {}", "Stopped".green(), r.pid, shell_words(&r.args).dimmed());
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,163 @@
+use anyhow::{bail, Result};
+use colored::Colorize;
+use exa_cli::{diag, diag_inline, websets, ExaClient};
+use std::time::{Duration, Instant};
+
+use crate::cli::{Cli, EnrichmentsCommand, WebsetsCommand};
+use crate::commands::check_query;
+use crate::render::to_json;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// Longest wait for an enrichment before leaving it to run on its own
+const MAX_ENRICHMENT_WAIT: Duration = Duration::from_secs(30 * 60);
+
+pub async fn cmd_websets(client: &mut ExaClient, cli: &Cli, action: &WebsetsCommand) -> Result<()> {
+    match action {
+        WebsetsCommand::Create { query, count, entity, criteria } => {
+            let query = query.join(" ");
+            if query.is_empty() {
+                bail!("No query provided");
+            }
+            check_query(cli, &query)?;
+            let request = websets::CreateWebsetRequest {
+                search: websets::SearchParams {
+                    query,
+                    count: *count,
+                    entity: entity.clone().map(|kind| websets::EntityParam { kind }),
+                    criteria: criteria.iter().map(|c| websets::Criterion { description: c.clone() }).collect(),
+                },
+            };
+            let webset = client.webset_create(request).await?;
+            if cli.json {
+                println!("{}", to_json(&webset, cli.compact)?);
+            } else {
+                websets::print_webset(&webset, cli.compact);
+                diag!("{}", format!("Check progress with: exa websets get {}", webset.id).dimmed());
+            }
+        }
+        WebsetsCommand::List { limit, all } => {
+            let list: Vec<websets::Webset> = client.paginate(websets::API, *limit, *all).await?;
+            if cli.json {
+                println!("{}", to_json(&list, cli.compact)?);
+            } else if list.is_empty() {
+                diag!("No websets.");
+            } else {
+                websets::print_websets(&list, cli.compact);
+            }
+        }
+        WebsetsCommand::Get { id } => {
+            let webset = client.webset_get(id).await?;
+            if cli.json {
+                println!("{}", to_json(&webset, cli.compact)?);
+            } else {
+                websets::print_webset(&webset, cli.compact);
+            }
+        }
+        WebsetsCommand::Delete { id } => {
+            let webset = client.webset_delete(id).await?;
+            if cli.json {
+                println!("{}", to_json(&webset, cli.compact)?);
+            } else {
+                diag!("{} {}", "Deleted".green(), webset.id);
+            }
+        }
+        WebsetsCommand::Enrich { id, description, format, options, wait } => {
+            if matches!(format, websets::EnrichmentFormat::Options) == options.is_empty() {
+                bail!("--option values go with --format options (and it needs at least one)");
+            }
+            let request = websets::CreateEnrichmentRequest {
+                description: description.clone(),
+                format: *format,
+                options: options.iter().map(|label| websets::EnrichmentOption { label: label.clone() }).collect(),
+            };
+            let mut enrichment = client.enrichment_create(id, request).await?;
+            if *wait {
+                enrichment = wait_for_enrichment(client, cli, id, enrichment).await?;
+            }
+            print_enrichment(cli, &enrichment)?;
+            if !cli.json && enrichment.status == "completed" {
+                diag!("{}", format!("Values are on the items: exa websets items {}", id).dimmed());
+            }
+        }
+        WebsetsCommand::Enrichments { action: EnrichmentsCommand::List { id } } => {
+            let webset = client.webset_get(id).await?;
+            if cli.json {
+                println!("{}", to_json(&webset.enrichments, cli.compact)?);
+            } else if webset.enrichments.is_empty() {
+                diag!("No enrichments.");
+            } else {
+                websets::print_enrichments(&webset.enrichments, cli.compact);
+            }
+        }
+        WebsetsCommand::Enrichments { action: EnrichmentsCommand::Get { id, enrichment_id, wait } } => {
+            let mut enrichment = client.enrichment_get(id, enrichment_id).await?;
+            if *wait {
+                enrichment = wait_for_enrichment(client, cli, id, enrichment).await?;
+            }
+            print_enrichment(cli, &enrichment)?;
+        }
+        WebsetsCommand::Items { id, limit, all } => {
+            let path = format!("{}/{}/items", websets::API, websets::segment(id)?);
+            let items: Vec<websets::WebsetItem> = client.paginate(&path, *limit, *all).await?;
+            if cli.json {
+                println!("{}", to_json(&items, cli.compact)?);
+            } else if items.is_empty() {
+                diag!("No items yet.");
+            } else {
+                websets::print_items(&items, cli.compact);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Poll an enrichment every 5s until it completes or is canceled, for up to
+/// [`MAX_ENRICHMENT_WAIT`]
+pub async fn wait_for_enrichment(
+    client: &mut ExaClient,
+    cli: &Cli,
+    webset_id: &str,
+    mut enrichment: websets::Enrichment,
+) -> Result<websets::Enrichment> {
+    let show_progress = !cli.json && !cli.compact;
+    if show_progress && !enrichment.is_finished() {
+        diag!("{}", format!("Waiting for enrichment {}...", enrichment.id).dimmed());
+    }
+    let deadline = Instant::now() + MAX_ENRICHMENT_WAIT;
+    while !enrichment.is_finished() {
+        if Instant::now() >= deadline {
+            if show_progress {
+                diag!();
+            }
+            bail!(
+                "Enrichment {} is still {} after {} minutes; check on it with `exa websets enrichments get {} {} --wait`",
+                enrichment.id,
+                enrichment.status,
+                MAX_ENRICHMENT_WAIT.as_secs() / 60,
+                webset_id,
+                enrichment.id
+            );
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+        enrichment = client.enrichment_get(webset_id, &enrichment.id).await?;
+        if show_progress {
+            diag_inline!(".");
+        }
+    }
+    if show_progress {
+        diag!(); // newline after dots
+    }
+    if enrichment.status == "canceled" {
+        bail!("Enrichment {} was canceled", enrichment.id);
+    }
+    Ok(enrichment)
+}
+
+pub fn print_enrichment(cli: &Cli, enrichment: &websets::Enrichment) -> Result<()> {
+    if cli.json {
+        println!("{}", to_json(enrichment, cli.compact)?);
+    } else {
+        websets::print_enrichments(std::slice::from_ref(enrichment), cli.compact);
+    }
+    Ok(())
+}
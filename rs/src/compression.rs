@@ -0,0 +1,116 @@
+use anyhow::{bail, Context, Result};
+use std::io::{Read, Write};
+
+/// Encodings advertised in Accept-Encoding
+pub const ACCEPT_ENCODING: &str = "gzip, br";
+
+/// Request bodies at least this large are gzipped when request compression is on
+pub const REQUEST_COMPRESS_THRESHOLD: usize = 8 * 1024;
+
+/// Decoding reader over a response body (per its Content-Encoding header)
+/// that fails once more than `limit` decoded bytes have been read, so large
+/// bodies can be parsed as they are decoded and compression bombs are cut off
+pub fn body_reader<'a>(bytes: &'a [u8], encoding: Option<&str>, limit: usize) -> Result<LimitedReader<Box<dyn Read + 'a>>> {
+    let inner: Box<dyn Read + 'a> = match encoding.map(|e| e.trim().to_ascii_lowercase()).as_deref() {
+        None | Some("") | Some("identity") => Box::new(bytes),
+        Some("gzip") | Some("x-gzip") => Box::new(flate2::read::GzDecoder::new(bytes)),
+        Some("br") => Box::new(brotli::Decompressor::new(bytes, 4096)),
+        Some(other) => bail!("Unsupported response encoding: {}", other),
+    };
+    Ok(LimitedReader { inner, limit, read: 0 })
+}
+
+/// Decode a whole response body, up to `limit` bytes
+pub fn decode_body(bytes: &[u8], encoding: Option<&str>, limit: usize) -> Result<Vec<u8>> {
+    let mut reader = body_reader(bytes, encoding, limit)?;
+    let mut out = Vec::new();
+    if let Err(e) = reader.read_to_end(&mut out) {
+        reader.check()?;
+        return Err(e).context("Failed to decode response");
+    }
+    Ok(out)
+}
+
+pub struct LimitedReader<R> {
+    inner: R,
+    limit: usize,
+    read: usize,
+}
+
+impl<R> LimitedReader<R> {
+    /// Decoded bytes read so far
+    pub fn bytes_read(&self) -> usize {
+        self.read
+    }
+
+    /// The size error, if the limit was hit (other read errors are the caller's)
+    pub fn check(&self) -> Result<()> {
+        if self.read > self.limit {
+            bail!("Response is larger than the {} limit", format_bytes(self.limit));
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for LimitedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.read += n;
+        if self.read > self.limit {
+            return Err(std::io::Error::other("response size limit exceeded"));
+        }
+        Ok(n)
+    }
+}
+
+/// Gzip a request body
+pub fn gzip(bytes: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(bytes)?;
+    Ok(encoder.finish()?)
+}
+
+/// Human-readable byte count (e.g. 512 B, 12.3 KB, 1.4 MB)
+pub fn format_bytes(n: usize) -> String {
+    if n >= 1024 * 1024 {
+        format!("{:.1} MB", n as f64 / (1024.0 * 1024.0))
+    } else if n >= 1024 {
+        format!("{:.1} KB", n as f64 / 1024.0)
+    } else {
+        format!("{} B", n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gzip_roundtrip() {
+        let body = br#"{"urls":["https://example.com"],"text":true}"#.repeat(50);
+        let packed = gzip(&body).unwrap();
+        assert!(packed.len() < body.len());
+        assert_eq!(decode_body(&packed, Some("gzip"), usize::MAX).unwrap(), body);
+    }
+
+    #[test]
+    fn test_size_limit() {
+        let body = vec![b' '; 100_000];
+        let packed = gzip(&body).unwrap();
+        assert!(packed.len() < 1000);
+        let err = decode_body(&packed, Some("gzip"), 50_000).unwrap_err();
+        assert!(err.to_string().contains("larger than the 48.8 KB limit"), "{}", err);
+        assert_eq!(decode_body(&packed, Some("gzip"), 100_000).unwrap().len(), 100_000);
+
+        let mut reader = body_reader(b"[1, 2, 3]", None, 1024).unwrap();
+        let parsed: Vec<u8> = serde_json::from_reader(&mut reader).unwrap();
+        assert_eq!((parsed, reader.bytes_read()), (vec![1, 2, 3], 9));
+    }
+
+    #[test]
+    fn test_identity_passthrough() {
+        assert_eq!(decode_body(b"{}", None, 2).unwrap(), b"{}");
+        assert_eq!(decode_body(b"{}", Some("identity"), 2).unwrap(), b"{}");
+        assert!(decode_body(b"{}", Some("zstd"), 2).is_err());
+    }
+}
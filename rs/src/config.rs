@@ -0,0 +1,864 @@
+use anyhow::{bail, Context, Result};
+use clap::parser::ValueSource;
+use clap::ArgAction;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::policy::DoNotIngest;
+use exa_cli::{vars, KeyManager};
+
+/// Environment variables that make up the configuration, in render order
+const VARS: &[&str] = &[
+    "EXA_API_KEYS",
+    "EXA_CACHE_BACKEND",
+    "EXA_CLIENT_TAG",
+    "EXA_LOG_REQUESTS",
+    "EXA_LOG_QUERIES",
+    "EXA_DO_NOT_INGEST",
+    "EXA_PRESET",
+    "EXA_PROFILE",
+    "EXA_BASE_URL",
+    "EXA_JIRA_EMAIL",
+    "EXA_JIRA_TOKEN",
+    "EXA_LINEAR_API_KEY",
+    "EXA_SMTP_USER",
+    "EXA_SMTP_PASSWORD",
+];
+
+/// Flags that also read an environment variable, which beats config.toml
+const FLAG_VARS: &[(&str, &str)] = &[
+    ("cache-backend", "EXA_CACHE_BACKEND"),
+    ("client-tag", "EXA_CLIENT_TAG"),
+    ("preset", "EXA_PRESET"),
+    ("profile", "EXA_PROFILE"),
+    ("base-url", "EXA_BASE_URL"),
+];
+
+/// The config.toml table holding `[profiles.<name>]` flag bundles
+const PROFILES: &str = "profiles";
+
+/// Settings that are not flags, as `<section>.<key>`; all take true or false
+const OPTIONS: &[&str] = &["log.include_queries"];
+
+/// Variables whose values are never rendered unless asked for
+const SECRETS: &[&str] = &["EXA_API_KEYS", "EXA_JIRA_TOKEN", "EXA_LINEAR_API_KEY", "EXA_SMTP_PASSWORD"];
+
+/// Map an EXA_CONFIG_JSON key ("cache_backend", "cacheBackend" or
+/// "EXA_CACHE_BACKEND") to its environment variable
+fn var_for_key(key: &str) -> Option<&'static str> {
+    let mut snake = String::new();
+    for c in key.chars() {
+        if c.is_ascii_uppercase() && !snake.is_empty() && !key.contains('_') {
+            snake.push('_');
+        }
+        snake.push(c.to_ascii_uppercase());
+    }
+    let name = if snake.starts_with("EXA_") { snake } else { format!("EXA_{}", snake) };
+    match name.as_str() {
+        "EXA_API_KEY" => Some("EXA_API_KEYS"),
+        other => VARS.iter().copied().find(|v| *v == other),
+    }
+}
+
+fn value_to_env(key: &str, value: &Value) -> Result<String> {
+    Ok(match value {
+        Value::String(s) => s.clone(),
+        Value::Bool(b) => if *b { "1" } else { "0" }.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::Array(items) => items
+            .iter()
+            .map(|v| value_to_env(key, v))
+            .collect::<Result<Vec<_>>>()?
+            .join(","),
+        _ => bail!("EXA_CONFIG_JSON: unsupported value for '{}'", key),
+    })
+}
+
+/// Parse an inline config blob into (variable, value) pairs
+fn parse_inline(json: &str) -> Result<Vec<(&'static str, String)>> {
+    let map: Map<String, Value> = serde_json::from_str(json).context("EXA_CONFIG_JSON is not a JSON object")?;
+    let mut out = Vec::new();
+    for (key, value) in &map {
+        let Some(var) = var_for_key(key) else {
+            bail!("EXA_CONFIG_JSON: unknown key '{}'", key);
+        };
+        if !value.is_null() {
+            out.push((var, value_to_env(key, value)?));
+        }
+    }
+    out.sort_by_key(|(var, _)| VARS.iter().position(|v| v == var));
+    Ok(out)
+}
+
+/// The variables EXA_CONFIG_JSON sets that the environment doesn't, so a
+/// single value can still be overridden per invocation
+pub fn load_inline() -> Result<Vec<(&'static str, String)>> {
+    let Ok(json) = env::var("EXA_CONFIG_JSON") else { return Ok(Vec::new()) };
+    if json.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    let in_env = |var: &str| env::var(var).is_ok() || (var == "EXA_API_KEYS" && env::var("EXA_API_KEY").is_ok());
+    Ok(parse_inline(&json)?.into_iter().filter(|(var, _)| !in_env(var)).collect())
+}
+
+/// `<config dir>/config.toml`
+pub fn file_path() -> Result<PathBuf> {
+    Ok(KeyManager::get_config_dir()?.join("config.toml"))
+}
+
+/// Parse the command line, with config.toml supplying defaults for global
+/// flags. Precedence: command line, then environment (with `inline`, from
+/// [`load_inline`]), then the file. `inline` is installed for
+/// [`vars::get`] on the way.
+pub fn parse_args<P: clap::Parser>(inline: Vec<(&'static str, String)>) -> Result<P> {
+    vars::set_inline(inline);
+    let args: Vec<OsString> = env::args_os().collect();
+    let path = file_path()?;
+    let command = P::command();
+    // `config` manages the file, so a broken file must not lock it out
+    let managing = command
+        .clone()
+        .try_get_matches_from(&args)
+        .is_ok_and(|m| m.subcommand_name() == Some("config"));
+    // Without a file there are no settings, but --profile still has to name one
+    let args = if managing {
+        args
+    } else {
+        let table = load_file()?.unwrap_or_default();
+        with_file_args(&command, args, &table, vars::is_set)
+            .with_context(|| format!("Invalid setting in {}", path.display()))?
+    };
+    Ok(P::parse_from(args))
+}
+
+/// Parse a saved command line (the words after `exa`) the way
+/// [`parse_args`] parses the real one, config.toml's defaults included
+pub fn parse_saved<P: clap::Parser>(args: &[String]) -> Result<P> {
+    let path = file_path()?;
+    let args: Vec<OsString> = std::iter::once(OsString::from("exa")).chain(args.iter().map(OsString::from)).collect();
+    let table = load_file()?.unwrap_or_default();
+    let args = with_file_args(&P::command(), args, &table, vars::is_set)
+        .with_context(|| format!("Invalid setting in {}", path.display()))?;
+    Ok(P::try_parse_from(args)?)
+}
+
+/// Whether `key` in `section` is one of the [`OPTIONS`]
+pub fn is_option(section: Option<&str>, key: &str) -> bool {
+    section.is_some_and(|s| OPTIONS.contains(&format!("{}.{}", s, key.replace('-', "_")).as_str()))
+}
+
+/// `log.include_queries`: whether query text is kept in the request log and
+/// history, rather than only a keyed hash of it. EXA_LOG_QUERIES beats the file.
+pub fn include_queries() -> bool {
+    if let Some(value) = vars::get("EXA_LOG_QUERIES").filter(|v| !v.trim().is_empty()) {
+        return value.trim() == "1";
+    }
+    let table = load_file().ok().flatten().unwrap_or_default();
+    section(&table, Some("log"))
+        .and_then(|log| log.get(&find_setting(log, "include_queries")?)?.as_bool())
+        .unwrap_or(false)
+}
+
+/// The settings in config.toml, if it exists
+pub fn load_file() -> Result<Option<toml::Table>> {
+    let path = file_path()?;
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    let table = toml::from_str(&text).with_context(|| format!("Failed to parse {}", path.display()))?;
+    Ok(Some(table))
+}
+
+pub fn save_file(table: &toml::Table) -> Result<()> {
+    let path = file_path()?;
+    fs::write(&path, toml::to_string(table)?).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// The flag a setting names (`cache_ttl` or `cache-ttl`): a global flag, or
+/// in a command's section, one of that command's own flags
+fn setting_flag<'a>(command: &'a clap::Command, section: Option<&str>, key: &str) -> Result<&'a clap::Arg> {
+    let name = key.replace('_', "-");
+    let named = |a: &&clap::Arg| a.get_long() == Some(name.as_str());
+    if let Some(arg) = command.get_arguments().filter(|a| a.is_global_set()).find(named) {
+        return Ok(arg);
+    }
+    match section_command(section).and_then(|s| command.find_subcommand(s)) {
+        Some(sub) => sub.get_arguments().filter(|a| !a.is_global_set()).find(named).with_context(|| {
+            format!("unknown setting '{}' (expected a global flag such as num or type, or a flag of {})", key, sub.get_name())
+        }),
+        None => bail!("unknown setting '{}' (expected a global flag such as num or type)", key),
+    }
+}
+
+/// The command a section is for: `find` for [find] and [profiles.papers.find]
+fn section_command(section: Option<&str>) -> Option<&str> {
+    let section = section?;
+    match section.strip_prefix(PROFILES).and_then(|r| r.strip_prefix('.')) {
+        Some(rest) => rest.split_once('.').map(|(_, command)| command),
+        None => Some(section),
+    }
+}
+
+/// Canonical name of the command a `[section]` is for
+fn section_name(command: &clap::Command, section: &str) -> Result<String> {
+    command
+        .find_subcommand(section)
+        .map(|c| c.get_name().to_string())
+        .with_context(|| format!("unknown section [{}] (expected a command such as [search])", section))
+}
+
+/// Split a `config` key into its command section, if any, and the setting:
+/// `find.type` is the `type` setting under `[find]`, `profiles.papers.num`
+/// the `num` setting under `[profiles.papers]`, and
+/// `profiles.papers.find.type` the `type` setting under `[profiles.papers.find]`
+pub fn split_key(command: &clap::Command, key: &str) -> Result<(Option<String>, String)> {
+    if let Some(rest) = key.strip_prefix(PROFILES).and_then(|r| r.strip_prefix('.')) {
+        let parts: Vec<&str> = rest.split('.').collect();
+        return match parts[..] {
+            [name, setting] if !name.is_empty() => Ok((Some(format!("{}.{}", PROFILES, name)), setting.into())),
+            [name, section, setting] if !name.is_empty() => {
+                let section = section_name(command, section)?;
+                Ok((Some(format!("{}.{}.{}", PROFILES, name, section)), setting.into()))
+            }
+            _ => bail!("expected {}.<profile>.<setting>, e.g. {}.papers.num", PROFILES, PROFILES),
+        };
+    }
+    match key.split_once('.') {
+        Some((section, setting)) => Ok((Some(section_name(command, section)?), setting.to_string())),
+        None => Ok((None, key.to_string())),
+    }
+}
+
+/// The top-level table or a section of it (dotted for a profile), if present
+pub fn section<'t>(table: &'t toml::Table, section: Option<&str>) -> Option<&'t toml::Table> {
+    let Some(path) = section else { return Some(table) };
+    path.split('.').try_fold(table, |t, name| t.get(name).and_then(|v| v.as_table()))
+}
+
+/// Like [`section`], creating the section if needed
+pub fn section_mut<'t>(table: &'t mut toml::Table, section: Option<&str>) -> Result<&'t mut toml::Table> {
+    let Some(path) = section else { return Ok(table) };
+    path.split('.').try_fold(table, |t, name| {
+        t.entry(name)
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+            .as_table_mut()
+            .with_context(|| format!("'{}' in the config file is not a [{}] section", name, path))
+    })
+}
+
+/// Remove an emptied section, and its parents (a profile, [profiles]) once
+/// they are empty too
+pub fn remove_section(table: &mut toml::Table, section: &str) {
+    let Some((parent, rest)) = section.split_once('.') else {
+        table.remove(section);
+        return;
+    };
+    if let Some(t) = table.get_mut(parent).and_then(|v| v.as_table_mut()) {
+        remove_section(t, rest);
+        if t.is_empty() {
+            table.remove(parent);
+        }
+    }
+}
+
+/// Canonical name of a setting: dashed for a flag, as listed in
+/// [`OPTIONS`] for the others
+pub fn setting_name(command: &clap::Command, section: Option<&str>, key: &str) -> Result<String> {
+    if is_option(section, key) {
+        return Ok(key.replace('-', "_"));
+    }
+    setting_flag(command, section, key).map(|arg| arg.get_long().unwrap_or(key).to_string())
+}
+
+/// Key of a setting in `table`, in whichever spelling it was written
+pub fn find_setting(table: &toml::Table, key: &str) -> Option<String> {
+    let name = key.replace('_', "-");
+    table.keys().find(|k| k.replace('_', "-") == name).cloned()
+}
+
+/// Turn `config set` input into a (key, value) for config.toml, typed for
+/// the flag and checked with the flag's own parser
+pub fn parse_setting(
+    command: &clap::Command,
+    section: Option<&str>,
+    key: &str,
+    raw: &str,
+) -> Result<(String, toml::Value)> {
+    let name = setting_name(command, section, key)?;
+    let raw = raw.trim();
+    if is_option(section, key) {
+        let on = raw.parse().map_err(|_| anyhow::anyhow!("'{}' takes true or false, not '{}'", name, raw))?;
+        return Ok((name, toml::Value::Boolean(on)));
+    }
+    let arg = setting_flag(command, section, key)?;
+    let value = match arg.get_action() {
+        ArgAction::SetTrue => toml::Value::Boolean(
+            raw.parse().map_err(|_| anyhow::anyhow!("'{}' takes true or false, not '{}'", name, raw))?,
+        ),
+        ArgAction::Count => toml::Value::Integer(
+            raw.parse::<u8>().map_err(|_| anyhow::anyhow!("'{}' takes a count, not '{}'", name, raw))?.into(),
+        ),
+        ArgAction::Append => toml::Value::Array(
+            raw.split(',').map(|v| v.trim()).filter(|v| !v.is_empty()).map(toml_scalar).collect(),
+        ),
+        _ => toml_scalar(raw),
+    };
+
+    // Any name will do for a profile; it is checked when used
+    if name == "profile" {
+        return Ok((name, value));
+    }
+
+    // A command's own flag can't be given without the command and what it
+    // requires, so a flag with just its parser checks the values instead
+    if !arg.is_global_set() {
+        let values: Vec<&str> = match arg.get_action() {
+            ArgAction::Append => raw.split(',').map(|v| v.trim()).filter(|v| !v.is_empty()).collect(),
+            ArgAction::Set => vec![raw],
+            _ => Vec::new(),
+        };
+        let mut check = clap::Arg::new(name.clone())
+            .long(name.clone())
+            .action(ArgAction::Append)
+            .allow_hyphen_values(true)
+            .value_parser(arg.get_value_parser().clone());
+        if let Some(value_name) = arg.get_value_names().and_then(|n| n.first()) {
+            check = check.value_name(value_name.clone());
+        }
+        let args = std::iter::once("exa".to_string()).chain(values.iter().map(|v| format!("--{}={}", name, v)));
+        clap::Command::new("exa").arg(check).try_get_matches_from(args).map_err(clap_error)?;
+        return Ok((name, value));
+    }
+
+    // Let clap validate it, as if given on the command line
+    let mut table = toml::Table::new();
+    table.insert(name.clone(), value.clone());
+    let command = command.clone().subcommand_required(false).arg_required_else_help(false);
+    let args = with_file_args(&command, vec!["exa".into()], &table, |_| false)?;
+    command.try_get_matches_from(args).map_err(clap_error)?;
+    Ok((name, value))
+}
+
+/// The first line of a clap error, without its `error: ` prefix
+fn clap_error(e: clap::Error) -> anyhow::Error {
+    let message = e.to_string();
+    anyhow::anyhow!("{}", message.lines().next().unwrap_or_default().trim_start_matches("error: "))
+}
+
+fn toml_scalar(raw: &str) -> toml::Value {
+    if let Ok(n) = raw.parse::<i64>() {
+        return toml::Value::Integer(n);
+    }
+    match raw.parse::<f64>() {
+        Ok(f) if raw.contains('.') => toml::Value::Float(f),
+        _ => toml::Value::String(raw.to_string()),
+    }
+}
+
+/// `args` with the file's settings inserted as flags (global ones after the
+/// program name, a command's own after the command), leaving out those the
+/// command line or (per `env_set`) the environment already sets. Keys are
+/// long flag names; `_` may stand in for `-`.
+fn with_file_args(
+    command: &clap::Command,
+    args: Vec<OsString>,
+    table: &toml::Table,
+    env_set: impl Fn(&str) -> bool,
+) -> Result<Vec<OsString>> {
+    // Errors and --help are reported by the real parse. A command's required
+    // flags may come from the file, so nothing is required here.
+    let names: Vec<String> = command.get_subcommands().map(|c| c.get_name().to_string()).collect();
+    let relaxed = names.iter().fold(command.clone(), |command, name| {
+        command.mut_subcommand(name, |sub| sub.mut_args(|arg| arg.required(false)))
+    });
+    let Ok(matches) = relaxed.try_get_matches_from(&args) else { return Ok(args) };
+    // Built, so the command's conflicts with global flags can be looked up
+    let mut command = command.clone();
+    command.build();
+    let current = matches.subcommand().and_then(|(name, m)| Some((command.find_subcommand(name)?, m)));
+    let current_name = current.map(|(sub, _)| sub.get_name());
+    let on_command_line = |arg: &clap::Arg| {
+        let matches = match current {
+            Some((_, m)) if !arg.is_global_set() => m,
+            _ => &matches,
+        };
+        matches!(matches.value_source(arg.get_id().as_str()), Some(ValueSource::CommandLine))
+    };
+
+    // Lowest first: top-level settings, the command's [<command>] section, a
+    // selected profile's settings, then its [profiles.<name>.<command>] section.
+    // Each setting is taken from the last that has it.
+    let mut levels: [Vec<(&String, &toml::Value)>; 4] = Default::default();
+    let profiles = profiles(&command, table)?;
+    for (key, value) in table {
+        if key == PROFILES {
+            continue;
+        }
+        let toml::Value::Table(settings) = value else {
+            levels[0].push((key, value));
+            continue;
+        };
+        let name = section_name(&command, key)?;
+        let settings: Vec<_> = settings.iter().filter(|(key, _)| !is_option(Some(&name), key)).collect();
+        for (key, _) in &settings {
+            setting_flag(&command, Some(&name), key).with_context(|| format!("in [{}]", name))?;
+        }
+        if current_name == Some(name.as_str()) {
+            levels[1].extend(settings);
+        }
+    }
+    if let Some(name) = selected_profile(&matches, table, &env_set) {
+        let Some(profile) = profiles.get(name.as_str()) else {
+            let known: Vec<&str> = profiles.keys().copied().collect();
+            let known = if known.is_empty() { "none are defined".to_string() } else { known.join(", ") };
+            bail!("unknown profile '{}' ([profiles.<name>] sections: {})", name, known);
+        };
+        for (key, value) in profile.iter() {
+            match value {
+                toml::Value::Table(settings) if current_name == Some(section_name(&command, key)?.as_str()) => {
+                    levels[3].extend(settings);
+                }
+                toml::Value::Table(_) => {}
+                value => levels[2].push((key, value)),
+            }
+        }
+    }
+    let mut chosen: Vec<(&String, &toml::Value, Option<&str>)> = Vec::new();
+    for (level, settings) in levels.iter().enumerate().rev() {
+        // Sections may also hold the command's own flags
+        let section = if level % 2 == 1 { current_name } else { None };
+        for &(key, value) in settings {
+            if !chosen.iter().any(|(k, _, _)| same_setting(k, key)) {
+                chosen.push((key, value, section));
+            }
+        }
+    }
+
+    let mut global: Vec<OsString> = Vec::new();
+    let mut own: Vec<OsString> = Vec::new();
+    // Lowest precedence first
+    let chosen: Vec<_> = chosen.into_iter().rev().collect();
+    for (i, &(key, value, section)) in chosen.iter().enumerate() {
+        let name = key.replace('_', "-");
+        let arg = setting_flag(&command, section, key)?;
+        let conflicts = match current {
+            Some((sub, _)) if !arg.is_global_set() => sub.get_arg_conflicts_with(arg),
+            _ => command.get_arg_conflicts_with(arg),
+        };
+        // A setting also gives way to a conflicting one that takes precedence
+        // (`json = true` at the top, `yaml = true` in the command's section)
+        let set_later = |other: &clap::Arg| {
+            chosen[i + 1..].iter().any(|&(key, value, section)| {
+                *value != toml::Value::Boolean(false)
+                    && setting_flag(&command, section, key).is_ok_and(|a| a.get_id() == other.get_id())
+            })
+        };
+        if on_command_line(arg) || conflicts.into_iter().any(|c| on_command_line(c) || set_later(c)) {
+            continue;
+        }
+        if FLAG_VARS.iter().any(|(flag, var)| *flag == name && env_set(var)) {
+            continue;
+        }
+        let extra = if arg.is_global_set() { &mut global } else { &mut own };
+        let flag = format!("--{}", name);
+        match (arg.get_action(), value) {
+            (ArgAction::SetTrue, toml::Value::Boolean(on)) => {
+                if *on {
+                    extra.push(flag.into());
+                }
+            }
+            (ArgAction::Count, toml::Value::Integer(n)) => {
+                extra.extend((0..*n).map(|_| OsString::from(&flag)));
+            }
+            (ArgAction::Append, toml::Value::Array(items)) => {
+                for item in items {
+                    extra.push(format!("{}={}", flag, scalar(key, item)?).into());
+                }
+            }
+            (ArgAction::Set | ArgAction::Append, value) => {
+                extra.push(format!("{}={}", flag, scalar(key, value)?).into());
+            }
+            (_, value) => bail!("unsupported value for '{}': {}", key, value),
+        }
+    }
+
+    let at = command_position(&command, &args);
+    let mut merged = args;
+    let mut rest = merged.split_off(1.min(merged.len()));
+    if let Some(at) = at.filter(|_| !own.is_empty()) {
+        let after = rest.split_off(at);
+        rest.extend(own);
+        rest.extend(after);
+    }
+    merged.extend(global);
+    merged.extend(rest);
+    Ok(merged)
+}
+
+/// Index in `args` of the command's name, past the global flags and their values
+fn command_position(command: &clap::Command, args: &[OsString]) -> Option<usize> {
+    let takes_value = |arg: &clap::Arg| matches!(arg.get_action(), ArgAction::Set | ArgAction::Append);
+    let mut i = 1;
+    while let Some(word) = args.get(i).and_then(|a| a.to_str()) {
+        let arg = if let Some(long) = word.strip_prefix("--") {
+            command.get_arguments().find(|a| a.get_long() == Some(long))
+        } else if let Some(short) = word.strip_prefix('-').filter(|s| !s.is_empty()) {
+            // `-n5` carries its value, `-n 5` doesn't
+            let mut chars = short.chars();
+            let first = chars.next();
+            command.get_arguments().find(|a| a.get_short() == first).filter(|_| chars.as_str().is_empty())
+        } else {
+            return Some(i);
+        };
+        i += if arg.is_some_and(takes_value) { 2 } else { 1 };
+    }
+    None
+}
+
+/// The `[profiles.<name>]` sections, each checked to hold only flags: global
+/// ones, and in a `[profiles.<name>.<command>]` section the command's own
+fn profiles<'t>(command: &clap::Command, table: &'t toml::Table) -> Result<BTreeMap<&'t str, &'t toml::Table>> {
+    let Some(value) = table.get(PROFILES) else { return Ok(BTreeMap::new()) };
+    let toml::Value::Table(profiles) = value else { bail!("'{}' must hold [{}.<name>] sections", PROFILES, PROFILES) };
+    let mut out = BTreeMap::new();
+    for (name, settings) in profiles {
+        let toml::Value::Table(settings) = settings else {
+            bail!("profile '{}' must be a [{}.{}] section", name, PROFILES, name);
+        };
+        for (key, value) in settings {
+            let (section, keys): (Option<String>, Vec<&String>) = match value {
+                toml::Value::Table(settings) => {
+                    let section = section_name(command, key).with_context(|| format!("in profile '{}'", name))?;
+                    (Some(section), settings.keys().collect())
+                }
+                _ => (None, vec![key]),
+            };
+            for key in keys {
+                if same_setting(key, "profile") {
+                    bail!("profile '{}' can't select another profile", name);
+                }
+                setting_flag(command, section.as_deref(), key).with_context(|| format!("in profile '{}'", name))?;
+            }
+        }
+        out.insert(name.as_str(), settings);
+    }
+    Ok(out)
+}
+
+/// The profile asked for: --profile, then EXA_PROFILE, then `profile` in the file
+fn selected_profile(matches: &clap::ArgMatches, table: &toml::Table, env_set: impl Fn(&str) -> bool) -> Option<String> {
+    let from_command_line = matches
+        .try_get_one::<String>("profile")
+        .ok()
+        .flatten()
+        .filter(|_| matches!(matches.value_source("profile"), Some(ValueSource::CommandLine)));
+    if let Some(name) = from_command_line {
+        return Some(name.clone());
+    }
+    if env_set("EXA_PROFILE") {
+        return vars::get("EXA_PROFILE").map(|v| v.trim().to_string());
+    }
+    let key = find_setting(table, "profile")?;
+    table.get(&key)?.as_str().map(String::from)
+}
+
+fn same_setting(a: &str, b: &str) -> bool {
+    a.replace('_', "-") == b.replace('_', "-")
+}
+
+fn scalar(key: &str, value: &toml::Value) -> Result<String> {
+    Ok(match value {
+        toml::Value::String(s) => s.clone(),
+        toml::Value::Integer(n) => n.to_string(),
+        toml::Value::Float(f) => f.to_string(),
+        toml::Value::Boolean(b) => b.to_string(),
+        other => bail!("unsupported value for '{}': {}", key, other),
+    })
+}
+
+/// The effective configuration: environment plus file-backed settings
+/// (the do-not-ingest list), keyed by variable name
+pub fn effective() -> Result<Vec<(&'static str, String)>> {
+    let mut out = Vec::new();
+    for var in VARS {
+        let value = match *var {
+            "EXA_API_KEYS" => vars::get(var).or_else(|| vars::get("EXA_API_KEY")),
+            "EXA_DO_NOT_INGEST" => {
+                let list = DoNotIngest::load()?;
+                (!list.is_empty()).then(|| list.domains().join(","))
+            }
+            _ => vars::get(var),
+        };
+        if let Some(value) = value.filter(|v| !v.trim().is_empty()) {
+            out.push((*var, value));
+        }
+    }
+    Ok(out)
+}
+
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// `VAR='value'` lines; secrets become comments unless `show_secrets`
+pub fn render_env(config: &[(&str, String)], show_secrets: bool) -> String {
+    let mut out = String::new();
+    for (var, value) in config {
+        if SECRETS.contains(var) && !show_secrets {
+            let hidden = match *var {
+                "EXA_API_KEYS" => format!("{} key(s)", value.split(',').filter(|k| !k.trim().is_empty()).count()),
+                "EXA_JIRA_TOKEN" => "token".to_string(),
+                "EXA_SMTP_PASSWORD" => "password".to_string(),
+                _ => "API key".to_string(),
+            };
+            out.push_str(&format!("# {}=<{} hidden; pass --show-secrets>\n", var, hidden));
+        } else {
+            out.push_str(&format!("{}={}\n", var, shell_quote(value)));
+        }
+    }
+    out
+}
+
+/// A JSON object accepted by EXA_CONFIG_JSON; secrets are omitted unless `show_secrets`
+pub fn render_json(config: &[(&str, String)], show_secrets: bool) -> Value {
+    let map: Map<String, Value> = config
+        .iter()
+        .filter(|(var, _)| show_secrets || !SECRETS.contains(var))
+        .map(|(var, value)| (var.to_string(), Value::String(value.clone())))
+        .collect();
+    Value::Object(map)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_inline() {
+        let vars = parse_inline(
+            r#"{"apiKeys": ["k1", "k2"], "cache_backend": "redis://cache:6379",
+                "EXA_LOG_REQUESTS": true, "clientTag": null}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            vars,
+            vec![
+                ("EXA_API_KEYS", "k1,k2".to_string()),
+                ("EXA_CACHE_BACKEND", "redis://cache:6379".to_string()),
+                ("EXA_LOG_REQUESTS", "1".to_string()),
+            ]
+        );
+        assert!(parse_inline(r#"{"colour": "red"}"#).is_err());
+        assert!(parse_inline("[1]").is_err());
+    }
+
+    #[derive(clap::Parser, Debug)]
+    struct TestCli {
+        #[arg(short = 'n', long = "num", global = true, default_value = "5")]
+        num: usize,
+        #[arg(short = 'v', long = "verbose", global = true, action = clap::ArgAction::Count)]
+        verbose: u8,
+        #[arg(long = "compact", global = true)]
+        compact: bool,
+        #[arg(long = "include-domains", global = true, value_delimiter = ',')]
+        include_domains: Vec<String>,
+        #[arg(long = "cache-backend", global = true)]
+        cache_backend: Option<String>,
+        #[arg(long = "csv", global = true, conflicts_with = "tsv")]
+        csv: bool,
+        #[arg(long = "tsv", global = true)]
+        tsv: bool,
+        #[arg(long = "profile", global = true)]
+        profile: Option<String>,
+        #[command(subcommand)]
+        command: TestCommand,
+    }
+
+    #[derive(clap::Subcommand, Debug)]
+    enum TestCommand {
+        Search {
+            query: Vec<String>,
+        },
+        Find {
+            query: Vec<String>,
+            #[arg(long = "depth")]
+            depth: Option<u8>,
+            #[arg(long = "exclude-source", conflicts_with = "compact")]
+            exclude_source: Option<String>,
+        },
+    }
+
+    /// `find --depth`, from the command line or the file
+    fn depth(cli: &TestCli) -> Option<u8> {
+        match cli.command {
+            TestCommand::Find { depth, .. } => depth,
+            TestCommand::Search { .. } => None,
+        }
+    }
+
+    fn merged(argv: &[&str], file: &str, env_vars: &[&str]) -> Result<TestCli> {
+        use clap::{CommandFactory, Parser};
+        let args = argv.iter().map(OsString::from).collect();
+        let table: toml::Table = toml::from_str(file).unwrap();
+        let args = with_file_args(&TestCli::command(), args, &table, |v| env_vars.contains(&v))?;
+        Ok(TestCli::try_parse_from(args)?)
+    }
+
+    #[test]
+    fn test_file_args() {
+        let file = r#"
+            num = 8
+            verbose = 2
+            compact = true
+            include_domains = ["a.com", "b.com"]
+            cache-backend = "/mnt/cache"
+            csv = true
+        "#;
+        let cli = merged(&["exa", "search", "rust"], file, &[]).unwrap();
+        assert_eq!((cli.num, cli.verbose, cli.compact, cli.csv), (8, 2, true, true));
+        assert_eq!(cli.include_domains, ["a.com", "b.com"]);
+        assert_eq!(cli.cache_backend.as_deref(), Some("/mnt/cache"));
+
+        // The command line beats the environment, which beats the file
+        let cli = merged(&["exa", "search", "rust", "-n", "3", "--tsv"], file, &["EXA_CACHE_BACKEND"]).unwrap();
+        assert_eq!((cli.num, cli.csv, cli.tsv), (3, false, true));
+        assert_eq!(cli.cache_backend, None);
+
+        assert!(merged(&["exa", "search"], "colour = \"red\"", &[]).is_err());
+        assert!(merged(&["exa", "search"], "num = [1]", &[]).is_err());
+    }
+
+    #[test]
+    fn test_command_sections() {
+        let file = "num = 8\ncompact = true\n[find]\nnum = 2\n";
+        assert_eq!(merged(&["exa", "search", "rust"], file, &[]).unwrap().num, 8);
+        let cli = merged(&["exa", "find", "rust"], file, &[]).unwrap();
+        assert_eq!((cli.num, cli.compact), (2, true));
+        assert_eq!(merged(&["exa", "find", "rust", "-n", "4"], file, &[]).unwrap().num, 4);
+
+        assert!(merged(&["exa", "search"], "[serch]\nnum = 2", &[]).is_err());
+        assert!(merged(&["exa", "search"], "[find]\ncolour = 2", &[]).is_err());
+
+        // A section may also set the command's own flags, which go after the
+        // command's name, past global flags and their values
+        let file = "[find]\ndepth = 3\n";
+        assert_eq!(depth(&merged(&["exa", "-n", "7", "find", "rust"], file, &[]).unwrap()), Some(3));
+        assert_eq!(depth(&merged(&["exa", "find", "rust", "--depth", "1"], file, &[]).unwrap()), Some(1));
+        assert!(merged(&["exa", "search", "rust"], file, &[]).is_ok());
+        assert!(merged(&["exa", "find", "rust"], "depth = 3", &[]).is_err());
+        assert!(merged(&["exa", "find", "rust"], "[search]\ndepth = 3", &[]).is_err());
+        // Including when they conflict with a global flag on the command line
+        let cli = merged(&["exa", "find", "rust", "--compact"], "[find]\nexclude-source = \"x\"", &[]).unwrap();
+        assert!(cli.compact);
+        // Conflicting settings: the section's beats the top level's
+        let file = "csv = true\n[find]\ntsv = true\n";
+        let cli = merged(&["exa", "find", "rust"], file, &[]).unwrap();
+        assert_eq!((cli.csv, cli.tsv), (false, true));
+        let cli = merged(&["exa", "search", "rust"], file, &[]).unwrap();
+        assert_eq!((cli.csv, cli.tsv), (true, false));
+        let cli = merged(&["exa", "find", "rust"], "csv = true\n[find]\ntsv = false\n", &[]).unwrap();
+        assert_eq!((cli.csv, cli.tsv), (true, false));
+
+        use clap::CommandFactory;
+        let command = TestCli::command();
+        assert_eq!(split_key(&command, "find.num").unwrap(), (Some("find".to_string()), "num".to_string()));
+        assert_eq!(split_key(&command, "num").unwrap(), (None, "num".to_string()));
+        assert!(split_key(&command, "serch.num").is_err());
+    }
+
+    #[test]
+    fn test_profiles() {
+        let file = "num = 8\ncompact = true\n[find]\nnum = 2\n[profiles.wide]\nnum = 20\ncsv = true\n";
+        let cli = merged(&["exa", "find", "rust", "--profile", "wide"], file, &[]).unwrap();
+        assert_eq!((cli.num, cli.compact, cli.csv), (20, true, true));
+        // Explicit flags still win, including ones that conflict with the profile's
+        let cli = merged(&["exa", "search", "rust", "--profile", "wide", "-n", "3", "--tsv"], file, &[]).unwrap();
+        assert_eq!((cli.num, cli.csv, cli.tsv), (3, false, true));
+        assert_eq!(merged(&["exa", "find", "rust"], file, &[]).unwrap().num, 2);
+        let selected = format!("profile = \"wide\"\n{}", file);
+        assert_eq!(merged(&["exa", "search", "rust"], &selected, &[]).unwrap().num, 20);
+
+        assert!(merged(&["exa", "search", "rust", "--profile", "narrow"], file, &[]).is_err());
+        assert!(merged(&["exa", "search", "rust", "--profile", "x"], "", &[]).is_err());
+        assert!(merged(&["exa", "search"], "[profiles.bad]\ncolour = 1", &[]).is_err());
+
+        // A profile's command sections beat its other settings and [<command>]
+        let deep = "[find]\ndepth = 1\nnum = 2\n[profiles.deep]\nnum = 20\n[profiles.deep.find]\nnum = 9\ndepth = 4\n";
+        let cli = merged(&["exa", "find", "rust", "--profile", "deep"], deep, &[]).unwrap();
+        assert_eq!((cli.num, depth(&cli)), (9, Some(4)));
+        assert_eq!(merged(&["exa", "search", "rust", "--profile", "deep"], deep, &[]).unwrap().num, 20);
+        assert_eq!(depth(&merged(&["exa", "find", "rust"], deep, &[]).unwrap()), Some(1));
+        assert!(merged(&["exa", "search"], "[profiles.bad]\ndepth = 1", &[]).is_err());
+        assert!(merged(&["exa", "search"], "[profiles.bad.serch]\nnum = 1", &[]).is_err());
+
+        use clap::CommandFactory;
+        let command = TestCli::command();
+        let split = split_key(&command, "profiles.wide.num").unwrap();
+        assert_eq!(split, (Some("profiles.wide".to_string()), "num".to_string()));
+        assert!(split_key(&command, "profiles.num").is_err());
+        let split = split_key(&command, "profiles.wide.find.depth").unwrap();
+        assert_eq!(split, (Some("profiles.wide.find".to_string()), "depth".to_string()));
+        assert!(split_key(&command, "profiles.wide.serch.num").is_err());
+        let mut table: toml::Table = toml::from_str(file).unwrap();
+        assert_eq!(section(&table, Some("profiles.wide")).unwrap().len(), 2);
+        section_mut(&mut table, Some("profiles.news")).unwrap().insert("num".into(), 5.into());
+        assert_eq!(section(&table, Some("profiles.news")).unwrap()["num"].as_integer(), Some(5));
+        section_mut(&mut table, Some("profiles.deep.find")).unwrap().insert("depth".into(), 2.into());
+        remove_section(&mut table, "profiles.news");
+        remove_section(&mut table, "profiles.wide");
+        remove_section(&mut table, "profiles.deep.find");
+        assert!(!table.contains_key("profiles"));
+    }
+
+    #[test]
+    fn test_parse_setting() {
+        use clap::CommandFactory;
+        let command = TestCli::command();
+        let parse = |key, raw| parse_setting(&command, None, key, raw).map(|(k, v)| format!("{} = {}", k, v));
+        assert_eq!(parse("num", "10").unwrap(), "num = 10");
+        assert_eq!(parse("compact", "true").unwrap(), "compact = true");
+        assert_eq!(parse("verbose", "2").unwrap(), "verbose = 2");
+        assert_eq!(parse("include_domains", "a.com, b.com").unwrap(), r#"include-domains = ["a.com", "b.com"]"#);
+        assert_eq!(parse("cache-backend", "/mnt/cache").unwrap(), r#"cache-backend = "/mnt/cache""#);
+        assert!(parse("num", "ten").unwrap_err().to_string().contains("invalid value 'ten'"));
+        assert!(parse("compact", "yes").is_err());
+        assert!(parse("colour", "red").is_err());
+        let option = parse_setting(&command, Some("log"), "include-queries", "true").unwrap();
+        assert_eq!(option, ("include_queries".to_string(), toml::Value::Boolean(true)));
+        assert!(parse_setting(&command, Some("log"), "include_queries", "1").is_err());
+        let own = |section, raw| parse_setting(&command, Some(section), "depth", raw).map(|(k, v)| format!("{} = {}", k, v));
+        assert_eq!(own("find", "3").unwrap(), "depth = 3");
+        assert_eq!(own("profiles.deep.find", "3").unwrap(), "depth = 3");
+        assert!(own("find", "300").unwrap_err().to_string().contains("invalid value '300'"));
+        assert!(own("search", "3").is_err());
+        assert!(parse("depth", "3").is_err());
+
+        let table: toml::Table = toml::from_str("cache_backend = 'x'").unwrap();
+        assert_eq!(find_setting(&table, "cache-backend").as_deref(), Some("cache_backend"));
+        assert_eq!(find_setting(&table, "num"), None);
+    }
+
+    #[test]
+    fn test_render_env() {
+        let config = [
+            ("EXA_API_KEYS", "k1,k2".to_string()),
+            ("EXA_CLIENT_TAG", "ci's job".to_string()),
+            ("EXA_JIRA_TOKEN", "t0ken".to_string()),
+            ("EXA_SMTP_PASSWORD", "hunter2".to_string()),
+        ];
+        assert_eq!(
+            render_env(&config, false),
+            "# EXA_API_KEYS=<2 key(s) hidden; pass --show-secrets>\nEXA_CLIENT_TAG='ci'\\''s job'\n\
+             # EXA_JIRA_TOKEN=<token hidden; pass --show-secrets>\n\
+             # EXA_SMTP_PASSWORD=<password hidden; pass --show-secrets>\n"
+        );
+        assert!(render_env(&config, true).starts_with("EXA_API_KEYS='k1,k2'\n"));
+        assert_eq!(render_json(&config, false), serde_json::json!({"EXA_CLIENT_TAG": "ci's job"}));
+    }
+}
@@ -0,0 +1,109 @@
+use std::collections::HashSet;
+
+/// Phrases that usually introduce a rebuttal of another claim
+const CONTRADICTION_CUES: &[&str] = &[
+    "contrary to",
+    "misconception",
+    "myth",
+    "debunk",
+    "no evidence",
+    "not true",
+    "is false",
+    "incorrect",
+    "disputed",
+    "contradict",
+];
+
+const NEGATIONS: &[&str] = &["not", "no", "never", "none", "cannot", "without", "neither", "nor"];
+
+/// Minimum shared-vocabulary score for two snippets to be about the same thing
+const TOPIC_OVERLAP: f64 = 0.15;
+
+/// Two snippets from different sources that appear to disagree
+#[derive(Debug, PartialEq)]
+pub struct Conflict {
+    /// Indexes into the snippets passed to `detect`
+    pub a: usize,
+    pub b: usize,
+    pub reason: &'static str,
+}
+
+fn content_words(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| w.len() > 3)
+        .map(|w| w.to_lowercase())
+        .collect()
+}
+
+fn is_negated(text: &str) -> bool {
+    let lower = text.to_lowercase();
+    lower.contains("n't")
+        || lower
+            .split(|c: char| !c.is_alphanumeric())
+            .any(|w| NEGATIONS.contains(&w))
+}
+
+fn has_cue(text: &str) -> bool {
+    let lower = text.to_lowercase();
+    CONTRADICTION_CUES.iter().any(|cue| lower.contains(cue))
+}
+
+/// Find the strongest disagreement among `(source, snippet)` pairs: topically
+/// overlapping snippets from different sources where one rebuts or negates
+/// what the other asserts
+pub fn detect(snippets: &[(usize, &str)]) -> Option<Conflict> {
+    let words: Vec<HashSet<String>> = snippets.iter().map(|(_, s)| content_words(s)).collect();
+    let mut best: Option<(f64, Conflict)> = None;
+
+    for i in 0..snippets.len() {
+        for j in i + 1..snippets.len() {
+            if snippets[i].0 == snippets[j].0 {
+                continue;
+            }
+            let shared = words[i].intersection(&words[j]).count() as f64;
+            let union = words[i].union(&words[j]).count().max(1) as f64;
+            let overlap = shared / union;
+            if overlap < TOPIC_OVERLAP {
+                continue;
+            }
+            let (a, b) = (snippets[i].1, snippets[j].1);
+            let reason = if has_cue(a) != has_cue(b) {
+                "one source rebuts a claim the other makes"
+            } else if is_negated(a) != is_negated(b) {
+                "sources make opposite statements"
+            } else {
+                continue;
+            };
+            if best.as_ref().is_none_or(|(score, _)| overlap > *score) {
+                best = Some((overlap, Conflict { a: i, b: j, reason }));
+            }
+        }
+    }
+    best.map(|(_, c)| c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        let agree = [
+            (0, "Coffee consumption is linked to lower risk of liver disease."),
+            (1, "Studies link coffee consumption with lower liver disease risk."),
+        ];
+        assert_eq!(detect(&agree), None);
+
+        let disagree = [
+            (0, "Coffee consumption is linked to lower risk of liver disease."),
+            (1, "Coffee consumption is not linked to liver disease risk at all."),
+            (2, "Unrelated snippet about gardening tools."),
+        ];
+        let c = detect(&disagree).unwrap();
+        assert_eq!((c.a, c.b), (0, 1));
+
+        // Same source never conflicts with itself
+        let same = [(0, disagree[0].1), (0, disagree[1].1)];
+        assert_eq!(detect(&same), None);
+    }
+}
@@ -0,0 +1,41 @@
+/// Column separator for delimited output
+#[derive(Clone, Copy, PartialEq)]
+pub enum Delimiter {
+    Tab,
+    Comma,
+}
+
+/// One cell. Newlines (and tabs in TSV) are written as `\n` / `\t` so every
+/// record stays on one line; CSV cells are quoted when needed (RFC 4180).
+pub fn cell(value: &str, delim: Delimiter) -> String {
+    let escaped = value.replace('\\', "\\\\").replace("\r\n", "\\n").replace(['\n', '\r'], "\\n");
+    match delim {
+        Delimiter::Tab => escaped.replace('\t', "\\t"),
+        Delimiter::Comma if escaped.contains([',', '"']) || escaped.starts_with(' ') || escaped.ends_with(' ') => {
+            format!("\"{}\"", escaped.replace('"', "\"\""))
+        }
+        Delimiter::Comma => escaped,
+    }
+}
+
+pub fn row<S: AsRef<str>>(values: &[S], delim: Delimiter) -> String {
+    let sep = match delim {
+        Delimiter::Tab => "\t",
+        Delimiter::Comma => ",",
+    };
+    values.iter().map(|v| cell(v.as_ref(), delim)).collect::<Vec<_>>().join(sep)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cells() {
+        assert_eq!(cell("a\tb\nc\\d", Delimiter::Tab), "a\\tb\\nc\\\\d");
+        assert_eq!(cell("plain", Delimiter::Comma), "plain");
+        assert_eq!(cell("Hello, \"world\"", Delimiter::Comma), "\"Hello, \"\"world\"\"\"");
+        assert_eq!(cell("line1\r\nline2", Delimiter::Comma), "line1\\nline2");
+        assert_eq!(row(&["t", "https://x.com/?a=1,2"], Delimiter::Comma), "t,\"https://x.com/?a=1,2\"");
+    }
+}
@@ -0,0 +1,115 @@
+//! Destination for diagnostics (progress, retries, warnings): stderr, or a
+//! log file with `--log-file`. Data only ever goes to stdout. Prompts stay on
+//! stderr either way, since they are for whoever is at the terminal.
+
+use anyhow::{Context, Result};
+use std::cell::Cell;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+
+static LOG_FILE: Mutex<Option<File>> = Mutex::new(None);
+
+thread_local! {
+    /// Set while a prompt is being written
+    static PROMPTING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Append diagnostics to `path` instead of writing them to stderr
+pub fn set_log_file(path: &Path) -> Result<()> {
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open log file {}", path.display()))?;
+    if let Ok(mut log) = LOG_FILE.lock() {
+        *log = Some(file);
+    }
+    Ok(())
+}
+
+/// Whether diagnostics go to a log file
+pub fn is_redirected() -> bool {
+    LOG_FILE.lock().map(|log| log.is_some()).unwrap_or(false)
+}
+
+/// Run `f`, a prompt, with diagnostics on stderr even under `--log-file`
+pub fn on_terminal<T>(f: impl FnOnce() -> T) -> T {
+    PROMPTING.with(|p| p.set(true));
+    let out = f();
+    PROMPTING.with(|p| p.set(false));
+    out
+}
+
+/// The log file, unless diagnostics go to stderr (no --log-file, or a prompt)
+fn log_file() -> Option<std::sync::MutexGuard<'static, Option<File>>> {
+    if PROMPTING.with(Cell::get) {
+        return None;
+    }
+    LOG_FILE.lock().ok().filter(|log| log.is_some())
+}
+
+#[doc(hidden)]
+pub fn write(args: fmt::Arguments, newline: bool) {
+    let _ = Writer.write_fmt(args);
+    if newline {
+        let _ = Writer.write_all(b"\n");
+    }
+    let _ = Writer.flush();
+}
+
+/// `io::Write` handle for diagnostics, e.g. for streamed text
+pub struct Writer;
+
+impl Write for Writer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match log_file().as_mut().and_then(|log| log.as_mut()) {
+            Some(file) => file.write(buf),
+            None => std::io::stderr().write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match log_file().as_mut().and_then(|log| log.as_mut()) {
+            Some(file) => file.flush(),
+            None => std::io::stderr().flush(),
+        }
+    }
+}
+
+/// Like `eprintln!`, but honors `--log-file`
+#[macro_export]
+macro_rules! diag {
+    () => {
+        $crate::diag::write(format_args!(""), true)
+    };
+    ($($arg:tt)*) => {
+        $crate::diag::write(format_args!($($arg)*), true)
+    };
+}
+
+/// Like `eprint!`, but honors `--log-file`
+#[macro_export]
+macro_rules! diag_inline {
+    ($($arg:tt)*) => {
+        $crate::diag::write(format_args!($($arg)*), false)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_log_file() {
+        let path = std::env::temp_dir().join(format!("exa-diag-test-{}.log", std::process::id()));
+        super::set_log_file(&path).unwrap();
+        crate::diag!("retrying in {}s", 2);
+        crate::diag_inline!(".");
+        super::on_terminal(|| crate::diag_inline!("Pick [1-3]: "));
+        crate::diag_inline!(".");
+        *super::LOG_FILE.lock().unwrap() = None;
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "retrying in 2s\n..");
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,198 @@
+use exa_cli::fingerprint::{content_hash, distance, simhash};
+use exa_cli::api::SearchResult;
+use serde::Serialize;
+use similar::{ChangeTag, TextDiff};
+
+/// Line-level comparison of two versions of a page's text
+#[derive(Serialize, Debug)]
+pub struct ContentDiff {
+    pub url: String,
+    /// Whether the content hashes differ; whitespace-only edits don't count
+    pub changed: bool,
+    #[serde(rename = "previousHash")]
+    pub previous_hash: String,
+    #[serde(rename = "currentHash")]
+    pub current_hash: String,
+    /// Bits between the two simhashes: 0-3 is a near-identical page
+    #[serde(rename = "simhashDistance")]
+    pub simhash_distance: u32,
+    #[serde(rename = "linesAdded")]
+    pub lines_added: usize,
+    #[serde(rename = "linesRemoved")]
+    pub lines_removed: usize,
+    /// Unified diff (empty when unchanged)
+    pub diff: String,
+}
+
+/// Compare the previous and current text of a URL
+pub fn diff_text(url: &str, previous: &str, current: &str) -> ContentDiff {
+    let diff = TextDiff::from_lines(previous, current);
+
+    let mut lines_added = 0;
+    let mut lines_removed = 0;
+    for change in diff.iter_all_changes() {
+        match change.tag() {
+            ChangeTag::Insert => lines_added += 1,
+            ChangeTag::Delete => lines_removed += 1,
+            ChangeTag::Equal => {}
+        }
+    }
+
+    let previous_hash = content_hash(previous);
+    let current_hash = content_hash(current);
+    let changed = previous_hash != current_hash;
+    let unified = if changed {
+        diff.unified_diff()
+            .context_radius(2)
+            .header(&format!("{} (previous)", url), &format!("{} (current)", url))
+            .to_string()
+    } else {
+        String::new()
+    };
+
+    ContentDiff {
+        url: url.to_string(),
+        changed,
+        previous_hash,
+        current_hash,
+        simhash_distance: distance(simhash(previous), simhash(current)),
+        lines_added,
+        lines_removed,
+        diff: unified,
+    }
+}
+
+/// How two result sets for a query differ, matched by URL
+#[derive(Serialize, Debug, Default)]
+pub struct ResultDiff {
+    pub added: Vec<Ranked>,
+    pub removed: Vec<Ranked>,
+    pub changed: Vec<Changed>,
+    pub unchanged: usize,
+}
+
+impl ResultDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// A result with its 1-based rank in the set it is from
+#[derive(Serialize, Debug)]
+pub struct Ranked {
+    pub rank: usize,
+    pub url: String,
+    pub title: Option<String>,
+}
+
+/// A result in both sets whose rank, title, date or text differs
+#[derive(Serialize, Debug)]
+pub struct Changed {
+    pub url: String,
+    pub title: Option<String>,
+    pub changes: Vec<FieldChange>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct FieldChange {
+    /// rank, title, publishedDate or content
+    pub field: &'static str,
+    pub previous: Option<String>,
+    pub current: Option<String>,
+}
+
+/// Compare a previous and a current result set. Text is only compared when
+/// both sides have it, by content hash.
+pub fn diff_results(previous: &[SearchResult], current: &[SearchResult]) -> ResultDiff {
+    let key = |url: &str| url.trim_end_matches('/').to_string();
+    let ranked = |i: usize, r: &SearchResult| Ranked { rank: i + 1, url: r.url.clone(), title: r.title.clone() };
+    let find = |set: &[SearchResult], url: &str| set.iter().position(|r| key(&r.url) == key(url));
+
+    let mut diff = ResultDiff::default();
+    for (i, old) in previous.iter().enumerate() {
+        if find(current, &old.url).is_none() {
+            diff.removed.push(ranked(i, old));
+        }
+    }
+    for (i, new) in current.iter().enumerate() {
+        let Some(j) = find(previous, &new.url) else {
+            diff.added.push(ranked(i, new));
+            continue;
+        };
+        let old = &previous[j];
+        let mut changes = Vec::new();
+        let mut compare = |field, previous: Option<String>, current: Option<String>| {
+            if previous != current {
+                changes.push(FieldChange { field, previous, current });
+            }
+        };
+        compare("rank", Some((j + 1).to_string()), Some((i + 1).to_string()));
+        compare("title", old.title.clone(), new.title.clone());
+        compare("publishedDate", old.published_date.clone(), new.published_date.clone());
+        if let (Some(a), Some(b)) = (&old.text, &new.text) {
+            compare("content", Some(content_hash(a)), Some(content_hash(b)));
+        }
+        if changes.is_empty() {
+            diff.unchanged += 1;
+        } else {
+            diff.changed.push(Changed { url: new.url.clone(), title: new.title.clone(), changes });
+        }
+    }
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_results() {
+        let result = |url: &str, title: &str, text: Option<&str>| {
+            let mut r: SearchResult = serde_json::from_value(serde_json::json!({"url": url, "title": title})).unwrap();
+            r.text = text.map(String::from);
+            r
+        };
+        let previous = [
+            result("https://a.com/", "A", Some("one")),
+            result("https://b.com", "B", None),
+            result("https://c.com", "C", Some("same")),
+        ];
+        let current = [
+            result("https://c.com", "C", Some("same")),
+            result("https://a.com", "A", Some("two")),
+            result("https://d.com", "D", None),
+        ];
+        let d = diff_results(&previous, &current);
+        let urls = |v: &[Ranked]| v.iter().map(|r| (r.rank, r.url.clone())).collect::<Vec<_>>();
+        assert_eq!(urls(&d.added), [(3, "https://d.com".to_string())]);
+        assert_eq!(urls(&d.removed), [(2, "https://b.com".to_string())]);
+        assert_eq!(d.changed.len(), 2);
+        let fields = |c: &Changed| c.changes.iter().map(|f| f.field).collect::<Vec<_>>();
+        assert_eq!(fields(&d.changed[0]), ["rank"]);
+        assert_eq!(fields(&d.changed[1]), ["rank", "content"]);
+        assert_eq!(d.unchanged, 0);
+        assert!(diff_results(&previous, &previous).is_empty());
+        assert_eq!(diff_results(&previous, &previous).unchanged, 3);
+    }
+
+    #[test]
+    fn test_diff_text() {
+        let d = diff_text("https://example.com", "a\nb\nc\n", "a\nB\nc\nd\n");
+        assert!(d.changed);
+        assert_eq!(d.lines_added, 2);
+        assert_eq!(d.lines_removed, 1);
+        assert!(d.diff.contains("-b\n"));
+        assert!(d.diff.contains("+B\n"));
+
+        assert_ne!(d.previous_hash, d.current_hash);
+
+        let same = diff_text("https://example.com", "a\n", "a\n");
+        assert!(!same.changed);
+        assert!(same.diff.is_empty());
+        assert_eq!(same.simhash_distance, 0);
+
+        let rewrapped = diff_text("https://example.com", "a b\nc\n", "a\nb c\n");
+        assert!(!rewrapped.changed);
+        assert!(rewrapped.diff.is_empty());
+    }
+}
@@ -0,0 +1,128 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// One query and the URL patterns that count as relevant for it
+#[derive(Deserialize)]
+pub struct EvalCase {
+    pub query: String,
+    /// Substrings of relevant URLs; `*` matches any run of characters
+    pub expect: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CasesFile {
+    List(Vec<EvalCase>),
+    Map { cases: Vec<EvalCase> },
+}
+
+/// Score of a single case
+#[derive(Serialize)]
+pub struct CaseScore {
+    pub query: String,
+    /// 1-based rank of the first relevant result, if any
+    pub rank: Option<usize>,
+    pub hit: bool,
+    #[serde(rename = "reciprocalRank")]
+    pub reciprocal_rank: f64,
+}
+
+/// Aggregate over all cases
+#[derive(Serialize)]
+pub struct EvalReport {
+    pub k: usize,
+    #[serde(rename = "searchType")]
+    pub search_type: String,
+    #[serde(rename = "hitAtK")]
+    pub hit_at_k: f64,
+    pub mrr: f64,
+    pub cases: Vec<CaseScore>,
+}
+
+/// Load cases from YAML: either a list of cases or `{cases: [...]}`
+pub fn load_cases(path: &Path) -> Result<Vec<EvalCase>> {
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let file: CasesFile =
+        serde_yaml::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))?;
+    Ok(match file {
+        CasesFile::List(cases) | CasesFile::Map { cases } => cases,
+    })
+}
+
+/// Case-insensitive match of a URL pattern anywhere in the URL
+pub fn url_matches(pattern: &str, url: &str) -> bool {
+    let url = url.to_lowercase();
+    let pattern = pattern.to_lowercase();
+    let mut pos = 0;
+    for part in pattern.split('*').filter(|p| !p.is_empty()) {
+        match url[pos..].find(part) {
+            Some(i) => pos += i + part.len(),
+            None => return false,
+        }
+    }
+    true
+}
+
+/// Score one case against the ranked result URLs
+pub fn score_case(case: &EvalCase, urls: &[&str]) -> CaseScore {
+    let rank = urls
+        .iter()
+        .position(|url| case.expect.iter().any(|p| url_matches(p, url)))
+        .map(|i| i + 1);
+    CaseScore {
+        query: case.query.clone(),
+        rank,
+        hit: rank.is_some(),
+        reciprocal_rank: rank.map(|r| 1.0 / r as f64).unwrap_or(0.0),
+    }
+}
+
+/// Combine per-case scores into hit@k and MRR
+pub fn report(k: usize, search_type: &str, cases: Vec<CaseScore>) -> EvalReport {
+    let n = cases.len().max(1) as f64;
+    EvalReport {
+        k,
+        search_type: search_type.to_string(),
+        hit_at_k: cases.iter().filter(|c| c.hit).count() as f64 / n,
+        mrr: cases.iter().map(|c| c.reciprocal_rank).sum::<f64>() / n,
+        cases,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_url_matches() {
+        assert!(url_matches("rust-lang.org", "https://doc.Rust-Lang.org/book/"));
+        assert!(url_matches("github.com/*/tokio", "https://github.com/tokio-rs/tokio"));
+        assert!(!url_matches("github.com/*/tokio", "https://tokio.rs/github.com/"));
+    }
+
+    #[test]
+    fn test_scoring() {
+        let case = EvalCase {
+            query: "q".into(),
+            expect: vec!["b.com".into()],
+        };
+        let score = score_case(&case, &["https://a.com", "https://b.com/x"]);
+        assert_eq!(score.rank, Some(2));
+        let miss = score_case(&case, &["https://a.com"]);
+        assert!(!miss.hit);
+
+        let r = report(5, "auto", vec![score, miss]);
+        assert_eq!(r.hit_at_k, 0.5);
+        assert_eq!(r.mrr, 0.25);
+    }
+
+    #[test]
+    fn test_load_cases_shapes() {
+        let list: CasesFile = serde_yaml::from_str("- query: a\n  expect: [x]\n").unwrap();
+        assert!(matches!(list, CasesFile::List(c) if c.len() == 1));
+        let map: CasesFile = serde_yaml::from_str("cases:\n  - query: a\n    expect: [x]\n").unwrap();
+        assert!(matches!(map, CasesFile::Map { cases } if cases[0].query == "a"));
+    }
+}
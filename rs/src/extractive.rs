@@ -0,0 +1,158 @@
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+/// Approximate characters per token, for token budgets
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Target size for --compress-content: a fraction of the text or a token count
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Budget {
+    Ratio(f64),
+    Tokens(usize),
+}
+
+impl FromStr for Budget {
+    type Err = String;
+
+    /// `0.3` or `30%` is a ratio; `400` or `400t` is a token budget
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some(pct) = s.strip_suffix('%') {
+            let pct: f64 = pct.parse().map_err(|_| format!("invalid percentage: {}", s))?;
+            return Budget::ratio(pct / 100.0);
+        }
+        if s.contains('.') {
+            let ratio: f64 = s.parse().map_err(|_| format!("invalid ratio: {}", s))?;
+            return Budget::ratio(ratio);
+        }
+        let digits = s.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+        match digits.parse::<usize>() {
+            Ok(tokens) if tokens > 0 => Ok(Budget::Tokens(tokens)),
+            _ => Err(format!("expected a ratio (0.3, 30%) or a token count (400): {}", s)),
+        }
+    }
+}
+
+impl Budget {
+    fn ratio(r: f64) -> Result<Self, String> {
+        if r > 0.0 && r <= 1.0 {
+            Ok(Budget::Ratio(r))
+        } else {
+            Err(format!("ratio must be in (0, 1]: {}", r))
+        }
+    }
+
+    fn max_chars(self, text_len: usize) -> usize {
+        match self {
+            Budget::Ratio(r) => (text_len as f64 * r).ceil() as usize,
+            Budget::Tokens(t) => t * CHARS_PER_TOKEN,
+        }
+    }
+}
+
+/// Split text into sentences at `.`, `!` or `?` followed by whitespace, and at line breaks
+pub fn sentences(text: &str) -> Vec<&str> {
+    let mut out = Vec::new();
+    for line in text.lines() {
+        let mut start = 0;
+        let chars: Vec<(usize, char)> = line.char_indices().collect();
+        for (i, &(pos, c)) in chars.iter().enumerate() {
+            let at_boundary = matches!(c, '.' | '!' | '?')
+                && chars.get(i + 1).is_some_and(|&(_, next)| next.is_whitespace());
+            if at_boundary {
+                let end = pos + c.len_utf8();
+                out.push(line[start..end].trim());
+                start = end;
+            }
+        }
+        out.push(line[start..].trim());
+    }
+    out.retain(|s| !s.is_empty());
+    out
+}
+
+/// Lowercased words of three or more characters
+pub fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| w.chars().count() > 2)
+        .map(|w| w.to_lowercase())
+}
+
+/// Keep the sentences most relevant to `query` within `budget`, in their
+/// original order. Sentences are scored by IDF-weighted query-term overlap;
+/// without query terms, by how common their words are in the whole text.
+pub fn compress(text: &str, query: &str, budget: Budget) -> String {
+    let max_chars = budget.max_chars(text.len());
+    if text.len() <= max_chars {
+        return text.to_string();
+    }
+    let sents = sentences(text);
+    let sent_words: Vec<HashSet<String>> = sents.iter().map(|s| words(s).collect()).collect();
+
+    let mut df: HashMap<&str, usize> = HashMap::new();
+    for ws in &sent_words {
+        for w in ws {
+            *df.entry(w.as_str()).or_default() += 1;
+        }
+    }
+    let n = sents.len() as f64;
+    let query_terms: HashSet<String> = words(query).collect();
+
+    let mut scored: Vec<(usize, f64)> = sent_words
+        .iter()
+        .enumerate()
+        .map(|(i, ws)| {
+            let score = if query_terms.is_empty() {
+                let total: usize = ws.iter().map(|w| df[w.as_str()]).sum();
+                total as f64 / (ws.len().max(1) as f64).sqrt()
+            } else {
+                query_terms
+                    .iter()
+                    .filter(|t| ws.contains(*t))
+                    .map(|t| (n / df[t.as_str()] as f64).ln() + 1.0)
+                    .sum()
+            };
+            // Slight lead bias: early sentences tend to carry the summary
+            (i, score + 0.1 / (i + 1) as f64)
+        })
+        .collect();
+    scored.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+
+    let mut keep = Vec::new();
+    let mut used = 0;
+    for (i, _) in scored {
+        let len = sents[i].len() + 1;
+        if used + len > max_chars && !keep.is_empty() {
+            continue;
+        }
+        keep.push(i);
+        used += len;
+    }
+    keep.sort_unstable();
+    keep.iter().map(|&i| sents[i]).collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_budget() {
+        assert_eq!("0.25".parse::<Budget>(), Ok(Budget::Ratio(0.25)));
+        assert_eq!("30%".parse::<Budget>(), Ok(Budget::Ratio(0.3)));
+        assert_eq!("400".parse::<Budget>(), Ok(Budget::Tokens(400)));
+        assert_eq!("400t".parse::<Budget>(), Ok(Budget::Tokens(400)));
+        assert!("1.5".parse::<Budget>().is_err());
+        assert!("0".parse::<Budget>().is_err());
+    }
+
+    #[test]
+    fn test_compress_keeps_relevant_sentences() {
+        let text = "The weather was nice. Tokio is an async runtime for Rust. \
+                    Lunch was pasta. Rust futures are polled by the runtime.";
+        let out = compress(text, "rust async runtime", Budget::Ratio(0.7));
+        assert_eq!(out, "Tokio is an async runtime for Rust. Rust futures are polled by the runtime.");
+
+        assert_eq!(compress("Short.", "x", Budget::Tokens(100)), "Short.");
+    }
+}
@@ -0,0 +1,253 @@
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+use exa_cli::fingerprint;
+use regex::Regex;
+use std::sync::LazyLock;
+use std::time::Duration;
+
+static LINK_TAG: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?is)<link\b[^>]*>").unwrap());
+static ATTR: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?is)([a-z-]+)\s*=\s*(?:"([^"]*)"|'([^']*)')"#).unwrap());
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Only the head of a page is needed to find its feed links
+const MAX_PAGE_BYTES: usize = 512 * 1024;
+
+/// A feed advertised by a page
+#[derive(Debug, Clone, PartialEq)]
+pub struct Feed {
+    pub title: String,
+    pub url: String,
+    /// "rss" or "atom"
+    pub kind: &'static str,
+    /// Site the feed was found on
+    pub site: String,
+}
+
+/// Feeds declared with `<link rel="alternate" type="application/rss+xml|atom+xml">`,
+/// with relative hrefs resolved against the page URL
+pub fn discover_links(html: &str, page_url: &str) -> Vec<Feed> {
+    let Ok(base) = reqwest::Url::parse(page_url) else { return Vec::new() };
+    let site = base.host_str().unwrap_or("").trim_start_matches("www.").to_string();
+    let mut feeds = Vec::new();
+
+    for tag in LINK_TAG.find_iter(html) {
+        let mut rel = String::new();
+        let mut kind = String::new();
+        let mut href = String::new();
+        let mut title = String::new();
+        for attr in ATTR.captures_iter(tag.as_str()) {
+            let value = attr.get(2).or(attr.get(3)).map(|m| m.as_str()).unwrap_or("");
+            match attr[1].to_ascii_lowercase().as_str() {
+                "rel" => rel = value.to_ascii_lowercase(),
+                "type" => kind = value.to_ascii_lowercase(),
+                "href" => href = value.replace("&amp;", "&"),
+                "title" => title = value.to_string(),
+                _ => {}
+            }
+        }
+        let kind = match kind.as_str() {
+            "application/rss+xml" => "rss",
+            "application/atom+xml" => "atom",
+            _ => continue,
+        };
+        if !rel.split_whitespace().any(|r| r == "alternate") || href.is_empty() {
+            continue;
+        }
+        let Ok(url) = base.join(&href) else { continue };
+        let feed = Feed {
+            title: if title.is_empty() { site.clone() } else { title },
+            url: url.to_string(),
+            kind,
+            site: site.clone(),
+        };
+        if !feeds.iter().any(|f: &Feed| f.url == feed.url) {
+            feeds.push(feed);
+        }
+    }
+    feeds
+}
+
+/// Fetch the head of a page and return the feeds it advertises; network
+/// errors yield none. Only the first [`MAX_PAGE_BYTES`] are asked for (a
+/// server that ignores the range is cut off there).
+pub async fn probe(http: &reqwest::Client, page_url: &str) -> Vec<Feed> {
+    let request = http
+        .get(page_url)
+        .header(reqwest::header::RANGE, format!("bytes=0-{}", MAX_PAGE_BYTES - 1))
+        .timeout(PROBE_TIMEOUT);
+    let Ok(mut resp) = request.send().await else { return Vec::new() };
+    if !resp.status().is_success() {
+        return Vec::new();
+    }
+    let mut body = Vec::new();
+    while let Ok(Some(chunk)) = resp.chunk().await {
+        body.extend_from_slice(&chunk);
+        if body.len() >= MAX_PAGE_BYTES {
+            break;
+        }
+    }
+    let html = String::from_utf8_lossy(&body[..body.len().min(MAX_PAGE_BYTES)]);
+    discover_links(&html, page_url)
+}
+
+/// Escaped for XML text and attributes; control characters XML 1.0 forbids are dropped
+fn xml_escape(s: &str) -> String {
+    s.replace(|c: char| c.is_control() && !matches!(c, '\t' | '\n' | '\r'), "")
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// OPML 2.0 subscription list
+pub fn opml(title: &str, feeds: &[Feed]) -> String {
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<opml version=\"2.0\">\n");
+    out.push_str(&format!("  <head>\n    <title>{}</title>\n  </head>\n  <body>\n", xml_escape(title)));
+    for f in feeds {
+        out.push_str(&format!(
+            "    <outline type=\"rss\" text=\"{}\" title=\"{}\" xmlUrl=\"{}\" htmlUrl=\"https://{}/\"/>\n",
+            xml_escape(&f.title),
+            xml_escape(&f.title),
+            xml_escape(&f.url),
+            xml_escape(&f.site)
+        ));
+    }
+    out.push_str("  </body>\n</opml>\n");
+    out
+}
+
+/// One search result as a feed entry
+pub struct Item {
+    pub title: String,
+    pub url: String,
+    pub published: Option<DateTime<Utc>>,
+    pub summary: Option<String>,
+}
+
+/// A publishedDate as Exa returns it: RFC 3339, or a bare date
+pub fn parse_date(date: &str) -> Option<DateTime<Utc>> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(date) {
+        return Some(dt.with_timezone(&Utc));
+    }
+    let day = NaiveDate::parse_from_str(date.get(..10)?, "%Y-%m-%d").ok()?;
+    Some(day.and_hms_opt(0, 0, 0)?.and_utc())
+}
+
+/// RSS 2.0 channel; `updated` is its lastBuildDate
+pub fn rss(title: &str, items: &[Item], updated: DateTime<Utc>) -> String {
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<rss version=\"2.0\">\n  <channel>\n");
+    out.push_str(&format!("    <title>{}</title>\n", xml_escape(title)));
+    out.push_str("    <link>https://exa.ai/</link>\n");
+    out.push_str(&format!("    <description>{}</description>\n", xml_escape(title)));
+    out.push_str(&format!("    <lastBuildDate>{}</lastBuildDate>\n", updated.to_rfc2822()));
+    out.push_str("    <generator>exa</generator>\n");
+    for item in items {
+        out.push_str("    <item>\n");
+        out.push_str(&format!("      <title>{}</title>\n", xml_escape(&item.title)));
+        out.push_str(&format!("      <link>{}</link>\n", xml_escape(&item.url)));
+        out.push_str(&format!("      <guid isPermaLink=\"true\">{}</guid>\n", xml_escape(&item.url)));
+        if let Some(date) = item.published {
+            out.push_str(&format!("      <pubDate>{}</pubDate>\n", date.to_rfc2822()));
+        }
+        if let Some(summary) = &item.summary {
+            out.push_str(&format!("      <description>{}</description>\n", xml_escape(summary)));
+        }
+        out.push_str("    </item>\n");
+    }
+    out.push_str("  </channel>\n</rss>\n");
+    out
+}
+
+/// Atom 1.0 feed. Its id is derived from the title, so a query keeps one
+/// feed identity across runs; entries without a date use `updated`.
+pub fn atom(title: &str, items: &[Item], updated: DateTime<Utc>) -> String {
+    let date = |dt: DateTime<Utc>| dt.to_rfc3339_opts(SecondsFormat::Secs, true);
+    let id = &fingerprint::sha256_hex(title.as_bytes())[..32];
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+    out.push_str(&format!("  <title>{}</title>\n", xml_escape(title)));
+    out.push_str(&format!("  <id>urn:exa:feed:{}</id>\n", id));
+    out.push_str(&format!("  <updated>{}</updated>\n", date(updated)));
+    out.push_str("  <author><name>Exa</name></author>\n");
+    out.push_str("  <link href=\"https://exa.ai/\"/>\n");
+    out.push_str("  <generator>exa</generator>\n");
+    for item in items {
+        out.push_str("  <entry>\n");
+        out.push_str(&format!("    <title>{}</title>\n", xml_escape(&item.title)));
+        out.push_str(&format!("    <link href=\"{}\"/>\n", xml_escape(&item.url)));
+        out.push_str(&format!("    <id>{}</id>\n", xml_escape(&item.url)));
+        out.push_str(&format!("    <updated>{}</updated>\n", date(item.published.unwrap_or(updated))));
+        if let Some(published) = item.published {
+            out.push_str(&format!("    <published>{}</published>\n", date(published)));
+        }
+        if let Some(summary) = &item.summary {
+            out.push_str(&format!("    <summary>{}</summary>\n", xml_escape(summary)));
+        }
+        out.push_str("  </entry>\n");
+    }
+    out.push_str("</feed>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_discover_links() {
+        let html = r#"<head>
+            <link rel="stylesheet" href="/style.css">
+            <link rel="alternate" type="application/rss+xml" title="Blog &amp; News" href="/feed.xml">
+            <LINK REL='alternate' TYPE='application/atom+xml' HREF='https://cdn.example.com/atom'>
+            <link rel="alternate" hreflang="de" href="/de/">
+        </head>"#;
+        let feeds = discover_links(html, "https://www.example.com/post/1");
+        assert_eq!(feeds.len(), 2);
+        assert_eq!(feeds[0].url, "https://www.example.com/feed.xml");
+        assert_eq!(feeds[0].kind, "rss");
+        assert_eq!(feeds[1].kind, "atom");
+        assert_eq!(feeds[1].title, "example.com");
+    }
+
+    #[test]
+    fn test_opml_escapes() {
+        let feed = Feed {
+            title: "A & B".into(),
+            url: "https://x.com/feed?a=1&b=2".into(),
+            kind: "rss",
+            site: "x.com".into(),
+        };
+        let out = opml("q <1>", &[feed]);
+        assert!(out.contains("<title>q &lt;1&gt;</title>"));
+        assert!(out.contains("xmlUrl=\"https://x.com/feed?a=1&amp;b=2\""));
+    }
+
+    #[test]
+    fn test_rss_and_atom() {
+        let published = parse_date("2024-03-01T12:30:00.000Z");
+        assert_eq!(parse_date("2024-03-01"), parse_date("2024-03-01T00:00:00Z"));
+        assert_eq!(parse_date("March 2024"), None);
+        let items = [
+            Item {
+                title: "Rust & <WASM>".into(),
+                url: "https://x.com/?a=1&b=2".into(),
+                published,
+                summary: Some("bad\u{1}byte".into()),
+            },
+            Item { title: "Undated".into(), url: "https://y.com".into(), published: None, summary: None },
+        ];
+        let updated = published.unwrap();
+
+        let rss = rss("Exa: rust", &items, updated);
+        assert!(rss.contains("<title>Rust &amp; &lt;WASM&gt;</title>"));
+        assert!(rss.contains("<link>https://x.com/?a=1&amp;b=2</link>"));
+        assert!(rss.contains("<pubDate>Fri, 1 Mar 2024 12:30:00 +0000</pubDate>"));
+        assert!(rss.contains("<description>badbyte</description>"));
+        assert_eq!(rss.matches("<item>").count(), 2);
+
+        let atom = atom("Exa: rust", &items, updated);
+        assert!(atom.contains("<published>2024-03-01T12:30:00Z</published>"));
+        assert_eq!(atom.matches("<updated>2024-03-01T12:30:00Z</updated>").count(), 3);
+        assert!(atom.contains("<id>urn:exa:feed:"));
+    }
+}
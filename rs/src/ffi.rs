@@ -0,0 +1,122 @@
+//! The C API (feature `ffi`; header in `include/exa.h`): each call takes an
+//! Exa API request body as a JSON string and returns the response as a JSON
+//! string, or `{"error": "..."}`, which the caller frees with [`exa_free`].
+//! Calls share one [`blocking::Client`], created on first use and serialized
+//! by a lock, so the key rotation state carries over from call to call.
+
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+
+use crate::blocking::{self, Endpoint};
+
+static CLIENT: Mutex<Option<blocking::Client>> = Mutex::new(None);
+
+/// The request at `request`, parsed
+///
+/// # Safety
+/// `request` is null or a NUL-terminated string
+unsafe fn parse(request: *const c_char) -> Result<Value> {
+    anyhow::ensure!(!request.is_null(), "the request is null");
+    // SAFETY: the caller passes a NUL-terminated string
+    let text = unsafe { CStr::from_ptr(request) }.to_str().context("the request is not UTF-8")?;
+    serde_json::from_str(text).context("the request is not JSON")
+}
+
+fn send(endpoint: Endpoint, request: Value) -> Result<Value> {
+    // A panic in an earlier call leaves the client as it was, which is still usable
+    let mut client = CLIENT.lock().unwrap_or_else(|e| e.into_inner());
+    if client.is_none() {
+        *client = Some(blocking::Client::new()?);
+    }
+    client.as_mut().expect("created above").request(endpoint, &request)
+}
+
+/// Answer `request` at `endpoint` as a JSON string the caller owns
+///
+/// # Safety
+/// `request` is null or a NUL-terminated string
+unsafe fn call(endpoint: Endpoint, request: *const c_char) -> *mut c_char {
+    // SAFETY: passed on from the caller
+    let result = catch_unwind(AssertUnwindSafe(|| send(endpoint, unsafe { parse(request) }?)))
+        .unwrap_or_else(|_| Err(anyhow::anyhow!("the client panicked")));
+    let body = match result {
+        Ok(body) => body.to_string(),
+        Err(e) => json!({ "error": format!("{:#}", e) }).to_string(),
+    };
+    // JSON escapes control characters, so there is no NUL to reject
+    CString::new(body).unwrap_or_default().into_raw()
+}
+
+/// POST /search
+///
+/// # Safety
+/// `request` is null or a NUL-terminated string
+#[no_mangle]
+pub unsafe extern "C" fn exa_search(request: *const c_char) -> *mut c_char {
+    unsafe { call(Endpoint::Search, request) }
+}
+
+/// POST /findSimilar
+///
+/// # Safety
+/// `request` is null or a NUL-terminated string
+#[no_mangle]
+pub unsafe extern "C" fn exa_find_similar(request: *const c_char) -> *mut c_char {
+    unsafe { call(Endpoint::FindSimilar, request) }
+}
+
+/// POST /contents
+///
+/// # Safety
+/// `request` is null or a NUL-terminated string
+#[no_mangle]
+pub unsafe extern "C" fn exa_contents(request: *const c_char) -> *mut c_char {
+    unsafe { call(Endpoint::Contents, request) }
+}
+
+/// POST /answer
+///
+/// # Safety
+/// `request` is null or a NUL-terminated string
+#[no_mangle]
+pub unsafe extern "C" fn exa_answer(request: *const c_char) -> *mut c_char {
+    unsafe { call(Endpoint::Answer, request) }
+}
+
+/// Free a string returned by the calls above; null is ignored
+///
+/// # Safety
+/// `response` is null or a string from this library, not yet freed
+#[no_mangle]
+pub unsafe extern "C" fn exa_free(response: *mut c_char) {
+    if !response.is_null() {
+        // SAFETY: the string came from CString::into_raw in `call`
+        drop(unsafe { CString::from_raw(response) });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The JSON string at `out`, freed
+    fn take(out: *mut c_char) -> Value {
+        let text = unsafe { CStr::from_ptr(out) }.to_str().unwrap().to_string();
+        unsafe { exa_free(out) };
+        serde_json::from_str(&text).unwrap()
+    }
+
+    #[test]
+    fn test_ffi_errors() {
+        let out = take(unsafe { exa_search(std::ptr::null()) });
+        assert_eq!(out, json!({"error": "the request is null"}));
+        let request = CString::new("{query").unwrap();
+        let out = take(unsafe { exa_answer(request.as_ptr()) });
+        assert!(out["error"].as_str().unwrap().starts_with("the request is not JSON"), "{}", out);
+        unsafe { exa_free(std::ptr::null_mut()) };
+    }
+}
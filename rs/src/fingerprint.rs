@@ -0,0 +1,128 @@
+//! Fingerprints of page text, shared by the archive, `content --diff`,
+//! `--dedupe` and the `contentHash` / `simhash` fields in JSON and exports,
+//! so "is this the same page text" has one answer everywhere.
+//!
+//! The content hash is exact (after collapsing whitespace, so re-wrapped text
+//! hashes the same); the simhash is fuzzy: texts that differ in a few words
+//! have simhashes a few bits apart.
+
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+
+use crate::api::SearchResult;
+
+/// Simhashes at most this many bits apart are near-duplicates
+pub const NEAR_DUPLICATE_BITS: u32 = 3;
+
+/// Words per shingle fed to the simhash
+const SHINGLE: usize = 3;
+
+/// The text with runs of whitespace collapsed to one space and trimmed
+fn normalize(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Hex-encoded SHA-256
+pub fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Hex-encoded HMAC-SHA256 of `data` under `key`
+pub fn hmac_sha256_hex(key: &[u8], data: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Hex-encoded SHA-256 of the text, ignoring differences in whitespace
+pub fn content_hash(text: &str) -> String {
+    sha256_hex(normalize(text).as_bytes())
+}
+
+/// 64-bit FNV-1a; stable across builds, unlike `DefaultHasher`
+fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf29ce484222325, |h, b| (h ^ u64::from(*b)).wrapping_mul(0x100000001b3))
+}
+
+/// Charikar simhash over lowercased word shingles
+pub fn simhash(text: &str) -> u64 {
+    let words: Vec<String> = text.split_whitespace().map(|w| w.to_lowercase()).collect();
+    let mut weights = [0i64; 64];
+    let mut add = |feature: &str| {
+        let h = fnv1a(feature.as_bytes());
+        for (bit, w) in weights.iter_mut().enumerate() {
+            *w += if h >> bit & 1 == 1 { 1 } else { -1 };
+        }
+    };
+    if words.len() < SHINGLE {
+        words.iter().for_each(|w| add(w));
+    } else {
+        words.windows(SHINGLE).for_each(|s| add(&s.join(" ")));
+    }
+    weights.iter().enumerate().filter(|(_, w)| **w > 0).fold(0, |h, (bit, _)| h | 1 << bit)
+}
+
+/// Simhash as 16 hex digits (JSON numbers can't hold every u64 exactly)
+pub fn simhash_hex(text: &str) -> String {
+    format!("{:016x}", simhash(text))
+}
+
+/// Number of differing bits between two simhashes
+pub fn distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// Set `contentHash` and `simhash` on every result that has text
+pub fn annotate(results: &mut [SearchResult]) {
+    for r in results {
+        if let Some(text) = r.text.as_deref() {
+            r.content_hash = Some(content_hash(text));
+            r.simhash = Some(simhash_hex(text));
+        }
+    }
+}
+
+/// Drop results whose text is a near-duplicate of an earlier result's;
+/// returns how many were dropped. Results without text are kept.
+pub fn dedupe(results: &mut Vec<SearchResult>) -> usize {
+    let before = results.len();
+    let mut kept: Vec<u64> = Vec::new();
+    results.retain(|r| {
+        let Some(text) = r.text.as_deref() else { return true };
+        let hash = simhash(text);
+        if kept.iter().any(|k| distance(*k, hash) <= NEAR_DUPLICATE_BITS) {
+            return false;
+        }
+        kept.push(hash);
+        true
+    });
+    before - results.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &str = "Rust is a multi-paradigm, general-purpose programming language that emphasizes \
+        performance, type safety, and concurrency. It enforces memory safety without a garbage collector, \
+        using a borrow checker that tracks the lifetime of references at compile time.";
+
+    #[test]
+    fn test_content_hash() {
+        assert_eq!(content_hash("a  b\n c "), content_hash("a b c"));
+        assert_ne!(content_hash("a b c"), content_hash("a b d"));
+        assert_eq!(content_hash("hello").len(), 64);
+    }
+
+    #[test]
+    fn test_simhash() {
+        // One edited phrase in a page-length text is a near-duplicate
+        let page = TEXT.repeat(10);
+        let edited = page.replacen("compile time", "build time", 1);
+        assert!(distance(simhash(&page), simhash(&edited)) <= NEAR_DUPLICATE_BITS);
+        let other = "Paris is the capital and largest city of France, on the Seine river in the north of the country.";
+        assert!(distance(simhash(TEXT), simhash(other)) > NEAR_DUPLICATE_BITS * 4);
+        assert_eq!(simhash(TEXT), simhash(&TEXT.to_uppercase()));
+        assert_eq!(simhash_hex("").len(), 16);
+    }
+}
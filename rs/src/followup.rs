@@ -0,0 +1,135 @@
+use crate::extractive::{self, Budget};
+use serde::Serialize;
+
+/// Earlier turns restated in a follow-up question
+const CONTEXT_TURNS: usize = 3;
+/// Characters kept from each earlier answer
+const ANSWER_CHARS: usize = 600;
+/// Sources from the previous turn whose passages are carried forward
+const CARRIED_SOURCES: usize = 3;
+const PASSAGE_TOKENS: usize = 60;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Source {
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip)]
+    pub text: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Turn {
+    pub question: String,
+    pub answer: String,
+    pub sources: Vec<Source>,
+}
+
+/// A follow-up conversation: every question with its answer and sources
+#[derive(Serialize, Debug, Default)]
+pub struct Transcript {
+    pub turns: Vec<Turn>,
+}
+
+impl Transcript {
+    pub fn push(&mut self, question: &str, answer: &str, sources: Vec<Source>) {
+        self.turns.push(Turn { question: question.to_string(), answer: answer.to_string(), sources });
+    }
+
+    /// The query sent for a follow-up: recent questions and answers, the
+    /// previous sources' passages relevant to the new question, then the question.
+    /// /answer is stateless, so this is how earlier turns ground the next one.
+    pub fn contextual_query(&self, question: &str) -> String {
+        if self.turns.is_empty() {
+            return question.to_string();
+        }
+        let mut query = String::from("Earlier in this conversation:\n");
+        let start = self.turns.len().saturating_sub(CONTEXT_TURNS);
+        for turn in &self.turns[start..] {
+            query.push_str(&format!("Q: {}\nA: {}\n", turn.question, truncate(&turn.answer, ANSWER_CHARS)));
+        }
+        let last = self.turns.last().expect("turns is not empty");
+        let passages: Vec<String> = last
+            .sources
+            .iter()
+            .take(CARRIED_SOURCES)
+            .filter_map(|s| {
+                let text = s.text.as_deref()?;
+                let passage = extractive::compress(text, question, Budget::Tokens(PASSAGE_TOKENS));
+                (!passage.trim().is_empty()).then(|| format!("[{}] {}", s.url, passage.trim()))
+            })
+            .collect();
+        if !passages.is_empty() {
+            query.push_str("\nFrom the sources cited so far:\n");
+            for p in passages {
+                query.push_str(&p);
+                query.push('\n');
+            }
+        }
+        query.push_str(&format!("\nFollow-up question: {}", question));
+        query
+    }
+
+    pub fn markdown(&self) -> String {
+        let mut out = String::new();
+        for turn in &self.turns {
+            out.push_str(&format!("## {}\n\n{}\n", turn.question, turn.answer.trim()));
+            if !turn.sources.is_empty() {
+                out.push_str("\nSources:\n");
+                for s in &turn.sources {
+                    match &s.title {
+                        Some(title) => out.push_str(&format!("- [{}]({})\n", title, s.url)),
+                        None => out.push_str(&format!("- {}\n", s.url)),
+                    }
+                }
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    let text = text.trim();
+    match text.char_indices().nth(max_chars) {
+        Some((i, _)) => format!("{}...", &text[..i]),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(url: &str, text: &str) -> Source {
+        Source { url: url.to_string(), title: Some("T".to_string()), text: Some(text.to_string()) }
+    }
+
+    #[test]
+    fn test_contextual_query() {
+        let mut t = Transcript::default();
+        assert_eq!(t.contextual_query("what is rust"), "what is rust");
+        t.push("what is rust", "A systems language.", vec![source("https://a.com", "Rust 1.0 shipped in 2015.")]);
+        let q = t.contextual_query("when was it released");
+        assert!(q.starts_with("Earlier in this conversation:\nQ: what is rust\nA: A systems language.\n"));
+        assert!(q.contains("[https://a.com] Rust 1.0 shipped in 2015."));
+        assert!(q.ends_with("Follow-up question: when was it released"));
+
+        for i in 0..5 {
+            t.push(&format!("q{}", i), &"x".repeat(1000), vec![]);
+        }
+        let q = t.contextual_query("next");
+        assert!(!q.contains("what is rust") && !q.contains("q1\n") && q.contains("Q: q2\n"));
+        assert!(!q.contains("From the sources"));
+        assert!(q.len() < CONTEXT_TURNS * (ANSWER_CHARS + 20) + 100);
+    }
+
+    #[test]
+    fn test_markdown() {
+        let mut t = Transcript::default();
+        t.push("q", "a\n", vec![source("https://a.com", "x")]);
+        assert_eq!(t.markdown(), "## q\n\na\n\nSources:\n- [T](https://a.com)\n\n");
+        // Source text is only kept for context, not exported
+        assert!(!serde_json::to_string(&t).unwrap().contains("\"text\""));
+    }
+}
@@ -0,0 +1,160 @@
+//! `exa research --commit-notes <dir>`: the report written into a git
+//! repository and committed, on the current branch or another one. Only the
+//! note is committed; whatever else is staged or modified is left as it was.
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Longest commit subject before the query is cut
+const SUBJECT_CHARS: usize = 72;
+
+/// Run git in `dir` with `envs`, returning trimmed stdout
+fn git(dir: &Path, args: &[&str], envs: &[(&str, &Path)]) -> Result<String> {
+    let mut command = Command::new("git");
+    command.arg("-C").arg(dir).args(args);
+    for (key, value) in envs {
+        command.env(key, value);
+    }
+    let output = command.output().context("Failed to run git")?;
+    if !output.status.success() {
+        bail!("git {} failed: {}", args.first().unwrap_or(&""), String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// The work tree `dir` is (or, if missing, will be) in; checked before the
+/// research task is started so a bad path costs nothing
+pub fn repo_root(dir: &Path) -> Result<PathBuf> {
+    let existing = dir.ancestors().find(|p| p.is_dir()).unwrap_or(Path::new("."));
+    let root = git(existing, &["rev-parse", "--show-toplevel"], &[])
+        .with_context(|| format!("{} is not inside a git repository", dir.display()))?;
+    Ok(PathBuf::from(root))
+}
+
+/// `YYYY-MM-DD-words-of-the-query.md`
+fn file_name(query: &str, date: DateTime<Utc>) -> String {
+    let slug: String = query.to_lowercase().chars().map(|c| if c.is_alphanumeric() { c } else { ' ' }).collect();
+    let slug: Vec<&str> = slug.split_whitespace().collect();
+    let mut name = date.format("%Y-%m-%d").to_string();
+    for word in slug {
+        if name.len() + word.len() > 60 {
+            break;
+        }
+        name.push('-');
+        name.push_str(word);
+    }
+    name + ".md"
+}
+
+/// Subject naming the query (cut to fit), then the full query, cost and task
+pub fn commit_message(query: &str, cost: Option<f64>, task_id: &str) -> String {
+    let query = query.split_whitespace().collect::<Vec<_>>().join(" ");
+    let prefix = "Research: ";
+    let subject = if prefix.len() + query.chars().count() > SUBJECT_CHARS {
+        let cut: String = query.chars().take(SUBJECT_CHARS - prefix.len() - 3).collect();
+        format!("{}{}...", prefix, cut.trim_end())
+    } else {
+        format!("{}{}", prefix, query)
+    };
+    let cost = cost.map_or_else(|| "unknown".to_string(), |c| format!("${:.4}", c));
+    format!("{}\n\nQuery: {}\nCost: {}\nTask: {}\n", subject, query, cost, task_id)
+}
+
+/// Write `note` into `dir` and commit it with `message`, on `branch` when
+/// given (created from HEAD if it doesn't exist). Returns the note's path
+/// and the commit id.
+pub fn commit_note(
+    dir: &Path,
+    query: &str,
+    note: &str,
+    message: &str,
+    branch: Option<&str>,
+) -> Result<(PathBuf, String)> {
+    let root = repo_root(dir)?;
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let mut path = dir.join(file_name(query, Utc::now()));
+    for n in 2.. {
+        if !path.exists() {
+            break;
+        }
+        path = dir.join(file_name(query, Utc::now()).replace(".md", &format!("-{}.md", n)));
+    }
+    fs::write(&path, note).with_context(|| format!("Failed to write {}", path.display()))?;
+
+    let absolute = fs::canonicalize(&path)?;
+    let relative = absolute.strip_prefix(fs::canonicalize(&root)?).context("Note is outside the repository")?;
+    let relative = relative.to_string_lossy().replace('\\', "/");
+
+    let current = git(&root, &["symbolic-ref", "--quiet", "--short", "HEAD"], &[]).ok();
+    let commit = match branch {
+        Some(branch) if current.as_deref() != Some(branch) => commit_to_branch(&root, &relative, message, branch)?,
+        _ => {
+            git(&root, &["add", "--", &relative], &[])?;
+            git(&root, &["commit", "--quiet", "-m", message, "--", &relative], &[])?;
+            git(&root, &["rev-parse", "HEAD"], &[])?
+        }
+    };
+    Ok((path, commit))
+}
+
+/// Commit the file onto another branch without checking it out: its tree
+/// plus the note, built in a scratch index
+fn commit_to_branch(root: &Path, relative: &str, message: &str, branch: &str) -> Result<String> {
+    let refname = format!("refs/heads/{}", branch);
+    git(root, &["check-ref-format", &refname], &[]).with_context(|| format!("Invalid branch name {}", branch))?;
+    let tip = git(root, &["rev-parse", "--verify", "--quiet", &format!("{}^{{commit}}", refname)], &[]).ok();
+    let parent = tip.clone().or_else(|| git(root, &["rev-parse", "--verify", "--quiet", "HEAD^{commit}"], &[]).ok());
+
+    let index = PathBuf::from(git(root, &["rev-parse", "--git-path", "exa-notes-index"], &[])?);
+    let index = if index.is_absolute() { index } else { root.join(index) };
+    let env = [("GIT_INDEX_FILE", index.as_path())];
+    let built = (|| -> Result<String> {
+        match &parent {
+            Some(parent) => git(root, &["read-tree", parent], &env)?,
+            None => git(root, &["read-tree", "--empty"], &env)?,
+        };
+        let blob = git(root, &["hash-object", "-w", "--", relative], &[])?;
+        git(root, &["update-index", "--add", "--cacheinfo", &format!("100644,{},{}", blob, relative)], &env)?;
+        git(root, &["write-tree"], &env)
+    })();
+    let _ = fs::remove_file(&index);
+    let tree = built?;
+
+    let mut args = vec!["commit-tree", tree.as_str(), "-m", message];
+    if let Some(parent) = &parent {
+        args.extend(["-p", parent.as_str()]);
+    }
+    let commit = git(root, &args, &[])?;
+    // Only move the branch if nobody else did in the meantime (or, when
+    // creating it, if it still doesn't exist)
+    let expected = tip.unwrap_or_else(|| "0".repeat(40));
+    git(root, &["update-ref", &refname, &commit, &expected], &[])?;
+    Ok(commit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_name_and_message() {
+        let date = DateTime::parse_from_rfc3339("2026-03-01T12:00:00Z").unwrap().with_timezone(&Utc);
+        assert_eq!(file_name("What's new in Rust 2024?", date), "2026-03-01-what-s-new-in-rust-2024.md");
+        assert_eq!(file_name("?!", date), "2026-03-01.md");
+        assert!(file_name(&"word ".repeat(40), date).len() <= 63);
+
+        assert_eq!(
+            commit_message("rust  async\nruntimes", Some(0.12345), "r_1"),
+            "Research: rust async runtimes\n\nQuery: rust async runtimes\nCost: $0.1235\nTask: r_1\n"
+        );
+        let long = "compare ".repeat(20);
+        let message = commit_message(&long, None, "r_2");
+        let subject = message.lines().next().unwrap();
+        assert!(subject.chars().count() <= SUBJECT_CHARS);
+        assert!(subject.starts_with("Research: compare compare") && subject.ends_with("..."));
+        assert!(message.contains("Cost: unknown\n"));
+    }
+}
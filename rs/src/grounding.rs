@@ -0,0 +1,119 @@
+use serde::Serialize;
+use std::collections::HashSet;
+
+use crate::extractive;
+
+/// Words too common to show that a source says the same thing
+const STOPWORDS: &[&str] = &[
+    "the", "and", "for", "that", "with", "this", "are", "was", "were", "from", "has", "have", "had", "its", "but",
+    "can", "which", "their", "they", "also", "been", "will", "more", "than", "into", "such", "these", "those",
+    "there", "about", "other", "most", "some", "when", "what", "who", "how", "all", "any", "one", "may", "not",
+];
+
+/// Sentences with fewer content words are not claims (headings, "In short:")
+const MIN_CLAIM_WORDS: usize = 4;
+
+/// Share of a claim's content words a source sentence must contain to support it
+const SUPPORT_OVERLAP: f64 = 0.5;
+
+/// A source passage that supports a claim
+#[derive(Serialize, Debug)]
+pub struct Support {
+    /// 1-based position in the answer's citations
+    pub source: usize,
+    pub url: String,
+    /// Share of the claim's content words found in the passage
+    pub score: f64,
+    pub passage: String,
+}
+
+/// One sentence of the answer and the sources that back it
+#[derive(Serialize, Debug)]
+pub struct Claim {
+    pub sentence: String,
+    /// Best-supported first; empty when no source contains the claim
+    pub sources: Vec<Support>,
+}
+
+/// Sentence-to-source alignment for an answer or report
+#[derive(Serialize, Debug)]
+pub struct Grounding {
+    pub claims: Vec<Claim>,
+    pub supported: usize,
+}
+
+fn content_words(text: &str) -> HashSet<String> {
+    extractive::words(text).filter(|w| !STOPWORDS.contains(&w.as_str())).collect()
+}
+
+/// Align each claim in `text` with the `(url, text)` sources containing
+/// supporting text, by word overlap with the sources' sentences
+pub fn align(text: &str, sources: &[(&str, &str)]) -> Grounding {
+    let source_sentences: Vec<Vec<(&str, HashSet<String>)>> = sources
+        .iter()
+        .map(|(_, text)| extractive::sentences(text).into_iter().map(|s| (s, content_words(s))).collect())
+        .collect();
+
+    let mut claims = Vec::new();
+    for sentence in extractive::sentences(text) {
+        let words = content_words(sentence);
+        if words.len() < MIN_CLAIM_WORDS {
+            continue;
+        }
+        let mut support: Vec<Support> = source_sentences
+            .iter()
+            .enumerate()
+            .filter_map(|(i, sents)| {
+                let (passage, score) = sents
+                    .iter()
+                    .map(|(s, w)| (*s, words.intersection(w).count() as f64 / words.len() as f64))
+                    .max_by(|a, b| a.1.total_cmp(&b.1))?;
+                (score >= SUPPORT_OVERLAP).then(|| Support {
+                    source: i + 1,
+                    url: sources[i].0.to_string(),
+                    score: (score * 100.0).round() / 100.0,
+                    passage: passage.to_string(),
+                })
+            })
+            .collect();
+        support.sort_by(|a, b| b.score.total_cmp(&a.score));
+        claims.push(Claim { sentence: sentence.to_string(), sources: support });
+    }
+    let supported = claims.iter().filter(|c| !c.sources.is_empty()).count();
+    Grounding { claims, supported }
+}
+
+impl Claim {
+    /// `[1,3]`, or `[-]` when unsupported
+    pub fn marker(&self) -> String {
+        if self.sources.is_empty() {
+            return "[-]".to_string();
+        }
+        let ids: Vec<String> = self.sources.iter().map(|s| s.source.to_string()).collect();
+        format!("[{}]", ids.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_align() {
+        let answer = "## Summary\nRust guarantees memory safety without a garbage collector. \
+                      Its mascot is a friendly orange crab called Ferris.";
+        let sources = [
+            ("https://a.com", "Rust is fast. The language guarantees memory safety without using a garbage collector."),
+            ("https://b.com", "Ownership rules guarantee memory safety at compile time, with no garbage collector."),
+        ];
+        let g = align(answer, &sources);
+        // The heading is not a claim
+        assert_eq!(g.claims.len(), 2);
+        assert_eq!(g.supported, 1);
+        let first = &g.claims[0];
+        assert_eq!(first.marker(), "[1,2]");
+        assert_eq!(first.sources[0].url, "https://a.com");
+        assert!(first.sources[0].passage.starts_with("The language guarantees"));
+        assert_eq!(g.claims[1].marker(), "[-]");
+    }
+}
@@ -0,0 +1,138 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use exa_cli::key_manager::query_hmac;
+use exa_cli::{warnings, KeyManager};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+use crate::cli::{Cli, Commands};
+
+const HISTORY_FILE: &str = "history.jsonl";
+
+/// One executed query. Its text and arguments are kept only with
+/// `log.include_queries`; otherwise just a keyed hash of the query is.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Entry {
+    pub ts: DateTime<Utc>,
+    pub command: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query: Option<String>,
+    /// Keyed hash of the query (see [`query_hmac`])
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query_hmac: Option<String>,
+    /// Arguments as typed (config.toml settings are applied again on rerun)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+    /// Results, sources or pages returned
+    pub results: usize,
+}
+
+pub fn path() -> Result<PathBuf> {
+    Ok(KeyManager::get_config_dir()?.join(HISTORY_FILE))
+}
+
+/// Command name and query of a command that queries the API
+pub fn describe(command: &Commands) -> Option<(&'static str, String)> {
+    match command {
+        Commands::Search { query } => Some(("search", query.join(" "))),
+        Commands::Find { query, .. } => Some(("find", query.join(" "))),
+        Commands::Answer { query, .. } => Some(("answer", query.join(" "))),
+        Commands::Research { query, .. } => Some(("research", query.join(" "))),
+        Commands::Content { urls, .. } => Some(("content", urls.join(" "))),
+        _ => None,
+    }
+}
+
+/// Append this run to the history, unless --no-history; a failure only warns
+pub fn record(cli: &Cli, results: usize) {
+    if cli.no_history {
+        return;
+    }
+    let Some((command, query)) = describe(&cli.command) else { return };
+    let append = || -> Result<()> {
+        let keep = crate::config::include_queries();
+        let entry = Entry {
+            ts: Utc::now(),
+            command: command.to_string(),
+            query_hmac: Some(query_hmac(&KeyManager::get_config_dir()?, &query)?),
+            query: keep.then_some(query),
+            args: if keep { std::env::args().skip(1).collect() } else { Vec::new() },
+            results,
+        };
+        let mut file = OpenOptions::new().create(true).append(true).open(path()?)?;
+        writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+        Ok(())
+    };
+    if let Err(e) = append() {
+        warnings::emit("history_failed", format!("could not record history: {:#}", e), cli.verbose > 0);
+    }
+}
+
+/// Every recorded entry, oldest first; unreadable lines are skipped
+pub fn load() -> Result<Vec<Entry>> {
+    let path = path()?;
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    Ok(text.lines().filter_map(|l| serde_json::from_str(l).ok()).collect())
+}
+
+/// Entries with their 1-based numbers, keeping those whose query contains
+/// every term (case-insensitive) and, if given, run by `command`. An entry
+/// with only a hash matches when `terms_hmac`, the keyed hash of the terms
+/// joined by spaces, is its hash: the exact query.
+pub fn filter<'a>(
+    entries: &'a [Entry],
+    terms: &[String],
+    terms_hmac: Option<&str>,
+    command: Option<&str>,
+) -> Vec<(usize, &'a Entry)> {
+    let terms: Vec<String> = terms.iter().map(|t| t.to_lowercase()).collect();
+    entries
+        .iter()
+        .enumerate()
+        .map(|(i, e)| (i + 1, e))
+        .filter(|(_, e)| command.is_none_or(|c| e.command == c))
+        .filter(|(_, e)| match &e.query {
+            _ if terms.is_empty() => true,
+            Some(query) => {
+                let query = query.to_lowercase();
+                terms.iter().all(|t| query.contains(t))
+            }
+            None => terms_hmac.is_some() && e.query_hmac.as_deref() == terms_hmac,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter() {
+        let entry = |command: &str, query: &str| Entry {
+            ts: Utc::now(),
+            command: command.into(),
+            query: Some(query.into()),
+            query_hmac: Some(format!("k({})", query)),
+            args: vec![command.into(), query.into()],
+            results: 5,
+        };
+        let hashed = Entry { query: None, args: Vec::new(), ..entry("search", "rust book") };
+        let entries = vec![entry("search", "Rust async"), entry("answer", "what is rust"), entry("search", "tokio"), hashed];
+        let ids = |terms: &[&str], command: Option<&str>| -> Vec<usize> {
+            let terms: Vec<String> = terms.iter().map(|t| t.to_string()).collect();
+            let terms_hmac = format!("k({})", terms.join(" "));
+            filter(&entries, &terms, Some(&terms_hmac), command).into_iter().map(|(i, _)| i).collect()
+        };
+        assert_eq!(ids(&[], None), [1, 2, 3, 4]);
+        assert_eq!(ids(&["rust"], None), [1, 2]);
+        assert_eq!(ids(&["rust"], Some("search")), [1]);
+        assert_eq!(ids(&["rust", "book"], None), [4]);
+        assert_eq!(ids(&["rust", "tokio"], None), Vec::<usize>::new());
+    }
+}
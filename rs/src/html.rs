@@ -0,0 +1,105 @@
+//! HTML documents for --format html: search/find results as a standalone,
+//! styled page (also the body of --notify email digests). Styles are inline,
+//! since mail clients drop stylesheets.
+
+use exa_cli::model::Page;
+
+const PAGE_STYLE: &str = "margin:0;padding:24px 16px;background:#f6f8fa";
+const BODY_STYLE: &str = "max-width:680px;margin:0 auto;padding:24px;background:#ffffff;border:1px solid #d0d7de;\
+border-radius:6px;font-family:-apple-system,'Segoe UI',Helvetica,Arial,sans-serif;color:#1f2328;line-height:1.5";
+const HEADING_STYLE: &str = "margin:0 0 16px;font-size:20px";
+const ITEM_STYLE: &str = "margin:0 0 18px";
+const LINK_STYLE: &str = "color:#0969da;font-weight:600;text-decoration:none";
+const META_STYLE: &str = "color:#656d76;font-size:13px";
+const QUOTE_STYLE: &str = "margin:6px 0 0;padding-left:12px;border-left:3px solid #d0d7de;color:#424a53";
+
+/// Text with the characters HTML gives meaning to escaped, for element
+/// content and quoted attributes alike
+pub fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// A link target for `url`, or None when it isn't http(s) (javascript:, data:)
+fn href(url: &str) -> Option<String> {
+    let lower = url.trim_start().to_ascii_lowercase();
+    (lower.starts_with("https://") || lower.starts_with("http://")).then(|| escape(url.trim()))
+}
+
+/// `host · date` under a result's title
+fn meta(r: &Page) -> String {
+    let host = reqwest::Url::parse(&r.url).ok().and_then(|u| u.host_str().map(String::from));
+    let date = r.published.as_deref().map(|d| d.get(..10).unwrap_or(d).to_string());
+    [host, date].into_iter().flatten().map(|s| escape(&s)).collect::<Vec<_>>().join(" · ")
+}
+
+/// Search/find results under `heading`. `excerpt` gives each result's quoted
+/// text (highlights, or the text cut to size), if any.
+pub fn results(heading: &str, results: &[Page], excerpt: impl Fn(&Page) -> Option<String>) -> String {
+    let heading = escape(heading.split_whitespace().collect::<Vec<_>>().join(" ").as_str());
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{heading}</title>\n</head>\n<body style=\"{PAGE_STYLE}\">\n<div style=\"{BODY_STYLE}\">\n\
+         <h1 style=\"{HEADING_STYLE}\">{heading}</h1>\n<ol>\n"
+    );
+    for r in results {
+        let title = r.title.as_deref().map(str::trim).filter(|t| !t.is_empty()).unwrap_or(&r.url);
+        let title = escape(title.split_whitespace().collect::<Vec<_>>().join(" ").as_str());
+        out.push_str(&format!("<li style=\"{}\">", ITEM_STYLE));
+        match href(&r.url) {
+            Some(href) => out.push_str(&format!("<a href=\"{}\" style=\"{}\">{}</a>", href, LINK_STYLE, title)),
+            None => out.push_str(&format!("<strong>{}</strong>", title)),
+        }
+        let meta = meta(r);
+        if !meta.is_empty() {
+            out.push_str(&format!("<br>\n<span style=\"{}\">{}</span>", META_STYLE, meta));
+        }
+        if let Some(text) = excerpt(r).filter(|t| !t.trim().is_empty()) {
+            out.push_str(&format!("\n<blockquote style=\"{}\">{}</blockquote>", QUOTE_STYLE, escape(text.trim())));
+        }
+        out.push_str("</li>\n");
+    }
+    out.push_str("</ol>\n</div>\n</body>\n</html>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_html() {
+        assert_eq!(escape("<b>\"Tom & Jerry's\"</b>"), "&lt;b&gt;&quot;Tom &amp; Jerry&#39;s&quot;&lt;/b&gt;");
+        assert_eq!(href("https://a.com/?x=1&y=2").as_deref(), Some("https://a.com/?x=1&amp;y=2"));
+        assert_eq!(href("javascript:alert(1)"), None);
+
+        let list = [
+            Page {
+                url: "https://a.com/post".to_string(),
+                title: Some("Rust <2024>".to_string()),
+                published: Some("2024-03-01T00:00:00Z".to_string()),
+                text: Some("Ownership & borrowing.".to_string()),
+                ..Page::default()
+            },
+            Page { url: "data:text/html,x".to_string(), ..Page::default() },
+        ];
+        let out = results("rust  news", &list, |r| r.text.clone());
+        assert!(out.starts_with("<!DOCTYPE html>") && out.ends_with("</html>\n"));
+        assert!(out.contains("<title>rust news</title>"));
+        assert!(out.contains("<a href=\"https://a.com/post\" style=\"") && out.contains("\">Rust &lt;2024&gt;</a>"));
+        assert!(out.contains(">a.com · 2024-03-01</span>"));
+        assert!(out.contains(">Ownership &amp; borrowing.</blockquote>"));
+        assert!(out.contains("<strong>data:text/html,x</strong></li>"));
+    }
+}
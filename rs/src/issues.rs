@@ -0,0 +1,353 @@
+//! --create-issue: file an answer or research report, with its source links,
+//! as a Jira or Linear issue. Where to file comes from --issue-endpoint and
+//! --issue-project (settable in config.toml); tokens come from the
+//! environment (EXA_JIRA_TOKEN with EXA_JIRA_EMAIL, or EXA_LINEAR_API_KEY).
+
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use exa_cli::{diag, vars, warnings};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+use crate::cli::{Cli, IssueTracker};
+use crate::markdown;
+
+const LINEAR_ENDPOINT: &str = "https://api.linear.app/graphql";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// Longest issue title before the query is cut
+const TITLE_CHARS: usize = 120;
+
+const LINEAR_MUTATION: &str = "mutation IssueCreate($input: IssueCreateInput!) {
+  issueCreate(input: $input) { success issue { identifier url } }
+}";
+
+/// What goes into the issue: the question, the answer or report, its sources
+pub struct Issue<'a> {
+    pub query: &'a str,
+    pub body: &'a str,
+    pub sources: Vec<markdown::Source<'a>>,
+}
+
+/// A filed issue: its key (PROJ-123, ENG-45) and where to see it
+pub struct Created {
+    pub key: String,
+    pub url: String,
+}
+
+enum Auth {
+    Basic { user: String, token: String },
+    Bearer(String),
+    /// Linear takes the API key bare
+    Key(String),
+}
+
+/// Where and as whom to file, from flags and the environment
+struct Target {
+    endpoint: String,
+    project: String,
+    auth: Auth,
+}
+
+fn target(cli: &Cli, tracker: IssueTracker) -> Result<Target> {
+    let var = |name: &str| vars::get(name).filter(|v| !v.trim().is_empty());
+    let Some(project) = cli.issue_project.clone() else {
+        bail!("--create-issue needs --issue-project (the Jira project key or Linear team id)");
+    };
+    match tracker {
+        IssueTracker::Jira => {
+            let Some(endpoint) = cli.issue_endpoint.clone() else {
+                bail!("--create-issue jira needs --issue-endpoint, e.g. https://acme.atlassian.net");
+            };
+            let Some(token) = var("EXA_JIRA_TOKEN") else { bail!("--create-issue jira needs EXA_JIRA_TOKEN") };
+            // Jira Cloud takes an API token with the account email; Data
+            // Center a personal access token on its own
+            let auth = match var("EXA_JIRA_EMAIL") {
+                Some(user) => Auth::Basic { user, token },
+                None => Auth::Bearer(token),
+            };
+            Ok(Target { endpoint: endpoint.trim_end_matches('/').to_string(), project, auth })
+        }
+        IssueTracker::Linear => {
+            let Some(key) = var("EXA_LINEAR_API_KEY") else { bail!("--create-issue linear needs EXA_LINEAR_API_KEY") };
+            let endpoint = cli.issue_endpoint.clone().unwrap_or_else(|| LINEAR_ENDPOINT.to_string());
+            Ok(Target { endpoint, project, auth: Auth::Key(key) })
+        }
+    }
+}
+
+/// Fail before the (paid) request when the issue couldn't be filed anyway
+pub fn check(cli: &Cli) -> Result<()> {
+    match cli.create_issue {
+        Some(tracker) => target(cli, tracker).map(drop),
+        None => Ok(()),
+    }
+}
+
+fn title(query: &str) -> String {
+    let query = query.split_whitespace().collect::<Vec<_>>().join(" ");
+    if query.chars().count() <= TITLE_CHARS {
+        return query;
+    }
+    let cut: String = query.chars().take(TITLE_CHARS - 3).collect();
+    format!("{}...", cut.trim_end())
+}
+
+/// The closing `delim` in `chars` from `from`, when there is text before it
+fn closing(chars: &[char], from: usize, delim: &[char]) -> Option<usize> {
+    (from + 1..=chars.len().checked_sub(delim.len())?).find(|&i| chars[i..].starts_with(delim))
+}
+
+/// Markdown inline formatting (bold, italics, code, strikethrough, links and
+/// images) as Jira wiki markup, with other markup characters escaped
+fn wiki_inline(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let word = |i: Option<usize>| i.and_then(|i| chars.get(i)).is_some_and(|c| c.is_alphanumeric());
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let rest = &chars[i..];
+        let span = |delim: &[char]| {
+            let end = closing(&chars, i + delim.len() - 1, delim)?;
+            let inner: String = chars[i + delim.len()..end].iter().collect();
+            (!inner.trim().is_empty() && inner.trim() == inner).then_some((inner, end + delim.len()))
+        };
+        let wrap = |(text, next): (String, usize), mark: &str| (format!("{0}{1}{0}", mark, wiki_inline(&text)), next);
+        // `**bold**`, `*italic*`, `~~struck~~`; `_` only around whole words, not in snake_case
+        let styled = match rest {
+            ['`', ..] => span(&['`']).map(|(code, next)| (format!("{{{{{}}}}}", code), next)),
+            ['*', '*', ..] | ['_', '_', ..] => span(&rest[..2]).map(|s| wrap(s, "*")),
+            ['~', '~', ..] => span(&['~', '~']).map(|s| wrap(s, "-")),
+            ['*', ..] => span(&['*']).map(|s| wrap(s, "_")),
+            ['_', ..] if !word(i.checked_sub(1)) => {
+                span(&['_']).filter(|(_, next)| !word(Some(*next))).map(|s| wrap(s, "_"))
+            }
+            ['!', '[', ..] | ['[', ..] => link(&chars, i),
+            _ => None,
+        };
+        match styled {
+            Some((markup, next)) => {
+                out.push_str(&markup);
+                i = next;
+            }
+            None => {
+                if matches!(chars[i], '{' | '}' | '[' | ']' | '|' | '*' | '_' | '~' | '^') {
+                    out.push('\\');
+                }
+                out.push(chars[i]);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+/// `[text](url)` as `[text|url]`, `![alt](url)` as `!url!`, starting at `i`
+fn link(chars: &[char], i: usize) -> Option<(String, usize)> {
+    let image = chars[i] == '!';
+    let open = if image { i + 1 } else { i };
+    let close = (open + 1..chars.len()).find(|&j| chars[j] == ']')?;
+    if chars.get(close + 1) != Some(&'(') {
+        return None;
+    }
+    let end = (close + 2..chars.len()).find(|&j| chars[j] == ')')?;
+    let text: String = chars[open + 1..close].iter().collect();
+    let url: String = chars[close + 2..end].iter().collect();
+    let url = url.split_whitespace().next()?.to_string();
+    let markup = if image {
+        format!("!{}!", url)
+    } else {
+        let text = wiki_inline(&text.replace('|', " "));
+        if text.trim().is_empty() { format!("[{}]", url) } else { format!("[{}|{}]", text, url) }
+    };
+    Some((markup, end + 1))
+}
+
+/// A Markdown answer or report as Jira wiki markup: headings, lists, quotes,
+/// code blocks, tables and rules, with inline formatting converted
+fn wiki_markup(markdown: &str) -> String {
+    let mut out: Vec<String> = Vec::new();
+    let lines: Vec<&str> = markdown.lines().collect();
+    let mut code = false;
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i].trim_end();
+        let trimmed = line.trim_start();
+        i += 1;
+        if let Some(lang) = trimmed.strip_prefix("```") {
+            let lang = lang.trim();
+            out.push(if code || lang.is_empty() { "{code}".to_string() } else { format!("{{code:{}}}", lang) });
+            code = !code;
+            continue;
+        }
+        if code {
+            out.push(line.to_string());
+            continue;
+        }
+        let depth = (line.len() - trimmed.len()) / 2 + 1;
+        let heading = trimmed.chars().take_while(|c| *c == '#').count();
+        let converted = if (1..=6).contains(&heading) && trimmed[heading..].starts_with(' ') {
+            format!("h{}. {}", heading, wiki_inline(trimmed[heading..].trim()))
+        } else if let Some(item) = trimmed.strip_prefix("- ").or_else(|| trimmed.strip_prefix("* ")) {
+            format!("{} {}", "*".repeat(depth), wiki_inline(item.trim()))
+        } else if let Some(item) = trimmed
+            .split_once(". ")
+            .filter(|(n, _)| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+            .map(|(_, item)| item)
+        {
+            format!("{} {}", "#".repeat(depth), wiki_inline(item.trim()))
+        } else if let Some(quote) = trimmed.strip_prefix('>') {
+            format!("bq. {}", wiki_inline(quote.trim()))
+        } else if !trimmed.is_empty() && trimmed.chars().all(|c| matches!(c, '-' | '*' | '_')) && trimmed.len() >= 3 {
+            "----".to_string()
+        } else if trimmed.starts_with('|') {
+            let cells = |row: &str| -> Vec<String> {
+                row.trim().trim_matches('|').split('|').map(|c| wiki_inline(c.trim())).collect()
+            };
+            let separator = lines.get(i).is_some_and(|next| {
+                let next = next.trim();
+                next.starts_with('|') && next.chars().all(|c| matches!(c, '|' | '-' | ':' | ' '))
+            });
+            if separator {
+                i += 1;
+                format!("||{}||", cells(trimmed).join("||"))
+            } else {
+                format!("|{}|", cells(trimmed).join("|"))
+            }
+        } else {
+            // `#hashtag` would start a numbered list
+            let text = wiki_inline(trimmed);
+            if text.starts_with('#') { format!("\\{}", text) } else { text }
+        };
+        out.push(converted);
+    }
+    if code {
+        out.push("{code}".to_string());
+    }
+    out.join("\n")
+}
+
+/// Jira REST v2 create-issue body. v2 (unlike v3's document format) is the
+/// same on Jira Cloud and Data Center; its description is wiki markup, so the
+/// Markdown answer is converted.
+fn jira_body(project: &str, issue: &Issue) -> Value {
+    let mut description = wiki_markup(issue.body.trim());
+    if !issue.sources.is_empty() {
+        description.push_str("\n\nh3. Sources\n");
+        for (url, title, _) in &issue.sources {
+            let text = title.unwrap_or(url).replace(['|', '[', ']'], " ");
+            description.push_str(&format!("# [{}|{}]\n", text.split_whitespace().collect::<Vec<_>>().join(" "), url));
+        }
+    }
+    json!({
+        "fields": {
+            "project": { "key": project },
+            "summary": title(issue.query),
+            "description": description,
+            "issuetype": { "name": "Task" },
+        }
+    })
+}
+
+/// Linear GraphQL issueCreate request; the description is Markdown
+fn linear_body(team: &str, issue: &Issue) -> Value {
+    json!({
+        "query": LINEAR_MUTATION,
+        "variables": {
+            "input": {
+                "teamId": team,
+                "title": title(issue.query),
+                "description": markdown::document(issue.query, issue.body, &issue.sources),
+            }
+        }
+    })
+}
+
+/// File the issue with the tracker
+async fn create(http: &reqwest::Client, cli: &Cli, tracker: IssueTracker, issue: &Issue<'_>) -> Result<Created> {
+    let target = target(cli, tracker)?;
+    let (url, body) = match tracker {
+        IssueTracker::Jira => (format!("{}/rest/api/2/issue", target.endpoint), jira_body(&target.project, issue)),
+        IssueTracker::Linear => (target.endpoint.clone(), linear_body(&target.project, issue)),
+    };
+    let request = match &target.auth {
+        Auth::Basic { user, token } => http.post(&url).basic_auth(user, Some(token)),
+        Auth::Bearer(token) => http.post(&url).bearer_auth(token),
+        Auth::Key(key) => http.post(&url).header("Authorization", key),
+    };
+    let resp = request
+        .json(&body)
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .await
+        .with_context(|| format!("Failed to reach {}", url))?;
+    let status = resp.status();
+    let text = resp.text().await.unwrap_or_default();
+    if !status.is_success() {
+        bail!("{} returned {}: {}", url, status, text.chars().take(300).collect::<String>());
+    }
+    let value: Value = serde_json::from_str(&text).with_context(|| format!("Unexpected response from {}", url))?;
+    match tracker {
+        IssueTracker::Jira => {
+            let key = value["key"].as_str().context("Jira response has no issue key")?.to_string();
+            Ok(Created { url: format!("{}/browse/{}", target.endpoint, key), key })
+        }
+        IssueTracker::Linear => {
+            if let Some(message) = value["errors"][0]["message"].as_str() {
+                bail!("Linear refused the issue: {}", message);
+            }
+            let created = &value["data"]["issueCreate"]["issue"];
+            let key = created["identifier"].as_str().context("Linear response has no issue")?.to_string();
+            Ok(Created { url: created["url"].as_str().unwrap_or_default().to_string(), key })
+        }
+    }
+}
+
+/// With --create-issue, file `issue` and say where it went; a failure only
+/// warns, since the answer or report has been paid for and printed
+pub async fn file(http: &reqwest::Client, cli: &Cli, issue: &Issue<'_>) {
+    let Some(tracker) = cli.create_issue else { return };
+    match create(http, cli, tracker, issue).await {
+        Ok(created) => diag!("{} {} {}", "Filed".green(), created.key, created.url.dimmed()),
+        Err(e) => warnings::emit("issue_failed", format!("could not file the issue: {:#}", e), true),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_issue_bodies() {
+        let issue = Issue {
+            query: "is  rust\nmemory safe?",
+            body: "Yes, mostly.\n",
+            sources: vec![("https://a.com", Some("Rust [book] | ch4"), None), ("https://b.com", None, None)],
+        };
+        let jira = jira_body("SEC", &issue);
+        assert_eq!(jira["fields"]["project"]["key"], "SEC");
+        assert_eq!(jira["fields"]["summary"], "is rust memory safe?");
+        assert_eq!(
+            jira["fields"]["description"],
+            "Yes, mostly.\n\nh3. Sources\n# [Rust book ch4|https://a.com]\n# [https://b.com|https://b.com]\n"
+        );
+        let answer = "# Summary\n\nRust is **memory safe** [1](https://a.com), see `unsafe` and *the_book*.\n\n\
+                      - one\n  - nested snake_case {x}\n1. first\n> quoted\n\n```rust\nlet x = [1];\n```\n\n\
+                      | a | b |\n|---|:-:|\n| 1 | 2 |\n---\n#hashtag";
+        assert_eq!(
+            wiki_markup(answer),
+            "h1. Summary\n\nRust is *memory safe* [1|https://a.com], see {{unsafe}} and _the\\_book_.\n\n\
+             * one\n** nested snake\\_case \\{x\\}\n# first\nbq. quoted\n\n{code:rust}\nlet x = [1];\n{code}\n\n\
+             ||a||b||\n|1|2|\n----\n\\#hashtag"
+        );
+
+        let linear = linear_body("team-1", &issue);
+        let input = &linear["variables"]["input"];
+        assert_eq!(input["teamId"], "team-1");
+        assert_eq!(input["title"], "is rust memory safe?");
+        let description = input["description"].as_str().unwrap();
+        assert!(description.contains("## Sources\n\n1. [Rust \\[book\\] | ch4](https://a.com)"));
+
+        let long = title(&"word ".repeat(40));
+        assert!(long.chars().count() <= TITLE_CHARS && long.ends_with("..."));
+    }
+}
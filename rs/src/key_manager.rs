@@ -6,11 +6,65 @@ use std::collections::HashMap;
 use std::env;
 use std::fs::{self, OpenOptions};
 use std::io::{BufWriter, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use crate::{diag, vars, warnings};
 
 const DEFAULT_COOLDOWN_SECS: i64 = 60;
 const STALE_THRESHOLD_HOURS: i64 = 24;
 const MAX_LOG_SIZE: u64 = 5 * 1024 * 1024; // 5MB
+/// Per-install secret keying the query hashes in the request log and history
+const QUERY_KEY_FILE: &str = "query.key";
+/// Research tasks remembered as already counted against a budget
+const MAX_BILLED_TASKS: usize = 500;
+
+/// Write `contents` and a newline to a file only the owner can read
+pub fn write_private(path: &Path, contents: &str) -> Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options
+        .open(path)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    // An existing file keeps its old mode when opened
+    #[cfg(unix)]
+    fs::set_permissions(path, std::os::unix::fs::PermissionsExt::from_mode(0o600))?;
+    writeln!(file, "{}", contents)?;
+    Ok(())
+}
+
+/// Hex HMAC-SHA256 of `query` under the install's secret (`query.key` in
+/// `config_dir`, created on first use). Unlike a bare hash, it can't be
+/// matched against guessed queries without the secret.
+pub fn query_hmac(config_dir: &Path, query: &str) -> Result<String> {
+    let path = config_dir.join(QUERY_KEY_FILE);
+    let secret = match fs::read_to_string(&path) {
+        Ok(secret) => secret,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            let mut bytes = [0u8; 32];
+            getrandom::getrandom(&mut bytes).map_err(|e| anyhow::anyhow!("No system randomness: {}", e))?;
+            let secret: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+            fs::create_dir_all(config_dir)?;
+            let mut options = OpenOptions::new();
+            options.write(true).create_new(true);
+            #[cfg(unix)]
+            std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+            match options.open(&path) {
+                Ok(mut file) => {
+                    writeln!(file, "{}", secret)?;
+                    secret
+                }
+                // Another process created it first; use theirs
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => fs::read_to_string(&path)?,
+                Err(e) => return Err(e).with_context(|| format!("Failed to write {}", path.display())),
+            }
+        }
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    Ok(crate::fingerprint::hmac_sha256_hex(secret.trim().as_bytes(), query.as_bytes()))
+}
 
 /// Masks an API key, showing only the last 3 characters
 pub fn mask_key(key: &str) -> String {
@@ -21,6 +75,242 @@ pub fn mask_key(key: &str) -> String {
     }
 }
 
+/// A request rate applied to each key, written `5/s`, `120/m` or `1000/h`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub requests: u32,
+    /// Seconds in the period
+    pub per_secs: u32,
+}
+
+impl RateLimit {
+    fn per_sec(&self) -> f64 {
+        f64::from(self.requests) / f64::from(self.per_secs)
+    }
+
+    /// Bucket size: a second's worth of requests, at least one
+    fn burst(&self) -> f64 {
+        self.per_sec().max(1.0)
+    }
+}
+
+impl std::str::FromStr for RateLimit {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let (requests, unit) = s.split_once('/').ok_or_else(|| format!("'{}' is not N/s, N/m or N/h", s))?;
+        let requests: u32 = requests.trim().parse().map_err(|_| format!("'{}' is not a request count", requests))?;
+        if requests == 0 {
+            return Err("the rate must allow at least one request".to_string());
+        }
+        let per_secs = match unit.trim() {
+            "s" | "sec" | "second" => 1,
+            "m" | "min" | "minute" => 60,
+            "h" | "hour" => 3600,
+            other => return Err(format!("unknown period '{}' (use s, m or h)", other)),
+        };
+        Ok(Self { requests, per_secs })
+    }
+}
+
+impl std::fmt::Display for RateLimit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let unit = match self.per_secs {
+            1 => "s",
+            60 => "m",
+            _ => "h",
+        };
+        write!(f, "{}/{}", self.requests, unit)
+    }
+}
+
+/// A --key-weights entry, `<key id>=<weight>`. The id is the end of the key
+/// as `exa keys list` shows it (`...x7Q`, or just `x7Q`), so a weight stays
+/// with its key when keys are added or removed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyWeight {
+    pub id: String,
+    pub weight: u32,
+}
+
+impl std::str::FromStr for KeyWeight {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let (id, weight) = s.split_once('=').ok_or_else(|| format!("'{}' is not <key id>=<weight>", s))?;
+        let id = id.trim().trim_start_matches('.');
+        if id.is_empty() {
+            return Err(format!("'{}' has no key id (the end of the key, as `exa keys list` shows it)", s));
+        }
+        let weight = weight.trim().parse().map_err(|_| format!("'{}' is not a weight", weight))?;
+        Ok(Self { id: id.to_string(), weight })
+    }
+}
+
+impl std::fmt::Display for KeyWeight {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}={}", self.id, self.weight)
+    }
+}
+
+/// How the next key is chosen among those available
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum KeyStrategy {
+    /// Round-robin order, skipping ahead to the least-used key
+    #[default]
+    Hybrid,
+    /// Strictly in turn
+    RoundRobin,
+    /// Fewest requests so far
+    LeastUsed,
+    /// Requests in proportion to per-key weights (the key furthest below its
+    /// share of today's requests)
+    Weighted,
+    /// The last key used until it becomes unavailable, e.g. for a research session
+    Sticky,
+}
+
+impl std::str::FromStr for KeyStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().replace('-', "_").as_str() {
+            "hybrid" => Ok(Self::Hybrid),
+            "round_robin" => Ok(Self::RoundRobin),
+            "least_used" => Ok(Self::LeastUsed),
+            "weighted" => Ok(Self::Weighted),
+            "sticky" => Ok(Self::Sticky),
+            other => Err(format!(
+                "unknown key strategy '{}' (use hybrid, round_robin, least_used, weighted or sticky)",
+                other
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for KeyStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Hybrid => "hybrid",
+            Self::RoundRobin => "round_robin",
+            Self::LeastUsed => "least_used",
+            Self::Weighted => "weighted",
+            Self::Sticky => "sticky",
+        })
+    }
+}
+
+/// Tokens for one key; refilled continuously at the rate, in this process only
+#[derive(Debug, Clone, Copy)]
+struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// `disk + ours - saved`, floored at zero
+fn merge_count(disk: u64, saved: u64, ours: u64) -> u64 {
+    (i128::from(disk) + i128::from(ours) - i128::from(saved)).max(0) as u64
+}
+
+/// Ours if we changed it since the last save, else whatever is on disk
+fn merge_field<T: PartialEq + Clone>(disk: &T, saved: &T, ours: &T) -> T {
+    if ours != saved { ours.clone() } else { disk.clone() }
+}
+
+/// Combine the state on disk with our changes since `saved`: counters add
+/// up, other fields take our value only where we changed it
+fn merge_state(mut disk: KeyState, saved: &KeyState, ours: &KeyState, now: DateTime<Utc>) -> KeyState {
+    for (idx, mine) in &ours.keys {
+        let base = saved.keys.get(idx).cloned().unwrap_or_default();
+        let entry = disk.keys.entry(*idx).or_default();
+        entry.usage = UsageStats {
+            requests: merge_count(entry.usage.requests, base.usage.requests, mine.usage.requests),
+            success: merge_count(entry.usage.success, base.usage.success, mine.usage.success),
+            errors: merge_count(entry.usage.errors, base.usage.errors, mine.usage.errors),
+        };
+        let (base_spend, my_spend) = (base.spend_at(now), mine.spend_at(now));
+        entry.spend.roll(now);
+        entry.spend.day_requests =
+            merge_count(entry.spend.day_requests, base_spend.day_requests, my_spend.day_requests);
+        entry.spend.month_requests =
+            merge_count(entry.spend.month_requests, base_spend.month_requests, my_spend.month_requests);
+        entry.spend.day_dollars = (entry.spend.day_dollars + my_spend.day_dollars - base_spend.day_dollars).max(0.0);
+        entry.spend.month_dollars =
+            (entry.spend.month_dollars + my_spend.month_dollars - base_spend.month_dollars).max(0.0);
+        entry.cooldown_until = merge_field(&entry.cooldown_until, &base.cooldown_until, &mine.cooldown_until);
+        entry.valid = merge_field(&entry.valid, &base.valid, &mine.valid);
+        entry.budget = merge_field(&entry.budget, &base.budget, &mine.budget);
+    }
+    for task in ours.billed_tasks.iter().filter(|t| !saved.billed_tasks.contains(t)) {
+        if !disk.billed_tasks.contains(task) {
+            disk.billed_tasks.push(task.clone());
+        }
+    }
+    let excess = disk.billed_tasks.len().saturating_sub(MAX_BILLED_TASKS);
+    disk.billed_tasks.drain(..excess);
+    disk.current_index = ours.current_index;
+    disk.last_validated = disk.last_validated.max(ours.last_validated);
+    disk.version = ours.version;
+    disk
+}
+
+/// Keys separated by commas or newlines
+pub fn parse_keys(text: &str) -> Vec<String> {
+    text.split([',', '\n'])
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// Whether the API at `base_url` accepts `key`, checked with a one-result search.
+/// Errors only when the API couldn't be reached.
+pub async fn check_key(client: &reqwest::Client, base_url: &str, key: &str) -> Result<bool> {
+    let resp = client
+        .post(format!("{}/search", base_url))
+        .header("x-api-key", key)
+        .header("Content-Type", "application/json")
+        .json(&serde_json::json!({
+            "query": "test",
+            "numResults": 1
+        }))
+        .send()
+        .await?;
+    Ok(!matches!(resp.status().as_u16(), 401 | 403))
+}
+
+#[cfg(feature = "keyring")]
+fn keyring_entry() -> Result<keyring::Entry> {
+    keyring::Entry::new("exa-cli", "api-keys").context("Failed to open the OS keyring")
+}
+
+/// Keys stored in the OS keyring (empty if none are)
+#[cfg(feature = "keyring")]
+pub fn load_keyring_keys() -> Result<Vec<String>> {
+    match keyring_entry()?.get_password() {
+        Ok(secret) => Ok(parse_keys(&secret)),
+        Err(keyring::Error::NoEntry) => Ok(Vec::new()),
+        Err(e) => Err(e).context("Failed to read keys from the OS keyring"),
+    }
+}
+
+/// Replace the keys stored in the OS keyring
+#[cfg(feature = "keyring")]
+pub fn store_keyring_keys(keys: &[String]) -> Result<()> {
+    keyring_entry()?
+        .set_password(&keys.join("\n"))
+        .context("Failed to store keys in the OS keyring")
+}
+
+/// Remove the keys from the OS keyring; false if there were none
+#[cfg(feature = "keyring")]
+pub fn forget_keyring_keys() -> Result<bool> {
+    match keyring_entry()?.delete_credential() {
+        Ok(()) => Ok(true),
+        Err(keyring::Error::NoEntry) => Ok(false),
+        Err(e) => Err(e).context("Failed to remove keys from the OS keyring"),
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct UsageStats {
     pub requests: u64,
@@ -28,6 +318,62 @@ pub struct UsageStats {
     pub errors: u64,
 }
 
+/// Per-key limits; a key over any of them is skipped until the period rolls over
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct KeyBudget {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub daily_requests: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub monthly_requests: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub daily_dollars: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub monthly_dollars: Option<f64>,
+}
+
+impl KeyBudget {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Requests and dollars in the current UTC day and month
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct PeriodSpend {
+    /// `YYYY-MM-DD`
+    #[serde(default)]
+    pub day: String,
+    #[serde(default)]
+    pub day_requests: u64,
+    #[serde(default)]
+    pub day_dollars: f64,
+    /// `YYYY-MM`
+    #[serde(default)]
+    pub month: String,
+    #[serde(default)]
+    pub month_requests: u64,
+    #[serde(default)]
+    pub month_dollars: f64,
+}
+
+impl PeriodSpend {
+    /// Reset the counters of any period that has ended by `now`
+    pub fn roll(&mut self, now: DateTime<Utc>) {
+        let day = now.format("%Y-%m-%d").to_string();
+        if self.day != day {
+            self.day = day;
+            self.day_requests = 0;
+            self.day_dollars = 0.0;
+        }
+        let month = now.format("%Y-%m").to_string();
+        if self.month != month {
+            self.month = month;
+            self.month_requests = 0;
+            self.month_dollars = 0.0;
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct KeyInfo {
     #[serde(default)]
@@ -36,6 +382,56 @@ pub struct KeyInfo {
     pub valid: bool,
     #[serde(default)]
     pub usage: UsageStats,
+    #[serde(default, skip_serializing_if = "KeyBudget::is_empty")]
+    pub budget: KeyBudget,
+    #[serde(default)]
+    pub spend: PeriodSpend,
+}
+
+impl KeyInfo {
+    /// Spend in the periods current at `now`
+    pub fn spend_at(&self, now: DateTime<Utc>) -> PeriodSpend {
+        let mut spend = self.spend.clone();
+        spend.roll(now);
+        spend
+    }
+
+    /// Which budget the key has used up at `now`, if any
+    pub fn over_budget(&self, now: DateTime<Utc>) -> Option<&'static str> {
+        let spend = self.spend_at(now);
+        let b = &self.budget;
+        if b.daily_requests.is_some_and(|max| spend.day_requests >= max) {
+            Some("daily requests")
+        } else if b.monthly_requests.is_some_and(|max| spend.month_requests >= max) {
+            Some("monthly requests")
+        } else if b.daily_dollars.is_some_and(|max| spend.day_dollars >= max) {
+            Some("daily dollars")
+        } else if b.monthly_dollars.is_some_and(|max| spend.month_dollars >= max) {
+            Some("monthly dollars")
+        } else {
+            None
+        }
+    }
+
+    /// What is left of each budget at `now`, e.g. "88 requests today"
+    pub fn remaining_budget(&self, now: DateTime<Utc>) -> Vec<String> {
+        let spend = self.spend_at(now);
+        let b = &self.budget;
+        let mut out = Vec::new();
+        if let Some(max) = b.daily_requests {
+            out.push(format!("{}/{} requests today", max.saturating_sub(spend.day_requests), max));
+        }
+        if let Some(max) = b.monthly_requests {
+            out.push(format!("{}/{} requests this month", max.saturating_sub(spend.month_requests), max));
+        }
+        if let Some(max) = b.daily_dollars {
+            out.push(format!("${:.2}/${:.2} today", (max - spend.day_dollars).max(0.0), max));
+        }
+        if let Some(max) = b.monthly_dollars {
+            out.push(format!("${:.2}/${:.2} this month", (max - spend.month_dollars).max(0.0), max));
+        }
+        out
+    }
 }
 
 fn default_valid() -> bool {
@@ -48,6 +444,8 @@ impl Default for KeyInfo {
             cooldown_until: None,
             valid: true,
             usage: UsageStats::default(),
+            budget: KeyBudget::default(),
+            spend: PeriodSpend::default(),
         }
     }
 }
@@ -58,6 +456,9 @@ pub struct KeyState {
     pub current_index: usize,
     pub last_validated: DateTime<Utc>,
     pub keys: HashMap<usize, KeyInfo>,
+    /// Research tasks whose cost has been counted, oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub billed_tasks: Vec<String>,
 }
 
 impl Default for KeyState {
@@ -67,6 +468,71 @@ impl Default for KeyState {
             current_index: 0,
             last_validated: Utc::now(),
             keys: HashMap::new(),
+            billed_tasks: Vec::new(),
+        }
+    }
+}
+
+/// One key's availability when a request gave up on 429s
+#[derive(Debug, Clone, Serialize)]
+pub struct KeyCooldown {
+    pub index: usize,
+    /// Masked key
+    pub key: String,
+    /// "ready", "cooldown", "over_budget" or "invalid"
+    pub state: &'static str,
+    /// When a key on cooldown can be used again
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub until: Option<DateTime<Utc>>,
+}
+
+/// Error for a request that stayed rate limited through every retry, with
+/// each key's cooldown and the earliest time a retry can succeed
+#[derive(Debug, Clone, Serialize)]
+pub struct RateLimited {
+    #[serde(rename = "error")]
+    pub message: String,
+    /// None when no key will become usable by waiting (all invalid or over budget)
+    pub retry_at: Option<DateTime<Utc>>,
+    pub keys: Vec<KeyCooldown>,
+}
+
+impl std::fmt::Display for RateLimited {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)?;
+        if let Some(at) = self.retry_at {
+            write!(f, "; retry after {}", at.format("%H:%M:%S UTC"))?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for RateLimited {}
+
+/// The key [`KeyManager::get_next_key`] chose, or how long the caller waits
+/// before asking again, every usable key being throttled or on cooldown
+#[derive(Debug, Clone, PartialEq)]
+pub enum NextKey {
+    Ready(usize, String),
+    Wait(std::time::Duration),
+}
+
+/// Where the key pool was loaded from, and where `exa keys add/remove` save it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeySource {
+    Keyring,
+    Env,
+    File,
+    Memory,
+}
+
+impl KeySource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Keyring => "keyring",
+            Self::Env => "environment",
+            Self::File => "keys file",
+            Self::Memory => "memory",
         }
     }
 }
@@ -74,12 +540,30 @@ impl Default for KeyState {
 #[derive(Debug)]
 pub struct KeyManager {
     keys: Vec<String>,
+    source: KeySource,
     state: KeyState,
     config_dir: PathBuf,
     pub verbose: bool,
     log_enabled: bool,
+    /// Log query text as well as its keyed hash (`log.include_queries`)
+    log_queries: bool,
+    /// Client tag recorded in request log entries
+    pub client_tag: Option<String>,
+    /// Per-key rate enforced before sending (--key-rate)
+    rate_limit: Option<RateLimit>,
+    buckets: HashMap<usize, TokenBucket>,
+    /// How keys are chosen (--key-strategy), and their weights for `weighted`
+    strategy: KeyStrategy,
+    weights: Vec<KeyWeight>,
+    /// State as last read from or written to disk; saving merges our changes
+    /// since then into what other processes wrote meanwhile. None when the
+    /// state didn't come from disk, and after remove_key (indices shifted).
+    saved: Option<KeyState>,
 }
 
+/// The request log, oldest file first (see [`KeyManager::log_request`])
+pub const LOG_FILES: [&str; 2] = ["requests.log.1", "requests.log"];
+
 /// Log entry for request logging
 #[derive(Serialize)]
 struct LogEntry {
@@ -87,21 +571,58 @@ struct LogEntry {
     key: String,
     cmd: String,
     status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    client: Option<String>,
+    /// SHA-256 of the request body, so a query can be matched to its entry
+    #[serde(skip_serializing_if = "Option::is_none")]
+    body_sha256: Option<String>,
+    /// Keyed hash of the query or research instructions (see [`query_hmac`])
+    #[serde(skip_serializing_if = "Option::is_none")]
+    query_hmac: Option<String>,
+    /// The query text itself, only with `log.include_queries`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    query: Option<String>,
+    /// Hash of the previous entry, when entries are signed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    prev: Option<String>,
 }
 
 impl KeyManager {
     /// Create a new KeyManager, loading keys from environment and state from disk
     pub fn new(verbose: bool) -> Result<Self> {
-        let keys = Self::load_keys_from_env()?;
+        let manager = Self::open(verbose)?;
+        if manager.keys.is_empty() {
+            bail!(
+                "No API keys found.\nRun `exa init`, or set EXA_API_KEYS (comma-separated) or EXA_API_KEY.\nGet your key at: https://exa.ai"
+            );
+        }
+        Ok(manager)
+    }
+
+    /// Like [`new`](Self::new), but an empty key pool is not an error (for
+    /// managing the pool)
+    pub fn open(verbose: bool) -> Result<Self> {
+        let (keys, source) = Self::load_keys()?;
         let config_dir = Self::get_config_dir()?;
-        let log_enabled = env::var("EXA_LOG_REQUESTS").map(|v| v == "1").unwrap_or(false);
+        let log_enabled = vars::get("EXA_LOG_REQUESTS").is_some_and(|v| v == "1");
+        let log_queries = vars::get("EXA_LOG_QUERIES").is_some_and(|v| v == "1");
 
         let mut manager = Self {
             keys,
+            source,
             state: KeyState::default(),
             config_dir,
             verbose,
             log_enabled,
+            log_queries,
+            client_tag: None,
+            rate_limit: None,
+            buckets: HashMap::new(),
+            strategy: KeyStrategy::default(),
+            weights: Vec::new(),
+            saved: None,
         };
 
         // Load existing state if available
@@ -109,41 +630,159 @@ impl KeyManager {
 
         // Initialize key info for any new keys
         for i in 0..manager.keys.len() {
-            manager.state.keys.entry(i).or_insert_with(KeyInfo::default);
+            manager.state.keys.entry(i).or_default();
         }
 
         Ok(manager)
     }
 
-    /// Load API keys from environment variables
-    fn load_keys_from_env() -> Result<Vec<String>> {
-        // First try EXA_API_KEYS (comma-separated)
-        if let Ok(keys_str) = env::var("EXA_API_KEYS") {
-            let keys: Vec<String> = keys_str
-                .split(',')
-                .map(|s| s.trim().to_string())
-                .filter(|s| !s.is_empty())
-                .collect();
+    /// KeyManager over the given keys, with fresh in-memory state and no
+    /// request log (for library use and tests)
+    pub fn from_keys(keys: Vec<String>) -> Self {
+        let mut state = KeyState::default();
+        for i in 0..keys.len() {
+            state.keys.entry(i).or_default();
+        }
+        Self {
+            keys,
+            source: KeySource::Memory,
+            state,
+            config_dir: env::temp_dir().join("exa"),
+            verbose: false,
+            log_enabled: false,
+            log_queries: false,
+            client_tag: None,
+            rate_limit: None,
+            buckets: HashMap::new(),
+            strategy: KeyStrategy::default(),
+            weights: Vec::new(),
+            saved: None,
+        }
+    }
 
+    /// Load API keys from the OS keyring, else the environment, else the
+    /// keys file `exa init` writes. With no keys anywhere the pool is empty
+    /// and new keys go to the keyring (when it works) or the keys file.
+    fn load_keys() -> Result<(Vec<String>, KeySource)> {
+        // An unavailable keyring (no Secret Service on a headless box) counts as empty
+        #[cfg(feature = "keyring")]
+        let keyring_works = match load_keyring_keys() {
+            Ok(keys) if !keys.is_empty() => return Ok((keys, KeySource::Keyring)),
+            Ok(_) => true,
+            Err(_) => false,
+        };
+        #[cfg(not(feature = "keyring"))]
+        let keyring_works = false;
+
+        // First try EXA_API_KEYS (comma-separated)
+        if let Some(keys_str) = vars::get("EXA_API_KEYS") {
+            let keys = parse_keys(&keys_str);
             if !keys.is_empty() {
-                return Ok(keys);
+                return Ok((keys, KeySource::Env));
             }
         }
 
         // Fall back to single EXA_API_KEY
-        if let Ok(key) = env::var("EXA_API_KEY") {
+        if let Some(key) = vars::get("EXA_API_KEY") {
             if !key.trim().is_empty() {
-                return Ok(vec![key.trim().to_string()]);
+                return Ok((vec![key.trim().to_string()], KeySource::Env));
+            }
+        }
+
+        if let Ok(text) = fs::read_to_string(Self::keys_file_path()?) {
+            let keys = parse_keys(&text);
+            if !keys.is_empty() {
+                return Ok((keys, KeySource::File));
             }
         }
 
-        bail!(
-            "No API keys found.\nSet EXA_API_KEYS (comma-separated) or EXA_API_KEY.\nGet your key at: https://exa.ai"
-        )
+        Ok((Vec::new(), if keyring_works { KeySource::Keyring } else { KeySource::File }))
+    }
+
+    pub fn source(&self) -> KeySource {
+        self.source
+    }
+
+    pub fn key_count(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// The keys in the pool, in index order (for moving them elsewhere)
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    /// State and usage of the key at `idx`
+    pub fn key_info(&self, idx: usize) -> KeyInfo {
+        self.state.keys.get(&idx).cloned().unwrap_or_default()
+    }
+
+    /// Add a key to the pool and save the pool; returns its index
+    pub fn add_key(&mut self, key: &str) -> Result<usize> {
+        let key = key.trim();
+        if key.is_empty() {
+            bail!("Empty API key");
+        }
+        if self.keys.iter().any(|k| k == key) {
+            bail!("Key {} is already in the pool", mask_key(key));
+        }
+        self.keys.push(key.to_string());
+        let idx = self.keys.len() - 1;
+        self.state.keys.insert(idx, KeyInfo::default());
+        self.save_keys()?;
+        self.save_state()?;
+        Ok(idx)
+    }
+
+    /// Remove the key at `idx` and save the pool; later keys move down one index
+    pub fn remove_key(&mut self, idx: usize) -> Result<String> {
+        if idx >= self.keys.len() {
+            bail!("No key {} (the pool has {})", idx, self.keys.len());
+        }
+        let key = self.keys.remove(idx);
+        let old = std::mem::take(&mut self.state.keys);
+        self.state.keys = old
+            .into_iter()
+            .filter(|(i, _)| *i != idx)
+            .map(|(i, info)| (if i > idx { i - 1 } else { i }, info))
+            .collect();
+        if self.state.current_index > idx {
+            self.state.current_index -= 1;
+        }
+        self.buckets.clear();
+        self.saved = None;
+        self.save_keys()?;
+        self.save_state()?;
+        Ok(key)
+    }
+
+    /// Write the pool back to where it was loaded from
+    fn save_keys(&self) -> Result<()> {
+        match self.source {
+            #[cfg(feature = "keyring")]
+            KeySource::Keyring => store_keyring_keys(&self.keys),
+            KeySource::File => Self::save_keys_file(&self.keys).map(|_| ()),
+            KeySource::Env => {
+                bail!("Keys come from EXA_API_KEYS / EXA_API_KEY; change the variable instead")
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// `<config dir>/keys`, one key per line
+    pub fn keys_file_path() -> Result<PathBuf> {
+        Ok(Self::get_config_dir()?.join("keys"))
+    }
+
+    /// Replace the keys file, readable only by the current user
+    pub fn save_keys_file(keys: &[String]) -> Result<PathBuf> {
+        let path = Self::keys_file_path()?;
+        write_private(&path, &keys.join("\n"))?;
+        Ok(path)
     }
 
     /// Get the config directory path
-    fn get_config_dir() -> Result<PathBuf> {
+    pub fn get_config_dir() -> Result<PathBuf> {
         let config_dir = if cfg!(windows) {
             dirs::config_dir()
                 .context("Could not find config directory")?
@@ -168,6 +807,10 @@ impl KeyManager {
         self.config_dir.join("state.json")
     }
 
+    fn state_backup_path(&self) -> PathBuf {
+        self.config_dir.join("state.json.bak")
+    }
+
     /// Get the log file path
     fn log_file_path(&self) -> PathBuf {
         self.config_dir.join("requests.log")
@@ -175,30 +818,91 @@ impl KeyManager {
 
     /// Load state from disk
     fn load_state(&mut self) -> Result<()> {
-        let state_path = self.state_file_path();
-        if state_path.exists() {
-            let content = fs::read_to_string(&state_path).context("Failed to read state file")?;
-            self.state = serde_json::from_str(&content).unwrap_or_else(|_| KeyState::default());
+        let _lock = self.lock_state(false)?;
+        self.state = self.read_state()?.unwrap_or_default();
+        self.saved = Some(self.state.clone());
+        Ok(())
+    }
+
+    /// Save state to disk, adding our usage since the last load or save to
+    /// the counters on disk, so parallel `exa` runs don't overwrite each other
+    pub fn save_state(&mut self) -> Result<()> {
+        let _lock = self.lock_state(true)?;
+        if let Some(saved) = &self.saved {
+            if let Some(disk) = self.read_state()? {
+                self.state = merge_state(disk, saved, &self.state, Utc::now());
+            }
         }
+        self.write_state()?;
+        self.saved = Some(self.state.clone());
         Ok(())
     }
 
-    /// Save state to disk
-    pub fn save_state(&self) -> Result<()> {
+    /// Replace state.json atomically: write a temp file next to it, sync,
+    /// keep the previous version as state.json.bak, then rename over it
+    fn write_state(&self) -> Result<()> {
         let state_path = self.state_file_path();
+        let tmp_path = self.config_dir.join(format!("state.json.{}.tmp", std::process::id()));
         let content = serde_json::to_string_pretty(&self.state)?;
-        fs::write(&state_path, content).context("Failed to write state file")?;
+        let written = fs::File::create(&tmp_path)
+            .and_then(|mut file| file.write_all(content.as_bytes()).and_then(|_| file.sync_all()));
+        if let Err(e) = written {
+            let _ = fs::remove_file(&tmp_path);
+            return Err(e).context("Failed to write state file");
+        }
+        if state_path.exists() {
+            let _ = fs::copy(&state_path, self.state_backup_path());
+        }
+        fs::rename(&tmp_path, &state_path).context("Failed to replace state file")?;
         Ok(())
     }
 
+    /// The state on disk, None if there is none. A corrupt state.json falls
+    /// back to state.json.bak, so cooldowns and usage aren't silently reset.
+    fn read_state(&self) -> Result<Option<KeyState>> {
+        let state_path = self.state_file_path();
+        if !state_path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&state_path).context("Failed to read state file")?;
+        match serde_json::from_str(&content) {
+            Ok(state) => Ok(Some(state)),
+            Err(e) => {
+                let backup = fs::read_to_string(self.state_backup_path())
+                    .ok()
+                    .and_then(|content| serde_json::from_str(&content).ok());
+                let fallback = if backup.is_some() { "using state.json.bak" } else { "starting over" };
+                let message = format!("{} is unreadable ({}); {}", state_path.display(), e, fallback);
+                warnings::emit("state_corrupt", message, true);
+                Ok(backup)
+            }
+        }
+    }
+
+    /// Advisory lock on state.json, held until the returned file is dropped.
+    /// A separate lock file, so the lock survives state.json being rewritten.
+    fn lock_state(&self, exclusive: bool) -> Result<fs::File> {
+        fs::create_dir_all(&self.config_dir).context("Failed to create config directory")?;
+        let path = self.config_dir.join("state.json.lock");
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        if exclusive { file.lock() } else { file.lock_shared() }.context("Failed to lock the state file")?;
+        Ok(file)
+    }
+
     /// Check if state is stale (older than 24 hours)
     pub fn is_state_stale(&self) -> bool {
         let threshold = Utc::now() - Duration::hours(STALE_THRESHOLD_HOURS);
         self.state.last_validated < threshold
     }
 
-    /// Get the next available key (cooldown-aware)
-    pub fn get_next_key(&mut self) -> Result<(usize, String)> {
+    /// Get the next available key (cooldown-aware). Never blocks: when every
+    /// usable key is throttled or on cooldown, says how long to wait instead.
+    pub fn get_next_key(&mut self) -> Result<NextKey> {
         let now = Utc::now();
         let valid_indices: Vec<usize> = (0..self.keys.len())
             .filter(|&i| {
@@ -211,6 +915,15 @@ impl KeyManager {
             bail!("No valid API keys available");
         }
 
+        // Keys over a budget are skipped until the period rolls over
+        let valid_indices: Vec<usize> = valid_indices
+            .into_iter()
+            .filter(|i| self.state.keys.get(i).is_none_or(|info| info.over_budget(now).is_none()))
+            .collect();
+        if valid_indices.is_empty() {
+            bail!("Every valid API key is over its budget; see `exa status`");
+        }
+
         // Find keys not on cooldown
         let available: Vec<usize> = valid_indices
             .iter()
@@ -224,12 +937,27 @@ impl KeyManager {
             .copied()
             .collect();
 
-        let selected_idx = if available.is_empty() {
-            // All keys on cooldown - find the one with shortest remaining cooldown
-            if self.verbose {
-                eprintln!("{}", "All keys on cooldown, waiting...".yellow());
+        // With --key-rate, prefer keys with a token; if none has one, wait
+        // for the first token rather than sending and collecting a 429
+        let available = match self.rate_limit {
+            Some(limit) if !available.is_empty() => {
+                let ready: Vec<usize> = available.iter().copied().filter(|&i| self.token_wait(i).is_zero()).collect();
+                if ready.is_empty() {
+                    let wait = available.iter().map(|&i| self.token_wait(i)).min().context("No keys available")?;
+                    warnings::emit(
+                        "keys_throttled",
+                        format!("All keys at the --key-rate limit of {}, waiting {:.2}s", limit, wait.as_secs_f64()),
+                        self.verbose,
+                    );
+                    return Ok(NextKey::Wait(wait));
+                }
+                ready
             }
+            _ => available,
+        };
 
+        if available.is_empty() {
+            // All keys on cooldown - find the one with shortest remaining cooldown
             let (idx, wait_until) = valid_indices
                 .iter()
                 .filter_map(|&i| {
@@ -241,53 +969,126 @@ impl KeyManager {
 
             // Wait for cooldown to expire
             let wait_duration = (wait_until - now).to_std().unwrap_or_default();
-            if self.verbose {
-                eprintln!(
-                    "Waiting {:.1}s for key {} to become available",
+            warnings::emit(
+                "keys_on_cooldown",
+                format!(
+                    "All keys on cooldown, waiting {:.1}s for key {} to become available",
                     wait_duration.as_secs_f64(),
                     mask_key(&self.keys[idx])
-                );
-            }
-            std::thread::sleep(wait_duration);
-
-            idx
-        } else {
-            // Round-robin among available keys, preferring lower usage
-            let start = self.state.current_index % self.keys.len();
-            let mut best_idx = available[0];
-            let mut best_usage = u64::MAX;
-
-            // Try to find the next key in round-robin order with lowest usage
-            for offset in 0..self.keys.len() {
-                let idx = (start + offset) % self.keys.len();
-                if available.contains(&idx) {
-                    let usage = self
-                        .state
-                        .keys
-                        .get(&idx)
-                        .map(|info| info.usage.requests)
-                        .unwrap_or(0);
-                    if usage < best_usage {
-                        best_usage = usage;
-                        best_idx = idx;
-                    }
-                }
-            }
-            best_idx
-        };
+                ),
+                self.verbose,
+            );
+            return Ok(NextKey::Wait(wait_duration));
+        }
+        let selected_idx = self.pick(&available);
 
         // Update current index for round-robin
         self.state.current_index = (selected_idx + 1) % self.keys.len();
+        self.take_token(selected_idx);
 
         if self.verbose {
-            eprintln!(
+            diag!(
                 "Using key {} (index {})",
                 mask_key(&self.keys[selected_idx]),
                 selected_idx
             );
         }
 
-        Ok((selected_idx, self.keys[selected_idx].clone()))
+        Ok(NextKey::Ready(selected_idx, self.keys[selected_idx].clone()))
+    }
+
+    /// Choose among the available keys by the strategy
+    fn pick(&self, available: &[usize]) -> usize {
+        let len = self.keys.len();
+        let start = self.state.current_index % len;
+        let usage = |i: usize| self.state.keys.get(&i).map_or(0, |info| info.usage.requests);
+        // Weighted shares are of today's requests, so a key added later (or
+        // a reweighting) evens out within the day instead of after as many
+        // requests as the others have made in their lifetime
+        let today = Utc::now().format("%Y-%m-%d").to_string();
+        let today_usage = |i: usize| {
+            self.state.keys.get(&i).filter(|info| info.spend.day == today).map_or(0, |info| info.spend.day_requests)
+        };
+        // Available keys in round-robin order from the next position
+        let order: Vec<usize> = (0..len).map(|o| (start + o) % len).filter(|i| available.contains(i)).collect();
+        let picked = match self.strategy {
+            KeyStrategy::Hybrid => order.iter().copied().min_by_key(|&i| usage(i)),
+            KeyStrategy::RoundRobin => order.first().copied(),
+            KeyStrategy::LeastUsed => available.iter().copied().min_by_key(|&i| (usage(i), i)),
+            KeyStrategy::Weighted => order
+                .iter()
+                .copied()
+                .filter(|&i| self.weight(i) > 0)
+                .min_by(|&a, &b| {
+                    let share = |i: usize| today_usage(i) as f64 / f64::from(self.weight(i));
+                    share(a).total_cmp(&share(b))
+                })
+                .or(order.first().copied()),
+            KeyStrategy::Sticky => {
+                let last = (start + len - 1) % len;
+                if available.contains(&last) { Some(last) } else { order.first().copied() }
+            }
+        };
+        picked.unwrap_or(available[0])
+    }
+
+    /// Choose keys with `strategy`, and for `weighted` by `weights` (1 for a
+    /// key without one). An id matching several keys is an error; one
+    /// matching none only warns, as the key may just be gone from the pool.
+    pub fn set_strategy(&mut self, strategy: KeyStrategy, weights: Vec<KeyWeight>) -> Result<()> {
+        for w in &weights {
+            match self.keys.iter().filter(|k| k.ends_with(&w.id)).count() {
+                0 => warnings::emit("key_weights", format!("no key ends with '{}'; its weight is unused", w.id), true),
+                1 => {}
+                n => bail!("key id '{}' matches {} keys; give more of the key's end", w.id, n),
+            }
+        }
+        self.strategy = strategy;
+        self.weights = weights;
+        Ok(())
+    }
+
+    fn weight(&self, idx: usize) -> u32 {
+        let key = &self.keys[idx];
+        self.weights.iter().find(|w| key.ends_with(&w.id)).map_or(1, |w| w.weight)
+    }
+
+    /// Limit each key to `limit` requests (None for no limit)
+    pub fn set_rate_limit(&mut self, limit: Option<RateLimit>) {
+        self.rate_limit = limit;
+        self.buckets.clear();
+    }
+
+    /// Log query text as well as its keyed hash (EXA_LOG_QUERIES=1 sets this
+    /// in [`open`](Self::open); the CLI also reads `log.include_queries`)
+    pub fn set_log_queries(&mut self, on: bool) {
+        self.log_queries = on;
+    }
+
+    /// The key's bucket refilled up to now; a key not used yet has a full one
+    fn bucket(&self, idx: usize, limit: RateLimit) -> TokenBucket {
+        let now = Instant::now();
+        match self.buckets.get(&idx) {
+            Some(b) => {
+                let refill = now.duration_since(b.updated).as_secs_f64() * limit.per_sec();
+                TokenBucket { tokens: (b.tokens + refill).min(limit.burst()), updated: now }
+            }
+            None => TokenBucket { tokens: limit.burst(), updated: now },
+        }
+    }
+
+    /// How long until the key has a token (zero without a rate limit)
+    fn token_wait(&self, idx: usize) -> std::time::Duration {
+        let Some(limit) = self.rate_limit else { return std::time::Duration::ZERO };
+        let missing = 1.0 - self.bucket(idx, limit).tokens;
+        std::time::Duration::from_secs_f64((missing / limit.per_sec()).max(0.0))
+    }
+
+    fn take_token(&mut self, idx: usize) {
+        let Some(limit) = self.rate_limit else { return };
+        let mut bucket = self.bucket(idx, limit);
+        bucket.tokens = (bucket.tokens - 1.0).max(0.0);
+        self.buckets.insert(idx, bucket);
     }
 
     /// Mark a key as rate limited with cooldown
@@ -295,47 +1096,139 @@ impl KeyManager {
         let cooldown_secs = retry_after.unwrap_or(DEFAULT_COOLDOWN_SECS as u64) as i64;
         let cooldown_until = Utc::now() + Duration::seconds(cooldown_secs);
 
-        let info = self.state.keys.entry(key_idx).or_insert_with(KeyInfo::default);
+        let info = self.state.keys.entry(key_idx).or_default();
         info.cooldown_until = Some(cooldown_until);
         info.usage.errors += 1;
 
-        if self.verbose {
-            eprintln!(
-                "{} Key {} rate limited, cooldown {}s",
-                "Warning:".yellow(),
-                mask_key(&self.keys[key_idx]),
-                cooldown_secs
-            );
-        }
+        warnings::emit(
+            "key_rate_limited",
+            format!("Key {} rate limited, cooldown {}s", mask_key(&self.keys[key_idx]), cooldown_secs),
+            self.verbose,
+        );
+    }
+
+    /// Every key's availability at `now`, and the earliest time one is usable
+    pub fn cooldowns(&self, now: DateTime<Utc>) -> (Vec<KeyCooldown>, Option<DateTime<Utc>>) {
+        let keys: Vec<KeyCooldown> = (0..self.keys.len())
+            .map(|index| {
+                let info = self.state.keys.get(&index).cloned().unwrap_or_default();
+                let until = info.cooldown_until.filter(|&until| until > now);
+                let state = if !info.valid {
+                    "invalid"
+                } else if info.over_budget(now).is_some() {
+                    "over_budget"
+                } else if until.is_some() {
+                    "cooldown"
+                } else {
+                    "ready"
+                };
+                KeyCooldown { index, key: mask_key(&self.keys[index]), state, until }
+            })
+            .collect();
+        let retry_at = keys
+            .iter()
+            .filter_map(|k| match k.state {
+                "ready" => Some(now),
+                "cooldown" => k.until,
+                _ => None,
+            })
+            .min();
+        (keys, retry_at)
+    }
+
+    /// The error for a request that was still rate limited after its last retry
+    pub fn rate_limited(&self, message: String) -> RateLimited {
+        let (keys, retry_at) = self.cooldowns(Utc::now());
+        RateLimited { message, retry_at, keys }
     }
 
     /// Record a successful request
     pub fn record_success(&mut self, key_idx: usize) {
-        let info = self.state.keys.entry(key_idx).or_insert_with(KeyInfo::default);
+        let info = self.state.keys.entry(key_idx).or_default();
         info.usage.requests += 1;
         info.usage.success += 1;
+        info.spend.roll(Utc::now());
+        info.spend.day_requests += 1;
+        info.spend.month_requests += 1;
         // Clear cooldown on success
         info.cooldown_until = None;
     }
 
+    /// Record a request that failed after reaching the API. It may still have
+    /// been billed, so it counts against the key's request budgets.
+    pub fn record_failure(&mut self, key_idx: usize) {
+        let info = self.state.keys.entry(key_idx).or_default();
+        info.usage.requests += 1;
+        info.usage.errors += 1;
+        info.spend.roll(Utc::now());
+        info.spend.day_requests += 1;
+        info.spend.month_requests += 1;
+    }
+
+    /// Count a finished research task's cost once, however many times its
+    /// status is fetched
+    pub fn record_task_cost(&mut self, key_idx: usize, task_id: &str, dollars: f64) {
+        if self.state.billed_tasks.iter().any(|t| t == task_id) {
+            return;
+        }
+        self.state.billed_tasks.push(task_id.to_string());
+        let excess = self.state.billed_tasks.len().saturating_sub(MAX_BILLED_TASKS);
+        self.state.billed_tasks.drain(..excess);
+        self.record_cost(key_idx, dollars);
+    }
+
+    /// Count what a request cost against the key's budgets
+    pub fn record_cost(&mut self, key_idx: usize, dollars: f64) {
+        let info = self.state.keys.entry(key_idx).or_default();
+        info.spend.roll(Utc::now());
+        info.spend.day_dollars += dollars;
+        info.spend.month_dollars += dollars;
+    }
+
+    /// Replace the budgets of the key at `idx`
+    pub fn set_budget(&mut self, idx: usize, budget: KeyBudget) -> Result<()> {
+        if idx >= self.keys.len() {
+            bail!("No key {} (the pool has {})", idx, self.keys.len());
+        }
+        self.state.keys.entry(idx).or_default().budget = budget;
+        self.save_state()
+    }
+
     /// Mark a key as invalid
     pub fn mark_invalid(&mut self, key_idx: usize) {
-        let info = self.state.keys.entry(key_idx).or_insert_with(KeyInfo::default);
+        let info = self.state.keys.entry(key_idx).or_default();
         info.valid = false;
 
-        eprintln!(
-            "{} Key {} is invalid and will be skipped",
-            "Warning:".yellow(),
-            mask_key(&self.keys[key_idx])
+        warnings::emit(
+            "key_invalid",
+            format!("Key {} is invalid and will be skipped", mask_key(&self.keys[key_idx])),
+            true,
         );
     }
 
-    /// Log a request if logging is enabled
-    pub fn log_request(&self, key_idx: usize, cmd: &str, status: u16) -> Result<()> {
+    /// Log a request if logging is enabled. `body_sha256` is the hash of the
+    /// request body; `query` is logged as a keyed hash, and as text only when
+    /// [`set_log_queries`](Self::set_log_queries) allows it. With an audit key (feature `audit`), entries are
+    /// hash-chained and signed; see [`crate::audit`].
+    pub fn log_request(
+        &self,
+        key_idx: usize,
+        cmd: &str,
+        status: u16,
+        request_id: Option<&str>,
+        body_sha256: Option<&str>,
+        query: Option<&str>,
+    ) -> Result<()> {
         if !self.log_enabled {
             return Ok(());
         }
 
+        // Signed entries chain to the previous line, so writers take turns
+        #[cfg(feature = "audit")]
+        let signer = crate::audit::AuditKey::load(&self.config_dir)?;
+        #[cfg(feature = "audit")]
+        let _lock = signer.as_ref().map(|_| self.lock_state(true)).transpose()?;
+
         let log_path = self.log_file_path();
 
         // Check for rotation
@@ -353,6 +1246,27 @@ impl KeyManager {
             key: mask_key(&self.keys[key_idx]),
             cmd: cmd.to_string(),
             status,
+            request_id: request_id.map(|id| id.to_string()),
+            client: self.client_tag.clone(),
+            body_sha256: body_sha256.map(|h| h.to_string()),
+            query_hmac: query.map(|q| query_hmac(&self.config_dir, q)).transpose()?,
+            query: query.filter(|_| self.log_queries).map(|q| q.to_string()),
+            prev: None,
+        };
+        #[cfg(feature = "audit")]
+        let entry = LogEntry {
+            prev: signer.as_ref().map(|_| {
+                let last = crate::audit::last_line(&log_path)
+                    .or_else(|| crate::audit::last_line(&self.config_dir.join("requests.log.1")));
+                last.map(|l| crate::audit::line_hash(&l)).unwrap_or_default()
+            }),
+            ..entry
+        };
+        let line = serde_json::to_string(&entry)?;
+        #[cfg(feature = "audit")]
+        let line = match &signer {
+            Some(signer) => signer.sign_line(&line),
+            None => line,
         };
 
         let file = OpenOptions::new()
@@ -362,68 +1276,56 @@ impl KeyManager {
             .context("Failed to open log file")?;
 
         let mut writer = BufWriter::new(file);
-        serde_json::to_writer(&mut writer, &entry)?;
-        writeln!(writer)?;
+        writeln!(writer, "{}", line)?;
         writer.flush()?;
 
         Ok(())
     }
 
     /// Validate all keys if state is stale
-    pub async fn validate_keys_if_stale(&mut self, client: &reqwest::Client) -> Result<()> {
+    pub async fn validate_keys_if_stale(&mut self, client: &reqwest::Client, base_url: &str) -> Result<()> {
         if !self.is_state_stale() {
             return Ok(());
         }
 
         if self.verbose {
-            eprintln!("Validating API keys (state is stale)...");
+            diag!("Validating API keys (state is stale)...");
         }
 
-        let mut invalid_indices = Vec::new();
-
-        for (idx, key) in self.keys.iter().enumerate() {
-            let resp = client
-                .post("https://api.exa.ai/search")
-                .header("x-api-key", key)
-                .header("Content-Type", "application/json")
-                .json(&serde_json::json!({
-                    "query": "test",
-                    "numResults": 1
-                }))
-                .send()
-                .await;
-
-            match resp {
-                Ok(r) => {
-                    let status = r.status();
-                    if status.as_u16() == 401 || status.as_u16() == 403 {
-                        invalid_indices.push(idx);
-                    } else if self.verbose {
-                        eprintln!("Key {} is valid", mask_key(key));
-                    }
-                }
-                Err(e) => {
-                    if self.verbose {
-                        eprintln!(
-                            "{} Failed to validate key {}: {}",
-                            "Warning:".yellow(),
-                            mask_key(key),
-                            e
-                        );
-                    }
+        let results = self.validate_keys(client, base_url).await?;
+        if self.verbose {
+            for (key, result) in self.keys.iter().zip(&results) {
+                match result {
+                    Ok(true) => diag!("Key {} is valid", mask_key(key)),
+                    Ok(false) => {}
+                    Err(e) => diag!("{} Failed to validate key {}: {}", "Warning:".yellow(), mask_key(key), e),
                 }
             }
         }
 
-        // Mark invalid keys after the iteration
-        for idx in invalid_indices {
-            self.mark_invalid(idx);
+        Ok(())
+    }
+
+    /// Check every key against the API at `base_url` now and mark it valid or invalid.
+    /// Keys that couldn't be checked keep their state. One result per key.
+    pub async fn validate_keys(&mut self, client: &reqwest::Client, base_url: &str) -> Result<Vec<Result<bool>>> {
+        let mut results = Vec::new();
+        for key in &self.keys {
+            results.push(check_key(client, base_url, key).await);
+        }
+
+        for (idx, result) in results.iter().enumerate() {
+            match result {
+                Ok(true) => self.state.keys.entry(idx).or_default().valid = true,
+                Ok(false) => self.mark_invalid(idx),
+                Err(_) => {}
+            }
         }
 
         self.state.last_validated = Utc::now();
         self.save_state()?;
 
-        Ok(())
+        Ok(results)
     }
 
     /// Reset all cooldowns and usage statistics
@@ -436,7 +1338,7 @@ impl KeyManager {
         self.save_state()?;
 
         if self.verbose {
-            eprintln!("Reset all cooldowns and usage statistics");
+            diag!("Reset all cooldowns and usage statistics");
         }
 
         Ok(())
@@ -454,6 +1356,13 @@ impl KeyManager {
             "Next Key Index".bold(),
             self.state.current_index % self.keys.len()
         );
+        if self.strategy == KeyStrategy::Weighted {
+            let weights: Vec<String> =
+                (0..self.keys.len()).map(|i| format!("{}={}", mask_key(&self.keys[i]), self.weight(i))).collect();
+            println!("{}: weighted ({})", "Key Strategy".bold(), weights.join(", "));
+        } else {
+            println!("{}: {}", "Key Strategy".bold(), self.strategy);
+        }
         println!(
             "{}: {}",
             "Last Validated".bold(),
@@ -495,13 +1404,18 @@ impl KeyManager {
                 "  Requests: {} | Success: {} | Errors: {}",
                 info.usage.requests, info.usage.success, info.usage.errors
             );
+            let remaining = info.remaining_budget(now);
+            if !remaining.is_empty() {
+                let over = info.over_budget(now).map(|b| format!(" ({} used up)", b).red().to_string());
+                println!("  Budget left: {}{}", remaining.join(" | "), over.unwrap_or_default());
+            }
         }
 
         println!();
         println!(
             "{}: {}",
             "Logging".bold(),
-            if env::var("EXA_LOG_REQUESTS").map(|v| v == "1").unwrap_or(false) {
+            if vars::get("EXA_LOG_REQUESTS").is_some_and(|v| v == "1") {
                 "Enabled".green()
             } else {
                 "Disabled".dimmed()
@@ -523,6 +1437,14 @@ impl KeyManager {
 mod tests {
     use super::*;
 
+    /// The index of the key chosen next, which must not need a wait
+    fn next_idx(km: &mut KeyManager) -> usize {
+        match km.get_next_key().unwrap() {
+            NextKey::Ready(idx, _) => idx,
+            NextKey::Wait(wait) => panic!("waits {:?}", wait),
+        }
+    }
+
     #[test]
     fn test_mask_key() {
         assert_eq!(mask_key("abc123def"), "...def");
@@ -530,4 +1452,220 @@ mod tests {
         assert_eq!(mask_key(""), "***");
         assert_eq!(mask_key("abcdefghijklmnop"), "...nop");
     }
+
+    #[test]
+    fn test_add_remove_key() {
+        let mut km = KeyManager::from_keys(vec!["key-a".to_string(), "key-b".to_string(), "key-c".to_string()]);
+        km.config_dir = env::temp_dir().join(format!("exa-test-keys-{}", std::process::id()));
+        fs::create_dir_all(&km.config_dir).unwrap();
+        km.state.keys.get_mut(&2).unwrap().usage.requests = 7;
+        km.state.current_index = 2;
+
+        assert_eq!(km.remove_key(1).unwrap(), "key-b");
+        // State follows its key down an index
+        assert_eq!(km.key_info(1).usage.requests, 7);
+        assert_eq!(km.state.current_index, 1);
+        assert!(km.remove_key(5).is_err());
+
+        assert_eq!(km.add_key(" key-d ").unwrap(), 2);
+        assert_eq!(km.get_key_by_index(2).as_deref(), Some("key-d"));
+        assert!(km.add_key("key-a").is_err());
+        let _ = fs::remove_dir_all(&km.config_dir);
+    }
+
+    #[test]
+    fn test_merge_state() {
+        let now = Utc::now();
+        let mut saved = KeyState::default();
+        saved.keys.entry(0).or_default().usage.requests = 10;
+        // Another run added 5 requests and rate-limited key 1 meanwhile
+        let mut disk = saved.clone();
+        disk.keys.get_mut(&0).unwrap().usage.requests = 15;
+        disk.keys.entry(1).or_default().cooldown_until = Some(now);
+        // We added 3 requests and some spend, and invalidated key 0
+        let mut ours = saved.clone();
+        let mine = ours.keys.get_mut(&0).unwrap();
+        mine.usage.requests = 13;
+        mine.valid = false;
+        mine.spend.roll(now);
+        mine.spend.day_dollars = 0.25;
+
+        let merged = merge_state(disk, &saved, &ours, now);
+        assert_eq!(merged.keys[&0].usage.requests, 18);
+        assert!(!merged.keys[&0].valid);
+        assert!((merged.keys[&0].spend.day_dollars - 0.25).abs() < 1e-9);
+        assert_eq!(merged.keys[&1].cooldown_until, Some(now));
+    }
+
+    #[test]
+    fn test_save_state_merges() {
+        let dir = env::temp_dir().join(format!("exa-test-state-{}", std::process::id()));
+        let open = || {
+            let mut km = KeyManager::from_keys(vec!["key-a".to_string()]);
+            km.config_dir = dir.clone();
+            km.load_state().unwrap();
+            km
+        };
+        let (mut a, mut b) = (open(), open());
+        a.record_success(0);
+        b.record_success(0);
+        b.record_success(0);
+        a.save_state().unwrap();
+        b.save_state().unwrap();
+        assert_eq!(b.key_info(0).usage.requests, 3);
+        assert_eq!(open().key_info(0).usage.requests, 3);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_state_backup() {
+        let dir = env::temp_dir().join(format!("exa-test-state-bak-{}", std::process::id()));
+        let mut km = KeyManager::from_keys(vec!["key-a".to_string()]);
+        km.config_dir = dir.clone();
+        km.record_success(0);
+        km.save_state().unwrap();
+        km.record_success(0);
+        km.save_state().unwrap();
+        assert!(dir.join("state.json.bak").exists());
+        assert!(fs::read_dir(&dir).unwrap().all(|e| !e.unwrap().file_name().to_string_lossy().ends_with(".tmp")));
+
+        // A torn write falls back to the previous generation
+        fs::write(dir.join("state.json"), "{\"version\": 1, \"keys\": {").unwrap();
+        let mut reopened = KeyManager::from_keys(vec!["key-a".to_string()]);
+        reopened.config_dir = dir.clone();
+        reopened.load_state().unwrap();
+        assert_eq!(reopened.key_info(0).usage.requests, 1);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        assert_eq!("5/s".parse::<RateLimit>().unwrap(), RateLimit { requests: 5, per_secs: 1 });
+        assert_eq!("120/m".parse::<RateLimit>().unwrap().to_string(), "120/m");
+        assert!("0/s".parse::<RateLimit>().is_err());
+        assert!("5/d".parse::<RateLimit>().is_err());
+
+        let mut km = KeyManager::from_keys(vec!["key-a".to_string(), "key-b".to_string()]);
+        km.set_rate_limit(Some("1/h".parse().unwrap()));
+        assert_eq!(next_idx(&mut km), 0);
+        // Round-robin would pick key 0 again, but its token is spent
+        km.state.current_index = 0;
+        assert_eq!(next_idx(&mut km), 1);
+        assert!(km.token_wait(0) > std::time::Duration::from_secs(3500));
+
+        // Out of tokens everywhere: wait for the first one
+        let mut km = KeyManager::from_keys(vec!["key-a".to_string()]);
+        km.set_rate_limit(Some("20/s".parse().unwrap()));
+        for _ in 0..20 {
+            next_idx(&mut km);
+        }
+        let NextKey::Wait(wait) = km.get_next_key().unwrap() else { panic!("no wait") };
+        assert!(wait > std::time::Duration::from_millis(40) && wait <= std::time::Duration::from_millis(50));
+        std::thread::sleep(wait + std::time::Duration::from_millis(5));
+        assert_eq!(next_idx(&mut km), 0);
+
+        // All keys on cooldown: wait for the first to come off it
+        let mut km = KeyManager::from_keys(vec!["key-a".to_string(), "key-b".to_string()]);
+        km.mark_rate_limited(0, Some(30));
+        km.mark_rate_limited(1, Some(10));
+        let NextKey::Wait(wait) = km.get_next_key().unwrap() else { panic!("no wait") };
+        assert!(wait > std::time::Duration::from_secs(9) && wait <= std::time::Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_cooldowns() {
+        let mut km = KeyManager::from_keys(vec!["key-a".into(), "key-b".into(), "key-c".into()]);
+        let now = Utc::now();
+        km.state.keys.entry(0).or_default().cooldown_until = Some(now + Duration::seconds(30));
+        km.state.keys.entry(1).or_default().cooldown_until = Some(now + Duration::seconds(10));
+        km.state.keys.entry(2).or_default().valid = false;
+        let (keys, retry_at) = km.cooldowns(now);
+        let states: Vec<&str> = keys.iter().map(|k| k.state).collect();
+        assert_eq!(states, ["cooldown", "cooldown", "invalid"]);
+        assert_eq!(retry_at, Some(now + Duration::seconds(10)));
+
+        km.state.keys.entry(1).or_default().cooldown_until = Some(now - Duration::seconds(1));
+        assert_eq!(km.cooldowns(now).1, Some(now));
+        km.state.keys.entry(0).or_default().valid = false;
+        km.state.keys.entry(1).or_default().valid = false;
+        assert_eq!(km.cooldowns(now).1, None);
+    }
+
+    #[test]
+    fn test_key_strategies() {
+        assert_eq!("least-used".parse::<KeyStrategy>().unwrap(), KeyStrategy::LeastUsed);
+        assert!("random".parse::<KeyStrategy>().is_err());
+        let keys = || vec!["key-a".to_string(), "key-b".to_string(), "key-c".to_string()];
+        let picks = |km: &mut KeyManager, n: usize| -> Vec<usize> {
+            (0..n)
+                .map(|_| {
+                    let idx = next_idx(km);
+                    km.record_success(idx);
+                    idx
+                })
+                .collect()
+        };
+
+        let mut km = KeyManager::from_keys(keys());
+        let weights = ["...y-a=8", "b=2", "c=0"].iter().map(|w| w.parse().unwrap()).collect();
+        km.set_strategy(KeyStrategy::Weighted, weights).unwrap();
+        // Lifetime usage doesn't count against a key's share, only today's
+        km.state.keys.get_mut(&0).unwrap().usage.requests = 1000;
+        let used = picks(&mut km, 50);
+        assert_eq!((0..3).map(|i| used.iter().filter(|&&u| u == i).count()).collect::<Vec<_>>(), [40, 10, 0]);
+        let mut ambiguous = KeyManager::from_keys(vec!["ab-x".to_string(), "cd-x".to_string()]);
+        assert!(ambiguous.set_strategy(KeyStrategy::Weighted, vec!["x=1".parse().unwrap()]).is_err());
+        assert!("=5".parse::<KeyWeight>().is_err() && "abc".parse::<KeyWeight>().is_err());
+
+        let mut km = KeyManager::from_keys(keys());
+        km.set_strategy(KeyStrategy::Sticky, Vec::new()).unwrap();
+        // Sticks to the key before the round-robin position
+        assert_eq!(picks(&mut km, 3), [2, 2, 2]);
+        km.mark_rate_limited(2, Some(60));
+        assert_eq!(picks(&mut km, 2), [0, 0]);
+
+        let mut km = KeyManager::from_keys(keys());
+        km.state.keys.get_mut(&0).unwrap().usage.requests = 5;
+        km.set_strategy(KeyStrategy::RoundRobin, Vec::new()).unwrap();
+        assert_eq!(picks(&mut km, 4), [0, 1, 2, 0]);
+        km.set_strategy(KeyStrategy::LeastUsed, Vec::new()).unwrap();
+        assert_eq!(picks(&mut km, 3), [1, 2, 1]);
+    }
+
+    #[test]
+    fn test_budgets() {
+        let mut km = KeyManager::from_keys(vec!["key-a".to_string(), "key-b".to_string()]);
+        km.state.keys.get_mut(&0).unwrap().budget = KeyBudget { daily_requests: Some(1), ..Default::default() };
+        km.state.keys.get_mut(&1).unwrap().budget = KeyBudget { monthly_dollars: Some(0.01), ..Default::default() };
+
+        assert_eq!(next_idx(&mut km), 0);
+        km.record_success(0);
+        assert_eq!(km.key_info(0).over_budget(Utc::now()), Some("daily requests"));
+        assert_eq!(next_idx(&mut km), 1);
+        km.record_success(1);
+        km.record_cost(1, 0.01);
+        assert!(km.get_next_key().is_err());
+
+        // A new day resets the daily counters but not the monthly ones
+        let tomorrow = Utc::now() + Duration::days(1);
+        assert_eq!(km.key_info(0).over_budget(tomorrow), None);
+        assert_eq!(km.key_info(0).remaining_budget(tomorrow), ["1/1 requests today"]);
+        let next_month = Utc::now() + Duration::days(32);
+        assert_eq!(km.key_info(1).over_budget(next_month), None);
+
+        // Failed requests count; a research task's cost counts once
+        let mut km = KeyManager::from_keys(vec!["key-a".to_string()]);
+        km.record_failure(0);
+        assert_eq!(km.key_info(0).spend.day_requests, 1);
+        km.record_task_cost(0, "r_1", 0.5);
+        km.record_task_cost(0, "r_1", 0.5);
+        assert_eq!(km.key_info(0).spend.day_dollars, 0.5);
+    }
+
+    #[test]
+    fn test_parse_keys() {
+        assert_eq!(parse_keys(" k1, k2 ,,"), vec!["k1", "k2"]);
+        assert_eq!(parse_keys("k1\nk2\n\n"), vec!["k1", "k2"]);
+        assert!(parse_keys("\n").is_empty());
+    }
 }
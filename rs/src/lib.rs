@@ -1 +1,52 @@
+//! Client for the [Exa](https://exa.ai) search API, as used by the `exa` CLI.
+//!
+//! [`ExaClient`] rotates requests across several API keys (read from
+//! `EXA_API_KEYS` / `EXA_API_KEY` by [`KeyManager`]), backs off on 429s and
+//! can record and replay responses. [`ResponseCache`] is the CLI's response
+//! cache, on disk or in Redis. [`blocking::Client`] wraps both for callers
+//! without an async runtime; the C API (feature `ffi`) and the Python module
+//! (feature `python`) are built on it.
+//!
+//! ```no_run
+//! use exa_cli::api::SearchRequest;
+//! use exa_cli::{ExaClient, KeyManager};
+//!
+//! # async fn run() -> anyhow::Result<()> {
+//! let mut client = ExaClient::new(KeyManager::new(false)?, reqwest::Client::new());
+//! let request = SearchRequest::builder().query("rust async runtimes").num(5).build()?;
+//! let results = client.search(request).await?;
+//! for r in &results.results {
+//!     println!("{}", r.url);
+//! }
+//! client.key_manager.save_state()?;
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod api;
+pub mod api_version;
+#[cfg(feature = "audit")]
+pub mod audit;
+pub mod blocking;
+pub mod cache;
+pub mod client;
+pub mod compression;
+pub mod diag;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod fingerprint;
 pub mod key_manager;
+pub mod model;
+pub mod polite;
+#[cfg(feature = "python")]
+mod python;
+pub mod redis_cache;
+pub mod sse;
+pub mod transport;
+pub mod vars;
+pub mod warnings;
+pub mod websets;
+
+pub use cache::ResponseCache;
+pub use client::ExaClient;
+pub use key_manager::KeyManager;
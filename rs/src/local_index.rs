@@ -0,0 +1,177 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tantivy::collector::TopDocs;
+use tantivy::directory::MmapDirectory;
+use tantivy::query::QueryParser;
+use tantivy::schema::{Field, Schema, Value, STORED, STRING, TEXT};
+use tantivy::snippet::SnippetGenerator;
+use tantivy::{doc, Index, IndexWriter, TantivyDocument, Term};
+
+use crate::archive;
+
+const WRITER_MEMORY: usize = 50_000_000;
+
+/// A document matched by a local search
+#[derive(Serialize)]
+pub struct LocalHit {
+    pub score: f32,
+    pub id: String,
+    pub url: String,
+    pub title: String,
+    pub snippet: String,
+}
+
+/// A page to add to the index
+pub struct Doc {
+    /// [`content_hash`](exa_cli::fingerprint::content_hash) of the text, as in the archive
+    pub id: String,
+    pub url: String,
+    pub title: String,
+    pub text: String,
+}
+
+/// Full-text index over previously fetched content, stored in the data dir
+pub struct LocalIndex {
+    index: Index,
+    id: Field,
+    url: Field,
+    title: Field,
+    text: Field,
+}
+
+impl LocalIndex {
+    /// Open the index at ~/.local/share/exa/index, creating it on first use
+    pub fn open() -> Result<Self> {
+        Self::open_at(&Self::index_dir()?)
+    }
+
+    fn open_at(dir: &Path) -> Result<Self> {
+        let mut builder = Schema::builder();
+        let id = builder.add_text_field("id", STRING | STORED);
+        let url = builder.add_text_field("url", STRING | STORED);
+        let title = builder.add_text_field("title", TEXT | STORED);
+        let text = builder.add_text_field("text", TEXT | STORED);
+        let schema = builder.build();
+
+        let directory = MmapDirectory::open(dir).context("Failed to open local index directory")?;
+        let index = Index::open_or_create(directory, schema).context("Failed to open local index")?;
+        Ok(Self { index, id, url, title, text })
+    }
+
+    fn index_dir() -> Result<PathBuf> {
+        let dir = dirs::data_dir()
+            .context("Could not find data directory")?
+            .join("exa")
+            .join("index");
+        fs::create_dir_all(&dir).context("Failed to create index directory")?;
+        Ok(dir)
+    }
+
+    /// Index archived pages that aren't in the index yet. Returns how many were added.
+    pub fn sync_archive(&self) -> Result<usize> {
+        let searcher = self.index.reader()?.searcher();
+        let mut writer: IndexWriter = self.index.writer(WRITER_MEMORY)?;
+        let mut added = 0;
+
+        for (meta, _) in archive::list()? {
+            if searcher.doc_freq(&Term::from_field_text(self.id, &meta.hash))? > 0 {
+                continue;
+            }
+            let (meta, text) = archive::show(&meta.hash)?;
+            writer.add_document(doc!(
+                self.id => meta.hash,
+                self.url => meta.url,
+                self.title => meta.title.unwrap_or_default(),
+                self.text => text,
+            ))?;
+            added += 1;
+        }
+
+        if added > 0 {
+            writer.commit()?;
+        }
+        Ok(added)
+    }
+
+    /// Add pages whose text isn't in the index yet. Returns how many were added.
+    pub fn add(&self, docs: Vec<Doc>) -> Result<usize> {
+        let searcher = self.index.reader()?.searcher();
+        let mut writer: IndexWriter = self.index.writer(WRITER_MEMORY)?;
+        let mut seen = std::collections::HashSet::new();
+        let mut added = 0;
+
+        for d in docs {
+            if !seen.insert(d.id.clone()) || searcher.doc_freq(&Term::from_field_text(self.id, &d.id))? > 0 {
+                continue;
+            }
+            writer.add_document(doc!(self.id => d.id, self.url => d.url, self.title => d.title, self.text => d.text))?;
+            added += 1;
+        }
+
+        if added > 0 {
+            writer.commit()?;
+        }
+        Ok(added)
+    }
+
+    /// Ranked search over titles and text
+    pub fn search(&self, query: &str, limit: usize) -> Result<Vec<LocalHit>> {
+        let searcher = self.index.reader()?.searcher();
+        let mut parser = QueryParser::for_index(&self.index, vec![self.title, self.text]);
+        parser.set_conjunction_by_default();
+        let (query, _errors) = parser.parse_query_lenient(query);
+
+        let snippets = SnippetGenerator::create(&searcher, &*query, self.text)?;
+        let top = searcher.search(&query, &TopDocs::with_limit(limit))?;
+
+        let mut hits = Vec::new();
+        for (score, address) in top {
+            let doc: TantivyDocument = searcher.doc(address)?;
+            let get = |field: Field| {
+                doc.get_first(field)
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("")
+                    .to_string()
+            };
+            let snippet = snippets.snippet_from_doc(&doc);
+            hits.push(LocalHit {
+                score,
+                id: get(self.id),
+                url: get(self.url),
+                title: get(self.title),
+                snippet: snippet.fragment().split_whitespace().collect::<Vec<_>>().join(" "),
+            });
+        }
+        Ok(hits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_and_search() {
+        let dir = std::env::temp_dir().join(format!("exa-index-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let index = LocalIndex::open_at(&dir).unwrap();
+        let doc = |id: &str, text: &str| Doc {
+            id: id.into(),
+            url: format!("https://{}.com", id),
+            title: format!("Page {}", id),
+            text: text.into(),
+        };
+        let docs = || vec![doc("a", "The borrow checker enforces ownership"), doc("b", "Tokio is an async runtime")];
+        assert_eq!(index.add(docs()).unwrap(), 2);
+        // Already indexed, by content hash
+        assert_eq!(index.add(docs()).unwrap(), 0);
+
+        let hits = index.search("borrow checker", 10).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].url, "https://a.com");
+        assert!(hits[0].snippet.contains("borrow checker"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
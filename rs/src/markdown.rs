@@ -0,0 +1,136 @@
+//! Markdown documents for --markdown: a numbered list of linked results with
+//! blockquoted excerpts, or an answer/report followed by its sources
+
+use exa_cli::model::Page;
+
+/// A cited source: url, title and published date
+pub type Source<'a> = (&'a str, Option<&'a str>, Option<&'a str>);
+
+/// Link text with the characters that would end or break the link escaped
+fn escape_text(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.split_whitespace().collect::<Vec<_>>().join(" ").chars() {
+        if matches!(c, '\\' | '[' | ']' | '*' | '_' | '`' | '<' | '>') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// A link destination that survives spaces and parentheses in the URL
+fn escape_url(url: &str) -> String {
+    url.replace(' ', "%20").replace('(', "%28").replace(')', "%29")
+}
+
+/// `[title](url) — date`, or the bare URL without a title
+fn link(url: &str, title: Option<&str>, date: Option<&str>) -> String {
+    let mut out = match title.map(str::trim).filter(|t| !t.is_empty()) {
+        Some(title) => format!("[{}]({})", escape_text(title), escape_url(url)),
+        None => format!("<{}>", escape_url(url)),
+    };
+    if let Some(date) = date {
+        out.push_str(&format!(" — {}", date.get(..10).unwrap_or(date)));
+    }
+    out
+}
+
+/// A line of page text that stays text: a leading `#` can't start a
+/// heading, and brackets can't form a link or image
+fn escape_line(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let body = line.trim_start();
+    out.push_str(&line[..line.len() - body.len()]);
+    if body.starts_with('#') {
+        out.push('\\');
+    }
+    for c in body.chars() {
+        if matches!(c, '\\' | '[' | ']') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Every line of `text` quoted (and escaped), indented to sit inside a list item
+fn blockquote(text: &str, indent: &str) -> String {
+    text.trim()
+        .lines()
+        .map(|line| format!("{}> {}", indent, escape_line(line.trim_end())).trim_end().to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn sources_section(out: &mut String, sources: &[Source]) {
+    if sources.is_empty() {
+        return;
+    }
+    out.push_str("\n## Sources\n\n");
+    for (i, (url, title, date)) in sources.iter().enumerate() {
+        out.push_str(&format!("{}. {}\n", i + 1, link(url, *title, *date)));
+    }
+}
+
+/// Search/find results under `heading`. `excerpt` gives each result's quoted
+/// text (highlights, or the text cut to size), if any.
+pub fn results(heading: &str, results: &[Page], excerpt: impl Fn(&Page) -> Option<String>) -> String {
+    let mut out = format!("# {}\n\n", escape_text(heading));
+    for (i, r) in results.iter().enumerate() {
+        let marker = format!("{}. ", i + 1);
+        let indent = " ".repeat(marker.len());
+        out.push_str(&format!("{}{}\n", marker, link(&r.url, r.title.as_deref(), r.published.as_deref())));
+        if let Some(text) = excerpt(r).filter(|t| !t.trim().is_empty()) {
+            out.push('\n');
+            out.push_str(&blockquote(&text, &indent));
+            out.push('\n');
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// An answer or research report under `heading`, then its sources
+pub fn document(heading: &str, body: &str, sources: &[Source]) -> String {
+    let mut out = format!("# {}\n\n{}\n", escape_text(heading), body.trim());
+    sources_section(&mut out, sources);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_markdown() {
+        let result = |url: &str, title: Option<&str>, text: Option<&str>| {
+            let date = "2024-03-01T00:00:00Z";
+            Page {
+                url: url.to_string(),
+                title: title.map(String::from),
+                published: Some(date.to_string()),
+                text: text.map(String::from),
+                ..Page::default()
+            }
+        };
+        let list = [
+            result("https://a.com/x_(y)", Some("Rust [stable] *now*"), Some("First line.\nSecond line.")),
+            result("https://b.com", None, None),
+            result("https://c.com", Some("C"), Some("# Injected\n  ## [click](https://evil.com) C:\\dir")),
+        ];
+        let out = results("rust news", &list, |r| r.text.clone());
+        assert_eq!(
+            out,
+            "# rust news\n\n\
+             1. [Rust \\[stable\\] \\*now\\*](https://a.com/x_%28y%29) — 2024-03-01\n\n\
+             \x20  > First line.\n   > Second line.\n\n\
+             2. <https://b.com> — 2024-03-01\n\n\
+             3. [C](https://c.com) — 2024-03-01\n\n\
+             \x20  > \\# Injected\n   >   \\## \\[click\\](https://evil.com) C:\\\\dir\n\n"
+        );
+
+        let doc = document("what is rust?", "A language.\n", &[("https://a.com", Some("A"), None)]);
+        assert_eq!(doc, "# what is rust?\n\nA language.\n\n## Sources\n\n1. [A](https://a.com)\n");
+        assert_eq!(document("q", "text", &[]), "# q\n\ntext\n");
+    }
+}
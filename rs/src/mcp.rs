@@ -0,0 +1,135 @@
+use crate::tools::ToolSpec;
+use serde_json::{json, Map, Value};
+
+/// Protocol revisions this server speaks, newest first. A client asking for
+/// another one is offered the newest, and decides whether to go on.
+pub const PROTOCOL_VERSIONS: [&str; 2] = ["2025-03-26", "2024-11-05"];
+
+/// What to do with one incoming JSON-RPC message
+#[derive(Debug, PartialEq)]
+pub enum Action {
+    /// Write this response
+    Reply(Value),
+    /// Run a tool, then answer request `id` with `tool_result`
+    Call { id: Value, name: String, arguments: Map<String, Value> },
+    /// Notifications and responses get no reply
+    Ignore,
+}
+
+/// Handle one line from stdin. Everything but `tools/call` is answered here.
+pub fn dispatch(line: &str, specs: &[ToolSpec]) -> Action {
+    let message: Value = match serde_json::from_str(line) {
+        Ok(v) => v,
+        Err(e) => return Action::Reply(error(Value::Null, -32700, &format!("Parse error: {}", e))),
+    };
+    let method = message.get("method").and_then(|m| m.as_str());
+    let id = match message.get("id") {
+        Some(id) if !id.is_null() => id.clone(),
+        _ => return Action::Ignore,
+    };
+    let Some(method) = method else {
+        return Action::Ignore;
+    };
+    let params = message.get("params").cloned().unwrap_or_else(|| json!({}));
+
+    match method {
+        "initialize" => {
+            let requested = params["protocolVersion"].as_str();
+            let version = PROTOCOL_VERSIONS.into_iter().find(|v| Some(*v) == requested).unwrap_or(PROTOCOL_VERSIONS[0]);
+            Action::Reply(result(
+                id,
+                json!({
+                    "protocolVersion": version,
+                    "capabilities": { "tools": {} },
+                    "serverInfo": { "name": "exa", "version": env!("CARGO_PKG_VERSION") },
+                }),
+            ))
+        }
+        "ping" => Action::Reply(result(id, json!({}))),
+        "tools/list" => Action::Reply(result(id, json!({ "tools": tool_list(specs) }))),
+        "tools/call" => {
+            let Some(name) = params["name"].as_str() else {
+                return Action::Reply(error(id, -32602, "tools/call is missing 'name'"));
+            };
+            if !specs.iter().any(|t| t.name == name) {
+                return Action::Reply(error(id, -32602, &format!("Unknown tool: {}", name)));
+            }
+            let arguments = match &params["arguments"] {
+                Value::Object(args) => args.clone(),
+                Value::Null => Map::new(),
+                _ => return Action::Reply(error(id, -32602, "Tool arguments must be an object")),
+            };
+            Action::Call { id, name: name.to_string(), arguments }
+        }
+        other => Action::Reply(error(id, -32601, &format!("Method not found: {}", other))),
+    }
+}
+
+fn tool_list(specs: &[ToolSpec]) -> Value {
+    specs
+        .iter()
+        .map(|t| json!({ "name": t.name, "description": t.description, "inputSchema": t.parameters }))
+        .collect()
+}
+
+/// Response to a `tools/call`. Tool failures are results with `isError`, not
+/// protocol errors, so the model sees them.
+pub fn tool_result(id: Value, text: &str, is_error: bool) -> Value {
+    result(id, json!({ "content": [{ "type": "text", "text": text }], "isError": is_error }))
+}
+
+fn result(id: Value, result: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "result": result })
+}
+
+fn error(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::tool_specs;
+
+    fn reply(line: &str) -> Value {
+        match dispatch(line, &tool_specs()) {
+            Action::Reply(v) => v,
+            other => panic!("expected a reply, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_handshake_and_list() {
+        let init = reply(r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{"protocolVersion":"2025-03-26"}}"#);
+        assert_eq!(init["result"]["protocolVersion"], "2025-03-26");
+        let old = reply(r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{"protocolVersion":"2024-11-05"}}"#);
+        assert_eq!(old["result"]["protocolVersion"], "2024-11-05");
+        let unknown = reply(r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{"protocolVersion":"1999-01-01"}}"#);
+        assert_eq!(unknown["result"]["protocolVersion"], PROTOCOL_VERSIONS[0]);
+        assert!(init["result"]["capabilities"]["tools"].is_object());
+        assert_eq!(
+            dispatch(r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#, &tool_specs()),
+            Action::Ignore
+        );
+        let list = reply(r#"{"jsonrpc":"2.0","id":"a","method":"tools/list"}"#);
+        assert_eq!(list["id"], "a");
+        assert_eq!(list["result"]["tools"][0]["name"], "exa_search");
+        assert_eq!(list["result"]["tools"][0]["inputSchema"]["required"], json!(["query"]));
+    }
+
+    #[test]
+    fn test_calls_and_errors() {
+        let call = r#"{"jsonrpc":"2.0","id":7,"method":"tools/call","params":{"name":"exa_answer","arguments":{"question":"why"}}}"#;
+        match dispatch(call, &tool_specs()) {
+            Action::Call { id, name, arguments } => {
+                assert_eq!((id, name.as_str()), (json!(7), "exa_answer"));
+                assert_eq!(arguments["question"], "why");
+            }
+            other => panic!("expected a call, got {:?}", other),
+        }
+        assert_eq!(reply(r#"{"jsonrpc":"2.0","id":2,"method":"tools/call","params":{"name":"nope"}}"#)["error"]["code"], -32602);
+        assert_eq!(reply(r#"{"jsonrpc":"2.0","id":3,"method":"resources/list"}"#)["error"]["code"], -32601);
+        assert_eq!(reply("not json")["error"]["code"], -32700);
+        assert_eq!(tool_result(json!(1), "boom", true)["result"]["isError"], true);
+    }
+}
@@ -0,0 +1,265 @@
+//! What the CLI renders and processes, decoupled from the wire structs in
+//! [`api`](crate::api). The `From` impls are the one place that knows the
+//! API's field names, so a new API version is adapted here. Serialized,
+//! these types give the CLI's `--json` shape.
+
+use serde::{Serialize, Serializer};
+
+use crate::api::{AnswerCitation, AnswerResponse, Citation, CostDollars, Entity, ResearchStatusResponse, SearchResult};
+use crate::fingerprint;
+
+/// A page returned by search, find-similar or contents
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct Page {
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(rename = "publishedDate", skip_serializing_if = "Option::is_none")]
+    pub published: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub highlights: Vec<String>,
+    /// Structured data about a company (with `category: company`)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub entities: Vec<Entity>,
+    /// Representative image of the page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub favicon: Option<String>,
+    /// The domain is on the do-not-ingest list
+    #[serde(rename = "doNotIngest", skip_serializing_if = "std::ops::Not::not")]
+    pub do_not_ingest: bool,
+    /// The text looks paywalled or login-gated
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub paywalled: bool,
+    /// [`fingerprint::content_hash`] of the text
+    #[serde(rename = "contentHash", skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub simhash: Option<String>,
+}
+
+impl Page {
+    /// The first highlight, else the text
+    pub fn excerpt(&self) -> Option<&str> {
+        self.highlights.first().or(self.text.as_ref()).map(String::as_str)
+    }
+}
+
+impl From<&SearchResult> for Page {
+    fn from(r: &SearchResult) -> Self {
+        Self {
+            url: r.url.clone(),
+            title: r.title.clone(),
+            published: r.published_date.clone(),
+            text: r.text.clone(),
+            highlights: r.highlights.clone().unwrap_or_default(),
+            entities: r.entities.clone().unwrap_or_default(),
+            image: r.image.clone(),
+            favicon: r.favicon.clone(),
+            do_not_ingest: r.do_not_ingest,
+            paywalled: r.paywalled,
+            // Kept when already set: the text may have been compressed since
+            content_hash: r.content_hash.clone().or_else(|| r.text.as_deref().map(fingerprint::content_hash)),
+            simhash: r.simhash.clone().or_else(|| r.text.as_deref().map(fingerprint::simhash_hex)),
+        }
+    }
+}
+
+/// A page cited by an answer or a research report
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct Source {
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(rename = "publishedDate", skip_serializing_if = "Option::is_none")]
+    pub published: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+}
+
+impl From<AnswerCitation> for Source {
+    fn from(c: AnswerCitation) -> Self {
+        Self { url: c.url, title: c.title, published: c.published_date, text: c.text }
+    }
+}
+
+impl From<Citation> for Source {
+    fn from(c: Citation) -> Self {
+        Self { url: c.url, title: c.title, published: c.published_date, text: None }
+    }
+}
+
+/// A synthesized answer and its sources
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct Answer {
+    #[serde(rename = "answer")]
+    pub text: String,
+    #[serde(rename = "citations", skip_serializing_if = "Vec::is_empty")]
+    pub sources: Vec<Source>,
+    /// Total cost in dollars
+    #[serde(rename = "costDollars", serialize_with = "cost_dollars", skip_serializing_if = "Option::is_none")]
+    pub cost: Option<f64>,
+    #[serde(rename = "requestId", skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+impl From<AnswerResponse> for Answer {
+    fn from(r: AnswerResponse) -> Self {
+        Self {
+            text: r.answer,
+            sources: r.citations.into_iter().map(Source::from).collect(),
+            cost: r.cost_dollars.and_then(|c| c.total),
+            request_id: r.request_id,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ResearchStatus {
+    Running,
+    Completed,
+    Failed,
+    Canceled,
+}
+
+impl ResearchStatus {
+    /// Statuses the CLI doesn't know (queued, pending, ...) count as running
+    pub fn parse(status: &str) -> Self {
+        match status {
+            "completed" => Self::Completed,
+            "failed" => Self::Failed,
+            "canceled" | "cancelled" => Self::Canceled,
+            _ => Self::Running,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Running => "running",
+            Self::Completed => "completed",
+            Self::Failed => "failed",
+            Self::Canceled => "canceled",
+        }
+    }
+}
+
+/// A research task; `report` or `outputs` are set once it has completed
+#[derive(Serialize, Debug, Clone)]
+pub struct ResearchTask {
+    pub status: ResearchStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// The report, for a task without an output schema
+    #[serde(rename = "output", serialize_with = "report", skip_serializing_if = "Option::is_none")]
+    pub report: Option<String>,
+    /// Structured outputs, for a task with an output schema
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub outputs: Vec<serde_json::Value>,
+    #[serde(rename = "citations", skip_serializing_if = "Vec::is_empty")]
+    pub sources: Vec<Source>,
+    #[serde(rename = "costDollars", serialize_with = "cost_dollars", skip_serializing_if = "Option::is_none")]
+    pub cost: Option<f64>,
+    #[serde(rename = "requestId", skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+impl From<ResearchStatusResponse> for ResearchTask {
+    fn from(r: ResearchStatusResponse) -> Self {
+        Self {
+            status: ResearchStatus::parse(&r.status),
+            error: r.error,
+            report: r.output.and_then(|o| o.content),
+            outputs: r.outputs.unwrap_or_default(),
+            sources: r.citations.unwrap_or_default().into_iter().map(Source::from).collect(),
+            cost: r.cost_dollars.and_then(|c| c.total),
+            request_id: r.request_id,
+        }
+    }
+}
+
+fn cost_dollars<S: Serializer>(cost: &Option<f64>, s: S) -> Result<S::Ok, S::Error> {
+    cost.map(|total| CostDollars { total: Some(total) }).serialize(s)
+}
+
+fn report<S: Serializer>(report: &Option<String>, s: S) -> Result<S::Ok, S::Error> {
+    report.as_ref().map(|content| serde_json::json!({ "content": content })).serialize(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_answer_from_wire() {
+        let wire: AnswerResponse = serde_json::from_value(json!({
+            "answer": "Yes",
+            "citations": [{"url": "https://a.com", "title": "A", "publishedDate": "2024-01-02", "text": "t"}],
+            "costDollars": {"total": 0.005},
+            "requestId": "r1"
+        }))
+        .unwrap();
+        let answer = Answer::from(wire);
+        assert_eq!(answer.text, "Yes");
+        assert_eq!(answer.sources[0].published.as_deref(), Some("2024-01-02"));
+        assert_eq!(answer.cost, Some(0.005));
+        // The --json shape is unchanged
+        assert_eq!(
+            serde_json::to_value(&answer).unwrap(),
+            json!({
+                "answer": "Yes",
+                "citations": [{"url": "https://a.com", "title": "A", "publishedDate": "2024-01-02", "text": "t"}],
+                "costDollars": {"total": 0.005},
+                "requestId": "r1"
+            })
+        );
+    }
+
+    #[test]
+    fn test_research_task_from_wire() {
+        let wire: ResearchStatusResponse = serde_json::from_value(json!({
+            "status": "completed",
+            "error": null,
+            "output": {"content": "Report"},
+            "outputs": null,
+            "citations": [{"url": "https://a.com"}],
+            "costDollars": null
+        }))
+        .unwrap();
+        let task = ResearchTask::from(wire);
+        assert_eq!(task.status, ResearchStatus::Completed);
+        assert_eq!(task.report.as_deref(), Some("Report"));
+        assert_eq!(
+            serde_json::to_value(&task).unwrap(),
+            json!({"status": "completed", "output": {"content": "Report"}, "citations": [{"url": "https://a.com"}]})
+        );
+        assert_eq!(ResearchStatus::parse("queued"), ResearchStatus::Running);
+        assert_eq!(ResearchStatus::parse("cancelled"), ResearchStatus::Canceled);
+    }
+
+    #[test]
+    fn test_page_excerpt() {
+        let r = SearchResult {
+            title: None,
+            url: "https://a.com".to_string(),
+            published_date: None,
+            text: Some("body".to_string()),
+            highlights: None,
+            entities: None,
+            image: None,
+            favicon: None,
+            do_not_ingest: false,
+            paywalled: false,
+            content_hash: None,
+            simhash: None,
+        };
+        assert_eq!(Page::from(&r).excerpt(), Some("body"));
+        assert_eq!(Page::from(&r).content_hash, Some(fingerprint::content_hash("body")));
+        let page = Page { highlights: vec!["hl".to_string()], ..Page::from(&r) };
+        assert_eq!(page.excerpt(), Some("hl"));
+    }
+}
@@ -0,0 +1,153 @@
+use anyhow::{Context, Result};
+use chrono::Utc;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Note flavours for --format org / obsidian
+#[derive(Clone, Copy, PartialEq)]
+pub enum NoteKind {
+    Org,
+    Obsidian,
+}
+
+impl NoteKind {
+    fn extension(self) -> &'static str {
+        match self {
+            NoteKind::Org => "org",
+            NoteKind::Obsidian => "md",
+        }
+    }
+}
+
+/// The parts of a result that end up in a note
+pub struct Note<'a> {
+    pub title: &'a str,
+    pub url: &'a str,
+    pub published_date: Option<&'a str>,
+    pub text: Option<&'a str>,
+    pub highlights: &'a [String],
+}
+
+/// Title usable as a file name and inside [[wiki links]]: drops the
+/// characters Obsidian rejects in links and collapses whitespace
+pub fn note_title(title: &str) -> String {
+    let cleaned: String = title
+        .chars()
+        .map(|c| if "[]#|^:\\/*?\"<>".contains(c) { ' ' } else { c })
+        .collect();
+    let cleaned = cleaned.split_whitespace().collect::<Vec<_>>().join(" ");
+    let cleaned: String = cleaned.trim_start_matches('.').chars().take(120).collect();
+    if cleaned.is_empty() {
+        "Untitled".to_string()
+    } else {
+        cleaned
+    }
+}
+
+/// Render one note
+pub fn render(kind: NoteKind, note: &Note, tags: &[String]) -> String {
+    match kind {
+        NoteKind::Org => render_org(note, tags),
+        NoteKind::Obsidian => render_obsidian(note, tags),
+    }
+}
+
+fn render_org(note: &Note, tags: &[String]) -> String {
+    let mut out = format!("* {}", note_title(note.title));
+    if !tags.is_empty() {
+        let tags: Vec<String> = tags
+            .iter()
+            .map(|t| t.replace(|c: char| !c.is_alphanumeric() && c != '_' && c != '@', "_"))
+            .collect();
+        out.push_str(&format!(" :{}:", tags.join(":")));
+    }
+    out.push_str("\n:PROPERTIES:\n");
+    out.push_str(&format!(":URL: {}\n", note.url));
+    if let Some(date) = note.published_date {
+        out.push_str(&format!(":PUBLISHED: {}\n", date));
+    }
+    out.push_str(&format!(":CAPTURED: [{}]\n", Utc::now().format("%Y-%m-%d %a %H:%M")));
+    out.push_str(":END:\n");
+    if let Some(text) = note.text {
+        out.push('\n');
+        out.push_str(text.trim());
+        out.push('\n');
+    }
+    if !note.highlights.is_empty() {
+        out.push('\n');
+        for h in note.highlights {
+            out.push_str(&format!("- {}\n", h.trim()));
+        }
+    }
+    out
+}
+
+fn render_obsidian(note: &Note, tags: &[String]) -> String {
+    // JSON strings are valid YAML scalars, so they double as safe quoting
+    let quote = |s: &str| serde_json::to_string(s).unwrap_or_default();
+    let title = note_title(note.title);
+
+    let mut out = String::from("---\n");
+    out.push_str(&format!("title: {}\n", quote(&title)));
+    out.push_str(&format!("source: {}\n", quote(note.url)));
+    if let Some(date) = note.published_date {
+        out.push_str(&format!("published: {}\n", quote(date)));
+    }
+    out.push_str(&format!("created: {}\n", Utc::now().format("%Y-%m-%d")));
+    if !tags.is_empty() {
+        out.push_str("tags:\n");
+        for tag in tags {
+            out.push_str(&format!("  - {}\n", quote(&tag.replace(' ', "-"))));
+        }
+    }
+    out.push_str("---\n\n");
+    out.push_str(&format!("# {}\n\n", title));
+    if let Some(text) = note.text {
+        out.push_str(text.trim());
+        out.push_str("\n\n");
+    }
+    for h in note.highlights {
+        out.push_str(&format!("> {}\n\n", h.trim()));
+    }
+    out.push_str(&format!("[Source]({})\n", note.url));
+    out
+}
+
+/// Write one note file into `dir`. Existing notes are left alone (they may
+/// have been edited); returns None in that case.
+pub fn write_note(dir: &Path, kind: NoteKind, note: &Note, tags: &[String]) -> Result<Option<PathBuf>> {
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let path = dir.join(format!("{}.{}", note_title(note.title), kind.extension()));
+    if path.exists() {
+        return Ok(None);
+    }
+    fs::write(&path, render(kind, note, tags)).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(Some(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_note_title() {
+        assert_eq!(note_title("Rust: The [Book] #1 / 2"), "Rust The Book 1 2");
+        assert_eq!(note_title("  "), "Untitled");
+        assert_eq!(note_title("..hidden"), "hidden");
+    }
+
+    #[test]
+    fn test_render_obsidian() {
+        let note = Note {
+            title: "Notes: a title",
+            url: "https://example.com",
+            published_date: Some("2024-01-02"),
+            text: Some("Body"),
+            highlights: &[],
+        };
+        let out = render(NoteKind::Obsidian, &note, &["web research".to_string()]);
+        assert!(out.starts_with("---\ntitle: \"Notes a title\"\nsource: \"https://example.com\"\n"));
+        assert!(out.contains("tags:\n  - \"web-research\"\n"));
+        assert!(out.contains("# Notes a title\n\nBody\n"));
+    }
+}
@@ -0,0 +1,173 @@
+//! --notify: email search/find results (with --watch, each round's new ones)
+//! as a digest, in Markdown, or as a styled HTML page with a plain-text
+//! alternative under --format html. The server and sender come from
+//! --smtp-server and --smtp-from (settable in config.toml); the login comes
+//! from the environment (EXA_SMTP_USER, EXA_SMTP_PASSWORD).
+
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use exa_cli::api::SearchResponse;
+use exa_cli::{diag, vars, warnings};
+use lettre::message::{Mailbox, MultiPart, SinglePart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use std::time::Duration;
+
+use crate::cli::{Cli, NotifyTarget};
+use crate::render;
+
+const SEND_TIMEOUT: Duration = Duration::from_secs(30);
+/// Longest subject before the query is cut
+const SUBJECT_CHARS: usize = 100;
+
+/// How to reach the mail server, from flags and the environment
+struct Smtp {
+    server: String,
+    from: Mailbox,
+    credentials: Option<Credentials>,
+}
+
+/// The server URL as lettre takes it. Plain `smtp://` means STARTTLS, as it
+/// does for mail clients, unless `?tls=none` asks for no encryption (a local
+/// relay); lettre itself would send in the clear.
+fn server_url(server: &str) -> Result<String> {
+    let mut url = reqwest::Url::parse(server)?;
+    if url.scheme() != "smtp" {
+        return Ok(server.to_string());
+    }
+    let pairs: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+    let tls = pairs.iter().find(|(k, _)| k == "tls").map(|(_, v)| v.as_str());
+    let tls = match tls {
+        None => Some("required"),
+        Some("none") => None,
+        Some(other) => Some(other),
+    };
+    let mut query = url.query_pairs_mut();
+    query.clear();
+    query.extend_pairs(pairs.iter().filter(|(k, _)| k != "tls"));
+    if let Some(tls) = tls {
+        query.append_pair("tls", tls);
+    }
+    drop(query);
+    if url.query() == Some("") {
+        url.set_query(None);
+    }
+    Ok(url.to_string())
+}
+
+fn smtp(cli: &Cli) -> Result<Smtp> {
+    let var = |name: &str| vars::get(name).filter(|v| !v.trim().is_empty());
+    let Some(server) = cli.smtp_server.clone() else {
+        bail!("--notify email needs --smtp-server, e.g. smtps://smtp.example.com");
+    };
+    let user = var("EXA_SMTP_USER");
+    let Some(from) = cli.smtp_from.clone().or_else(|| user.clone()) else {
+        bail!("--notify email needs --smtp-from (or EXA_SMTP_USER)");
+    };
+    let from: Mailbox = from.parse().with_context(|| format!("Invalid --smtp-from address {}", from))?;
+    let credentials = match (user, var("EXA_SMTP_PASSWORD")) {
+        (Some(user), Some(password)) => Some(Credentials::new(user, password)),
+        (Some(_), None) => bail!("--notify email needs EXA_SMTP_PASSWORD with EXA_SMTP_USER"),
+        (None, _) => None,
+    };
+    // A bad URL is caught here too, rather than after the search is paid for
+    let invalid = || format!("Invalid --smtp-server {}", server);
+    let url = server_url(&server).with_context(invalid)?;
+    AsyncSmtpTransport::<Tokio1Executor>::from_url(&url).with_context(invalid)?;
+    Ok(Smtp { server: url, from, credentials })
+}
+
+fn recipients(target: &NotifyTarget) -> Result<Vec<Mailbox>> {
+    let NotifyTarget::Email(addresses) = target;
+    addresses
+        .iter()
+        .map(|a| a.parse().with_context(|| format!("Invalid --notify address {}", a)))
+        .collect()
+}
+
+/// Fail before the (paid) request when the digest couldn't be sent anyway
+pub fn check(cli: &Cli) -> Result<()> {
+    match &cli.notify {
+        Some(target) => recipients(target).and_then(|_| smtp(cli)).map(drop),
+        None => Ok(()),
+    }
+}
+
+/// `exa: 3 new results for <query>`
+fn subject(query: &str, count: usize, new: bool) -> String {
+    let query = query.split_whitespace().collect::<Vec<_>>().join(" ");
+    let query = if query.chars().count() > SUBJECT_CHARS {
+        let cut: String = query.chars().take(SUBJECT_CHARS - 3).collect();
+        format!("{}...", cut.trim_end())
+    } else {
+        query
+    };
+    let noun = if count == 1 { "result" } else { "results" };
+    format!("exa: {} {}{} for {}", count, if new { "new " } else { "" }, noun, query)
+}
+
+async fn deliver(cli: &Cli, target: &NotifyTarget, subject: String, text: String, page: Option<String>) -> Result<()> {
+    let smtp = smtp(cli)?;
+    let mut builder = Message::builder().from(smtp.from).subject(subject);
+    for to in recipients(target)? {
+        builder = builder.to(to);
+    }
+    let message = match page {
+        Some(page) => builder.multipart(MultiPart::alternative_plain_html(text, page)),
+        None => builder.singlepart(SinglePart::plain(text)),
+    }
+    .context("Failed to build the email")?;
+
+    let mut transport = AsyncSmtpTransport::<Tokio1Executor>::from_url(&smtp.server)?.timeout(Some(SEND_TIMEOUT));
+    if let Some(credentials) = smtp.credentials {
+        transport = transport.credentials(credentials);
+    }
+    transport.build().send(message).await.with_context(|| format!("Failed to send through {}", smtp.server))?;
+    Ok(())
+}
+
+/// With --notify, email `results` (`new` when they are a watch round's new
+/// ones) and say where they went; a failure only warns, since the results
+/// have been paid for and printed
+pub async fn send(cli: &Cli, query: &str, results: &SearchResponse, new: bool) {
+    let Some(target) = &cli.notify else { return };
+    if results.results.is_empty() {
+        return;
+    }
+    let (text, page) = render::digest(cli, results);
+    let subject = subject(query, results.results.len(), new);
+    match deliver(cli, target, subject, text, page).await {
+        Ok(()) => {
+            let NotifyTarget::Email(addresses) = target;
+            diag!("{} {}", "Emailed".green(), addresses.join(", ").dimmed());
+        }
+        Err(e) => warnings::emit("notify_failed", format!("could not send the email: {:#}", e), true),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subject_and_target() {
+        assert_eq!(subject("rust  async\nruntimes", 3, true), "exa: 3 new results for rust async runtimes");
+        assert_eq!(subject("rust", 1, false), "exa: 1 result for rust");
+        let long = subject(&"word ".repeat(40), 5, false);
+        assert!(long.ends_with("...") && long.chars().count() <= SUBJECT_CHARS + 20);
+
+        let target: NotifyTarget = "email:me@example.com, Ann <ann@example.com>".parse().unwrap();
+        assert_eq!(recipients(&target).unwrap().len(), 2);
+        assert!("email:".parse::<NotifyTarget>().is_err());
+        assert!("email:nobody".parse::<NotifyTarget>().is_err());
+        assert!("slack:#news".parse::<NotifyTarget>().is_err());
+
+        assert_eq!(server_url("smtp://mail.example.com:587").unwrap(), "smtp://mail.example.com:587?tls=required");
+        assert_eq!(server_url("smtp://localhost:25?tls=none").unwrap(), "smtp://localhost:25");
+        assert_eq!(
+            server_url("smtp://mail.example.com?tls=opportunistic").unwrap(),
+            "smtp://mail.example.com?tls=opportunistic"
+        );
+        assert_eq!(server_url("smtps://mail.example.com").unwrap(), "smtps://mail.example.com");
+    }
+}
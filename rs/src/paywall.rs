@@ -0,0 +1,45 @@
+/// Phrases that only appear on gated pages
+const HARD_MARKERS: &[&str] = &[
+    "subscribe to continue reading",
+    "subscribe to read",
+    "subscribers only",
+    "for subscribers only",
+    "this article is for subscribers",
+    "this content is for subscribers",
+    "already a subscriber",
+    "sign in to continue reading",
+    "log in to continue reading",
+    "create a free account to continue",
+    "to continue reading, please",
+    "you have reached your limit of free articles",
+    "you've reached your free article limit",
+];
+
+/// Phrases that only indicate a gate on a page with little real text
+const SOFT_MARKERS: &[&str] = &["subscribe", "sign in", "log in", "login", "register", "paywall", "members only"];
+
+/// Below this many characters, a page with soft markers is treated as gated
+const SHORT_TEXT: usize = 600;
+
+/// Heuristic check for paywalled or login-gated page text
+pub fn is_paywalled(text: &str) -> bool {
+    let lower = text.to_lowercase();
+    if HARD_MARKERS.iter().any(|m| lower.contains(m)) {
+        return true;
+    }
+    text.trim().len() < SHORT_TEXT && SOFT_MARKERS.iter().any(|m| lower.contains(m))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_paywalled() {
+        assert!(is_paywalled("Markets rallied today. Subscribe to continue reading."));
+        assert!(is_paywalled("Please sign in to view this page."));
+        let long_article = format!("{} Follow us or subscribe to our newsletter.", "Body text. ".repeat(100));
+        assert!(!is_paywalled(&long_article));
+        assert!(!is_paywalled("A short but open note."));
+    }
+}
@@ -0,0 +1,43 @@
+use regex::Regex;
+use std::sync::LazyLock;
+
+pub static EMAIL: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}").unwrap());
+
+/// North American numbers, plus international numbers written with a leading +
+static PHONE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?:\+\d{1,3}[\s.-]?)?\(?\b\d{3}\)?[\s.-]\d{3}[\s.-]\d{4}\b|\+\d{1,3}(?:[\s.-]?\d{2,4}){2,5}\b").unwrap()
+});
+
+/// House number, one to three capitalized words and a street suffix
+static STREET: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"\b\d{1,5}\s+(?:[A-Z][A-Za-z]+\s+){1,3}(?:Street|St|Avenue|Ave|Road|Rd|Boulevard|Blvd|Lane|Ln|Drive|Dr|Court|Ct|Way|Place|Pl|Terrace|Square|Sq)\b\.?",
+    )
+    .unwrap()
+});
+
+/// Redact emails, phone numbers and street addresses
+pub fn scrub(text: &str) -> String {
+    let text = EMAIL.replace_all(text, "[EMAIL]");
+    let text = PHONE.replace_all(&text, "[PHONE]");
+    STREET.replace_all(&text, "[ADDRESS]").into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scrub() {
+        assert_eq!(
+            scrub("Mail jane.doe+x@example.co.uk or call (555) 123-4567."),
+            "Mail [EMAIL] or call [PHONE]."
+        );
+        assert_eq!(scrub("Tel +44 20 7946 0958 today"), "Tel [PHONE] today");
+        assert_eq!(scrub("Visit 221 Baker Street, London"), "Visit [ADDRESS], London");
+        // Years, versions and plain counts are left alone
+        let plain = "In 2024, version 1.2.3 sold 15000 units.";
+        assert_eq!(scrub(plain), plain);
+    }
+}
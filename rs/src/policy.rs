@@ -0,0 +1,70 @@
+use anyhow::Result;
+use std::fs;
+
+use crate::archive;
+use crate::key_manager::KeyManager;
+use exa_cli::vars;
+
+const LIST_FILE: &str = "do-not-ingest.txt";
+
+/// Domains whose content must not be stored or exported (licensing, ToS).
+/// Read from ~/.config/exa/do-not-ingest.txt (one domain per line, `#`
+/// comments) plus the comma-separated EXA_DO_NOT_INGEST variable.
+#[derive(Default)]
+pub struct DoNotIngest {
+    domains: Vec<String>,
+}
+
+impl DoNotIngest {
+    pub fn load() -> Result<Self> {
+        let mut list = match fs::read_to_string(KeyManager::get_config_dir()?.join(LIST_FILE)) {
+            Ok(content) => Self::parse(&content),
+            Err(_) => Self::default(),
+        };
+        if let Some(env) = vars::get("EXA_DO_NOT_INGEST") {
+            list.domains.extend(Self::parse(&env.replace(',', "\n")).domains);
+        }
+        Ok(list)
+    }
+
+    fn parse(content: &str) -> Self {
+        let domains = content
+            .lines()
+            .map(|l| l.split('#').next().unwrap_or("").trim())
+            .filter(|l| !l.is_empty())
+            .map(|l| l.trim_start_matches("*.").trim_start_matches("www.").to_lowercase())
+            .collect();
+        Self { domains }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.domains.is_empty()
+    }
+
+    pub fn domains(&self) -> &[String] {
+        &self.domains
+    }
+
+    /// Whether the URL's host is a listed domain or one of its subdomains
+    pub fn blocks(&self, url: &str) -> bool {
+        let host = archive::domain_of(url).to_lowercase();
+        self.domains
+            .iter()
+            .any(|d| host == *d || host.ends_with(&format!(".{}", d)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blocks() {
+        let list = DoNotIngest::parse("# licensed sources\nft.com\n*.wsj.com  # comment\n\n");
+        assert!(list.blocks("https://www.ft.com/content/abc"));
+        assert!(list.blocks("https://markets.ft.com/data"));
+        assert!(list.blocks("https://www.wsj.com/articles/x"));
+        assert!(!list.blocks("https://notft.com/"));
+        assert!(!list.blocks("https://example.com/ft.com"));
+    }
+}
@@ -0,0 +1,101 @@
+//! `--polite`: pacing for long bulk jobs. Requests are spaced at least
+//! [`BASE_INTERVAL`] apart with random jitter, and the spacing doubles on each
+//! 429 or 5xx and eases back on success (additive-increase/multiplicative-
+//! decrease, as in TCP congestion control), so a struggling API or a nearly
+//! exhausted key pool sees less traffic instead of more.
+
+use std::time::{Duration, Instant};
+
+/// Spacing at full speed: one request a second
+pub const BASE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Spacing never backs off past this
+pub const MAX_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Each success takes this much off a backed-off interval
+const RECOVERY_STEP: Duration = Duration::from_millis(250);
+
+#[derive(Debug)]
+pub struct Pacer {
+    interval: Duration,
+    last: Option<Instant>,
+    rng: u64,
+}
+
+impl Default for Pacer {
+    fn default() -> Self {
+        let seed = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or_default();
+        Self::with_seed(seed)
+    }
+}
+
+impl Pacer {
+    pub fn with_seed(seed: u64) -> Self {
+        Self { interval: BASE_INTERVAL, last: None, rng: seed | 1 }
+    }
+
+    /// Current spacing between requests, before jitter
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Uniform in [0.5, 1.5), from xorshift64
+    fn jitter(&mut self) -> f64 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        0.5 + (self.rng >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// How long to wait before sending the next request; marks it as sent
+    pub fn delay(&mut self) -> Duration {
+        let gap = self.interval.mul_f64(self.jitter());
+        let wait = match self.last {
+            Some(last) => gap.saturating_sub(last.elapsed()),
+            None => Duration::ZERO,
+        };
+        self.last = Some(Instant::now() + wait);
+        wait
+    }
+
+    /// Adapt to a response status; returns true when this slowed the pace
+    pub fn record(&mut self, status: u16) -> bool {
+        if status == 429 || status >= 500 {
+            self.interval = (self.interval * 2).min(MAX_INTERVAL);
+            true
+        } else {
+            self.interval = self.interval.saturating_sub(RECOVERY_STEP).max(BASE_INTERVAL);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pacer() {
+        let mut pacer = Pacer::with_seed(42);
+        assert_eq!(pacer.delay(), Duration::ZERO);
+        let wait = pacer.delay();
+        assert!(wait >= Duration::from_millis(400) && wait < Duration::from_millis(1500));
+
+        assert!(pacer.record(429));
+        assert!(pacer.record(503));
+        assert_eq!(pacer.interval(), Duration::from_secs(4));
+        for _ in 0..20 {
+            pacer.record(429);
+        }
+        assert_eq!(pacer.interval(), MAX_INTERVAL);
+        assert!(!pacer.record(200));
+        assert_eq!(pacer.interval(), MAX_INTERVAL - RECOVERY_STEP);
+        for _ in 0..1000 {
+            pacer.record(200);
+        }
+        assert_eq!(pacer.interval(), BASE_INTERVAL);
+    }
+}
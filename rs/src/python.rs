@@ -0,0 +1,154 @@
+//! The `exa_cli` Python module (feature `python`, built with maturin): a
+//! [`blocking::Client`] as `exa_cli.Client`, whose methods take the API's
+//! request fields as keyword arguments (`num_results=5` or `numResults=5`)
+//! and return the response as dicts and lists, ready for
+//! `polars.DataFrame(resp["results"])`. The GIL is released during requests.
+
+use pyo3::exceptions::PyRuntimeError;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+use serde_json::{Map, Value};
+
+use crate::blocking::{self, Endpoint};
+
+/// `num_results` -> `numResults`; camelCase names pass through
+fn camel_case(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    let mut upper = false;
+    for c in name.chars() {
+        match c {
+            '_' if !out.is_empty() => upper = true,
+            c if upper => {
+                out.extend(c.to_uppercase());
+                upper = false;
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// A Python value as JSON, through the json module
+fn to_json(py: Python<'_>, value: &Bound<'_, PyAny>) -> PyResult<Value> {
+    let text: String = py.import("json")?.call_method1("dumps", (value,))?.extract()?;
+    serde_json::from_str(&text).map_err(|e| PyRuntimeError::new_err(e.to_string()))
+}
+
+fn to_python<'py>(py: Python<'py>, value: &Value) -> PyResult<Bound<'py, PyAny>> {
+    py.import("json")?.call_method1("loads", (value.to_string(),))
+}
+
+/// The request body: `first` and the keyword arguments, their top-level
+/// names in the API's camelCase (nested values, such as a JSON schema, are
+/// sent as given)
+fn request(py: Python<'_>, first: (&str, Value), kwargs: Option<&Bound<'_, PyDict>>) -> PyResult<Value> {
+    let mut body = Map::new();
+    body.insert(first.0.to_string(), first.1);
+    if let Some(kwargs) = kwargs {
+        for (name, value) in kwargs.iter() {
+            body.insert(camel_case(&name.extract::<String>()?), to_json(py, &value)?);
+        }
+    }
+    Ok(Value::Object(body))
+}
+
+/// An Exa client with the CLI's keys (EXA_API_KEY / EXA_API_KEYS or the
+/// keys file), key rotation, retries and response cache
+#[pyclass(name = "Client", module = "exa_cli")]
+struct PyClient(blocking::Client);
+
+impl PyClient {
+    fn send<'py>(&mut self, py: Python<'py>, endpoint: Endpoint, body: Value) -> PyResult<Bound<'py, PyAny>> {
+        let client = &mut self.0;
+        let resp = py.detach(|| client.request(endpoint, &body));
+        to_python(py, &resp.map_err(|e| PyRuntimeError::new_err(format!("{:#}", e)))?)
+    }
+}
+
+#[pymethods]
+impl PyClient {
+    /// `cache=False` turns the response cache off
+    #[new]
+    #[pyo3(signature = (cache = true))]
+    fn new(cache: bool) -> PyResult<Self> {
+        let mut client = blocking::Client::new().map_err(|e| PyRuntimeError::new_err(format!("{:#}", e)))?;
+        if !cache {
+            client.cache = None;
+        }
+        Ok(Self(client))
+    }
+
+    /// POST /search: `client.search("rust async runtimes", num_results=5)`
+    #[pyo3(signature = (query, **kwargs))]
+    fn search<'py>(
+        &mut self,
+        py: Python<'py>,
+        query: String,
+        kwargs: Option<&Bound<'py, PyDict>>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let body = request(py, ("query", Value::String(query)), kwargs)?;
+        self.send(py, Endpoint::Search, body)
+    }
+
+    /// POST /findSimilar: pages like `url`
+    #[pyo3(signature = (url, **kwargs))]
+    fn find_similar<'py>(
+        &mut self,
+        py: Python<'py>,
+        url: String,
+        kwargs: Option<&Bound<'py, PyDict>>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let body = request(py, ("url", Value::String(url)), kwargs)?;
+        self.send(py, Endpoint::FindSimilar, body)
+    }
+
+    /// POST /contents for one URL or a list of them; the text unless other
+    /// contents (`highlights=...`, `summary=...`) are asked for
+    #[pyo3(signature = (urls, **kwargs))]
+    fn contents<'py>(
+        &mut self,
+        py: Python<'py>,
+        urls: &Bound<'py, PyAny>,
+        kwargs: Option<&Bound<'py, PyDict>>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let urls = match urls.extract::<String>() {
+            Ok(url) => vec![url],
+            Err(_) => urls.cast::<PyList>()?.extract()?,
+        };
+        let mut body = request(py, ("urls", urls.into()), kwargs)?;
+        if kwargs.is_none_or(|k| k.is_empty()) {
+            body["text"] = Value::Bool(true);
+        }
+        self.send(py, Endpoint::Contents, body)
+    }
+
+    /// POST /answer: an answer to `query` with its citations
+    #[pyo3(signature = (query, **kwargs))]
+    fn answer<'py>(
+        &mut self,
+        py: Python<'py>,
+        query: String,
+        kwargs: Option<&Bound<'py, PyDict>>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let body = request(py, ("query", Value::String(query)), kwargs)?;
+        self.send(py, Endpoint::Answer, body)
+    }
+}
+
+#[pymodule]
+fn exa_cli(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyClient>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_camel_case() {
+        assert_eq!(camel_case("num_results"), "numResults");
+        assert_eq!(camel_case("include_domains"), "includeDomains");
+        assert_eq!(camel_case("numResults"), "numResults");
+        assert_eq!(camel_case("_private"), "_private");
+    }
+}
@@ -0,0 +1,96 @@
+//! --query-filter: a jq filter (jaq's dialect, with jq's standard library)
+//! applied to JSON output before it is printed
+
+use anyhow::{anyhow, bail, Result};
+use jaq_core::load::{Arena, File, Loader};
+use jaq_core::{Compiler, Ctx, Filter, Native, RcIter};
+use jaq_json::Val;
+use serde_json::Value;
+
+/// The first problem with the filter, in words
+fn load_error(error: &jaq_core::load::Error<&str>) -> String {
+    use jaq_core::load::Error;
+    let found = |s: &str| if s.is_empty() { "end of filter".to_string() } else { format!("`{}`", s) };
+    match error {
+        Error::Io(errs) => errs.first().map(|(path, e)| format!("{}: {}", path, e)).unwrap_or_default(),
+        Error::Lex(errs) => errs
+            .first()
+            .map(|(expect, at)| format!("expected {} before {}", expect.as_str(), found(at)))
+            .unwrap_or_default(),
+        Error::Parse(errs) => errs
+            .first()
+            .map(|(expect, at)| format!("expected {}, found {}", expect.as_str(), found(at)))
+            .unwrap_or_default(),
+    }
+}
+
+/// A compiled --query-filter
+pub struct QueryFilter(Filter<Native<Val>>);
+
+fn compile_filter(expr: &str) -> Result<Filter<Native<Val>>> {
+    let loader = Loader::new(jaq_std::defs().chain(jaq_json::defs()));
+    let arena = Arena::default();
+    let modules = loader
+        .load(&arena, File { code: expr, path: () })
+        .map_err(|errs| anyhow!("{}", errs.first().map(|(_, e)| load_error(e)).unwrap_or_default()))?;
+    Compiler::default()
+        .with_funs(jaq_std::funs().chain(jaq_json::funs()))
+        .compile(modules)
+        .map_err(|errs| {
+            let undefined = errs.iter().flat_map(|(_, e)| e).next();
+            match undefined {
+                Some((name, kind)) => anyhow!("undefined {} `{}`", kind.as_str(), name),
+                None => anyhow!("cannot compile"),
+            }
+        })
+}
+
+/// Compile the filter, failing on one that doesn't parse or names unknown functions
+pub fn compile(expr: &str) -> Result<QueryFilter> {
+    compile_filter(expr).map(QueryFilter).map_err(|e| anyhow!("Invalid --query-filter: {}", e))
+}
+
+impl QueryFilter {
+    /// Every value the filter outputs for `input`
+    pub fn apply(&self, input: Value) -> Result<Vec<Value>> {
+        let inputs = RcIter::new(core::iter::empty());
+        let mut out = Vec::new();
+        for value in self.0.run((Ctx::new([], &inputs), Val::from(input))) {
+            match value {
+                Ok(value) => out.push(Value::from(value)),
+                Err(e) => bail!("--query-filter failed: {}", e),
+            }
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_apply() {
+        let response = json!({
+            "results": [
+                {"url": "https://a.com", "title": "A", "score": 0.9},
+                {"url": "https://b.com", "title": "B", "score": 0.4}
+            ]
+        });
+        let apply = |expr: &str, input: Value| compile(expr).unwrap().apply(input);
+        let check = |expr: &str| compile(expr).map(drop);
+        let urls = apply(".results[].url", response.clone()).unwrap();
+        assert_eq!(urls, vec![json!("https://a.com"), json!("https://b.com")]);
+        assert_eq!(
+            apply("[.results[] | select(.score > 0.5) | {url, title}]", response.clone()).unwrap(),
+            vec![json!([{"url": "https://a.com", "title": "A"}])]
+        );
+        assert_eq!(apply(".results | length", response.clone()).unwrap(), vec![json!(2)]);
+        assert_eq!(apply("empty", response.clone()).unwrap(), Vec::<Value>::new());
+
+        assert!(check(".results[").unwrap_err().to_string().starts_with("Invalid --query-filter: expected"));
+        assert_eq!(check("nope").unwrap_err().to_string(), "Invalid --query-filter: undefined filter `nope`");
+        assert!(apply(".results + 1", response).is_err());
+    }
+}
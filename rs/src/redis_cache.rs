@@ -0,0 +1,163 @@
+use anyhow::{bail, Context, Result};
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+const DEFAULT_PORT: u16 = 6379;
+const IO_TIMEOUT: Duration = Duration::from_millis(500);
+const KEY_PREFIX: &str = "exa:cache:";
+
+/// Minimal Redis client for the shared response cache (GET / SET EX only)
+pub struct RedisCache {
+    host: String,
+    port: u16,
+    password: Option<String>,
+    db: u32,
+}
+
+/// A parsed RESP reply
+#[derive(Debug, PartialEq)]
+enum Reply {
+    Status(String),
+    Integer(i64),
+    Bulk(Option<Vec<u8>>),
+}
+
+impl RedisCache {
+    /// Parse `redis://[:password@]host[:port][/db]`
+    pub fn from_url(url: &str) -> Result<Self> {
+        let parsed = reqwest::Url::parse(url).with_context(|| format!("Invalid cache backend URL: {}", url))?;
+        if parsed.scheme() != "redis" {
+            bail!("Unsupported cache backend scheme: {}", parsed.scheme());
+        }
+        let host = parsed
+            .host_str()
+            .filter(|h| !h.is_empty())
+            .context("Cache backend URL is missing a host")?
+            .to_string();
+        let db = match parsed.path().trim_start_matches('/') {
+            "" => 0,
+            db => db.parse().with_context(|| format!("Invalid Redis database: {}", db))?,
+        };
+        Ok(Self {
+            host,
+            port: parsed.port().unwrap_or(DEFAULT_PORT),
+            password: parsed.password().map(|p| p.to_string()),
+            db,
+        })
+    }
+
+    /// Fetch a cached body, or None on miss
+    pub fn get(&self, key: &str) -> Result<Option<String>> {
+        let mut conn = self.connect()?;
+        match conn.command(&["GET", &format!("{}{}", KEY_PREFIX, key)])? {
+            Reply::Bulk(Some(data)) => Ok(Some(String::from_utf8(data)?)),
+            Reply::Bulk(None) => Ok(None),
+            other => bail!("Unexpected Redis reply to GET: {:?}", other),
+        }
+    }
+
+    /// Store a body with an expiry
+    pub fn set(&self, key: &str, data: &str, ttl_secs: u64) -> Result<()> {
+        let mut conn = self.connect()?;
+        let ttl = ttl_secs.max(1).to_string();
+        conn.command(&["SET", &format!("{}{}", KEY_PREFIX, key), data, "EX", &ttl])?;
+        Ok(())
+    }
+
+    fn connect(&self) -> Result<Connection> {
+        let stream = TcpStream::connect((self.host.as_str(), self.port))
+            .with_context(|| format!("Failed to connect to Redis at {}:{}", self.host, self.port))?;
+        stream.set_read_timeout(Some(IO_TIMEOUT))?;
+        stream.set_write_timeout(Some(IO_TIMEOUT))?;
+        let mut conn = Connection {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+        };
+        if let Some(password) = &self.password {
+            conn.command(&["AUTH", password])?;
+        }
+        if self.db != 0 {
+            conn.command(&["SELECT", &self.db.to_string()])?;
+        }
+        Ok(conn)
+    }
+}
+
+struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Connection {
+    fn command(&mut self, args: &[&str]) -> Result<Reply> {
+        self.writer.write_all(&encode_command(args))?;
+        read_reply(&mut self.reader)
+    }
+}
+
+/// Encode a command as a RESP array of bulk strings
+fn encode_command(args: &[&str]) -> Vec<u8> {
+    let mut out = format!("*{}\r\n", args.len()).into_bytes();
+    for arg in args {
+        out.extend_from_slice(format!("${}\r\n", arg.len()).as_bytes());
+        out.extend_from_slice(arg.as_bytes());
+        out.extend_from_slice(b"\r\n");
+    }
+    out
+}
+
+fn read_reply<R: BufRead>(reader: &mut R) -> Result<Reply> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let line = line.trim_end_matches("\r\n");
+    let (kind, rest) = line.split_at(line.len().min(1));
+    match kind {
+        "+" => Ok(Reply::Status(rest.to_string())),
+        "-" => bail!("Redis error: {}", rest),
+        ":" => Ok(Reply::Integer(rest.parse()?)),
+        "$" => {
+            let len: i64 = rest.parse()?;
+            if len < 0 {
+                return Ok(Reply::Bulk(None));
+            }
+            let mut data = vec![0u8; len as usize + 2];
+            reader.read_exact(&mut data)?;
+            data.truncate(len as usize);
+            Ok(Reply::Bulk(Some(data)))
+        }
+        _ => bail!("Unsupported Redis reply: {}", line),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_command() {
+        assert_eq!(encode_command(&["GET", "k"]), b"*2\r\n$3\r\nGET\r\n$1\r\nk\r\n");
+    }
+
+    #[test]
+    fn test_read_reply() {
+        let mut ok = &b"+OK\r\n"[..];
+        assert_eq!(read_reply(&mut ok).unwrap(), Reply::Status("OK".into()));
+        let mut bulk = &b"$5\r\nhello\r\n"[..];
+        assert_eq!(read_reply(&mut bulk).unwrap(), Reply::Bulk(Some(b"hello".to_vec())));
+        let mut nil = &b"$-1\r\n"[..];
+        assert_eq!(read_reply(&mut nil).unwrap(), Reply::Bulk(None));
+        let mut err = &b"-ERR nope\r\n"[..];
+        assert!(read_reply(&mut err).is_err());
+    }
+
+    #[test]
+    fn test_from_url() {
+        let cache = RedisCache::from_url("redis://:secret@cache.lan:6380/2").unwrap();
+        assert_eq!(cache.host, "cache.lan");
+        assert_eq!(cache.port, 6380);
+        assert_eq!(cache.password.as_deref(), Some("secret"));
+        assert_eq!(cache.db, 2);
+        assert!(RedisCache::from_url("http://cache.lan").is_err());
+    }
+}
@@ -0,0 +1,942 @@
+//! Printing results: text, compact, JSON and delimited output
+
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use exa_cli::api::{Entity, SearchResponse};
+use exa_cli::model::Page;
+use exa_cli::{diag, diag_inline, fingerprint, warnings};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+
+use crate::cli::{AnnotationLevel, Cli, Commands, FeedFormat, OutputFormat, PickField, SourceField};
+use crate::commands::{load_policy, save_last_results};
+use crate::delimited;
+use crate::feeds;
+use crate::html;
+use crate::markdown;
+use crate::notes;
+use crate::paywall;
+use crate::query_filter::{self, QueryFilter};
+use crate::thumbnails;
+
+/// Get the effective max chars for content truncation
+pub fn get_max_chars(cli: &Cli) -> usize {
+    if cli.compress_content.is_some() && cli.max_chars.is_none() {
+        // The compression budget already bounds the text
+        return usize::MAX;
+    }
+    cli.max_chars.unwrap_or(if cli.compact { 300 } else { 500 })
+}
+
+/// Record (structured mode only) how many result texts will be cut to max_chars
+pub fn warn_truncated<'a>(results: impl Iterator<Item = &'a Page>, max_chars: usize) {
+    let cut = results.filter(|r| r.text.as_ref().is_some_and(|t| t.len() > max_chars)).count();
+    if cut > 0 {
+        let message = format!("{} result text(s) truncated to {} chars (raise with --max-chars)", cut, max_chars);
+        warnings::emit("content_truncated", message, false);
+    }
+}
+
+/// Truncate text at the last sentence boundary within max_chars.
+/// Falls back to last word boundary, then hard cut.
+pub fn truncate_text(text: &str, max_chars: usize) -> String {
+    if text.len() <= max_chars {
+        return text.to_string();
+    }
+    let window = &text[..max_chars];
+    // Find last sentence-ending punctuation followed by space or at end
+    let cut = window.rfind(". ")
+        .or_else(|| window.rfind("? "))
+        .or_else(|| window.rfind("! "))
+        .map(|i| i + 1)  // include the punctuation
+        .or_else(|| window.rfind(' '))  // fallback: last word boundary
+        .unwrap_or(max_chars);          // fallback: hard cut
+    format!("{}...", text[..cut].trim_end())
+}
+
+/// Output width for wrapping: --width, else the terminal width in normal mode.
+/// Compact/piped output is only wrapped when --width is given.
+pub fn output_width(cli: &Cli) -> Option<usize> {
+    if let Some(width) = cli.width {
+        return (width > 0).then_some(width);
+    }
+    if cli.compact {
+        return None;
+    }
+    terminal_size::terminal_size().map(|(w, _)| w.0 as usize)
+}
+
+/// Wrap text to `width` columns with a hanging indent: `initial` prefixes the
+/// first line, `subsequent` every continuation line. Existing line breaks are
+/// kept, and words longer than a line are split.
+pub fn fill(text: &str, width: Option<usize>, initial: &str, subsequent: &str) -> String {
+    let Some(width) = width.filter(|_| !text.is_empty()) else {
+        return format!("{}{}", initial, text);
+    };
+
+    let mut out = String::new();
+    let mut prefix = initial;
+    for (i, paragraph) in text.lines().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        let mut line = String::from(prefix);
+        let mut line_len = prefix.chars().count();
+        let mut has_word = false;
+        prefix = subsequent;
+
+        for word in paragraph.split_whitespace() {
+            let mut word: Vec<char> = word.chars().collect();
+            loop {
+                let sep = usize::from(has_word);
+                if line_len + sep + word.len() <= width {
+                    if has_word {
+                        line.push(' ');
+                    }
+                    line.extend(word.iter());
+                    line_len += sep + word.len();
+                    has_word = true;
+                    break;
+                }
+                let indent = subsequent.chars().count();
+                if !has_word {
+                    // Word doesn't fit on an empty line: split it
+                    let take = width.saturating_sub(line_len).max(1);
+                    let rest = word.split_off(take.min(word.len()));
+                    line.extend(word.iter());
+                    word = rest;
+                    if word.is_empty() {
+                        has_word = true;
+                        break;
+                    }
+                }
+                out.push_str(&line);
+                out.push('\n');
+                line = String::from(subsequent);
+                line_len = indent;
+                has_word = false;
+            }
+        }
+        out.push_str(&line);
+    }
+    out
+}
+
+/// Set by --yaml: structured output is written as YAML documents
+static YAML: AtomicBool = AtomicBool::new(false);
+
+pub fn set_yaml() {
+    YAML.store(true, Ordering::Relaxed);
+}
+
+/// Set by --query-filter: a jq filter applied to all JSON output
+static QUERY_FILTER: OnceLock<QueryFilter> = OnceLock::new();
+
+/// Compile --query-filter once, for all the output to come
+pub fn set_query_filter(expr: &str) -> Result<()> {
+    let _ = QUERY_FILTER.set(query_filter::compile(expr)?);
+    Ok(())
+}
+
+/// Serialize to JSON — compact (no whitespace) or pretty — or, with --yaml,
+/// to a YAML document in the same key order (each starts with `---`, so
+/// repeated output such as --watch rounds is a valid stream).
+/// Pending structured warnings are attached as a "warnings" array
+pub fn to_json<T: Serialize>(value: &T, compact: bool) -> Result<String> {
+    let value = serde_json::to_value(value)?;
+    // A filter decides the shape, so warnings go to stderr instead
+    if let Some(filter) = QUERY_FILTER.get() {
+        warnings::flush();
+        let outputs = filter.apply(value)?;
+        return Ok(outputs.iter().map(|v| format_value(v, compact)).collect::<Result<Vec<_>>>()?.join("\n"));
+    }
+    format_value(&with_warnings(value)?, compact)
+}
+
+/// A tool call's output: compact JSON with its warnings attached, whatever
+/// the server's --yaml and --query-filter
+#[cfg(feature = "serve")]
+pub fn tool_json<T: Serialize>(value: &T) -> Result<String> {
+    Ok(serde_json::to_string(&with_warnings(serde_json::to_value(value)?)?)?)
+}
+
+/// `value` with pending structured warnings attached as a "warnings" array
+fn with_warnings(mut value: serde_json::Value) -> Result<serde_json::Value> {
+    match &mut value {
+        serde_json::Value::Object(map) => {
+            let pending = warnings::take();
+            if !pending.is_empty() {
+                map.insert("warnings".to_string(), serde_json::to_value(pending)?);
+            }
+        }
+        // An array has nowhere to carry them; report them with the output,
+        // not at exit, which an early exit would skip
+        _ => warnings::flush(),
+    }
+    Ok(value)
+}
+
+fn format_value(value: &serde_json::Value, compact: bool) -> Result<String> {
+    if YAML.load(Ordering::Relaxed) {
+        let yaml = serde_yaml::to_string(value)?;
+        return Ok(format!("---\n{}", yaml.trim_end()));
+    }
+    if compact {
+        Ok(serde_json::to_string(value)?)
+    } else {
+        Ok(serde_json::to_string_pretty(value)?)
+    }
+}
+
+/// Parse --fields into a HashSet. None means "all fields".
+pub fn parse_fields(cli: &Cli) -> Option<HashSet<String>> {
+    cli.fields.as_ref().map(|f| {
+        f.split(',').map(|s| s.trim().to_lowercase()).collect()
+    })
+}
+
+/// Fields --fields can name, in default column order
+pub const RESULT_FIELDS: &[&str] = &["title", "url", "date", "content", "highlights", "snippet"];
+
+/// --fields in the order given; `default` when unset
+pub fn field_list(cli: &Cli, default: &[&str]) -> Result<Vec<String>> {
+    let Some(spec) = &cli.fields else {
+        return Ok(default.iter().map(|f| f.to_string()).collect());
+    };
+    let fields: Vec<String> = spec.split(',').map(|s| s.trim().to_lowercase()).filter(|s| !s.is_empty()).collect();
+    if let Some(unknown) = fields.iter().find(|f| !RESULT_FIELDS.contains(&f.as_str())) {
+        bail!("Unknown field '{}' (expected {})", unknown, RESULT_FIELDS.join(", "));
+    }
+    Ok(fields)
+}
+
+/// A result field as plain text; content is truncated to `max_chars`
+pub fn result_field(r: &Page, field: &str, max_chars: usize) -> String {
+    match field {
+        "title" => r.title.clone().unwrap_or_default(),
+        "url" => r.url.clone(),
+        "date" => r.published.clone().unwrap_or_default(),
+        "content" => r.text.as_deref().map(|t| truncate_text(t, max_chars)).unwrap_or_default(),
+        "highlights" => r.highlights.join(" | "),
+        "snippet" => snippet(r).map(|s| truncate_text(&s, max_chars)).unwrap_or_default(),
+        _ => String::new(),
+    }
+}
+
+/// JSON lines: one object per result with the --fields keys
+pub fn print_jsonl(cli: &Cli, pages: &[Page], max_chars: usize) -> Result<()> {
+    let keys = field_list(cli, &["title", "url", "date", "snippet"])?;
+    for r in pages {
+        let mut object = serde_json::Map::new();
+        for key in &keys {
+            let value = result_field(r, key, max_chars);
+            if !value.is_empty() {
+                object.insert(key.clone(), serde_json::Value::String(value));
+            }
+        }
+        println!("{}", serde_json::Value::Object(object));
+    }
+    Ok(())
+}
+
+/// One result as a line of --jsonl output (each output of --query-filter, with one)
+pub fn print_result_line(r: &Page) -> Result<()> {
+    if let Some(filter) = QUERY_FILTER.get() {
+        for value in filter.apply(serde_json::to_value(r)?)? {
+            println!("{}", serde_json::to_string(&value)?);
+        }
+        return Ok(());
+    }
+    println!("{}", serde_json::to_string(r)?);
+    Ok(())
+}
+
+/// TSV/CSV: a header row, then one row per result with the --fields columns
+pub fn print_delimited(cli: &Cli, pages: &[Page], delim: delimited::Delimiter, max_chars: usize) -> Result<()> {
+    let columns = field_list(cli, &["title", "url", "date"])?;
+    println!("{}", delimited::row(&columns, delim));
+    for r in pages {
+        let values: Vec<String> = columns.iter().map(|c| result_field(r, c, max_chars)).collect();
+        println!("{}", delimited::row(&values, delim));
+    }
+    Ok(())
+}
+
+/// Check if a specific field should be shown
+pub fn show_field(fields: &Option<HashSet<String>>, name: &str) -> bool {
+    fields.as_ref().is_none_or(|f| f.contains(name))
+}
+
+/// Format a dollar amount in a human-readable way (e.g. $107.0M, $17.0M, $500K)
+pub fn format_dollars(amount: f64) -> String {
+    if amount >= 1_000_000_000.0 {
+        format!("${:.1}B", amount / 1_000_000_000.0)
+    } else if amount >= 1_000_000.0 {
+        format!("${:.1}M", amount / 1_000_000.0)
+    } else if amount >= 1_000.0 {
+        format!("${:.0}K", amount / 1_000.0)
+    } else {
+        format!("${:.0}", amount)
+    }
+}
+
+/// Print entity (company) data in compact or normal mode
+pub fn print_entity(entity: &Entity, compact: bool, width: Option<usize>) {
+    let props = match &entity.properties {
+        Some(p) => p,
+        None => return,
+    };
+
+    if compact {
+        if let Some(desc) = &props.description {
+            let short = if desc.len() > 200 {
+                format!("{}...", desc[..200].trim_end())
+            } else {
+                desc.clone()
+            };
+            println!("{}", fill(&short, width, "about: ", "  "));
+        }
+        if let Some(hq) = &props.headquarters {
+            let parts: Vec<&str> = [hq.city.as_deref(), hq.country.as_deref()]
+                .iter().filter_map(|x| *x).collect();
+            if !parts.is_empty() {
+                println!("hq: {}", parts.join(", "));
+            }
+        }
+        if let Some(wf) = &props.workforce {
+            if let Some(total) = wf.total {
+                println!("employees: {}", total);
+            }
+        }
+        if let Some(fin) = &props.financials {
+            if let Some(total) = fin.funding_total {
+                print!("funding: {}", format_dollars(total));
+                if let Some(round) = &fin.funding_latest_round {
+                    let round_name = round.name.as_deref().unwrap_or("?");
+                    if let Some(amt) = round.amount {
+                        print!(" (latest: {} {})", round_name, format_dollars(amt));
+                    } else {
+                        print!(" (latest: {})", round_name);
+                    }
+                }
+                println!();
+            }
+        }
+        if let Some(wt) = &props.web_traffic {
+            if let Some(visits) = wt.visits_monthly {
+                println!("traffic: {}/mo", visits.to_string().as_bytes().rchunks(3)
+                    .rev().map(|c| std::str::from_utf8(c).unwrap())
+                    .collect::<Vec<_>>().join(","));
+            }
+        }
+    } else {
+        if let Some(desc) = &props.description {
+            println!("{}", fill(desc, width, "  ", "  "));
+        }
+        if let Some(hq) = &props.headquarters {
+            let parts: Vec<&str> = [hq.city.as_deref(), hq.country.as_deref()]
+                .iter().filter_map(|x| *x).collect();
+            if !parts.is_empty() {
+                println!("  {} {}", "HQ:".dimmed(), parts.join(", "));
+            }
+        }
+        if let Some(wf) = &props.workforce {
+            if let Some(total) = wf.total {
+                println!("  {} {}", "Employees:".dimmed(), total);
+            }
+        }
+        if let Some(fin) = &props.financials {
+            if let Some(total) = fin.funding_total {
+                print!("  {} {}", "Funding:".dimmed(), format_dollars(total));
+                if let Some(round) = &fin.funding_latest_round {
+                    let round_name = round.name.as_deref().unwrap_or("?");
+                    if let Some(amt) = round.amount {
+                        print!(" (latest: {} {})", round_name, format_dollars(amt));
+                    } else {
+                        print!(" (latest: {})", round_name);
+                    }
+                }
+                println!();
+            }
+        }
+        if let Some(wt) = &props.web_traffic {
+            if let Some(visits) = wt.visits_monthly {
+                println!("  {} {}/mo", "Traffic:".dimmed(), visits.to_string().as_bytes().rchunks(3)
+                    .rev().map(|c| std::str::from_utf8(c).unwrap())
+                    .collect::<Vec<_>>().join(","));
+            }
+        }
+    }
+}
+
+/// The results as shown: do-not-ingest and paywall flags, --skip-paywalled,
+/// --prefer-open, --dedupe, --deterministic and --sample applied. None when
+/// none of them changes anything.
+pub fn result_view(cli: &Cli, results: &SearchResponse) -> Option<SearchResponse> {
+    let mut view = None;
+    let policy = load_policy(cli);
+    let has_text = results.results.iter().any(|r| r.text.is_some());
+    if !policy.is_empty() || has_text {
+        let mut annotated = results.clone();
+        for r in &mut annotated.results {
+            r.do_not_ingest = policy.blocks(&r.url);
+            r.paywalled = r.text.as_deref().is_some_and(paywall::is_paywalled);
+        }
+        if cli.skip_paywalled {
+            annotated.results.retain(|r| !r.paywalled);
+        } else if cli.prefer_open {
+            annotated.results.sort_by_key(|r| r.paywalled);
+        }
+        view = Some(annotated);
+    }
+    if cli.dedupe {
+        let mut deduped = view.take().unwrap_or_else(|| results.clone());
+        let dropped = fingerprint::dedupe(&mut deduped.results);
+        if dropped > 0 {
+            warnings::emit("near_duplicates", format!("dropped {} near-duplicate result(s)", dropped), true);
+        }
+        view = Some(deduped);
+    }
+    if cli.deterministic {
+        view = Some(deterministic_view(view.as_ref().unwrap_or(results), true));
+    }
+    if let Some(n) = cli.sample {
+        let seed = cli.seed.unwrap_or_else(|| {
+            let seed = chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default() as u64;
+            diag!("{}", format!("sample seed: {} (pass --seed to reproduce)", seed).dimmed());
+            seed
+        });
+        let base = view.as_ref().unwrap_or(results);
+        let mut sampled = base.clone();
+        sampled.results = sample_indices(base.results.len(), n, seed)
+            .into_iter()
+            .map(|i| base.results[i].clone())
+            .collect();
+        view = Some(sampled);
+    }
+    view
+}
+
+pub fn print_search_results(cli: &Cli, results: &SearchResponse) -> Result<()> {
+    let view = result_view(cli, results);
+    let results = view.as_ref().unwrap_or(results);
+
+    if cli.json {
+        println!("{}", to_json(results, cli.compact)?);
+        return Ok(());
+    }
+
+    let pages: Vec<Page> = results.results.iter().map(Page::from).collect();
+    // Even with no results: a cron job writing the feed should still leave a valid one
+    if let Some(format) = cli.rss {
+        print_feed(cli, &pages, format);
+    }
+
+    if pages.is_empty() {
+        diag!("No results found.");
+        std::process::exit(3);
+    }
+
+    save_last_results(results);
+
+    if cli.rss.is_some() {
+        return Ok(());
+    }
+
+    if cli.jsonl {
+        for r in &pages {
+            print_result_line(r)?;
+        }
+        return Ok(());
+    }
+
+    if cli.markdown {
+        let max_chars = get_max_chars(cli);
+        warn_truncated(pages.iter(), max_chars);
+        print!("{}", markdown::results(&results_title(cli), &pages, |r| excerpt(r, max_chars)));
+        return Ok(());
+    }
+
+    if cli.format == Some(OutputFormat::Html) {
+        let max_chars = get_max_chars(cli);
+        warn_truncated(pages.iter(), max_chars);
+        print!("{}", html::results(&results_title(cli), &pages, |r| excerpt(r, max_chars)));
+        return Ok(());
+    }
+
+    if let Some(field) = cli.pick {
+        return pick_result(&pages, field);
+    }
+
+    if cli.fzf {
+        for (i, r) in pages.iter().enumerate() {
+            let title = r.title.as_deref().unwrap_or("N/A").replace(['\t', '\n'], " ");
+            println!("{}\t{}\t{}", i + 1, title, r.url);
+        }
+        return Ok(());
+    }
+
+    let max_chars = get_max_chars(cli);
+    warn_truncated(pages.iter(), max_chars);
+    let fields = parse_fields(cli);
+    let width = output_width(cli);
+
+    if cli.format == Some(OutputFormat::Jsonl) {
+        return print_jsonl(cli, &pages, max_chars);
+    }
+
+    if cli.format == Some(OutputFormat::Quickfix) {
+        for r in &pages {
+            println!("{}", quickfix_line(r, max_chars));
+        }
+        return Ok(());
+    }
+
+    if cli.format == Some(OutputFormat::GhAnnotations) {
+        for r in &pages {
+            println!("{}", gh_annotation(r, cli.annotation_level, max_chars));
+        }
+        return Ok(());
+    }
+
+    let note_kind = match cli.format {
+        Some(OutputFormat::Org) => Some(notes::NoteKind::Org),
+        Some(OutputFormat::Obsidian) => Some(notes::NoteKind::Obsidian),
+        _ => None,
+    };
+    if let Some(kind) = note_kind {
+        return print_notes(cli, &pages, kind);
+    }
+
+    if cli.tsv || cli.csv {
+        let delim = if cli.csv { delimited::Delimiter::Comma } else { delimited::Delimiter::Tab };
+        return print_delimited(cli, &pages, delim, max_chars);
+    }
+
+    if cli.compact {
+        for (i, r) in pages.iter().enumerate() {
+            if show_field(&fields, "title") {
+                println!("[{}] {}", i + 1, r.title.as_deref().unwrap_or("N/A"));
+            }
+            if show_field(&fields, "url") {
+                println!("url: {}", r.url);
+            }
+            if r.do_not_ingest {
+                println!("restricted: do-not-ingest");
+            }
+            if r.paywalled {
+                println!("access: paywalled");
+            }
+            if show_field(&fields, "date") {
+                if let Some(date) = &r.published {
+                    println!("date: {}", date);
+                }
+            }
+            if show_field(&fields, "content") {
+                if let Some(text) = &r.text {
+                    println!("{}", fill(&truncate_text(text, max_chars), width, "content: ", "  "));
+                }
+                if !r.highlights.is_empty() {
+                    for h in &r.highlights {
+                        println!("{}", fill(h, width, "highlight: ", "  "));
+                    }
+                }
+            }
+            // Snippets only on request; by default content and highlights are shown
+            if fields.as_ref().is_some_and(|f| f.contains("snippet")) {
+                if let Some(snippet) = snippet(r) {
+                    println!("{}", fill(&truncate_text(&snippet, max_chars), width, "snippet: ", "  "));
+                }
+            }
+            for entity in &r.entities {
+                print_entity(entity, true, width);
+            }
+        }
+    } else {
+        for (i, r) in pages.iter().enumerate() {
+            println!("{}", format!("--- Result {} ---", i + 1).dimmed());
+            if let Some(thumbnail) = thumbnails::get(&r.url) {
+                print!("{} ", thumbnail);
+            }
+            if show_field(&fields, "title") {
+                println!("{} {}", "Title:".bold(), r.title.as_deref().unwrap_or("N/A"));
+            }
+            if show_field(&fields, "url") {
+                println!("{} {}", "Link:".cyan(), r.url);
+            }
+            if r.do_not_ingest {
+                println!("{}", "Do not ingest: domain is on the do-not-ingest list".red());
+            }
+            if r.paywalled {
+                println!("{}", "Paywalled: likely needs a subscription or login".yellow());
+            }
+            if show_field(&fields, "date") {
+                if let Some(date) = &r.published {
+                    println!("{} {}", "Date:".dimmed(), date);
+                }
+            }
+            if show_field(&fields, "content") {
+                if let Some(text) = &r.text {
+                    println!("{}", "Content:".green());
+                    println!("{}", fill(&truncate_text(text, max_chars), width, "", ""));
+                }
+                if !r.highlights.is_empty() {
+                    println!("{}", "Highlights:".yellow());
+                    for h in &r.highlights {
+                        println!("{}", fill(h, width, "  ", "  "));
+                    }
+                }
+            }
+            for entity in &r.entities {
+                print_entity(entity, false, width);
+            }
+            println!();
+        }
+    }
+
+    Ok(())
+}
+
+/// Render results as org/obsidian notes, to stdout or one file each in --vault-dir
+pub fn print_notes(cli: &Cli, pages: &[Page], kind: notes::NoteKind) -> Result<()> {
+    for r in pages {
+        let text = match cli.max_chars {
+            Some(max) => r.text.as_deref().map(|t| truncate_text(t, max)),
+            None => r.text.clone(),
+        };
+        let note = notes::Note {
+            title: r.title.as_deref().unwrap_or(&r.url),
+            url: &r.url,
+            published_date: r.published.as_deref(),
+            text: text.as_deref(),
+            highlights: &r.highlights,
+        };
+        match &cli.vault_dir {
+            Some(_) if r.do_not_ingest && !cli.force => {
+                diag!("{} {} (do-not-ingest; use --force)", "Refused".red(), r.url);
+            }
+            Some(dir) => match notes::write_note(dir, kind, &note, &cli.tags)? {
+                Some(path) => diag!("{} {}", "Wrote".green(), path.display()),
+                None => diag!("{} {} (note exists)", "Skipped".dimmed(), notes::note_title(note.title)),
+            },
+            None => println!("{}", notes::render(kind, &note, &cli.tags)),
+        }
+    }
+    Ok(())
+}
+
+/// A result's first highlight, else its text, on one line
+pub fn snippet(r: &Page) -> Option<String> {
+    r.excerpt()
+        .map(|s| s.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|s| !s.is_empty())
+}
+
+/// What a result set is for: the search query, or the find seed
+/// The results as a --notify digest: the Markdown document, and the HTML
+/// page too with --format html
+#[cfg(feature = "email")]
+pub fn digest(cli: &Cli, results: &SearchResponse) -> (String, Option<String>) {
+    let view = result_view(cli, results);
+    let results = view.as_ref().unwrap_or(results);
+    let pages: Vec<Page> = results.results.iter().map(Page::from).collect();
+    let max_chars = get_max_chars(cli);
+    let title = results_title(cli);
+    let text = markdown::results(&title, &pages, |r| excerpt(r, max_chars));
+    let html = cli.format == Some(OutputFormat::Html);
+    (text, html.then(|| html::results(&title, &pages, |r| excerpt(r, max_chars))))
+}
+
+fn results_title(cli: &Cli) -> String {
+    match &cli.command {
+        Commands::Find { query, .. } => format!("similar to {}", query.join(" ")),
+        Commands::Search { query } => query.join(" "),
+        _ => "results".to_string(),
+    }
+}
+
+/// A result's highlights, or else its text, on one line and cut to `max_chars`
+fn excerpt(r: &Page, max_chars: usize) -> Option<String> {
+    let text = if r.highlights.is_empty() { r.text.clone()? } else { r.highlights.join(" … ") };
+    Some(truncate_text(&normalize_whitespace(&text), max_chars)).filter(|s| !s.is_empty())
+}
+
+/// The results as a feed named after the query; descriptions are the
+/// highlights, or else the text, cut to --max-chars
+fn print_feed(cli: &Cli, pages: &[Page], format: FeedFormat) {
+    let title = format!("Exa: {}", results_title(cli));
+    let max_chars = get_max_chars(cli);
+    let items: Vec<feeds::Item> = pages
+        .iter()
+        .map(|r| {
+            feeds::Item {
+                title: r.title.clone().filter(|t| !t.trim().is_empty()).unwrap_or_else(|| r.url.clone()),
+                url: r.url.clone(),
+                published: r.published.as_deref().and_then(feeds::parse_date),
+                summary: excerpt(r, max_chars),
+            }
+        })
+        .collect();
+    // The newest result date keeps the output stable between identical runs
+    let updated = items.iter().filter_map(|i| i.published).max().unwrap_or_else(chrono::Utc::now);
+    match format {
+        FeedFormat::Rss => print!("{}", feeds::rss(&title, &items, updated)),
+        FeedFormat::Atom => print!("{}", feeds::atom(&title, &items, updated)),
+    }
+}
+
+/// One `url:1: title — snippet` line; the snippet is the first highlight or the text
+pub fn quickfix_line(r: &Page, max_chars: usize) -> String {
+    let title = r.title.as_deref().unwrap_or("N/A").split_whitespace().collect::<Vec<_>>().join(" ");
+    match snippet(r) {
+        Some(snippet) => format!("{}:1: {} — {}", r.url, title, truncate_text(&snippet, max_chars)),
+        None => format!("{}:1: {}", r.url, title),
+    }
+}
+
+/// A GitHub Actions workflow command (`::notice title=...::url`, with the
+/// snippet on the following lines of the message)
+pub fn gh_annotation(r: &Page, level: AnnotationLevel, max_chars: usize) -> String {
+    // Data is percent-encoded; properties also encode their delimiters
+    let data = |s: &str| s.replace('%', "%25").replace('\r', "%0D").replace('\n', "%0A");
+    let property = |s: &str| data(s).replace(':', "%3A").replace(',', "%2C");
+    let title = r.title.as_deref().unwrap_or("N/A").split_whitespace().collect::<Vec<_>>().join(" ");
+    let mut message = r.url.clone();
+    if let Some(snippet) = snippet(r) {
+        message.push('\n');
+        message.push_str(&truncate_text(&snippet, max_chars));
+    }
+    format!("::{} title={}::{}", level.as_str(), property(&title), data(&message))
+}
+
+/// Copy of a response with run-specific noise removed for --deterministic:
+/// no request id or cost, whitespace normalized, and (if `sort`) URL order
+pub fn deterministic_view(results: &SearchResponse, sort: bool) -> SearchResponse {
+    let mut view = results.clone();
+    view.request_id = None;
+    view.cost_dollars = None;
+    for r in &mut view.results {
+        r.title = r.title.as_deref().map(normalize_whitespace);
+        r.text = r.text.as_deref().map(normalize_whitespace);
+        if let Some(highlights) = &mut r.highlights {
+            for h in highlights.iter_mut() {
+                *h = normalize_whitespace(h);
+            }
+        }
+    }
+    if sort {
+        view.results.sort_by(|a, b| a.url.cmp(&b.url));
+    }
+    view
+}
+
+/// Pick `n` distinct indices out of `len` in random order (partial
+/// Fisher-Yates driven by SplitMix64, so a seed always gives the same sample)
+pub fn sample_indices(len: usize, n: usize, seed: u64) -> Vec<usize> {
+    let mut state = seed;
+    let mut next = || {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    };
+    let mut indices: Vec<usize> = (0..len).collect();
+    let n = n.min(len);
+    for i in 0..n {
+        let j = i + (next() % (len - i) as u64) as usize;
+        indices.swap(i, j);
+    }
+    indices.truncate(n);
+    indices
+}
+
+/// Collapse runs of spaces within lines and runs of blank lines, trim the ends
+pub fn normalize_whitespace(text: &str) -> String {
+    let mut out = Vec::new();
+    for line in text.lines() {
+        let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        if line.is_empty() && out.last().is_none_or(|l: &String| l.is_empty()) {
+            continue;
+        }
+        out.push(line);
+    }
+    while out.last().is_some_and(|l| l.is_empty()) {
+        out.pop();
+    }
+    out.join("\n")
+}
+
+/// One source rendered with --source-fields (default: just the URL)
+pub fn source_line(cli: &Cli, url: &str, title: Option<&str>, date: Option<&str>, color: bool) -> String {
+    let fields = if cli.source_fields.is_empty() { &[SourceField::Url][..] } else { &cli.source_fields };
+    let parts: Vec<String> = fields
+        .iter()
+        .filter_map(|f| match f {
+            SourceField::Url if color => Some(url.cyan().to_string()),
+            SourceField::Url => Some(url.to_string()),
+            SourceField::Title => title.map(|t| t.to_string()),
+            SourceField::Date if color => date.map(|d| d.get(..10).unwrap_or(d).dimmed().to_string()),
+            SourceField::Date => date.map(|d| d.get(..10).unwrap_or(d).to_string()),
+        })
+        .collect();
+    if parts.is_empty() {
+        return url.to_string();
+    }
+    parts.join(if color { "  " } else { ", " })
+}
+
+/// Apply --sources and --source-fields to the "citations" array of JSON output
+pub fn select_sources(cli: &Cli, value: &mut serde_json::Value, default_n: usize) {
+    let Some(citations) = value.get_mut("citations").and_then(|c| c.as_array_mut()) else { return };
+    citations.truncate(cli.sources.unwrap_or(default_n));
+    if cli.source_fields.is_empty() {
+        return;
+    }
+    for c in citations.iter_mut() {
+        if let Some(obj) = c.as_object_mut() {
+            obj.retain(|k, _| k == "text" || cli.source_fields.iter().any(|f| f.json_key() == k));
+        }
+    }
+}
+
+/// Numbered list on stderr (even with --log-file), choice from stdin,
+/// selected field on stdout
+pub fn pick_result(pages: &[Page], field: PickField) -> Result<()> {
+    diag::on_terminal(|| {
+        for (i, r) in pages.iter().enumerate() {
+            diag!("[{}] {} {}", i + 1, r.title.as_deref().unwrap_or("N/A"), r.url.dimmed());
+        }
+        diag_inline!("Pick [1-{}]: ", pages.len());
+    });
+
+    let mut line = String::new();
+    std::io::stdin().read_line(&mut line).context("Failed to read selection")?;
+    let choice: usize = line.trim().parse().with_context(|| format!("Not a number: '{}'", line.trim()))?;
+    let Some(r) = choice.checked_sub(1).and_then(|i| pages.get(i)) else {
+        bail!("Selection out of range: {}", choice);
+    };
+
+    match field {
+        PickField::Url => println!("{}", r.url),
+        PickField::Content => {
+            if let Some(text) = &r.text {
+                println!("{}", text);
+            } else if !r.highlights.is_empty() {
+                println!("{}", r.highlights.join("\n"));
+            } else {
+                bail!("Result has no content; add --content or --highlights");
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::answer::ANSWER_SOURCES;
+    use clap::Parser;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_fill() {
+        assert_eq!(fill("one two three", None, "> ", "  "), "> one two three");
+        assert_eq!(
+            fill("one two three four", Some(12), "- ", "  "),
+            "- one two\n  three four"
+        );
+        assert_eq!(fill("a\nb", Some(10), "", ""), "a\nb");
+        assert_eq!(fill("abcdefghij", Some(4), "", ""), "abcd\nefgh\nij");
+    }
+
+    #[test]
+    fn test_sample_indices() {
+        let a = sample_indices(50, 10, 42);
+        assert_eq!(a, sample_indices(50, 10, 42));
+        assert_ne!(a, sample_indices(50, 10, 43));
+        assert_eq!(a.len(), 10);
+        assert_eq!(a.iter().collect::<HashSet<_>>().len(), 10);
+        assert!(a.iter().all(|&i| i < 50));
+
+        let mut all = sample_indices(3, 10, 1);
+        all.sort();
+        assert_eq!(all, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_result_fields() {
+        let cli = Cli::parse_from(["exa", "search", "q", "--csv", "--fields", "url,content"]);
+        assert_eq!(field_list(&cli, &["title"]).unwrap(), ["url", "content"]);
+        let bad = Cli::parse_from(["exa", "search", "q", "--fields", "url,body"]);
+        assert!(field_list(&bad, &["title"]).is_err());
+
+        let r = Page {
+            url: "https://a.com".into(),
+            text: Some("First line.\nSecond line is longer.".into()),
+            highlights: vec!["a".into(), "b".into()],
+            ..Page::default()
+        };
+        let row: Vec<String> = ["title", "content", "highlights"].iter().map(|f| result_field(&r, f, 100)).collect();
+        assert_eq!(
+            delimited::row(&row, delimited::Delimiter::Tab),
+            "\tFirst line.\\nSecond line is longer.\ta | b"
+        );
+    }
+
+    #[test]
+    fn test_select_sources() {
+        let cli = Cli::parse_from(["exa", "answer", "q", "--sources", "1", "--source-fields", "title,date"]);
+        let mut value = serde_json::json!({
+            "answer": "a",
+            "citations": [
+                { "url": "https://a.com", "title": "A", "publishedDate": "2024-01-02" },
+                { "url": "https://b.com", "title": "B" }
+            ]
+        });
+        select_sources(&cli, &mut value, ANSWER_SOURCES);
+        assert_eq!(value["citations"], serde_json::json!([{ "title": "A", "publishedDate": "2024-01-02" }]));
+        assert_eq!(source_line(&cli, "https://a.com", Some("A"), Some("2024-01-02T00:00:00Z"), false), "A, 2024-01-02");
+    }
+
+    #[test]
+    fn test_normalize_whitespace() {
+        assert_eq!(normalize_whitespace("  a \t b  \n\n\n c\n\n"), "a b\n\nc");
+        assert_eq!(normalize_whitespace("\n\nx"), "x");
+    }
+
+    #[test]
+    fn test_quickfix_line() {
+        let mut r = Page {
+            title: Some("Rust\nBook".into()),
+            url: "https://doc.rust-lang.org/book/".into(),
+            text: Some("The  Rust\nProgramming Language".into()),
+            ..Page::default()
+        };
+        assert_eq!(
+            quickfix_line(&r, 500),
+            "https://doc.rust-lang.org/book/:1: Rust Book — The Rust Programming Language"
+        );
+        r.text = None;
+        assert_eq!(quickfix_line(&r, 500), "https://doc.rust-lang.org/book/:1: Rust Book");
+    }
+
+    #[test]
+    fn test_gh_annotation() {
+        let value = serde_json::json!({
+            "url": "https://a.com/cve?id=1",
+            "title": "CVE-2026-1: acme, widgets\n100% affected",
+            "text": "Line one.\nLine two."
+        });
+        let r = Page::from(&serde_json::from_value(value).unwrap());
+        assert_eq!(
+            gh_annotation(&r, AnnotationLevel::Warning, 500),
+            "::warning title=CVE-2026-1%3A acme%2C widgets 100%25 affected::\
+             https://a.com/cve?id=1%0ALine one. Line two."
+        );
+        let r = Page { url: "https://b.com".into(), ..Page::default() };
+        assert_eq!(gh_annotation(&r, AnnotationLevel::Notice, 500), "::notice title=N/A::https://b.com");
+    }
+}
@@ -0,0 +1,90 @@
+//! Saved searches: a query and its flags stored under a name in
+//! `<config dir>/saved.toml`, run again with `exa run <name>`
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use clap::Parser;
+use exa_cli::KeyManager;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::cli::Cli;
+
+const SAVED_FILE: &str = "saved.toml";
+
+/// Commands a saved search can run; anything else is a search query
+const COMMANDS: [&str; 5] = ["search", "find", "content", "answer", "research"];
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Saved {
+    /// Command line after `exa`, e.g. ["search", "AI chip startups", "-n", "20"]
+    pub args: Vec<String>,
+    pub saved_at: DateTime<Utc>,
+}
+
+pub fn path() -> Result<PathBuf> {
+    Ok(KeyManager::get_config_dir()?.join(SAVED_FILE))
+}
+
+/// Every saved search by name
+pub fn load() -> Result<BTreeMap<String, Saved>> {
+    let path = path()?;
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    toml::from_str(&text).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+pub fn store(saved: &BTreeMap<String, Saved>) -> Result<()> {
+    let path = path()?;
+    fs::write(&path, toml::to_string(saved)?).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// The command line to save for `args`: `search` is assumed unless they
+/// start with a query command, and they must parse as an exa command line
+pub fn command_args(args: &[String]) -> Result<Vec<String>> {
+    let Some(first) = args.first() else { bail!("Nothing to save; give a query and its flags") };
+    let mut out = Vec::with_capacity(args.len() + 1);
+    if !COMMANDS.contains(&first.as_str()) {
+        out.push("search".to_string());
+    }
+    out.extend(args.iter().cloned());
+    if let Err(e) = Cli::try_parse_from(std::iter::once("exa").chain(out.iter().map(String::as_str))) {
+        bail!("Not a valid command line:\n{}", e.to_string().trim_end());
+    }
+    Ok(out)
+}
+
+/// Names are used on the command line, so keep them to one shell word
+pub fn check_name(name: &str) -> Result<()> {
+    let ok = !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !ok {
+        bail!("Invalid name '{}': use letters, digits, '-', '_' and '.'", name);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_args() {
+        let args = |a: &[&str]| command_args(&a.iter().map(|s| s.to_string()).collect::<Vec<_>>());
+        assert_eq!(
+            args(&["AI chip startups", "--category", "company", "-n", "20"]).unwrap(),
+            ["search", "AI chip startups", "--category", "company", "-n", "20"]
+        );
+        assert_eq!(args(&["answer", "what is rust"]).unwrap(), ["answer", "what is rust"]);
+        assert!(args(&["query", "--no-such-flag"]).is_err());
+        assert!(args(&[]).is_err());
+
+        assert!(check_name("nightly-ai").is_ok());
+        assert!(check_name("two words").is_err());
+        assert!(check_name("").is_err());
+    }
+}
@@ -0,0 +1,331 @@
+//! When a watch runs: every --watch interval or at each --cron time, moved
+//! out of --quiet-hours and delayed by up to --jitter. Cron expressions and
+//! quiet hours are in local time.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Datelike, Duration, Local, Months, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Timelike};
+
+use crate::cli::{Interval, Jitter};
+
+const MONTHS: &[&str] = &["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+const WEEKDAYS: &[&str] = &["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+/// How far ahead a cron expression is searched for its next time
+const CRON_HORIZON_DAYS: i64 = 5 * 366;
+/// Quiet windows skipped before a cron expression is taken never to run outside them
+const MAX_QUIET_SKIPS: usize = 1000;
+
+/// A five-field cron expression (minute hour day-of-month month day-of-week)
+/// with lists, ranges, steps and month and weekday names, or one of
+/// @hourly, @daily, @weekly, @monthly and @yearly
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cron {
+    expr: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// Day-of-month or day-of-week is `*`: then a day must match both (one
+    /// being everything); otherwise either, as cron does
+    any_day: bool,
+}
+
+/// The values one field allows, as a bit set
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str], name_base: u32) -> Result<u64, String> {
+    let value = |s: &str| -> Result<u32, String> {
+        let lower = s.to_ascii_lowercase();
+        let n = match names.iter().position(|n| *n == lower) {
+            Some(i) => i as u32 + name_base,
+            None => s.parse().map_err(|_| format!("'{}' is not a number", s))?,
+        };
+        if n < min || n > max {
+            return Err(format!("{} is outside {}-{}", n, min, max));
+        }
+        Ok(n)
+    };
+    let mut bits = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step.parse().ok().filter(|s| *s > 0).ok_or_else(|| format!("bad step in '{}'", part))?;
+                (range, Some(step))
+            }
+            None => (part, None),
+        };
+        let (start, end) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((a, b)) => (value(a)?, value(b)?),
+                // `5/15`: from 5 to the end, every 15
+                None if step.is_some() => (value(range)?, max),
+                None => (value(range)?, value(range)?),
+            },
+        };
+        if start > end {
+            return Err(format!("'{}' runs backwards", range));
+        }
+        for n in (start..=end).step_by(step.unwrap_or(1) as usize) {
+            bits |= 1 << n;
+        }
+    }
+    Ok(bits)
+}
+
+fn has(bits: u64, n: u32) -> bool {
+    bits & (1 << n) != 0
+}
+
+impl std::str::FromStr for Cron {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let expr = s.split_whitespace().collect::<Vec<_>>().join(" ");
+        let fields = match expr.as_str() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            other => other,
+        };
+        let fields: Vec<&str> = fields.split(' ').collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(format!("'{}' needs five fields: minute hour day month weekday", expr));
+        };
+        let field = |name: &str, result: Result<u64, String>| result.map_err(|e| format!("{} field: {}", name, e));
+        // Sunday is 0 or 7
+        let mut weekdays = field("weekday", parse_field(weekday, 0, 7, WEEKDAYS, 0))?;
+        if has(weekdays, 7) {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+        Ok(Self {
+            minutes: field("minute", parse_field(minute, 0, 59, &[], 0))?,
+            hours: field("hour", parse_field(hour, 0, 23, &[], 0))?,
+            days: field("day", parse_field(day, 1, 31, &[], 0))?,
+            months: field("month", parse_field(month, 1, 12, MONTHS, 1))?,
+            weekdays,
+            any_day: day.starts_with('*') || weekday.starts_with('*'),
+            expr,
+        })
+    }
+}
+
+impl std::fmt::Display for Cron {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.expr)
+    }
+}
+
+impl Cron {
+    fn day_matches(&self, date: NaiveDate) -> bool {
+        let day = has(self.days, date.day());
+        let weekday = has(self.weekdays, date.weekday().num_days_from_sunday());
+        if self.any_day {
+            day && weekday
+        } else {
+            day || weekday
+        }
+    }
+
+    /// The first matching minute after `after`
+    fn next_naive(&self, after: NaiveDateTime) -> Option<NaiveDateTime> {
+        let mut t = after.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let horizon = t + Duration::days(CRON_HORIZON_DAYS);
+        while t < horizon {
+            let date = t.date();
+            if !has(self.months, date.month()) {
+                let first = date.with_day(1)?;
+                t = first.checked_add_months(Months::new(1))?.and_time(NaiveTime::MIN);
+            } else if !self.day_matches(date) {
+                t = date.succ_opt()?.and_time(NaiveTime::MIN);
+            } else if !has(self.hours, t.hour()) {
+                t = date.and_hms_opt(t.hour(), 0, 0)? + Duration::hours(1);
+            } else if !has(self.minutes, t.minute()) {
+                t += Duration::minutes(1);
+            } else {
+                return Some(t);
+            }
+        }
+        None
+    }
+}
+
+/// --quiet-hours: a daily window, such as 22:00-07:00, in which a watch
+/// doesn't run
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QuietHours {
+    start: NaiveTime,
+    end: NaiveTime,
+}
+
+impl std::str::FromStr for QuietHours {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let time = |t: &str| {
+            NaiveTime::parse_from_str(t.trim(), "%H:%M").map_err(|_| format!("'{}' is not a time such as 07:30", t.trim()))
+        };
+        let Some((start, end)) = s.split_once('-') else {
+            return Err(format!("'{}' is not a window such as 22:00-07:00", s));
+        };
+        let (start, end) = (time(start)?, time(end)?);
+        if start == end {
+            return Err("quiet hours must start and end at different times".to_string());
+        }
+        Ok(Self { start, end })
+    }
+}
+
+impl std::fmt::Display for QuietHours {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}", self.start.format("%H:%M"), self.end.format("%H:%M"))
+    }
+}
+
+impl QuietHours {
+    /// When the quiet window `t` falls in ends, if it falls in one
+    fn end_after(&self, t: NaiveDateTime) -> Option<NaiveDateTime> {
+        let (date, time) = (t.date(), t.time());
+        if self.start < self.end {
+            (self.start <= time && time < self.end).then(|| date.and_time(self.end))
+        } else if time >= self.start {
+            date.succ_opt().map(|next| next.and_time(self.end))
+        } else {
+            (time < self.end).then(|| date.and_time(self.end))
+        }
+    }
+}
+
+/// How a watch is scheduled: --watch INTERVAL or --cron EXPR
+#[derive(Debug, Clone)]
+pub enum Schedule {
+    Every(Interval),
+    Cron(Cron),
+}
+
+impl std::fmt::Display for Schedule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Schedule::Every(every) => write!(f, "every {}", every),
+            Schedule::Cron(cron) => write!(f, "cron {}", cron),
+        }
+    }
+}
+
+impl Schedule {
+    /// When the next run starts, as of `now`: right away (the first run) or
+    /// an interval later, or at the next cron time; either way moved past
+    /// quiet hours. None when a cron expression never runs outside them.
+    fn next_naive(&self, now: NaiveDateTime, first: bool, quiet: Option<&QuietHours>) -> Option<NaiveDateTime> {
+        match self {
+            Schedule::Every(every) => {
+                let at = if first { now } else { now + Duration::from_std(every.0).ok()? };
+                Some(quiet.and_then(|q| q.end_after(at)).unwrap_or(at))
+            }
+            Schedule::Cron(cron) => {
+                let mut at = cron.next_naive(now)?;
+                for _ in 0..MAX_QUIET_SKIPS {
+                    match quiet.and_then(|q| q.end_after(at)) {
+                        // The window's end may itself be a cron time
+                        Some(end) => at = cron.next_naive(end - Duration::minutes(1))?,
+                        None => return Some(at),
+                    }
+                }
+                None
+            }
+        }
+    }
+
+    /// [`Self::next_naive`] in local time, plus up to `jitter`
+    pub fn next_run(
+        &self,
+        first: bool,
+        quiet: Option<&QuietHours>,
+        jitter: Option<Jitter>,
+    ) -> Result<DateTime<Local>> {
+        let now = Local::now();
+        let at = self.next_naive(now.naive_local(), first, quiet).with_context(|| match quiet {
+            Some(quiet) => format!("{} never runs outside --quiet-hours {}", self, quiet),
+            None => format!("{} never runs", self),
+        })?;
+        // In a daylight saving gap, go on once it has passed
+        let mut at = (0..=120)
+            .find_map(|m| Local.from_local_datetime(&(at + Duration::minutes(m))).earliest())
+            .unwrap_or(now);
+        if let Some(jitter) = jitter {
+            at += Duration::from_std(random_delay(jitter.0)).unwrap_or_default();
+        }
+        Ok(at)
+    }
+}
+
+/// A random delay from zero up to `max`, to the second
+fn random_delay(max: std::time::Duration) -> std::time::Duration {
+    let mut bytes = [0u8; 8];
+    if getrandom::getrandom(&mut bytes).is_err() {
+        return std::time::Duration::ZERO;
+    }
+    std::time::Duration::from_secs(u64::from_le_bytes(bytes) % (max.as_secs() + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_cron() {
+        let cron: Cron = "0 8 * * 1-5".parse().unwrap();
+        // 2026-10-16 is a Friday
+        assert_eq!(cron.next_naive(at("2026-10-16 07:59")), Some(at("2026-10-16 08:00")));
+        assert_eq!(cron.next_naive(at("2026-10-16 08:00")), Some(at("2026-10-19 08:00")));
+
+        let cron: Cron = "*/15 9-17 * * mon,WED".parse().unwrap();
+        assert_eq!(cron.next_naive(at("2026-10-19 17:50")), Some(at("2026-10-21 09:00")));
+        assert_eq!(cron.next_naive(at("2026-10-21 09:01")), Some(at("2026-10-21 09:15")));
+
+        // Day of month or weekday, when both are given
+        let cron: Cron = "30 6 1 * 0".parse().unwrap();
+        assert_eq!(cron.next_naive(at("2026-10-16 00:00")), Some(at("2026-10-18 06:30")));
+        assert_eq!(cron.next_naive(at("2026-10-25 07:00")), Some(at("2026-11-01 06:30")));
+
+        let cron: Cron = "@monthly".parse().unwrap();
+        assert_eq!(cron.next_naive(at("2026-12-05 00:00")), Some(at("2027-01-01 00:00")));
+        assert_eq!("0 0 29 2 7".parse::<Cron>().unwrap().weekdays, 1);
+        assert_eq!("0 0 30 2 *".parse::<Cron>().unwrap().next_naive(at("2026-01-01 00:00")), None);
+
+        assert!("0 8 * *".parse::<Cron>().unwrap_err().contains("five fields"));
+        assert!("60 * * * *".parse::<Cron>().unwrap_err().starts_with("minute field"));
+        assert!("0 8 * * fri-mon".parse::<Cron>().is_err());
+        assert!("*/0 * * * *".parse::<Cron>().is_err());
+    }
+
+    #[test]
+    fn test_quiet_hours_and_schedule() {
+        let night: QuietHours = "22:00-07:00".parse().unwrap();
+        assert_eq!(night.to_string(), "22:00-07:00");
+        assert_eq!(night.end_after(at("2026-10-16 23:30")), Some(at("2026-10-17 07:00")));
+        assert_eq!(night.end_after(at("2026-10-17 06:59")), Some(at("2026-10-17 07:00")));
+        assert_eq!(night.end_after(at("2026-10-17 07:00")), None);
+        let lunch: QuietHours = "12:00-13:30".parse().unwrap();
+        assert_eq!(lunch.end_after(at("2026-10-16 12:10")), Some(at("2026-10-16 13:30")));
+        assert_eq!(lunch.end_after(at("2026-10-16 11:59")), None);
+        assert!("22:00-22:00".parse::<QuietHours>().is_err());
+        assert!("10pm-7am".parse::<QuietHours>().is_err());
+
+        let every = Schedule::Every("2h".parse().unwrap());
+        assert_eq!(every.next_naive(at("2026-10-16 21:00"), true, Some(&night)), Some(at("2026-10-16 21:00")));
+        assert_eq!(every.next_naive(at("2026-10-16 21:00"), false, Some(&night)), Some(at("2026-10-17 07:00")));
+
+        let hourly = Schedule::Cron("0 * * * *".parse().unwrap());
+        assert_eq!(hourly.next_naive(at("2026-10-16 21:30"), false, Some(&night)), Some(at("2026-10-17 07:00")));
+        let late = Schedule::Cron("0 23 * * *".parse().unwrap());
+        assert_eq!(late.next_naive(at("2026-10-16 12:00"), false, Some(&night)), None);
+        assert_eq!(late.to_string(), "cron 0 23 * * *");
+
+        assert!(random_delay(std::time::Duration::from_secs(30)) <= std::time::Duration::from_secs(30));
+    }
+}
@@ -0,0 +1,82 @@
+//! --export-sqlite: results upserted into a SQLite database, one row per URL,
+//! so a corpus can be built up over many searches
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use exa_cli::api::SearchResult;
+use rusqlite::{params, Connection};
+use std::path::Path;
+
+const SCHEMA: &str = "CREATE TABLE IF NOT EXISTS results (
+    url TEXT PRIMARY KEY,
+    title TEXT,
+    published_date TEXT,
+    query TEXT,
+    fetched_at TEXT NOT NULL,
+    text TEXT
+)";
+
+/// Insert or update a row per result. A later run without text keeps the
+/// text already stored; query, title and fetched_at take the latest values.
+pub fn upsert(path: &Path, query: Option<&str>, results: &[SearchResult]) -> Result<usize> {
+    let mut db = Connection::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    db.execute(SCHEMA, []).context("Failed to create the results table")?;
+    let fetched_at = Utc::now().to_rfc3339();
+    let tx = db.transaction()?;
+    {
+        let mut insert = tx.prepare(
+            "INSERT INTO results (url, title, published_date, query, fetched_at, text)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT(url) DO UPDATE SET
+                 title = COALESCE(excluded.title, title),
+                 published_date = COALESCE(excluded.published_date, published_date),
+                 query = COALESCE(excluded.query, query),
+                 fetched_at = excluded.fetched_at,
+                 text = COALESCE(excluded.text, text)",
+        )?;
+        for r in results {
+            insert.execute(params![r.url, r.title, r.published_date, query, fetched_at, r.text])?;
+        }
+    }
+    tx.commit().with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(results.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_upsert() {
+        let dir = std::env::temp_dir().join(format!("exa-sqlite-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("corpus.db");
+        let result = |url: &str, title: &str, text: Option<&str>| {
+            let mut r: SearchResult = serde_json::from_value(serde_json::json!({"url": url, "title": title})).unwrap();
+            r.text = text.map(String::from);
+            r
+        };
+
+        let first = [result("https://a.com", "A", Some("first text")), result("https://b.com", "B", None)];
+        assert_eq!(upsert(&path, Some("rust"), &first).unwrap(), 2);
+        let second = [result("https://a.com", "A v2", None)];
+        assert_eq!(upsert(&path, Some("rust async"), &second).unwrap(), 1);
+
+        let db = Connection::open(&path).unwrap();
+        let rows: Vec<(String, String, String, Option<String>)> = db
+            .prepare("SELECT url, title, query, text FROM results ORDER BY url")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                ("https://a.com".into(), "A v2".into(), "rust async".into(), Some("first text".into())),
+                ("https://b.com".into(), "B".into(), "rust".into(), None),
+            ]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,50 @@
+/// Incremental parser for `text/event-stream` bodies. Feed it chunks as they
+/// arrive; it returns the `data` payload of every event completed so far.
+#[derive(Default)]
+pub struct SseParser {
+    buf: String,
+    data: Vec<String>,
+}
+
+impl SseParser {
+    pub fn push(&mut self, chunk: &str) -> Vec<String> {
+        self.buf.push_str(chunk);
+        let mut events = Vec::new();
+        while let Some(end) = self.buf.find('\n') {
+            let line: String = self.buf.drain(..=end).collect();
+            let line = line.trim_end_matches(['\n', '\r']);
+            if line.is_empty() {
+                // A blank line dispatches the event
+                if !self.data.is_empty() {
+                    events.push(self.data.join("\n"));
+                    self.data.clear();
+                }
+            } else if let Some(value) = line.strip_prefix("data:") {
+                self.data.push(value.strip_prefix(' ').unwrap_or(value).to_string());
+            }
+            // Comments (":") and other fields (event, id, retry) are ignored
+        }
+        events
+    }
+
+    /// Dispatch a final event the server did not terminate with a blank line
+    pub fn finish(&mut self) -> Option<String> {
+        let mut events = self.push("\n\n");
+        events.pop()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_chunks() {
+        let mut p = SseParser::default();
+        assert!(p.push("data: {\"a\":").is_empty());
+        assert_eq!(p.push("1}\r\n\r\n: keep-alive\n\ndata: x\ndata: y\n"), vec!["{\"a\":1}"]);
+        assert_eq!(p.push("\ndata: [DONE]"), vec!["x\ny"]);
+        assert_eq!(p.finish().as_deref(), Some("[DONE]"));
+        assert_eq!(p.finish(), None);
+    }
+}
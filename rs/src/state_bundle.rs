@@ -0,0 +1,197 @@
+use anyhow::{bail, Context, Result};
+use argon2::Argon2;
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+
+/// Entries under the config dir that are never exported: the cache is
+/// regenerable, the keys file and audit signing key are secrets and the
+/// lock file and state backup are per machine
+const SKIP_CONFIG_ENTRIES: &[&str] = &["cache", "keys", "audit.key", "state.json.lock", "state.json.bak"];
+
+/// The bundle entry holding the API keys, sealed with [`seal_keys`]
+const KEYS_ENTRY: &str = "keys.enc";
+/// Starts a sealed key list; the version fixes the KDF and cipher
+const KEYS_MAGIC: &[u8] = b"exa-keys-v1\n";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// Outcome of an import
+pub struct ImportSummary {
+    pub written: usize,
+    pub skipped: usize,
+    /// The bundle's API keys, still sealed
+    pub sealed_keys: Option<Vec<u8>>,
+}
+
+/// The cipher for `passphrase` and `salt`: a key derived with Argon2id
+/// (the crate's default cost), for ChaCha20-Poly1305
+fn cipher(passphrase: &str, salt: &[u8]) -> Result<ChaCha20Poly1305> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| anyhow::anyhow!("Failed to derive the key: {}", e))?;
+    Ok(ChaCha20Poly1305::new(Key::from_slice(&key)))
+}
+
+/// `keys` encrypted with `passphrase`: the magic line, a random salt and
+/// nonce, then the keys (one per line) encrypted and authenticated
+pub fn seal_keys(keys: &[String], passphrase: &str) -> Result<Vec<u8>> {
+    let mut random = [0u8; SALT_LEN + NONCE_LEN];
+    getrandom::getrandom(&mut random).context("Failed to get random bytes")?;
+    let (salt, nonce) = random.split_at(SALT_LEN);
+    let sealed = cipher(passphrase, salt)?
+        .encrypt(Nonce::from_slice(nonce), keys.join("\n").as_bytes())
+        .map_err(|_| anyhow::anyhow!("Failed to encrypt the keys"))?;
+    Ok([KEYS_MAGIC, &random, &sealed].concat())
+}
+
+/// The keys [`seal_keys`] sealed, given the same passphrase
+pub fn open_keys(sealed: &[u8], passphrase: &str) -> Result<Vec<String>> {
+    let Some(rest) = sealed.strip_prefix(KEYS_MAGIC).filter(|r| r.len() > SALT_LEN + NONCE_LEN) else {
+        bail!("The bundle's {} is not a key list this version can read", KEYS_ENTRY);
+    };
+    let (salt, rest) = rest.split_at(SALT_LEN);
+    let (nonce, data) = rest.split_at(NONCE_LEN);
+    let text = cipher(passphrase, salt)?
+        .decrypt(Nonce::from_slice(nonce), data)
+        .map_err(|_| anyhow::anyhow!("Wrong passphrase for the bundle's API keys (or the bundle is damaged)"))?;
+    Ok(exa_cli::key_manager::parse_keys(&String::from_utf8_lossy(&text)))
+}
+
+/// Write config (and optionally data) directories into a zstd-compressed tar.
+/// Entries are stored as `config/...` and `data/...`, and the API keys, when
+/// given (sealed), as `keys.enc`. Returns the file count.
+pub fn export(path: &Path, config_dir: &Path, data_dir: Option<&Path>, sealed_keys: Option<&[u8]>) -> Result<usize> {
+    let file = File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let encoder = zstd::Encoder::new(file, 0)?.auto_finish();
+    let mut builder = tar::Builder::new(encoder);
+
+    let mut count = add_dir(&mut builder, config_dir, Path::new("config"), SKIP_CONFIG_ENTRIES)?;
+    if let Some(data_dir) = data_dir {
+        if data_dir.exists() {
+            count += add_dir(&mut builder, data_dir, Path::new("data"), &[])?;
+        }
+    }
+    if let Some(sealed) = sealed_keys {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(sealed.len() as u64);
+        header.set_mode(0o600);
+        header.set_mtime(chrono::Utc::now().timestamp().max(0) as u64);
+        builder.append_data(&mut header, KEYS_ENTRY, sealed).context("Failed to add the keys")?;
+        count += 1;
+    }
+
+    builder.into_inner()?;
+    Ok(count)
+}
+
+fn add_dir<W: std::io::Write>(
+    builder: &mut tar::Builder<W>,
+    dir: &Path,
+    prefix: &Path,
+    skip: &[&str],
+) -> Result<usize> {
+    let mut count = 0;
+    for entry in fs::read_dir(dir)?.filter_map(|e| e.ok()) {
+        let name = entry.file_name();
+        if skip.iter().any(|s| name == *s) {
+            continue;
+        }
+        let path = entry.path();
+        let target = prefix.join(&name);
+        if path.is_dir() {
+            count += add_dir(builder, &path, &target, &[])?;
+        } else if path.is_file() {
+            builder
+                .append_path_with_name(&path, &target)
+                .with_context(|| format!("Failed to add {}", path.display()))?;
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+/// Restore a bundle. Existing files are kept unless `overwrite` is set;
+/// the sealed keys are returned for the caller to open.
+pub fn import(path: &Path, config_dir: &Path, data_dir: &Path, overwrite: bool) -> Result<ImportSummary> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let decoder = zstd::Decoder::new(file)?;
+    let mut archive = tar::Archive::new(decoder);
+    let mut summary = ImportSummary { written: 0, skipped: 0, sealed_keys: None };
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let entry_path = entry.path()?.into_owned();
+        if entry_path == Path::new(KEYS_ENTRY) {
+            let mut sealed = Vec::new();
+            entry.read_to_end(&mut sealed).context("Failed to read the keys")?;
+            summary.sealed_keys = Some(sealed);
+            continue;
+        }
+        let target = resolve_target(&entry_path, config_dir, data_dir)?;
+
+        if target.exists() && !overwrite {
+            summary.skipped += 1;
+            continue;
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        entry
+            .unpack(&target)
+            .with_context(|| format!("Failed to restore {}", target.display()))?;
+        summary.written += 1;
+    }
+
+    Ok(summary)
+}
+
+/// Map a bundle path (`config/...` or `data/...`) to its destination,
+/// rejecting absolute paths and `..` components
+fn resolve_target(entry_path: &Path, config_dir: &Path, data_dir: &Path) -> Result<PathBuf> {
+    let mut components = entry_path.components();
+    let root = match components.next() {
+        Some(Component::Normal(c)) if c == "config" => config_dir,
+        Some(Component::Normal(c)) if c == "data" => data_dir,
+        _ => bail!("Unexpected entry in state bundle: {}", entry_path.display()),
+    };
+    let rest = components.as_path();
+    if rest.as_os_str().is_empty() || !rest.components().all(|c| matches!(c, Component::Normal(_))) {
+        bail!("Unsafe path in state bundle: {}", entry_path.display());
+    }
+    Ok(root.join(rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_target_and_keys() {
+        let config = Path::new("/home/u/.config/exa");
+        let data = Path::new("/home/u/.local/share/exa");
+        assert_eq!(
+            resolve_target(Path::new("config/state.json"), config, data).unwrap(),
+            config.join("state.json")
+        );
+        assert_eq!(
+            resolve_target(Path::new("data/archive/a/b/meta.json"), config, data).unwrap(),
+            data.join("archive/a/b/meta.json")
+        );
+        assert!(resolve_target(Path::new("config/../../etc/passwd"), config, data).is_err());
+        assert!(resolve_target(Path::new("other/file"), config, data).is_err());
+
+        let keys = vec!["key-a".to_string(), "key-b".to_string()];
+        let sealed = seal_keys(&keys, "correct horse").unwrap();
+        assert!(sealed.starts_with(KEYS_MAGIC) && !sealed.windows(5).any(|w| w == b"key-a"));
+        assert_eq!(open_keys(&sealed, "correct horse").unwrap(), keys);
+        assert!(open_keys(&sealed, "wrong horse").unwrap_err().to_string().contains("Wrong passphrase"));
+        assert!(open_keys(b"keys\n", "correct horse").is_err());
+    }
+}
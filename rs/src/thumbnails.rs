@@ -0,0 +1,170 @@
+//! Inline result thumbnails for --thumbnails, drawn with the kitty or iTerm2
+//! graphics protocol. Anything that fails (no protocol, fetch error, an image
+//! the terminal can't take) just leaves the result without a thumbnail.
+
+use exa_cli::api::SearchResult;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_IMAGE_BYTES: usize = 512 * 1024;
+/// Kitty takes the payload in chunks of at most this many base64 bytes
+const KITTY_CHUNK: usize = 4096;
+const PNG_MAGIC: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// Escape sequences by result URL, filled by `prefetch` and read by the renderer
+static PREPARED: Mutex<Option<HashMap<String, String>>> = Mutex::new(None);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    Kitty,
+    Iterm,
+}
+
+fn detect_from(term: Option<&str>, term_program: Option<&str>, kitty_window_id: Option<&str>) -> Option<Protocol> {
+    if term == Some("xterm-kitty") || kitty_window_id.is_some() {
+        return Some(Protocol::Kitty);
+    }
+    match term_program {
+        Some("iTerm.app" | "WezTerm") => Some(Protocol::Iterm),
+        _ => None,
+    }
+}
+
+/// The graphics protocol the terminal speaks, from its environment
+pub fn detect() -> Option<Protocol> {
+    let var = |name| std::env::var(name).ok();
+    detect_from(
+        var("TERM").as_deref(),
+        var("TERM_PROGRAM").as_deref(),
+        var("KITTY_WINDOW_ID").as_deref(),
+    )
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let n = (u32::from(chunk[0]) << 16)
+            | (u32::from(chunk.get(1).copied().unwrap_or(0)) << 8)
+            | u32::from(chunk.get(2).copied().unwrap_or(0));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// A two-cell-wide, one-line-high image, or None when the protocol can't
+/// show it (kitty is only sent PNGs; it needs raw pixels for anything else)
+fn escape_sequence(protocol: Protocol, image: &[u8]) -> Option<String> {
+    let encoded = base64(image);
+    match protocol {
+        Protocol::Kitty => {
+            if !image.starts_with(PNG_MAGIC) {
+                return None;
+            }
+            let chunks: Vec<&[u8]> = encoded.as_bytes().chunks(KITTY_CHUNK).collect();
+            let mut out = String::new();
+            for (i, chunk) in chunks.iter().enumerate() {
+                let more = u8::from(i + 1 < chunks.len());
+                let control = if i == 0 { format!("f=100,a=T,c=2,r=1,m={}", more) } else { format!("m={}", more) };
+                out.push_str(&format!("\x1b_G{};{}\x1b\\", control, String::from_utf8_lossy(chunk)));
+            }
+            Some(out)
+        }
+        Protocol::Iterm => Some(format!(
+            "\x1b]1337;File=inline=1;size={};width=2;height=1;preserveAspectRatio=1:{}\x07",
+            image.len(),
+            encoded
+        )),
+    }
+}
+
+async fn fetch(http: &reqwest::Client, url: &str) -> Option<Vec<u8>> {
+    let resp = http.get(url).timeout(FETCH_TIMEOUT).send().await.ok()?;
+    if !resp.status().is_success() || resp.content_length().is_some_and(|len| len > MAX_IMAGE_BYTES as u64) {
+        return None;
+    }
+    let body = resp.bytes().await.ok()?;
+    (body.len() <= MAX_IMAGE_BYTES).then(|| body.to_vec())
+}
+
+/// Fetch each result's image (or, failing that, its favicon) and keep the
+/// escape sequence that draws it for `get`
+pub async fn prefetch(http: &reqwest::Client, protocol: Protocol, results: &[SearchResult]) {
+    let mut tasks = tokio::task::JoinSet::new();
+    for r in results {
+        let candidates: Vec<String> = [&r.image, &r.favicon].into_iter().flatten().cloned().collect();
+        if candidates.is_empty() {
+            continue;
+        }
+        let http = http.clone();
+        let url = r.url.clone();
+        tasks.spawn(async move {
+            for candidate in candidates {
+                let Some(image) = fetch(&http, &candidate).await else { continue };
+                if let Some(sequence) = escape_sequence(protocol, &image) {
+                    return Some((url, sequence));
+                }
+            }
+            None
+        });
+    }
+    let mut prepared = HashMap::new();
+    while let Some(joined) = tasks.join_next().await {
+        if let Ok(Some((url, sequence))) = joined {
+            prepared.insert(url, sequence);
+        }
+    }
+    if let Ok(mut slot) = PREPARED.lock() {
+        *slot = Some(prepared);
+    }
+}
+
+/// The thumbnail prepared for a result, if any
+pub fn get(url: &str) -> Option<String> {
+    PREPARED.lock().ok()?.as_ref()?.get(url).cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_from() {
+        assert_eq!(detect_from(Some("xterm-kitty"), None, None), Some(Protocol::Kitty));
+        assert_eq!(detect_from(Some("xterm-256color"), None, Some("1")), Some(Protocol::Kitty));
+        assert_eq!(detect_from(Some("xterm-256color"), Some("iTerm.app"), None), Some(Protocol::Iterm));
+        assert_eq!(detect_from(Some("xterm-256color"), Some("WezTerm"), None), Some(Protocol::Iterm));
+        assert_eq!(detect_from(Some("xterm-256color"), Some("Apple_Terminal"), None), None);
+        assert_eq!(detect_from(None, None, None), None);
+    }
+
+    #[test]
+    fn test_escape_sequence() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+
+        let png = [PNG_MAGIC, b"x"].concat();
+        let kitty = escape_sequence(Protocol::Kitty, &png).unwrap();
+        assert_eq!(kitty, format!("\x1b_Gf=100,a=T,c=2,r=1,m=0;{}\x1b\\", base64(&png)));
+        assert!(escape_sequence(Protocol::Kitty, b"\xff\xd8\xff jpeg").is_none());
+
+        let large = [PNG_MAGIC, &[0u8; 4000]].concat();
+        let kitty = escape_sequence(Protocol::Kitty, &large).unwrap();
+        assert_eq!(kitty.matches("\x1b_G").count(), 2);
+        assert!(kitty.starts_with("\x1b_Gf=100,a=T,c=2,r=1,m=1;"));
+        assert!(kitty.contains("\x1b\\\x1b_Gm=0;"));
+
+        let iterm = escape_sequence(Protocol::Iterm, b"fo").unwrap();
+        assert_eq!(iterm, "\x1b]1337;File=inline=1;size=2;width=2;height=1;preserveAspectRatio=1:Zm8=\x07");
+    }
+}
@@ -0,0 +1,225 @@
+use anyhow::{bail, Context, Result};
+use serde_json::{json, Map, Value};
+
+/// One CLI capability described as an LLM tool
+pub struct ToolSpec {
+    pub name: &'static str,
+    /// Subcommand the tool maps to
+    pub command: &'static str,
+    pub description: &'static str,
+    /// JSON Schema for the arguments object
+    pub parameters: Value,
+}
+
+/// Tools exposed to agent frameworks. `tool_call_argv` relies on the
+/// parameter names here, so keep both in sync.
+pub fn tool_specs() -> Vec<ToolSpec> {
+    let num_results = json!({
+        "type": "integer",
+        "minimum": 1,
+        "maximum": 100,
+        "description": "Number of results (default 5)"
+    });
+    vec![
+        ToolSpec {
+            name: "exa_search",
+            command: "search",
+            description: "Search the web with Exa. Returns titles, URLs, dates and optionally page text.",
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string", "description": "Search query" },
+                    "num_results": num_results,
+                    "type": {
+                        "type": "string",
+                        "enum": ["instant", "auto", "fast", "deep", "neural"],
+                        "description": "Search type (default instant)"
+                    },
+                    "category": {
+                        "type": "string",
+                        "enum": ["company", "people", "tweet", "news", "research paper", "personal site", "financial report"]
+                    },
+                    "domain": { "type": "string", "description": "Only return results from this domain" },
+                    "after": { "type": "string", "description": "Published after YYYY-MM-DD" },
+                    "before": { "type": "string", "description": "Published before YYYY-MM-DD" },
+                    "content": { "type": "boolean", "description": "Include page text" }
+                },
+                "required": ["query"]
+            }),
+        },
+        ToolSpec {
+            name: "exa_find_similar",
+            command: "find",
+            description: "Find pages similar to a URL or semantically related to a query.",
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string", "description": "URL or query" },
+                    "num_results": num_results,
+                    "content": { "type": "boolean", "description": "Include page text" }
+                },
+                "required": ["query"]
+            }),
+        },
+        ToolSpec {
+            name: "exa_get_contents",
+            command: "content",
+            description: "Fetch the clean text of a web page.",
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "url": { "type": "string", "description": "Page URL" }
+                },
+                "required": ["url"]
+            }),
+        },
+        ToolSpec {
+            name: "exa_answer",
+            command: "answer",
+            description: "Answer a question with a synthesized answer and the source URLs it cites.",
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "question": { "type": "string" }
+                },
+                "required": ["question"]
+            }),
+        },
+        ToolSpec {
+            name: "exa_research",
+            command: "research",
+            description: "Run a multi-step research task and return a written report. Slow (minutes).",
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "instructions": { "type": "string", "description": "What to research" },
+                    "model": { "type": "string", "enum": ["exa-research", "exa-research-pro"] }
+                },
+                "required": ["instructions"]
+            }),
+        },
+    ]
+}
+
+/// OpenAI `tools` array (function calling)
+pub fn openai_schema(specs: &[ToolSpec]) -> Value {
+    specs
+        .iter()
+        .map(|t| {
+            json!({
+                "type": "function",
+                "function": {
+                    "name": t.name,
+                    "description": t.description,
+                    "parameters": t.parameters,
+                }
+            })
+        })
+        .collect()
+}
+
+/// Anthropic `tools` array (tool use)
+pub fn anthropic_schema(specs: &[ToolSpec]) -> Value {
+    specs
+        .iter()
+        .map(|t| {
+            json!({
+                "name": t.name,
+                "description": t.description,
+                "input_schema": t.parameters,
+            })
+        })
+        .collect()
+}
+
+/// Translate a tool call (name + arguments) into CLI arguments, validating
+/// against the published schema
+pub fn tool_call_argv(name: &str, args: &Map<String, Value>) -> Result<Vec<String>> {
+    let specs = tool_specs();
+    let spec = specs
+        .iter()
+        .find(|t| t.name == name)
+        .with_context(|| format!("Unknown tool: {}", name))?;
+    let properties = spec.parameters["properties"].as_object().context("Malformed tool schema")?;
+    let required: Vec<&str> = spec.parameters["required"]
+        .as_array()
+        .map(|r| r.iter().filter_map(|v| v.as_str()).collect())
+        .unwrap_or_default();
+
+    if let Some(unknown) = args.keys().find(|k| !properties.contains_key(k.as_str())) {
+        bail!("Unknown argument for {}: {}", name, unknown);
+    }
+
+    let mut argv = vec!["exa".to_string(), spec.command.to_string()];
+    for (key, value) in args {
+        if required.contains(&key.as_str()) {
+            continue;
+        }
+        let flag = match key.as_str() {
+            "num_results" => "--num".to_string(),
+            other => format!("--{}", other),
+        };
+        match value {
+            Value::Bool(true) => argv.push(flag),
+            Value::Bool(false) | Value::Null => {}
+            // A value like `--log-file=x` must not become a flag of its own
+            Value::String(s) if s.starts_with('-') => bail!("Argument {} must not start with '-'", key),
+            Value::String(s) => argv.extend([flag, s.clone()]),
+            Value::Number(n) => argv.extend([flag, n.to_string()]),
+            _ => bail!("Argument {} must be a string, number or boolean", key),
+        }
+    }
+
+    // The single required argument is the positional one, after `--` so
+    // it is never read as a flag
+    argv.push("--".to_string());
+    for key in &required {
+        let value = args
+            .get(*key)
+            .and_then(|v| v.as_str())
+            .filter(|v| !v.is_empty())
+            .with_context(|| format!("Missing required string argument: {}", key))?;
+        argv.push(value.to_string());
+    }
+    Ok(argv)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schemas_cover_all_tools() {
+        let specs = tool_specs();
+        assert_eq!(openai_schema(&specs).as_array().unwrap().len(), specs.len());
+        let anthropic = anthropic_schema(&specs);
+        assert_eq!(anthropic[0]["name"], "exa_search");
+        assert_eq!(anthropic[0]["input_schema"]["required"], json!(["query"]));
+    }
+
+    #[test]
+    fn test_tool_call_argv() {
+        let args = json!({ "query": "rust async", "num_results": 3, "content": true, "after": "2024-01-01" });
+        let argv = tool_call_argv("exa_search", args.as_object().unwrap()).unwrap();
+        assert_eq!(
+            argv,
+            ["exa", "search", "--num", "3", "--content", "--after", "2024-01-01", "--", "rust async"]
+        );
+
+        // Model-supplied text never turns into flags
+        let args = json!({ "query": "--log-file=/tmp/x" });
+        let argv = tool_call_argv("exa_search", args.as_object().unwrap()).unwrap();
+        assert_eq!(argv, ["exa", "search", "--", "--log-file=/tmp/x"]);
+        let cli = <crate::cli::Cli as clap::Parser>::try_parse_from(&argv).unwrap();
+        assert!(cli.log_file.is_none());
+        assert!(matches!(cli.command, crate::cli::Commands::Search { query } if query == ["--log-file=/tmp/x"]));
+        let args = json!({ "query": "q", "domain": "--replay=/tmp" });
+        assert!(tool_call_argv("exa_search", args.as_object().unwrap()).is_err());
+
+        let missing = json!({ "num_results": 3 });
+        assert!(tool_call_argv("exa_search", missing.as_object().unwrap()).is_err());
+        let unknown = json!({ "url": "https://x.com", "bogus": 1 });
+        assert!(tool_call_argv("exa_get_contents", unknown.as_object().unwrap()).is_err());
+        assert!(tool_call_argv("nope", &Map::new()).is_err());
+    }
+}
@@ -0,0 +1,163 @@
+//! The HTTP layer under [`ExaClient`](crate::ExaClient). Key rotation, retries,
+//! decoding and replay live in the client; a [`Transport`] only moves bytes,
+//! so tests and library users can swap in [`MockTransport`].
+
+use anyhow::{Context, Result};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Method, StatusCode};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// One outgoing API request
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub method: Method,
+    pub url: String,
+    pub headers: Vec<(&'static str, String)>,
+    pub body: Option<Vec<u8>>,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Response head plus a body read chunk by chunk
+pub struct Response {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub http2: bool,
+    pub remote_addr: Option<SocketAddr>,
+    body: Body,
+}
+
+enum Body {
+    Http(reqwest::Response),
+    Chunks(VecDeque<Vec<u8>>),
+}
+
+impl Response {
+    /// A canned response, its body delivered in the given chunks
+    pub fn new(status: u16, headers: &[(&str, &str)], chunks: Vec<Vec<u8>>) -> Self {
+        let mut map = HeaderMap::new();
+        for (name, value) in headers {
+            if let (Ok(name), Ok(value)) = (HeaderName::try_from(*name), HeaderValue::try_from(*value)) {
+                map.append(name, value);
+            }
+        }
+        Self {
+            status: StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+            headers: map,
+            http2: false,
+            remote_addr: None,
+            body: Body::Chunks(chunks.into()),
+        }
+    }
+
+    /// A canned JSON response
+    pub fn json(status: u16, body: &serde_json::Value) -> Self {
+        Self::new(status, &[("content-type", "application/json")], vec![body.to_string().into_bytes()])
+    }
+
+    pub fn content_length(&self) -> Option<u64> {
+        match &self.body {
+            Body::Http(resp) => resp.content_length(),
+            Body::Chunks(chunks) => Some(chunks.iter().map(|c| c.len() as u64).sum()),
+        }
+    }
+
+    /// The next piece of the body, `None` at the end
+    pub async fn chunk(&mut self) -> Result<Option<Vec<u8>>> {
+        match &mut self.body {
+            Body::Http(resp) => Ok(resp.chunk().await?.map(|b| b.to_vec())),
+            Body::Chunks(chunks) => Ok(chunks.pop_front()),
+        }
+    }
+
+    /// The rest of the body as (lossy) text
+    pub async fn text(&mut self) -> Result<String> {
+        let mut body = Vec::new();
+        while let Some(chunk) = self.chunk().await? {
+            body.extend_from_slice(&chunk);
+        }
+        Ok(String::from_utf8_lossy(&body).into_owned())
+    }
+}
+
+/// Sends requests for [`ExaClient`](crate::ExaClient)
+pub trait Transport: Send + Sync {
+    fn send(&self, request: Request) -> BoxFuture<'_, Result<Response>>;
+}
+
+/// The real transport: a pooled reqwest client
+pub struct HttpTransport(pub reqwest::Client);
+
+impl Transport for HttpTransport {
+    fn send(&self, request: Request) -> BoxFuture<'_, Result<Response>> {
+        Box::pin(async move {
+            let mut req = self.0.request(request.method, &request.url);
+            for (name, value) in &request.headers {
+                req = req.header(*name, value);
+            }
+            if let Some(body) = request.body {
+                req = req.body(body);
+            }
+            let resp = req.send().await.context("request failed")?;
+            Ok(Response {
+                status: resp.status(),
+                headers: resp.headers().clone(),
+                http2: resp.version() == reqwest::Version::HTTP_2,
+                remote_addr: resp.remote_addr(),
+                body: Body::Http(resp),
+            })
+        })
+    }
+}
+
+/// Answers requests from a queue of canned responses and records each
+/// request. Clones share the queue, so a test can keep one to inspect.
+#[derive(Clone, Default)]
+pub struct MockTransport {
+    responses: Arc<Mutex<VecDeque<Response>>>,
+    requests: Arc<Mutex<Vec<Request>>>,
+}
+
+impl MockTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue the response for the next request
+    pub fn push(&self, response: Response) -> &Self {
+        self.responses.lock().expect("mock transport lock").push_back(response);
+        self
+    }
+
+    /// Requests sent so far
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.lock().expect("mock transport lock").clone()
+    }
+}
+
+impl Transport for MockTransport {
+    fn send(&self, request: Request) -> BoxFuture<'_, Result<Response>> {
+        Box::pin(async move {
+            let url = request.url.clone();
+            self.requests.lock().expect("mock transport lock").push(request);
+            self.responses
+                .lock()
+                .expect("mock transport lock")
+                .pop_front()
+                .with_context(|| format!("No mock response left for {}", url))
+        })
+    }
+}
@@ -0,0 +1,25 @@
+//! Configuration variables (EXA_API_KEYS, EXA_CACHE_BACKEND, ...): the
+//! process environment, then the values the CLI read from EXA_CONFIG_JSON at
+//! startup. Those are kept here rather than copied into the environment,
+//! which other threads may be reading by then.
+
+use std::collections::HashMap;
+use std::env;
+use std::sync::OnceLock;
+
+static INLINE: OnceLock<HashMap<&'static str, String>> = OnceLock::new();
+
+/// Install the EXA_CONFIG_JSON values; only the first call has an effect
+pub fn set_inline(vars: impl IntoIterator<Item = (&'static str, String)>) {
+    let _ = INLINE.set(vars.into_iter().collect());
+}
+
+/// `name` from the environment, else from EXA_CONFIG_JSON
+pub fn get(name: &str) -> Option<String> {
+    env::var(name).ok().or_else(|| INLINE.get()?.get(name).cloned())
+}
+
+/// Whether `name` is set to something other than blanks
+pub fn is_set(name: &str) -> bool {
+    get(name).is_some_and(|v| !v.trim().is_empty())
+}
@@ -0,0 +1,105 @@
+use crate::diag;
+use colored::Colorize;
+use serde::Serialize;
+use std::cell::RefCell;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+static STRUCTURED: AtomicBool = AtomicBool::new(false);
+static PENDING: Mutex<Vec<Warning>> = Mutex::new(Vec::new());
+
+tokio::task_local! {
+    /// The warnings of a task run in [`scope`], kept apart from other tasks'
+    static SCOPED: RefCell<Vec<Warning>>;
+}
+
+/// A non-fatal issue, reported separately from results
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Warning {
+    /// Stable identifier agents can match on, e.g. "key_rate_limited"
+    pub code: &'static str,
+    pub message: String,
+}
+
+/// Collect warnings as structured records instead of printing them for humans
+pub fn set_structured(on: bool) {
+    STRUCTURED.store(on, Ordering::Relaxed);
+}
+
+pub fn is_structured() -> bool {
+    STRUCTURED.load(Ordering::Relaxed)
+}
+
+/// Report a warning. In structured mode it is always recorded; otherwise it
+/// is printed to stderr only when `show` (e.g. verbose-only diagnostics).
+pub fn emit(code: &'static str, message: impl Into<String>, show: bool) {
+    let warning = Warning { code, message: message.into() };
+    if SCOPED.try_with(|scoped| scoped.borrow_mut().push(warning.clone())).is_ok() {
+        return;
+    }
+    if is_structured() {
+        if let Ok(mut pending) = PENDING.lock() {
+            pending.push(warning);
+        }
+    } else if show {
+        diag!("{} {}", "Warning:".yellow(), warning.message);
+    }
+}
+
+/// Drain the warnings collected so far (within [`scope`], the task's own)
+pub fn take() -> Vec<Warning> {
+    if let Ok(taken) = SCOPED.try_with(|scoped| std::mem::take(&mut *scoped.borrow_mut())) {
+        return taken;
+    }
+    PENDING.lock().map(|mut p| std::mem::take(&mut *p)).unwrap_or_default()
+}
+
+/// Run `task` with warnings of its own, such as one of several concurrent
+/// tool calls: they are collected as structured records whatever the mode,
+/// and [`take`] within the task drains only them
+pub async fn scope<F: Future>(task: F) -> F::Output {
+    SCOPED.scope(RefCell::new(Vec::new()), task).await
+}
+
+/// Print any warnings not yet attached to output as JSON lines on stderr
+pub fn flush() {
+    for w in take() {
+        if let Ok(line) = serde_json::to_string(&w) {
+            diag!("{}", line);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_structured_collects() {
+        set_structured(true);
+        emit("cache_unavailable", "redis down", false);
+        emit("key_invalid", "key abc… is invalid", true);
+        let taken = take();
+        assert_eq!(taken.len(), 2);
+        assert_eq!(taken[0].code, "cache_unavailable");
+        assert!(take().is_empty());
+        assert_eq!(
+            serde_json::to_string(&taken[0]).unwrap(),
+            r#"{"code":"cache_unavailable","message":"redis down"}"#
+        );
+        set_structured(false);
+    }
+
+    #[tokio::test]
+    async fn test_scoped_warnings() {
+        let call = |code: &'static str| async move {
+            emit(code, "in a tool call", false);
+            tokio::task::yield_now().await;
+            take()
+        };
+        let (a, b) = tokio::join!(scope(call("key_invalid")), scope(call("cache_unavailable")));
+        assert_eq!((a.len(), a[0].code), (1, "key_invalid"));
+        assert_eq!((b.len(), b[0].code), (1, "cache_unavailable"));
+    }
+}
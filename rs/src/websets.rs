@@ -0,0 +1,362 @@
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Websets API root, relative to the API base URL
+pub const API: &str = "/websets/v0/websets";
+
+/// Largest page the API returns
+pub const MAX_PAGE: usize = 100;
+
+/// An ID as one URL path segment: everything but unreserved characters is
+/// percent-encoded, so `/` or `?` can't change the path. `.` and `..` (even
+/// encoded, URL parsers resolve them) are refused.
+pub fn segment(id: &str) -> anyhow::Result<String> {
+    if id.bytes().all(|b| b == b'.') {
+        anyhow::bail!("Invalid ID '{}'", id);
+    }
+    let mut out = String::with_capacity(id.len());
+    for b in id.bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b'~') {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+    Ok(out)
+}
+
+#[derive(Serialize)]
+pub struct CreateWebsetRequest {
+    pub search: SearchParams,
+}
+
+#[derive(Serialize)]
+pub struct SearchParams {
+    pub query: String,
+    pub count: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entity: Option<EntityParam>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub criteria: Vec<Criterion>,
+}
+
+#[derive(Serialize)]
+pub struct EntityParam {
+    #[serde(rename = "type")]
+    pub kind: String,
+}
+
+#[derive(Serialize)]
+pub struct Criterion {
+    pub description: String,
+}
+
+/// Value type an enrichment extracts for each item
+#[derive(Clone, Copy, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum EnrichmentFormat {
+    Text,
+    Number,
+    Date,
+    Options,
+    Email,
+    Phone,
+    Url,
+}
+
+#[derive(Serialize)]
+pub struct CreateEnrichmentRequest {
+    pub description: String,
+    pub format: EnrichmentFormat,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub options: Vec<EnrichmentOption>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct EnrichmentOption {
+    pub label: String,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct Enrichment {
+    pub id: String,
+    /// pending, canceled or completed
+    pub status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub options: Option<Vec<EnrichmentOption>>,
+    #[serde(rename = "websetId", default, skip_serializing_if = "Option::is_none")]
+    pub webset_id: Option<String>,
+    #[serde(rename = "createdAt", default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<String>,
+}
+
+impl Enrichment {
+    pub fn is_finished(&self) -> bool {
+        matches!(self.status.as_str(), "completed" | "canceled")
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct Webset {
+    pub id: String,
+    pub status: String,
+    #[serde(rename = "externalId", default, skip_serializing_if = "Option::is_none")]
+    pub external_id: Option<String>,
+    #[serde(default)]
+    pub searches: Vec<WebsetSearch>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub enrichments: Vec<Enrichment>,
+    #[serde(rename = "createdAt", default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<String>,
+    #[serde(rename = "updatedAt", default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct WebsetSearch {
+    pub id: String,
+    pub status: String,
+    #[serde(default)]
+    pub query: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub count: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub progress: Option<SearchProgress>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct SearchProgress {
+    pub found: u64,
+    #[serde(default)]
+    pub completion: f64,
+}
+
+/// One page of a cursor-paginated listing
+#[derive(Deserialize, Debug)]
+pub struct Page<T> {
+    pub data: Vec<T>,
+    #[serde(rename = "hasMore", default)]
+    pub has_more: bool,
+    #[serde(rename = "nextCursor", default)]
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct WebsetItem {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    pub properties: ItemProperties,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub evaluations: Vec<Value>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub enrichments: Vec<Value>,
+    #[serde(rename = "createdAt", default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ItemProperties {
+    /// company, person, article, research_paper, custom
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Entity-specific details, keyed by the entity type
+    #[serde(flatten)]
+    pub details: Map<String, Value>,
+}
+
+impl ItemProperties {
+    /// Display name: the entity's name or title, else the URL
+    pub fn name(&self) -> &str {
+        self.details
+            .get(&self.kind)
+            .and_then(|d| d.get("name").or_else(|| d.get("title")))
+            .and_then(|v| v.as_str())
+            .unwrap_or(&self.url)
+    }
+}
+
+/// `query` of the first search, which is what a webset was created from
+fn webset_query(w: &Webset) -> &str {
+    w.searches.first().and_then(|s| s.query.as_deref()).unwrap_or("")
+}
+
+fn short_date(date: Option<&str>) -> &str {
+    date.map(|d| d.get(..10).unwrap_or(d)).unwrap_or("")
+}
+
+pub fn print_websets(websets: &[Webset], compact: bool) {
+    if compact {
+        for w in websets {
+            println!("{}\t{}\t{}\t{}", w.id, w.status, short_date(w.created_at.as_deref()), webset_query(w));
+        }
+        return;
+    }
+    println!("{}", format!("{:<28}  {:<10}  {:<10}  query", "id", "status", "created").bold());
+    for w in websets {
+        println!(
+            "{:<28}  {:<10}  {:<10}  {}",
+            w.id,
+            w.status,
+            short_date(w.created_at.as_deref()),
+            webset_query(w)
+        );
+    }
+}
+
+pub fn print_webset(w: &Webset, compact: bool) {
+    if compact {
+        println!("id: {}", w.id);
+        println!("status: {}", w.status);
+        for s in &w.searches {
+            let found = s.progress.as_ref().map(|p| p.found).unwrap_or(0);
+            println!("search: {} | {} | found {} | {}", s.id, s.status, found, s.query.as_deref().unwrap_or(""));
+        }
+        return;
+    }
+    println!("{} {}", "Webset:".bold(), w.id);
+    println!("{} {}", "Status:".dimmed(), w.status);
+    if let Some(created) = &w.created_at {
+        println!("{} {}", "Created:".dimmed(), created);
+    }
+    for s in &w.searches {
+        println!();
+        println!("{} {}", "Search:".cyan(), s.query.as_deref().unwrap_or(""));
+        let mut progress = format!("  {} ({})", s.status, s.id);
+        if let Some(p) = &s.progress {
+            let wanted = s.count.map(|c| format!("/{}", c)).unwrap_or_default();
+            progress.push_str(&format!(", found {}{}, {:.0}% done", p.found, wanted, p.completion));
+        }
+        println!("{}", progress.dimmed());
+    }
+}
+
+pub fn print_enrichments(enrichments: &[Enrichment], compact: bool) {
+    for e in enrichments {
+        let what = e.title.as_deref().or(e.description.as_deref()).unwrap_or("");
+        let format = e.format.as_deref().unwrap_or("");
+        if compact {
+            println!("{}\t{}\t{}\t{}", e.id, e.status, format, what);
+        } else {
+            println!("{} {}", e.id.bold(), format!("[{}, {}]", e.status, format).dimmed());
+            println!("  {}", what);
+        }
+    }
+}
+
+/// Values an item got from its enrichments, as (enrichment id, values)
+fn enrichment_values(item: &WebsetItem) -> Vec<(&str, String)> {
+    item.enrichments
+        .iter()
+        .filter_map(|e| {
+            let id = e.get("enrichmentId")?.as_str()?;
+            let values: Vec<String> = e
+                .get("result")?
+                .as_array()?
+                .iter()
+                .map(|v| v.as_str().map(str::to_string).unwrap_or_else(|| v.to_string()))
+                .collect();
+            (!values.is_empty()).then(|| (id, values.join(", ")))
+        })
+        .collect()
+}
+
+/// Tab-separated rows: id, type, name, URL, then a column per enrichment
+/// (in order of first appearance), empty where an item has no value for it
+fn compact_rows(items: &[WebsetItem]) -> Vec<String> {
+    let mut columns: Vec<&str> = Vec::new();
+    for (id, _) in items.iter().flat_map(enrichment_values) {
+        if !columns.contains(&id) {
+            columns.push(id);
+        }
+    }
+    items
+        .iter()
+        .map(|item| {
+            let p = &item.properties;
+            let values = enrichment_values(item);
+            let mut line = format!("{}\t{}\t{}\t{}", item.id, p.kind, p.name(), p.url);
+            for column in &columns {
+                line.push('\t');
+                if let Some((_, value)) = values.iter().find(|(id, _)| id == column) {
+                    line.push_str(value);
+                }
+            }
+            line
+        })
+        .collect()
+}
+
+pub fn print_items(items: &[WebsetItem], compact: bool) {
+    if compact {
+        compact_rows(items).iter().for_each(|row| println!("{}", row));
+        return;
+    }
+    for (i, item) in items.iter().enumerate() {
+        let p = &item.properties;
+        println!("{}", format!("--- Item {} ---", i + 1).dimmed());
+        println!("{} {}", "Name:".bold(), p.name());
+        println!("{} {}", "Link:".cyan(), p.url);
+        println!("{} {} ({})", "Type:".dimmed(), p.kind, item.id);
+        if let Some(desc) = &p.description {
+            println!("{}", desc);
+        }
+        for (id, values) in enrichment_values(item) {
+            println!("{} {}", format!("{}:", id).yellow(), values);
+        }
+        println!();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_segment() {
+        assert_eq!(segment("webset_01abc").unwrap(), "webset_01abc");
+        assert_eq!(segment("a/b?c=d").unwrap(), "a%2Fb%3Fc%3Dd");
+        assert_eq!(segment("v1.2").unwrap(), "v1.2");
+        assert!(segment("..").is_err() && segment("").is_err());
+    }
+
+    #[test]
+    fn test_parse_items_page() {
+        let page: Page<WebsetItem> = serde_json::from_str(
+            r#"{"data": [
+                {"id": "witem_1", "source": "search", "properties": {
+                    "type": "company", "url": "https://acme.com", "description": "Rockets",
+                    "company": {"name": "Acme", "location": "NYC"}},
+                 "enrichments": [{"enrichmentId": "wenrich_1", "status": "completed", "result": ["120", "approx"]},
+                                 {"enrichmentId": "wenrich_2", "status": "pending", "result": null}]},
+                {"id": "witem_2", "properties": {"type": "article", "url": "https://x.com/a",
+                    "article": {"author": "Ann"}}}
+            ], "hasMore": true, "nextCursor": "c2"}"#,
+        )
+        .unwrap();
+        assert!(page.has_more);
+        assert_eq!(page.next_cursor.as_deref(), Some("c2"));
+        assert_eq!(page.data[0].properties.name(), "Acme");
+        assert_eq!(enrichment_values(&page.data[0]), vec![("wenrich_1", "120, approx".to_string())]);
+        assert_eq!(page.data[1].properties.name(), "https://x.com/a");
+        assert!(enrichment_values(&page.data[1]).is_empty());
+        // An item without a value keeps an empty column, so columns line up
+        assert_eq!(
+            compact_rows(&page.data),
+            ["witem_1\tcompany\tAcme\thttps://acme.com\t120, approx", "witem_2\tarticle\thttps://x.com/a\thttps://x.com/a\t"]
+        );
+        // Entity details survive a round trip for --json
+        let json = serde_json::to_value(&page.data[0]).unwrap();
+        assert_eq!(json["properties"]["company"]["location"], "NYC");
+    }
+}
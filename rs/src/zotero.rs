@@ -0,0 +1,111 @@
+use anyhow::{bail, Context, Result};
+use chrono::Utc;
+use serde_json::{json, Value};
+
+/// Zotero's local connector endpoint (served by the desktop app)
+pub const CONNECTOR_URL: &str = "http://127.0.0.1:23119/connector";
+
+/// A result as a reference-manager item
+pub struct Reference<'a> {
+    pub title: &'a str,
+    pub url: &'a str,
+    pub published_date: Option<&'a str>,
+    pub abstract_note: Option<&'a str>,
+    /// Content hash of the page text, for matching the item to later fetches
+    pub content_hash: Option<&'a str>,
+}
+
+/// Split an ISO date (`2024-01-02` or `2024-01-02T00:00:00.000Z`) into
+/// year, month, day parts, keeping only the parts present
+fn date_parts(date: &str) -> Vec<u32> {
+    date.get(..10.min(date.len()))
+        .unwrap_or("")
+        .split('-')
+        .map_while(|p| p.parse().ok())
+        .take(3)
+        .collect()
+}
+
+/// CSL-JSON item (importable by Zotero, Mendeley, pandoc-citeproc, ...)
+pub fn csl_item(r: &Reference, id: usize) -> Value {
+    let mut item = json!({
+        "id": format!("exa-{}", id),
+        "type": "webpage",
+        "title": r.title,
+        "URL": r.url,
+        "accessed": { "date-parts": [date_parts(&Utc::now().format("%Y-%m-%d").to_string())] },
+    });
+    if let Some(date) = r.published_date {
+        let parts = date_parts(date);
+        if !parts.is_empty() {
+            item["issued"] = json!({ "date-parts": [parts] });
+        }
+    }
+    if let Some(note) = r.abstract_note {
+        item["abstract"] = json!(note);
+    }
+    if let Some(hash) = r.content_hash {
+        item["custom"] = json!({ "contentHash": hash });
+    }
+    item
+}
+
+/// Item in the Zotero connector's saveItems format
+fn connector_item(r: &Reference, id: usize) -> Value {
+    json!({
+        "id": format!("exa-{}", id),
+        "itemType": "webpage",
+        "title": r.title,
+        "url": r.url,
+        "date": r.published_date.map(|d| d.get(..10).unwrap_or(d)).unwrap_or(""),
+        "accessDate": Utc::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        "abstractNote": r.abstract_note.unwrap_or(""),
+        "extra": r.content_hash.map(|h| format!("Content hash: {}", h)).unwrap_or_default(),
+        "attachments": [],
+        "tags": [],
+    })
+}
+
+/// Save items into the currently selected Zotero collection via the connector
+pub async fn save_to_connector(http: &reqwest::Client, refs: &[Reference<'_>]) -> Result<()> {
+    let items: Vec<Value> = refs.iter().enumerate().map(|(i, r)| connector_item(r, i + 1)).collect();
+    let body = json!({ "items": items, "uri": refs.first().map(|r| r.url).unwrap_or("") });
+
+    let resp = http
+        .post(format!("{}/saveItems", CONNECTOR_URL))
+        .header("X-Zotero-Connector-API-Version", "3")
+        .json(&body)
+        .send()
+        .await
+        .context("Could not reach Zotero; is the desktop app running? (or use --to csl-json)")?;
+    if !resp.status().is_success() {
+        bail!("Zotero connector returned {}", resp.status());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csl_item() {
+        assert_eq!(date_parts("2024-01-02T00:00:00.000Z"), vec![2024, 1, 2]);
+        assert_eq!(date_parts("2024"), vec![2024]);
+        assert!(date_parts("unknown").is_empty());
+
+        let r = Reference {
+            title: "Title",
+            url: "https://example.com",
+            published_date: Some("2023-05-06T10:00:00Z"),
+            abstract_note: None,
+            content_hash: Some("abc"),
+        };
+        let item = csl_item(&r, 3);
+        assert_eq!(item["id"], "exa-3");
+        assert_eq!(item["URL"], "https://example.com");
+        assert_eq!(item["issued"]["date-parts"], json!([[2023, 5, 6]]));
+        assert!(item.get("abstract").is_none());
+        assert_eq!(item["custom"]["contentHash"], "abc");
+    }
+}